@@ -26,6 +26,90 @@ impl Default for BlendCfg {
     }
 }
 
+/// How a layer is composited with the layers drawn below it
+///
+/// Each mode sets the blend equation and the color factors of the
+/// blend function (c* being the color, a* the alpha, s* the drawn layer
+/// and d* the layers below):
+///
+/// * ``Over`` - ``FUNC_ADD``, ``SRC_ALPHA``, ``ONE_MINUS_SRC_ALPHA``, i.e. cs*as + cd*(1 - as)
+/// * ``Add`` - ``FUNC_ADD``, ``SRC_ALPHA``, ``ONE``, i.e. cs*as + cd
+/// * ``Screen`` - ``FUNC_ADD``, ``SRC_ALPHA``, ``ONE_MINUS_SRC_COLOR``, i.e. cs*as + cd*(1 - cs)
+/// * ``Multiply`` - ``FUNC_ADD``, ``DST_COLOR``, ``ONE_MINUS_SRC_ALPHA``, i.e. cs*cd + cd*(1 - as)
+///
+/// The alpha factors are ``ONE``, ``ONE``. Screen and multiply are exact for opaque layers.
+/// The transfer function and the colormap of the layer are applied before blending.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[wasm_bindgen]
+pub enum BlendMode {
+    Over,
+    Add,
+    Screen,
+    Multiply,
+}
+
+impl From<BlendMode> for BlendCfg {
+    fn from(mode: BlendMode) -> Self {
+        let (src_color_factor, dst_color_factor) = match mode {
+            BlendMode::Over => (BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha),
+            BlendMode::Add => (BlendFactor::SrcAlpha, BlendFactor::One),
+            BlendMode::Screen => (BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcColor),
+            BlendMode::Multiply => (BlendFactor::DstColor, BlendFactor::OneMinusSrcAlpha),
+        };
+
+        Self {
+            src_color_factor,
+            dst_color_factor,
+            func: BlendFunc::FuncAdd,
+        }
+    }
+}
+
+impl BlendCfg {
+    /// Blend a color over another one as the GPU would do it
+    ///
+    /// The constant color of the blend function is the WebGL default one, i.e.
+    /// a transparent black. The result is clamped as for a fixed point framebuffer.
+    ///
+    /// # Arguments
+    ///
+    /// * ``src`` - The RGBA color of the layer drawn
+    /// * ``dst`` - The RGBA color already in the framebuffer
+    pub fn blend(&self, src: [f32; 4], dst: [f32; 4]) -> [f32; 3] {
+        let factor = |f: &BlendFactor, i: usize| -> f32 {
+            match f {
+                BlendFactor::Zero => 0.0,
+                BlendFactor::One => 1.0,
+                BlendFactor::SrcColor => src[i],
+                BlendFactor::OneMinusSrcColor => 1.0 - src[i],
+                BlendFactor::DstColor => dst[i],
+                BlendFactor::OneMinusDstColor => 1.0 - dst[i],
+                BlendFactor::SrcAlpha => src[3],
+                BlendFactor::OneMinusSrcAlpha => 1.0 - src[3],
+                BlendFactor::DstAlpha => dst[3],
+                BlendFactor::OneMinusDstAlpha => 1.0 - dst[3],
+                BlendFactor::ConstantColor | BlendFactor::ConstantAlpha => 0.0,
+                BlendFactor::OneMinusConstantColor | BlendFactor::OneMinusConstantAlpha => 1.0,
+            }
+        };
+
+        let mut res = [0.0; 3];
+        for (i, c) in res.iter_mut().enumerate() {
+            let s = src[i] * factor(&self.src_color_factor, i);
+            let d = dst[i] * factor(&self.dst_color_factor, i);
+
+            *c = match self.func {
+                BlendFunc::FuncAdd => s + d,
+                BlendFunc::FuncSubstract => s - d,
+                BlendFunc::FuncReverseSubstract => d - s,
+            }
+            .clamp(0.0, 1.0);
+        }
+
+        res
+    }
+}
+
 #[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
 #[wasm_bindgen]
 pub enum BlendFactor {
@@ -116,3 +200,27 @@ impl fmt::Display for BlendFunc {
         write!(f, "{}", str)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{BlendCfg, BlendMode};
+
+    #[test]
+    fn blend_modes() {
+        let black = [0.0, 0.0, 0.0, 1.0];
+        let gray = [0.5, 0.5, 0.5, 1.0];
+
+        let over = BlendCfg::from(BlendMode::Over);
+        let add = BlendCfg::from(BlendMode::Add);
+
+        // Two half gray layers added saturate to white
+        let [r, g, b] = over.blend(gray, black);
+        let [r, g, b] = add.blend(gray, [r, g, b, 1.0]);
+        assert_eq!([r, g, b], [1.0, 1.0, 1.0]);
+
+        // whereas the top one hides the other one when drawn over
+        assert_eq!(over.blend(gray, gray), [0.5, 0.5, 0.5]);
+        assert_eq!(BlendCfg::from(BlendMode::Screen).blend(gray, gray), [0.75, 0.75, 0.75]);
+        assert_eq!(BlendCfg::from(BlendMode::Multiply).blend(gray, gray), [0.25, 0.25, 0.25]);
+    }
+}
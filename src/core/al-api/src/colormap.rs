@@ -2,8 +2,52 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct CmapLabel(String);
 
+/// Suffix of the name of a colormap selected in reversed order, e.g. ``viridis_r``
+pub const REVERSED_SUFFIX: &str = "_r";
+
+impl CmapLabel {
+    pub fn new(label: &str) -> Self {
+        CmapLabel(label.to_string())
+    }
+
+    /// Split the label into the name of the colormap and a flag
+    /// telling whether it has the reversed suffix
+    pub fn split_reversed(&self) -> (&str, bool) {
+        if let Some(name) = self.0.strip_suffix(REVERSED_SUFFIX) {
+            (name, true)
+        } else {
+            (&self.0, false)
+        }
+    }
+
+    /// The label selecting the colormap ``name``, suffixed when it is reversed
+    pub fn with_reversed(name: &str, reversed: bool) -> Self {
+        if reversed {
+            CmapLabel(format!("{}{}", name, REVERSED_SUFFIX))
+        } else {
+            CmapLabel::new(name)
+        }
+    }
+}
+
 impl AsRef<str> for CmapLabel {
     fn as_ref(&self) -> &str {
         self.0.as_ref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CmapLabel;
+
+    #[test]
+    fn reversed_suffix_round_trip() {
+        assert_eq!(CmapLabel::new("viridis").split_reversed(), ("viridis", false));
+        assert_eq!(CmapLabel::new("viridis_r").split_reversed(), ("viridis", true));
+
+        for reversed in [false, true] {
+            let label = CmapLabel::with_reversed("rdylbu", reversed);
+            assert_eq!(label.split_reversed(), ("rdylbu", reversed));
+        }
+    }
+}
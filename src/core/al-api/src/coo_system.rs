@@ -2,6 +2,10 @@ use cgmath::Matrix4;
 pub trait CooBaseFloat: Sized + 'static {
     const GALACTIC_TO_J2000: &'static Matrix4<Self>;
     const J2000_TO_GALACTIC: &'static Matrix4<Self>;
+    const J2000_TO_ECLIPTIC: &'static Matrix4<Self>;
+    const ECLIPTIC_TO_J2000: &'static Matrix4<Self>;
+    const GALACTIC_TO_ECLIPTIC: &'static Matrix4<Self>;
+    const ECLIPTIC_TO_GALACTIC: &'static Matrix4<Self>;
     const ID: &'static Matrix4<Self>;
 }
 
@@ -44,6 +48,83 @@ impl CooBaseFloat for f32 {
         1.0,
     );
 
+    // Mean obliquity of the ecliptic at J2000: 84381.448 arcsec
+    const J2000_TO_ECLIPTIC: &'static Matrix4<Self> = &Matrix4::new(
+        0.917482062,
+        -0.397777156,
+        0.0,
+        0.0,
+        0.397777156,
+        0.917482062,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    );
+
+    const ECLIPTIC_TO_J2000: &'static Matrix4<Self> = &Matrix4::new(
+        0.917482062,
+        0.397777156,
+        0.0,
+        0.0,
+        -0.397777156,
+        0.917482062,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    );
+
+    const GALACTIC_TO_ECLIPTIC: &'static Matrix4<Self> = &Matrix4::new(
+        -0.110990733,
+        0.862285875,
+        0.494109428,
+        0.0,
+        -0.000351589913,
+        0.497147192,
+        -0.867666149,
+        0.0,
+        -0.993821379,
+        -0.0964766261,
+        -0.0548755604,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    );
+
+    const ECLIPTIC_TO_GALACTIC: &'static Matrix4<Self> = &Matrix4::new(
+        -0.110990733,
+        -0.000351589913,
+        -0.993821379,
+        0.0,
+        0.862285875,
+        0.497147192,
+        -0.0964766261,
+        0.0,
+        0.494109428,
+        -0.867666149,
+        -0.0548755604,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    );
+
     const ID: &'static Matrix4<Self> = &Matrix4::new(
         1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
     );
@@ -87,22 +168,100 @@ impl CooBaseFloat for f64 {
         1.0,
     );
 
+    // Mean obliquity of the ecliptic at J2000: 84381.448 arcsec
+    const J2000_TO_ECLIPTIC: &'static Matrix4<Self> = &Matrix4::new(
+        0.9174820620691818,
+        -0.3977771559319137,
+        0.0,
+        0.0,
+        0.3977771559319137,
+        0.9174820620691818,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    );
+
+    const ECLIPTIC_TO_J2000: &'static Matrix4<Self> = &Matrix4::new(
+        0.9174820620691818,
+        0.3977771559319137,
+        0.0,
+        0.0,
+        -0.3977771559319137,
+        0.9174820620691818,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    );
+
+    const GALACTIC_TO_ECLIPTIC: &'static Matrix4<Self> = &Matrix4::new(
+        -0.1109907333885698,
+        0.8622858750548725,
+        0.4941094279435681,
+        0.0,
+        -0.00035158991268138817,
+        0.4971471917820061,
+        -0.867666148981161,
+        0.0,
+        -0.9938213790648698,
+        -0.0964766261024812,
+        -0.0548755604024359,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    );
+
+    const ECLIPTIC_TO_GALACTIC: &'static Matrix4<Self> = &Matrix4::new(
+        -0.1109907333885698,
+        -0.00035158991268138817,
+        -0.9938213790648698,
+        0.0,
+        0.8622858750548725,
+        0.4971471917820061,
+        -0.0964766261024812,
+        0.0,
+        0.4941094279435681,
+        -0.867666148981161,
+        -0.0548755604024359,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    );
+
     const ID: &'static Matrix4<Self> = &Matrix4::new(
         1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
     );
 }
 
 use cgmath::BaseFloat;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 #[wasm_bindgen]
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize, Serialize, Hash)]
 pub enum CooSystem {
     ICRS = 0,
     GAL = 1,
+    ECL = 2,
 }
 
-pub const NUM_COOSYSTEM: usize = 2;
+pub const NUM_COOSYSTEM: usize = 3;
 
 impl CooSystem {
     #[inline]
@@ -113,6 +272,10 @@ impl CooSystem {
         match (self, coo_system) {
             (CooSystem::GAL, CooSystem::ICRS) => S::GALACTIC_TO_J2000,
             (CooSystem::ICRS, CooSystem::GAL) => S::J2000_TO_GALACTIC,
+            (CooSystem::ICRS, CooSystem::ECL) => S::J2000_TO_ECLIPTIC,
+            (CooSystem::ECL, CooSystem::ICRS) => S::ECLIPTIC_TO_J2000,
+            (CooSystem::GAL, CooSystem::ECL) => S::GALACTIC_TO_ECLIPTIC,
+            (CooSystem::ECL, CooSystem::GAL) => S::ECLIPTIC_TO_GALACTIC,
             (_, _) => S::ID,
         }
     }
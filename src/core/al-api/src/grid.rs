@@ -23,6 +23,28 @@ pub struct GridCfg {
     pub enabled: Option<bool>,
     #[serde(default = "default_fmt")]
     pub fmt: Option<AngleSerializeFmt>,
+    #[serde(default = "default_color")]
+    pub prime_meridian_color: Option<ColorRGB>,
+    #[serde(default = "default_width_policy")]
+    pub width_policy: Option<LineWidthPolicy>,
+    #[serde(default = "default_thickness")]
+    pub min_thickness: Option<f32>,
+    #[serde(default = "default_thickness")]
+    pub max_thickness: Option<f32>,
+    // Angular distance in degrees over which the lines fade out before the
+    // limb of the projection, 0 to disable the fade
+    #[serde(default = "default_limb_fade")]
+    pub limb_fade: Option<f32>,
+}
+
+/// How the width of the grid lines on screen is given by their thickness
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[wasm_bindgen]
+pub enum LineWidthPolicy {
+    /// The thickness is given in pixels of the screen
+    Fixed,
+    /// The thickness is given in CSS pixels, i.e. scaled by the device pixel ratio
+    DevicePixelRatio,
 }
 
 fn default_labels() -> Option<bool> {
@@ -45,6 +67,14 @@ fn default_thickness() -> Option<f32> {
     None
 }
 
+fn default_width_policy() -> Option<LineWidthPolicy> {
+    None
+}
+
+fn default_limb_fade() -> Option<f32> {
+    None
+}
+
 fn default_fmt() -> Option<AngleSerializeFmt> {
     None
 }
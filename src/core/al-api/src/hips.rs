@@ -1,6 +1,6 @@
 use wasm_bindgen::JsValue;
 
-use super::blend::BlendCfg;
+use super::blend::{BlendCfg, BlendMode};
 use serde::Deserialize;
 
 #[derive(Deserialize, Debug, Clone)]
@@ -142,29 +142,401 @@ impl std::fmt::Display for ImageExt {
 
 use serde::Serialize;
 use wasm_bindgen::prelude::*;
-#[wasm_bindgen]
-#[derive(Clone, Copy, PartialEq, Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(from = "String", into = "String")]
 pub enum TransferFunction {
     Linear,
     Sqrt,
     Log,
-    Asinh,
+    /// Inverse hyperbolic sine asinh(x/a)/asinh(1/a) with a the positive softening.
+    /// Parsed from ids of the form "asinh:0.1"
+    Asinh(f32),
     Pow2,
+    /// Gamma curve x^gamma. Parsed from ids of the form "gamma:0.45"
+    Gamma(f32),
+    /// Histogram equalization given by a look-up table sampled on [0, 1].
+    /// The table is not part of the id, "histeq" gives an identity table. Its id
+    /// does not round-trip then, the table has to be stored apart, see `hist_eq_lut`
+    HistEq(Box<[f32; HIST_EQ_LUT_SIZE]>),
+    /// The wrapped function evaluated on 1 - x, i.e. bright and dark are swapped.
+    /// Parsed from ids ending with "_r" or "-", e.g. "sqrt_r"
+    Reversed(Box<TransferFunction>),
 }
 
+/// Number of entries of the histogram equalization look-up table
+pub const HIST_EQ_LUT_SIZE: usize = 256;
+
+/// Ids of the transfer functions, as accepted by `TransferFunction::try_from_id`
+pub const TRANSFER_FUNCTION_IDS: [&str; 7] = ["linear", "sqrt", "log", "asinh", "pow2", "gamma", "histeq"];
+
 impl TransferFunction {
+    /// Parse a transfer function from its id
+    ///
+    /// The canonical ids returned by `id` are matched first. Otherwise the
+    /// first name found in the id, in that priority order: "linear", "pow2",
+    /// "gamma", "histeq", "log", "sqrt", gives the function, e.g. "loglinear" resolves to
+    /// linear. Unknown ids fall back to asinh.
     pub fn new(id: &str) -> Self {
-        if id.contains("linear") {
-            TransferFunction::Linear
-        } else if id.contains("pow2") {
-            TransferFunction::Pow2
-        } else if id.contains("log") {
-            TransferFunction::Log
-        } else if id.contains("sqrt") {
-            TransferFunction::Sqrt
+        if let Some(id) = id.strip_suffix("_r").or_else(|| id.strip_suffix('-')) {
+            return Self::new(id).reversed();
+        }
+
+        // The gamma exponent or the asinh softening is given after the colon, e.g. "gamma:0.45"
+        let (name, param) = match id.split_once(':') {
+            Some((name, param)) => (name, Some(param)),
+            None => (id, None),
+        };
+        let param = param.and_then(|p| p.trim().parse::<f32>().ok());
+        let gamma = param.unwrap_or(1.0);
+        let softening = param
+            .filter(|a| *a > 0.0)
+            .unwrap_or(DEFAULT_ASINH_SOFTENING);
+
+        match name {
+            "linear" => TransferFunction::Linear,
+            "sqrt" => TransferFunction::Sqrt,
+            "log" => TransferFunction::Log,
+            "asinh" => TransferFunction::Asinh(softening),
+            "pow2" => TransferFunction::Pow2,
+            "gamma" => TransferFunction::Gamma(gamma),
+            "histeq" => TransferFunction::identity_hist_eq(),
+            _ => {
+                if name.contains("linear") {
+                    TransferFunction::Linear
+                } else if name.contains("pow2") {
+                    TransferFunction::Pow2
+                } else if name.contains("gamma") {
+                    TransferFunction::Gamma(gamma)
+                } else if name.contains("histeq") {
+                    TransferFunction::identity_hist_eq()
+                } else if name.contains("log") {
+                    TransferFunction::Log
+                } else if name.contains("sqrt") {
+                    TransferFunction::Sqrt
+                } else {
+                    TransferFunction::Asinh(softening)
+                }
+            }
+        }
+    }
+
+    /// Parse a transfer function from its exact id
+    ///
+    /// Contrary to `new`, an unknown id, or an invalid parameter, is an error so
+    /// that typos can be reported. The accepted ids are "linear", "sqrt", "log",
+    /// "asinh", "pow2", "gamma" and "histeq", optionally followed by a parameter
+    /// for "asinh" and "gamma" (e.g. "gamma:0.45") and by "_r" or "-" for reversing
+    pub fn try_from_id(id: &str) -> Result<Self, String> {
+        if let Some(base) = id.strip_suffix("_r").or_else(|| id.strip_suffix('-')) {
+            return Self::try_from_id(base).map(Self::reversed);
+        }
+
+        let (name, param) = match id.split_once(':') {
+            Some((name, param)) => {
+                let param = param
+                    .trim()
+                    .parse::<f32>()
+                    .map_err(|_| format!("Invalid parameter {:?} of the transfer function {:?}", param, id))?;
+                (name, Some(param))
+            }
+            None => (id, None),
+        };
+
+        let h = match (name, param) {
+            ("linear", None) => TransferFunction::Linear,
+            ("sqrt", None) => TransferFunction::Sqrt,
+            ("log", None) => TransferFunction::Log,
+            ("pow2", None) => TransferFunction::Pow2,
+            ("histeq", None) => TransferFunction::identity_hist_eq(),
+            ("gamma", gamma) => TransferFunction::Gamma(gamma.unwrap_or(1.0)),
+            ("asinh", None) => TransferFunction::Asinh(DEFAULT_ASINH_SOFTENING),
+            ("asinh", Some(a)) if a > 0.0 => TransferFunction::Asinh(a),
+            ("asinh", Some(_)) => {
+                return Err(format!("The softening of {:?} must be positive", id));
+            }
+            ("linear" | "sqrt" | "log" | "pow2" | "histeq", Some(_)) => {
+                return Err(format!("The transfer function {:?} takes no parameter", name));
+            }
+            _ => {
+                return Err(format!(
+                    "Unknown transfer function {:?}, expected one of: {}",
+                    id,
+                    TRANSFER_FUNCTION_IDS.join(", ")
+                ));
+            }
+        };
+
+        Ok(h)
+    }
+
+    /// Parse one transfer function per color channel from their ids
+    ///
+    /// See `new` for the ids accepted
+    pub fn new_rgb(r: &str, g: &str, b: &str) -> [Self; 3] {
+        [Self::new(r), Self::new(g), Self::new(b)]
+    }
+
+    /// Toggle the reversal of the function
+    pub fn reversed(self) -> Self {
+        if let TransferFunction::Reversed(h) = self {
+            *h
         } else {
-            TransferFunction::Asinh
+            TransferFunction::Reversed(Box::new(self))
+        }
+    }
+
+    pub fn is_reversed(&self) -> bool {
+        matches!(self, TransferFunction::Reversed(_))
+    }
+
+    /// The function without its reversal
+    pub fn base(&self) -> &Self {
+        if let TransferFunction::Reversed(h) = self {
+            h
+        } else {
+            self
+        }
+    }
+
+    /// Canonical id of the function
+    ///
+    /// The gamma exponent, the reversal and the histogram equalization table are not
+    /// part of it, see `From<TransferFunction> for String`
+    pub fn id(&self) -> &'static str {
+        match self {
+            TransferFunction::Linear => "linear",
+            TransferFunction::Sqrt => "sqrt",
+            TransferFunction::Log => "log",
+            TransferFunction::Asinh(_) => "asinh",
+            TransferFunction::Pow2 => "pow2",
+            TransferFunction::Gamma(_) => "gamma",
+            TransferFunction::HistEq(_) => "histeq",
+            TransferFunction::Reversed(h) => h.id(),
+        }
+    }
+
+    /// Histogram equalization from a look-up table
+    ///
+    /// The table values are clamped to [0, 1] and must be non-decreasing
+    pub fn hist_eq(lut: &[f32; HIST_EQ_LUT_SIZE]) -> Result<Self, String> {
+        let mut table = Box::new([0.0; HIST_EQ_LUT_SIZE]);
+        for (t, v) in table.iter_mut().zip(lut.iter()) {
+            if v.is_nan() {
+                return Err("The histogram equalization table contains NaN values".to_string());
+            }
+
+            *t = v.clamp(0.0, 1.0);
+        }
+
+        if table.windows(2).any(|w| w[0] > w[1]) {
+            return Err("The histogram equalization table must be non-decreasing".to_string());
+        }
+
+        Ok(TransferFunction::HistEq(table))
+    }
+
+    /// The histogram equalization of table ``lut``, reversed if this function is
+    ///
+    /// The table must have `HIST_EQ_LUT_SIZE` entries, see `TransferFunction::hist_eq`
+    pub fn with_hist_eq_lut(&self, lut: &[f32]) -> Result<Self, String> {
+        let lut: &[f32; HIST_EQ_LUT_SIZE] = lut.try_into().map_err(|_| {
+            format!(
+                "The histogram equalization table must have {} entries, not {}",
+                HIST_EQ_LUT_SIZE,
+                lut.len()
+            )
+        })?;
+        let h = TransferFunction::hist_eq(lut)?;
+
+        Ok(if self.is_reversed() { h.reversed() } else { h })
+    }
+
+    /// The table of the histogram equalization, None for the other functions
+    pub fn hist_eq_lut(&self) -> Option<&[f32; HIST_EQ_LUT_SIZE]> {
+        if let TransferFunction::HistEq(lut) = self.base() {
+            Some(lut)
+        } else {
+            None
+        }
+    }
+
+    fn identity_hist_eq() -> Self {
+        let mut table = Box::new([0.0; HIST_EQ_LUT_SIZE]);
+        for (i, t) in table.iter_mut().enumerate() {
+            *t = (i as f32) / ((HIST_EQ_LUT_SIZE - 1) as f32);
+        }
+
+        TransferFunction::HistEq(table)
+    }
+
+    /// Index of the function as expected by the `H` uniform of the shaders
+    pub fn shader_index(&self) -> i32 {
+        match self {
+            TransferFunction::Linear => 0,
+            TransferFunction::Sqrt => 1,
+            TransferFunction::Log => 2,
+            TransferFunction::Asinh(_) => 3,
+            TransferFunction::Pow2 => 4,
+            TransferFunction::Gamma(_) => 5,
+            TransferFunction::HistEq(_) => 6,
+            TransferFunction::Reversed(h) => h.shader_index(),
+        }
+    }
+
+    /// Softening of the asinh function, the default one for the other functions
+    pub fn asinh_softening(&self) -> f32 {
+        if let TransferFunction::Asinh(a) = self.base() {
+            *a
+        } else {
+            DEFAULT_ASINH_SOFTENING
+        }
+    }
+
+    /// Exponent of the gamma curve, 1.0 for the other functions
+    pub fn gamma_exp(&self) -> f32 {
+        if let TransferFunction::Gamma(gamma) = self.base() {
+            *gamma
+        } else {
+            1.0
+        }
+    }
+
+    /// Evaluate the function on the CPU
+    ///
+    /// This mirrors the GLSL code of `transfer_funcs.glsl` so that the
+    /// value returned is the one drawn on screen.
+    ///
+    /// # Arguments
+    ///
+    /// * ``x`` - The pixel value already normalized by the cuts. It is clamped to [0, 1]
+    pub fn apply(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+
+        match self {
+            TransferFunction::Linear => x,
+            TransferFunction::Sqrt => x.sqrt(),
+            TransferFunction::Log => (LOG_A * x + 1.0).ln() / LOG_A.ln(),
+            TransferFunction::Asinh(a) => (x / a).asinh() / (1.0 / a).asinh(),
+            TransferFunction::Pow2 => x * x,
+            TransferFunction::Gamma(gamma) => x.powf(*gamma),
+            TransferFunction::HistEq(lut) => {
+                // Linear interpolation between the two nearest entries
+                let p = x * ((HIST_EQ_LUT_SIZE - 1) as f32);
+                let i = (p.floor() as usize).min(HIST_EQ_LUT_SIZE - 1);
+                let j = (i + 1).min(HIST_EQ_LUT_SIZE - 1);
+
+                let t = p - (i as f32);
+                lut[i] * (1.0 - t) + lut[j] * t
+            }
+            TransferFunction::Reversed(h) => h.apply(1.0 - x),
+        }
+    }
+
+    /// Map a display value back to the normalized pixel domain
+    ///
+    /// Returns None if ``y`` is not reached by the function on [0, 1]
+    /// or if the function is not invertible (i.e. a null or negative gamma or softening).
+    /// For a histogram equalization table having flat parts, the smallest
+    /// antecedent is returned
+    pub fn apply_inverse(&self, y: f32) -> Option<f32> {
+        if let TransferFunction::Reversed(h) = self {
+            return h.apply_inverse(y).map(|x| 1.0 - x);
+        }
+
+        let (y_min, y_max) = (self.apply(0.0), self.apply(1.0));
+        if !(y_min..=y_max).contains(&y) {
+            return None;
+        }
+
+        let x = match self {
+            TransferFunction::Linear => y,
+            TransferFunction::Sqrt => y * y,
+            TransferFunction::Log => ((y * LOG_A.ln()).exp() - 1.0) / LOG_A,
+            TransferFunction::Asinh(a) => {
+                if *a <= 0.0 {
+                    return None;
+                }
+
+                (y * (1.0 / a).asinh()).sinh() * a
+            }
+            TransferFunction::Pow2 => y.sqrt(),
+            TransferFunction::Gamma(gamma) => {
+                if *gamma <= 0.0 {
+                    return None;
+                }
+
+                y.powf(1.0 / gamma)
+            }
+            TransferFunction::HistEq(lut) => {
+                // The table is non-decreasing, look for the first segment reaching y
+                let i = lut
+                    .windows(2)
+                    .position(|w| y <= w[1])
+                    .unwrap_or(HIST_EQ_LUT_SIZE - 2);
+                let (a, b) = (lut[i], lut[i + 1]);
+
+                let t = if b > a { (y - a) / (b - a) } else { 0.0 };
+                ((i as f32) + t) / ((HIST_EQ_LUT_SIZE - 1) as f32)
+            }
+            TransferFunction::Reversed(_) => unreachable!(),
+        };
+
+        Some(x.clamp(0.0, 1.0))
+    }
+}
+
+// Scale factor of the log transfer function
+const LOG_A: f32 = 1000.0;
+
+/// Softening of the asinh transfer function when not given
+pub const DEFAULT_ASINH_SOFTENING: f32 = 0.1;
+
+// Precision kept from the float payloads (gamma exponent, look-up table)
+// when comparing and hashing transfer functions
+const FLOAT_QUANTIZATION: f32 = 1e4;
+
+#[inline]
+fn quantize(v: f32) -> i32 {
+    (v * FLOAT_QUANTIZATION).round() as i32
+}
+
+impl TransferFunction {
+    // Key used for equality and hashing. Float payloads are quantized
+    // so that functions equal up to FLOAT_QUANTIZATION are the same key
+    fn key(&self) -> (i32, i32, bool) {
+        let payload = match self.base() {
+            TransferFunction::Gamma(gamma) => quantize(*gamma),
+            TransferFunction::Asinh(a) => quantize(*a),
+            _ => 0,
+        };
+
+        (self.shader_index(), payload, self.is_reversed())
+    }
+}
+
+impl PartialEq for TransferFunction {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (TransferFunction::Reversed(h1), TransferFunction::Reversed(h2)) => h1 == h2,
+            (TransferFunction::HistEq(lut1), TransferFunction::HistEq(lut2)) => lut1
+                .iter()
+                .zip(lut2.iter())
+                .all(|(v1, v2)| quantize(*v1) == quantize(*v2)),
+            _ => self.key() == other.key(),
+        }
+    }
+}
+
+impl Eq for TransferFunction {}
+
+impl std::hash::Hash for TransferFunction {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+
+        if let TransferFunction::HistEq(lut) = self.base() {
+            for v in lut.iter() {
+                quantize(*v).hash(state);
+            }
         }
     }
 }
@@ -181,18 +553,151 @@ impl From<String> for TransferFunction {
     }
 }
 
+impl From<TransferFunction> for String {
+    fn from(h: TransferFunction) -> Self {
+        match h {
+            TransferFunction::Gamma(gamma) => format!("{}:{}", h.id(), gamma),
+            TransferFunction::Asinh(a) => format!("{}:{}", h.id(), a),
+            TransferFunction::Reversed(h) => format!("{}_r", String::from(*h)),
+            _ => h.id().to_string(),
+        }
+    }
+}
+
+/// Black and white points of an image
+///
+/// The pixel values are mapped to [0, 1] before the transfer function
+/// is applied: x = clamp((pixel - low)/(high - low), 0, 1)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CutLevels {
+    pub low: f32,
+    pub high: f32,
+}
+
+impl CutLevels {
+    pub fn new(low: f32, high: f32) -> Self {
+        Self { low, high }
+    }
+
+    /// Compute the cuts from the percentiles of an histogram
+    ///
+    /// The bins are supposed to cover [0, 1] uniformly and the cuts returned
+    /// are the centers of the bins reaching the percentiles. An empty
+    /// histogram gives the [0, 1] cuts.
+    ///
+    /// # Arguments
+    ///
+    /// * ``hist`` - The number of pixels per bin
+    /// * ``plo`` - The percentile of the low cut, between [0, 100]
+    /// * ``phi`` - The percentile of the high cut, between [0, 100]
+    pub fn from_percentiles(hist: &[u32], plo: f64, phi: f64) -> Self {
+        let total = hist.iter().map(|c| *c as u64).sum::<u64>();
+        if total == 0 {
+            return Self::new(0.0, 1.0);
+        }
+
+        let percentile = |p: f64| -> f32 {
+            let target = (p.clamp(0.0, 100.0) / 100.0) * (total as f64);
+
+            let mut cumul = 0;
+            // Take the first bin containing pixels when the target is 0
+            let idx = hist
+                .iter()
+                .position(|c| {
+                    cumul += *c as u64;
+                    *c > 0 && (cumul as f64) >= target
+                })
+                .unwrap_or(hist.len() - 1);
+
+            ((idx as f32) + 0.5) / (hist.len() as f32)
+        };
+
+        Self::new(percentile(plo.min(phi)), percentile(phi.max(plo)))
+    }
+
+    /// Map a pixel value to [0, 1]
+    ///
+    /// When the cuts are equal, values below them give 0 and the others 1
+    pub fn normalize(&self, pixel: f32) -> f32 {
+        let d = self.high - self.low;
+        if d <= 0.0 {
+            if pixel < self.low {
+                0.0
+            } else {
+                1.0
+            }
+        } else {
+            ((pixel - self.low) / d).clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// Ticks of a colorbar drawn for an image
+///
+/// The ticks are evenly spaced along the colorbar, i.e. on the display values, and
+/// the pixel value they are labelled with is found by inverting the transfer function
+/// and the cuts. This way a log stretch gives log spaced values.
+///
+/// # Arguments
+///
+/// * ``cut`` - The cuts of the image
+/// * ``transfer`` - The transfer function applied after the cuts
+/// * ``n`` - The number of ticks. Both ends of the colorbar are ticked if it is at least 2
+///
+/// # Returns
+///
+/// The (position along the colorbar in [0, 1], pixel value) pairs. A function that
+/// cannot be inverted (e.g. a null gamma) gives no ticks
+pub fn colorbar_ticks(cut: CutLevels, transfer: &TransferFunction, n: usize) -> Vec<(f32, f32)> {
+    // The display range, a log or sqrt stretch starting at 0 as well.
+    // The bounds are not ordered for a reversed function
+    let (y0, y1) = (transfer.apply(0.0), transfer.apply(1.0));
+    let (y_min, y_max) = (y0.min(y1), y0.max(y1));
+
+    (0..n)
+        .filter_map(|i| {
+            let t = if n > 1 {
+                (i as f32) / ((n - 1) as f32)
+            } else {
+                0.5
+            };
+
+            // Rounding errors make the ends fall slightly out of the range
+            let y = y_min + t * (y_max - y_min);
+            let x = transfer.apply_inverse(y.clamp(y_min, y_max))?;
+
+            Some((t, cut.low + x * (cut.high - cut.low)))
+        })
+        .collect()
+}
+
 use crate::colormap::CmapLabel;
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct HiPSColor {
     // transfer function called before evaluating the colormap
     pub stretch: TransferFunction,
+    // transfer functions of the red, green and blue channels of color tiles.
+    // If not given, `stretch` is applied on the three channels
+    #[serde(default)]
+    pub channel_stretch: Option<[TransferFunction; 3]>,
     // low cut
     pub min_cut: Option<f32>,
     // high cut
     pub max_cut: Option<f32>,
-    // flag to tell the colormap is queried reversed
+    // flag to tell the colormap is queried reversed. A colormap name
+    // with the ``_r`` suffix is also queried reversed
+    #[serde(default)]
     pub reversed: bool,
+    // affine scaling of the pixel values, e.g. to normalize different
+    // exposure times. It is done before the cuts
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub offset: f32,
+    // draw the undefined pixels, i.e. NaN or equal to BLANK, transparent
+    #[serde(default = "default_blank_as_transparent")]
+    pub blank_as_transparent: bool,
     // the colormap
     pub cmap_name: CmapLabel,
     /// tonal color tuning factors
@@ -202,6 +707,57 @@ pub struct HiPSColor {
     pub k_brightness: f32,
 }
 
+impl HiPSColor {
+    /// The transfer functions applied on the red, green and blue channels
+    pub fn channel_stretches(&self) -> [&TransferFunction; 3] {
+        if let Some([r, g, b]) = &self.channel_stretch {
+            [r, g, b]
+        } else {
+            [&self.stretch, &self.stretch, &self.stretch]
+        }
+    }
+
+    /// Compute the value drawn on screen for a pixel value
+    ///
+    /// It mirrors what is done by the shaders: the pixel value is scaled, normalized
+    /// by the cuts and clamped to [0, 1] before being given to the transfer function.
+    /// Returns None for a NaN pixel, it has no value to draw
+    pub fn apply(&self, pixel: f32) -> Option<f32> {
+        if pixel.is_nan() {
+            return None;
+        }
+
+        let (min_cut, max_cut) = (self.min_cut.unwrap_or(0.0), self.max_cut.unwrap_or(1.0));
+        let v = self.scale * pixel + self.offset;
+
+        let range = max_cut - min_cut;
+        let x = if range == 0.0 {
+            // The values are thresholded at the cut
+            if v >= min_cut { 1.0 } else { 0.0 }
+        } else {
+            (v - min_cut) / range
+        };
+
+        Some(self.stretch.apply(x))
+    }
+
+    /// Tell whether a raw pixel value is drawn transparent
+    ///
+    /// # Arguments
+    ///
+    /// * ``raw`` - The pixel value as stored in the tile
+    /// * ``blank`` - The BLANK value of the tiles
+    pub fn is_masked(&self, raw: f32, blank: f32) -> bool {
+        self.blank_as_transparent && (raw.is_nan() || raw == blank)
+    }
+
+    /// The name of the colormap selected and whether it is sampled reversed
+    pub fn colormap(&self) -> (&str, bool) {
+        let (name, suffixed) = self.cmap_name.split_reversed();
+        (name, self.reversed || suffixed)
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 #[derive(Clone)]
@@ -214,6 +770,10 @@ pub struct ImageMetadata {
     // Blending config
     #[serde(default)]
     pub blend_cfg: BlendCfg,
+    // Blending mode, it takes precedence over the blending function if given
+    #[serde(default)]
+    #[wasm_bindgen(skip)]
+    pub blend_mode: Option<BlendMode>,
     #[serde(default = "default_opacity")]
     pub opacity: f32,
     #[serde(default = "default_longitude_reversed")]
@@ -229,6 +789,13 @@ fn default_longitude_reversed() -> bool {
 fn default_opacity() -> f32 {
     1.0
 }
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn default_blank_as_transparent() -> bool {
+    true
+}
 use crate::Abort;
 
 #[wasm_bindgen]
@@ -246,15 +813,21 @@ impl ImageMetadata {
 
         let HiPSColor {
             stretch,
+            channel_stretch,
             min_cut,
             max_cut,
-            reversed,
-            cmap_name,
             k_gamma,
             k_saturation,
             k_brightness,
             k_contrast,
+            scale,
+            offset,
+            blank_as_transparent,
+            ..
         } = &self.color;
+        // Both are given so that the color can be set back as it is
+        let (name, reversed) = self.color.colormap();
+        let cmap_name = CmapLabel::with_reversed(name, reversed);
 
         js_sys::Reflect::set(
             &js_color_obj,
@@ -262,6 +835,12 @@ impl ImageMetadata {
             &serde_wasm_bindgen::to_value(&stretch).unwrap_abort(),
         )
         .unwrap_abort();
+        js_sys::Reflect::set(
+            &js_color_obj,
+            &"channelStretch".into(),
+            &serde_wasm_bindgen::to_value(&channel_stretch).unwrap_abort(),
+        )
+        .unwrap_abort();
         js_sys::Reflect::set(
             &js_color_obj,
             &"minCut".into(),
@@ -298,10 +877,28 @@ impl ImageMetadata {
             &serde_wasm_bindgen::to_value(&k_contrast).unwrap_abort(),
         )
         .unwrap_abort();
+        js_sys::Reflect::set(
+            &js_color_obj,
+            &"scale".into(),
+            &serde_wasm_bindgen::to_value(&scale).unwrap_abort(),
+        )
+        .unwrap_abort();
+        js_sys::Reflect::set(
+            &js_color_obj,
+            &"offset".into(),
+            &serde_wasm_bindgen::to_value(&offset).unwrap_abort(),
+        )
+        .unwrap_abort();
+        js_sys::Reflect::set(
+            &js_color_obj,
+            &"blankAsTransparent".into(),
+            &JsValue::from_bool(*blank_as_transparent),
+        )
+        .unwrap_abort();
         js_sys::Reflect::set(
             &js_color_obj,
             &"reversed".into(),
-            &JsValue::from_bool(*reversed),
+            &JsValue::from_bool(reversed),
         )
         .unwrap_abort();
         js_sys::Reflect::set(
@@ -319,4 +916,297 @@ impl ImageMetadata {
     pub fn visible(&self) -> bool {
         self.opacity > 0.0
     }
+
+    /// The blending function the layer is drawn with
+    pub fn blend(&self) -> BlendCfg {
+        self.blend_mode.map(BlendCfg::from).unwrap_or(self.blend_cfg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TransferFunction, HIST_EQ_LUT_SIZE};
+
+    fn transfer_funcs() -> Vec<TransferFunction> {
+        vec![
+            TransferFunction::Linear,
+            TransferFunction::Sqrt,
+            TransferFunction::Log,
+            TransferFunction::new("asinh"),
+            TransferFunction::Pow2,
+            TransferFunction::Gamma(0.45),
+            TransferFunction::Gamma(2.2),
+            TransferFunction::new("histeq"),
+        ]
+    }
+
+    #[test]
+    fn apply_inverse_roundtrip() {
+        for h in &transfer_funcs() {
+            for i in 0..=20 {
+                let x = (i as f32) / 20.0;
+                let y = h.apply(x);
+
+                let x2 = h.apply_inverse(y).unwrap();
+                assert!((x2 - x).abs() < 1e-3, "{:?}: {} != {}", h, x, x2);
+            }
+        }
+    }
+
+    #[test]
+    fn hash_eq() {
+        use std::collections::HashSet;
+
+        let funcs = transfer_funcs();
+        let set: HashSet<_> = funcs.iter().cloned().collect();
+        assert_eq!(set.len(), funcs.len());
+
+        // exponents are quantized
+        assert_eq!(
+            TransferFunction::Gamma(0.45),
+            TransferFunction::Gamma(0.450001)
+        );
+        assert_ne!(TransferFunction::Gamma(1.0), TransferFunction::Linear);
+    }
+
+    #[test]
+    fn id_roundtrip() {
+        // The table of a histogram equalization is not part of its id
+        for h in transfer_funcs().iter().filter(|h| h.hist_eq_lut().is_none()) {
+            if !matches!(h, TransferFunction::Gamma(_)) {
+                assert_eq!(TransferFunction::new(h.id()), *h);
+            }
+
+            assert_eq!(TransferFunction::new(&String::from(h.clone())), *h);
+        }
+
+        // first-match priority
+        assert_eq!(TransferFunction::new("loglinear"), TransferFunction::Linear);
+        assert_eq!(TransferFunction::new("gamma"), TransferFunction::Gamma(1.0));
+
+        let mut lut = [0.0; HIST_EQ_LUT_SIZE];
+        lut[HIST_EQ_LUT_SIZE / 2..].fill(1.0);
+        let h = TransferFunction::hist_eq(&lut).unwrap();
+        assert_eq!(String::from(h.clone()), "histeq");
+        assert_ne!(TransferFunction::new("histeq"), h);
+    }
+
+    #[test]
+    fn try_from_id() {
+        for h in transfer_funcs().iter().filter(|h| h.hist_eq_lut().is_none()) {
+            let id = String::from(h.clone());
+            assert_eq!(TransferFunction::try_from_id(&id), Ok(h.clone()));
+        }
+        assert_eq!(
+            TransferFunction::try_from_id("sqrt_r"),
+            Ok(TransferFunction::Sqrt.reversed())
+        );
+        assert_eq!(
+            TransferFunction::try_from_id("gamma:2.2"),
+            Ok(TransferFunction::Gamma(2.2))
+        );
+
+        // typos are not resolved to another function
+        for id in ["sqrt2", "liner", "loglinear", ""] {
+            let err = TransferFunction::try_from_id(id).unwrap_err();
+            assert!(err.contains("linear, sqrt, log, asinh, pow2, gamma, histeq"), "{}", err);
+        }
+        assert!(TransferFunction::try_from_id("gamma:abc").is_err());
+        assert!(TransferFunction::try_from_id("asinh:-1").is_err());
+        assert!(TransferFunction::try_from_id("sqrt:2").is_err());
+    }
+
+    #[test]
+    fn hist_eq() {
+        // sqrt-like table
+        let mut lut = [0.0; HIST_EQ_LUT_SIZE];
+        for (i, v) in lut.iter_mut().enumerate() {
+            *v = ((i as f32) / ((HIST_EQ_LUT_SIZE - 1) as f32)).sqrt();
+        }
+        let h = TransferFunction::hist_eq(&lut).unwrap();
+
+        // entries are exactly reached
+        assert_eq!(h.apply(1.0), 1.0);
+        assert!((h.apply(0.25) - 0.5).abs() < 1e-2);
+
+        for i in 0..=20 {
+            let x = (i as f32) / 20.0;
+            let x2 = h.apply_inverse(h.apply(x)).unwrap();
+            assert!((x2 - x).abs() < 1e-3, "{} != {}", x, x2);
+        }
+
+        // not monotonic
+        lut[10] = 1.0;
+        assert!(TransferFunction::hist_eq(&lut).is_err());
+
+        assert!((TransferFunction::new("histeq").apply(0.3) - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn reversed() {
+        for h in &transfer_funcs() {
+            let r = h.clone().reversed();
+
+            for i in 0..=20 {
+                let x = (i as f32) / 20.0;
+                assert_eq!(r.apply(x), h.apply(1.0 - x));
+
+                let x2 = r.apply_inverse(r.apply(x)).unwrap();
+                assert!((x2 - x).abs() < 1e-3, "{:?}: {} != {}", r, x, x2);
+            }
+
+            assert_ne!(r, *h);
+            assert_eq!(r.clone().reversed(), *h);
+            assert_eq!(TransferFunction::new(&String::from(r.clone())), r);
+        }
+
+        assert_eq!(
+            TransferFunction::new("sqrt_r"),
+            TransferFunction::Sqrt.reversed()
+        );
+        assert_eq!(
+            TransferFunction::new("log-"),
+            TransferFunction::Log.reversed()
+        );
+    }
+
+    #[test]
+    fn asinh_softening() {
+        let soft = TransferFunction::new("asinh:1.0");
+        let hard = TransferFunction::new("asinh:0.01");
+        assert_eq!(soft.asinh_softening(), 1.0);
+
+        // A smaller softening brings out the faint values
+        assert!(hard.apply(0.1) - soft.apply(0.1) > 0.2);
+        assert_eq!(soft.apply(1.0), 1.0);
+        assert_eq!(hard.apply(1.0), 1.0);
+
+        // The default one matches the former asinh(10*x)/3 curve
+        let h = TransferFunction::new("asinh");
+        for i in 0..=20 {
+            let x = (i as f32) / 20.0;
+            assert!((h.apply(x) - (10.0 * x).asinh() / 3.0).abs() < 1e-3);
+        }
+
+        // Non positive softenings are not parsed
+        assert_eq!(TransferFunction::new("asinh:-1"), h);
+    }
+
+    #[test]
+    fn cut_levels_from_percentiles() {
+        use super::CutLevels;
+
+        // uniform histogram
+        let cuts = CutLevels::from_percentiles(&[10; 100], 5.0, 95.0);
+        assert!((cuts.low - 0.045).abs() < 1e-6);
+        assert!((cuts.high - 0.945).abs() < 1e-6);
+        assert!((cuts.normalize(0.495) - 0.5).abs() < 1e-6);
+
+        // every pixel has the same value
+        let mut hist = [0; 100];
+        hist[42] = 1000;
+        let cuts = CutLevels::from_percentiles(&hist, 1.0, 99.0);
+        assert_eq!(cuts.low, cuts.high);
+        assert_eq!(cuts.normalize(0.1), 0.0);
+        assert_eq!(cuts.normalize(cuts.low), 1.0);
+        assert!(cuts.normalize(0.9).is_finite());
+
+        assert_eq!(
+            CutLevels::from_percentiles(&[0; 10], 1.0, 99.0),
+            CutLevels::new(0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn colorbar_ticks_spacing() {
+        use super::{colorbar_ticks, CutLevels};
+
+        // Evenly spaced values for a linear stretch
+        let ticks = colorbar_ticks(CutLevels::new(10.0, 110.0), &TransferFunction::Linear, 5);
+        assert_eq!(ticks.len(), 5);
+        for (i, (t, v)) in ticks.iter().enumerate() {
+            assert!((t - (i as f32) * 0.25).abs() < 1e-6);
+            assert!((v - (10.0 + (i as f32) * 25.0)).abs() < 1e-3);
+        }
+
+        // Log spaced values for a log stretch, the cuts including 0
+        let ticks = colorbar_ticks(CutLevels::new(0.0, 1.0), &TransferFunction::Log, 4);
+        assert_eq!(ticks.len(), 4);
+        assert!(ticks[0].1.abs() < 1e-6);
+        assert!((ticks[3].1 - 1.0).abs() < 1e-3);
+        // x + 1/a is geometric along the colorbar
+        let shifted = ticks.iter().map(|(_, v)| v + 1.0 / 1000.0).collect::<Vec<_>>();
+        for w in shifted.windows(2) {
+            assert!((w[1] / w[0] - 10.0).abs() < 1e-1);
+        }
+
+        assert!(colorbar_ticks(CutLevels::new(0.0, 1.0), &TransferFunction::Gamma(0.0), 4).is_empty());
+    }
+
+    #[test]
+    fn new_rgb() {
+        let [r, g, b] = TransferFunction::new_rgb("sqrt", "gamma:2.2", "histeq");
+
+        assert_eq!(r.shader_index(), TransferFunction::Sqrt.shader_index());
+        assert!(matches!(g, TransferFunction::Gamma(gamma) if gamma == 2.2));
+        assert!(matches!(b, TransferFunction::HistEq(_)));
+    }
+
+    #[test]
+    fn apply_inverse_out_of_range() {
+        for h in &transfer_funcs() {
+            assert_eq!(h.apply_inverse(h.apply(1.0) + 0.1), None);
+            assert_eq!(h.apply_inverse(-0.1), None);
+        }
+
+        assert_eq!(TransferFunction::Gamma(0.0).apply_inverse(1.0), None);
+    }
+
+    #[test]
+    fn pixel_scaling_is_done_before_clamping() {
+        use super::HiPSColor;
+        use crate::colormap::CmapLabel;
+
+        let color = HiPSColor {
+            stretch: TransferFunction::Linear,
+            channel_stretch: None,
+            min_cut: Some(0.0),
+            max_cut: Some(1.0),
+            reversed: false,
+            scale: 1.0,
+            offset: 0.0,
+            blank_as_transparent: true,
+            cmap_name: CmapLabel::new("grayscale"),
+            k_gamma: 1.0,
+            k_saturation: 0.0,
+            k_contrast: 0.0,
+            k_brightness: 0.0,
+        };
+        // the default scaling does nothing
+        assert_eq!(color.apply(0.25), Some(0.25));
+
+        let color = HiPSColor { scale: 2.0, ..color };
+        assert_eq!(color.apply(0.25), Some(0.5));
+        // the scaled value is still capped
+        assert_eq!(color.apply(0.75), Some(1.0));
+
+        let color = HiPSColor { offset: -0.5, ..color };
+        assert_eq!(color.apply(0.5), Some(0.5));
+        assert_eq!(color.apply(0.1), Some(0.0));
+
+        // Undefined pixels
+        assert_eq!(color.apply(f32::NAN), None);
+        assert!(color.is_masked(-32768.0, -32768.0));
+        assert!(color.is_masked(f32::NAN, -32768.0));
+        assert!(!color.is_masked(0.0, -32768.0));
+
+        let color = HiPSColor { blank_as_transparent: false, ..color };
+        assert!(!color.is_masked(-32768.0, -32768.0));
+
+        // Equal cuts
+        let color = HiPSColor { offset: 0.0, min_cut: Some(0.5), max_cut: Some(0.5), ..color };
+        assert_eq!(color.apply(0.25), Some(1.0));
+        assert_eq!(color.apply(0.2), Some(0.0));
+        assert_eq!(color.apply(0.3), Some(1.0));
+    }
 }
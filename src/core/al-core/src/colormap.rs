@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
 
 use colorgrad::Color;
@@ -9,6 +10,7 @@ use crate::shader::SendUniformsWithParams;
 
 use wasm_bindgen::JsValue;
 use crate::webgl_ctx::WebGlRenderingCtx;
+use al_api::hips::HIST_EQ_LUT_SIZE;
 
 const WIDTH_CMAP_TEX: usize = 256;
 
@@ -23,23 +25,85 @@ impl Colormap {
         Self { label: label.to_string(), grad }
     }
 
-    pub fn label(&self) -> &Label {
-        &self.label
+    /// Build a colormap from color stops
+    ///
+    /// The colors are linearly interpolated between the stops. The stops do not
+    /// have to be sorted, their positions are clamped to [0, 1] and the first
+    /// and last colors are extended to 0 and 1. For stops sharing the same position,
+    /// the last one given is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * ``label`` - The name of the colormap
+    /// * ``stops`` - The positions in [0, 1] of the stops and their RGB colors
+    pub fn from_rgb(label: &str, stops: &[(f32, [u8; 3])]) -> Result<Self, JsValue> {
+        let mut stops = stops
+            .iter()
+            .map(|(t, rgb)| (t.clamp(0.0, 1.0) as f64, *rgb))
+            .collect::<Vec<_>>();
+        // Stable, so that the order of the stops sharing a position is kept
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut positions: Vec<f64> = vec![];
+        let mut colors: Vec<Color> = vec![];
+        for (t, [r, g, b]) in stops {
+            let color = Color::from_rgba8(r, g, b, 255);
+            if positions.last() == Some(&t) {
+                *colors.last_mut().unwrap_abort() = color;
+            } else {
+                positions.push(t);
+                colors.push(color);
+            }
+        }
+
+        match (positions.first().copied(), colors.first().cloned()) {
+            (Some(t), Some(first)) if t > 0.0 => {
+                positions.insert(0, 0.0);
+                colors.insert(0, first);
+            }
+            (None, _) => return Err(JsValue::from_str("A colormap needs at least one stop")),
+            _ => (),
+        }
+        if let (Some(t), Some(last)) = (positions.last().copied(), colors.last().cloned()) {
+            if t < 1.0 {
+                positions.push(1.0);
+                colors.push(last);
+            }
+        }
+
+        let grad = colorgrad::CustomGradient::new()
+            .colors(&colors)
+            .domain(&positions)
+            .build()
+            .map_err(|err| JsValue::from_str(&format!("{:?}", err)))?;
+
+        Ok(Self::new(label, grad))
+    }
+
+    /// Sample the colormap the way the shaders do, i.e. at the nearest texel
+    /// of the colormap texture. A reversed colormap is sampled at ``1 - t``
+    pub fn color_at(&self, t: f32, reversed: bool) -> [u8; 4] {
+        let t = if reversed { 1.0 - t } else { t };
+        let ix = ((t * WIDTH_CMAP_TEX as f32) as usize).min(WIDTH_CMAP_TEX - 1);
+
+        self.grad.at(ix as f64 / WIDTH_CMAP_TEX as f64).to_rgba8()
+    }
+
+    // The RGBA texels of the colormap texture row
+    fn texels(&self) -> [u8; 4 * WIDTH_CMAP_TEX] {
+        let mut values = [0_u8; 4 * WIDTH_CMAP_TEX];
+        for ix in 0..WIDTH_CMAP_TEX {
+            let rgba = self.grad.at(ix as f64 / WIDTH_CMAP_TEX as f64).to_rgba8();
+            values[4 * ix..4 * (ix + 1)].copy_from_slice(&rgba);
+        }
+
+        values
     }
 }
 
 fn build_cmaps_texture(gl: &WebGlContext, cmaps: &[Colormap]) -> Result<Texture2D, JsValue> {
     let tex_bytes: Vec<u8> = cmaps.iter()
-        .map(|cmap| {
-            let mut values = [0_u8; 1024];
-            for ix in 0..WIDTH_CMAP_TEX {
-                let rgba = cmap.grad.at(ix as f64 / WIDTH_CMAP_TEX as f64).to_rgba8();
-                let ptr = values[4*ix..].as_mut_ptr() as *mut [u8; 4];
-                unsafe { *ptr = rgba; }
-            }
-
-            values
-        })
+        .map(|cmap| cmap.texels())
         .flatten()
         .collect();
     let tex_params = &[
@@ -72,11 +136,48 @@ fn build_cmaps_texture(gl: &WebGlContext, cmaps: &[Colormap]) -> Result<Texture2
     )
 }
 
+fn build_hist_eq_texture(gl: &WebGlContext) -> Result<Texture2D, JsValue> {
+    // The look-up table is interpolated by the shader, R32F textures
+    // not being filterable
+    const TEX_PARAMS: &[(u32, u32)] = &[
+        (
+            WebGlRenderingCtx::TEXTURE_MIN_FILTER,
+            WebGlRenderingCtx::NEAREST,
+        ),
+        (
+            WebGlRenderingCtx::TEXTURE_MAG_FILTER,
+            WebGlRenderingCtx::NEAREST,
+        ),
+        (
+            WebGlRenderingCtx::TEXTURE_WRAP_S,
+            WebGlRenderingCtx::CLAMP_TO_EDGE,
+        ),
+        (
+            WebGlRenderingCtx::TEXTURE_WRAP_T,
+            WebGlRenderingCtx::CLAMP_TO_EDGE,
+        ),
+    ];
+
+    Texture2D::create_from_raw_pixels::<format::R32F>(
+        gl,
+        HIST_EQ_LUT_SIZE as i32,
+        1,
+        TEX_PARAMS,
+        None
+    )
+}
+
 pub struct Colormaps {
     cmaps: Vec<Colormap>,
     indices: HashMap<Label, i32>,
 
     cmaps_tex: Texture2D,
+    // Storage of the histogram equalization table of the
+    // transfer function being drawn
+    hist_eq_tex: Texture2D,
+    // Table currently stored in the texture, the texture is only
+    // filled again when a different one is drawn
+    hist_eq_lut: RefCell<Vec<f32>>,
 
     labels: Vec<Label>,
 
@@ -201,9 +302,12 @@ impl Colormaps {
         ];
 
         let cmaps_tex = build_cmaps_texture(gl, &cmaps[..])?;
+        let hist_eq_tex = build_hist_eq_texture(gl)?;
+
+        let hist_eq_lut = RefCell::new(vec![]);
 
         let gl = gl.clone();
-        Ok(Self { cmaps, cmaps_tex, labels, indices, gl })
+        Ok(Self { cmaps, cmaps_tex, hist_eq_tex, hist_eq_lut, labels, indices, gl })
     }
 
     #[inline]
@@ -222,6 +326,16 @@ impl Colormaps {
         }
     }
 
+    #[inline]
+    pub fn get_hist_eq_tex(&self) -> &Texture2D {
+        &self.hist_eq_tex
+    }
+
+    #[inline]
+    pub fn get_hist_eq_lut(&self) -> &RefCell<Vec<f32>> {
+        &self.hist_eq_lut
+    }
+
     #[inline]
     pub fn get_id(&self, label: &str) -> Option<&i32> {
         self.indices.get(label)
@@ -264,3 +378,55 @@ impl SendUniformsWithParams<Colormaps> for Colormap {
         shader
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Colormap, WIDTH_CMAP_TEX};
+
+    #[test]
+    fn two_stops_give_a_linear_ramp() {
+        let cmap = Colormap::from_rgb("ramp", &[(1.0, [255, 255, 255]), (0.0, [0, 0, 0])]).unwrap();
+        let texels = cmap.texels();
+
+        for (ix, rgba) in texels.chunks_exact(4).enumerate() {
+            let expected = 255.0 * (ix as f64) / (WIDTH_CMAP_TEX as f64);
+            assert!(((rgba[0] as f64) - expected).abs() <= 1.0);
+            assert_eq!(rgba[0], rgba[1]);
+            assert_eq!(rgba[0], rgba[2]);
+            assert_eq!(rgba[3], 255);
+        }
+    }
+
+    #[test]
+    fn stops_extended_and_deduplicated() {
+        // Not starting at 0, a duplicated position and not ending at 1
+        let cmap = Colormap::from_rgb(
+            "custom",
+            &[(0.25, [255, 0, 0]), (0.5, [0, 255, 0]), (0.5, [0, 0, 255]), (0.75, [0, 0, 255])],
+        )
+        .unwrap();
+        let texels = cmap.texels();
+
+        assert_eq!(&texels[0..4], &[255, 0, 0, 255]);
+        assert_eq!(&texels[4 * 255..], &[0, 0, 255, 255]);
+        // The last color given at 0.5 is kept
+        assert_eq!(&texels[4 * 160..4 * 161], &[0, 0, 255, 255]);
+
+        assert!(Colormap::from_rgb("empty", &[]).is_err());
+    }
+
+    #[test]
+    fn reversed_sampling_mirrors_the_texels() {
+        let cmap = Colormap::new("viridis", colorgrad::viridis());
+        let texels = cmap.texels();
+
+        for ix in 0..WIDTH_CMAP_TEX {
+            // center of the texel
+            let t = (ix as f32 + 0.5) / WIDTH_CMAP_TEX as f32;
+            let mirror = WIDTH_CMAP_TEX - 1 - ix;
+
+            assert_eq!(&cmap.color_at(t, false), &texels[4 * ix..4 * (ix + 1)]);
+            assert_eq!(&cmap.color_at(t, true), &texels[4 * mirror..4 * (mirror + 1)]);
+        }
+    }
+}
@@ -6,6 +6,13 @@ pub struct Fits<'a> {
     size: Vector2<i32>,
 
     pub data: Data<'a>,
+
+    // The physical values are given by bscale * raw + bzero. The scaling
+    // is applied by the shaders sampling the raw values of the tiles
+    pub bscale: f32,
+    pub bzero: f32,
+    // Raw value of the undefined pixels
+    pub blank: Option<f32>,
 }
 
 use std::borrow::Cow;
@@ -35,6 +42,17 @@ impl<'a> Fits<'a> {
             .get_naxisn(2)
             .ok_or_else(|| JsValue::from_str("NAXIS2 not found in the fits"))?;
 
+        let card_value = |keyword: &[u8; 8]| {
+            if let Some(fitsrs::card::Value::Float(v)) = header.get(keyword) {
+                Some(*v as f32)
+            } else {
+                None
+            }
+        };
+        let bscale = card_value(b"BSCALE  ").unwrap_or(1.0);
+        let bzero = card_value(b"BZERO   ").unwrap_or(0.0);
+        let blank = card_value(b"BLANK   ");
+
         let data = hdu.get_data();
         let data = match *data {
             InMemData::U8(slice) => Data::U8(Cow::Borrowed(slice)),
@@ -57,6 +75,10 @@ impl<'a> Fits<'a> {
 
             // Allocation info of the layout
             data,
+
+            bscale,
+            bzero,
+            blank,
         })
     }
 
@@ -212,3 +234,70 @@ impl FitsImageFormat for R16I {
 impl FitsImageFormat for R8UI {
     const BITPIX: i8 = 8;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Data, Fits};
+    use std::io::Cursor;
+
+    // Build a 2D FITS file from its header cards and its big endian data
+    fn fits_bytes(cards: &[&str], data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        for card in cards.iter().chain(std::iter::once(&"END")) {
+            bytes.extend(format!("{:<80}", card).into_bytes());
+        }
+        bytes.resize(((bytes.len() + 2879) / 2880) * 2880, b' ');
+
+        let start = bytes.len();
+        bytes.extend(data);
+        bytes.resize(start + ((data.len() + 2879) / 2880) * 2880, 0);
+
+        bytes
+    }
+
+    fn i16_tile(values: &[i16], extra_cards: &[&str]) -> Vec<u8> {
+        let mut cards = vec![
+            "SIMPLE  =                    T",
+            "BITPIX  =                   16",
+            "NAXIS   =                    2",
+            "NAXIS1  =                    2",
+            "NAXIS2  =                    2",
+        ];
+        cards.extend(extra_cards);
+
+        let data = values
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect::<Vec<_>>();
+        fits_bytes(&cards, &data)
+    }
+
+    #[test]
+    fn decode_16_bits_tile_with_scaling() {
+        let bytes = i16_tile(
+            &[-100, 0, 1, 1000],
+            &["BSCALE  =                  0.5", "BZERO   =                 10.0"],
+        );
+        let mut reader = Cursor::new(&bytes[..]);
+        let fits = Fits::from_byte_slice(&mut reader).unwrap();
+
+        assert_eq!(fits.get_size().x, 2);
+        assert_eq!((fits.bscale, fits.bzero, fits.blank), (0.5, 10.0, None));
+        match &fits.data {
+            Data::I16(v) => assert_eq!(v.as_ref(), &[-100, 0, 1, 1000]),
+            _ => panic!("the raw values of a 16 bits tile are kept"),
+        }
+    }
+
+    #[test]
+    fn blank_value_is_read() {
+        let bytes = i16_tile(
+            &[-32768, 5, -32768, 7],
+            &["BZERO   =                  1.0", "BLANK   =               -32768"],
+        );
+        let mut reader = Cursor::new(&bytes[..]);
+        let fits = Fits::from_byte_slice(&mut reader).unwrap();
+
+        assert_eq!((fits.bscale, fits.bzero, fits.blank), (1.0, 1.0, Some(-32768.0)));
+    }
+}
@@ -124,8 +124,72 @@ impl FrameBufferObject {
     }
 }
 
+impl FrameBufferObject {
+    /// Read back the RGBA pixels of the framebuffer
+    ///
+    /// The rows are given from the top to the bottom of the image
+    pub fn read_pixels(&self) -> Result<Vec<u8>, JsValue> {
+        let w = self.texture.width() as usize;
+        let h = self.texture.height() as usize;
+        let mut pixels = vec![0; rgba_buffer_len(w, h)];
+
+        self.gl
+            .bind_framebuffer(WebGlRenderingCtx::FRAMEBUFFER, Some(&self.fbo));
+        let res = self.gl.read_pixels_with_opt_u8_array(
+            0,
+            0,
+            w as i32,
+            h as i32,
+            WebGlRenderingCtx::RGBA,
+            WebGlRenderingCtx::UNSIGNED_BYTE,
+            Some(&mut pixels),
+        );
+        self.gl
+            .bind_framebuffer(WebGlRenderingCtx::FRAMEBUFFER, None);
+        res?;
+
+        // WebGL reads the rows from the bottom of the framebuffer
+        flip_rows(&mut pixels, w, h);
+
+        Ok(pixels)
+    }
+}
+
+/// Number of bytes of a RGBA8 image
+#[inline]
+pub fn rgba_buffer_len(width: usize, height: usize) -> usize {
+    4 * width * height
+}
+
+fn flip_rows(pixels: &mut [u8], width: usize, height: usize) {
+    let row_len = 4 * width;
+    for i in 0..(height / 2) {
+        let (top, bottom) = pixels.split_at_mut((height - 1 - i) * row_len);
+        top[i * row_len..(i + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+    }
+}
+
 impl Drop for FrameBufferObject {
     fn drop(&mut self) {
         self.gl.delete_framebuffer(Some(&self.fbo));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{flip_rows, rgba_buffer_len};
+
+    #[test]
+    fn rgba_buffer_of_an_offscreen_render() {
+        let (w, h) = (3, 5);
+        let mut pixels = (0..rgba_buffer_len(w, h))
+            .map(|i| (i / (4 * w)) as u8)
+            .collect::<Vec<_>>();
+        assert_eq!(pixels.len(), 60);
+
+        flip_rows(&mut pixels, w, h);
+        for (i, row) in pixels.chunks_exact(4 * w).enumerate() {
+            assert!(row.iter().all(|p| *p as usize == h - 1 - i));
+        }
+    }
+}
@@ -235,6 +235,7 @@ impl UniformType for Matrix4<f64> {
 }
 use super::texture::Texture2D;
 use super::texture::CUR_IDX_TEX_UNIT;
+use std::cell::RefCell;
 impl UniformType for Texture2D {
     fn uniform(gl: &WebGlContext, location: Option<&WebGlUniformLocation>, tex: &Self) {
         unsafe {
@@ -277,7 +278,11 @@ impl<'a> UniformType for &'a ColorRGBA {
 use al_api::hips::TransferFunction;
 impl SendUniforms for TransferFunction {
     fn attach_uniforms<'a>(&self, shader: &'a ShaderBound<'a>) -> &'a ShaderBound<'a> {
-        shader.attach_uniform("H", self);
+        shader
+            .attach_uniform("H", self)
+            .attach_uniform("gamma_exp", &self.gamma_exp())
+            .attach_uniform("asinh_softening", &self.asinh_softening())
+            .attach_uniform("H_reversed", &(self.is_reversed() as u8 as f32));
 
         shader
     }
@@ -285,7 +290,126 @@ impl SendUniforms for TransferFunction {
 
 impl UniformType for TransferFunction {
     fn uniform(gl: &WebGlContext, location: Option<&WebGlUniformLocation>, value: &Self) {
-        gl.uniform1i(location, *value as i32);
+        gl.uniform1i(location, value.shader_index());
+    }
+}
+
+// The histogram equalization table texture. It is bound whatever the
+// transfer function is so that the sampler never points to the unit of
+// a texture of another kind. The table is only uploaded when given and
+// different from the one the texture already stores.
+struct HistEqLut<'a> {
+    tex: &'a Texture2D,
+    stored: &'a RefCell<Vec<f32>>,
+    lut: Option<&'a [f32]>,
+}
+
+impl<'a> UniformType for HistEqLut<'a> {
+    fn uniform(gl: &WebGlContext, location: Option<&WebGlUniformLocation>, value: &Self) {
+        unsafe {
+            let tex = value
+                .tex
+                // 1. Active the texture unit of the texture so that
+                // the textures already bound are not replaced
+                .active_texture(CUR_IDX_TEX_UNIT)
+                // 2. Bind it
+                .bind();
+
+            // 3. Fill it with the table
+            if let Some(lut) = value.lut.filter(|lut| &value.stored.borrow()[..] != *lut) {
+                let buf = js_sys::Float32Array::view(lut);
+                tex.tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_array_buffer_view(
+                    0,
+                    0,
+                    lut.len() as i32,
+                    1,
+                    Some(buf.as_ref()),
+                );
+
+                *value.stored.borrow_mut() = lut.to_vec();
+            }
+
+            gl.uniform1i(location, CUR_IDX_TEX_UNIT as i32);
+            CUR_IDX_TEX_UNIT += 1;
+        };
+    }
+}
+
+impl SendUniformsWithParams<Colormaps> for TransferFunction {
+    fn attach_uniforms_with_params<'a>(
+        &self,
+        shader: &'a ShaderBound<'a>,
+        cmaps: &Colormaps,
+    ) -> &'a ShaderBound<'a> {
+        shader.attach_uniforms_from(self);
+        attach_hist_eq_lut(shader, cmaps, hist_eq_lut(self))
+    }
+}
+
+fn hist_eq_lut(h: &TransferFunction) -> Option<&[f32]> {
+    if let TransferFunction::HistEq(lut) = h.base() {
+        Some(&lut[..])
+    } else {
+        None
+    }
+}
+
+fn attach_hist_eq_lut<'a>(
+    shader: &'a ShaderBound<'a>,
+    cmaps: &Colormaps,
+    lut: Option<&[f32]>,
+) -> &'a ShaderBound<'a> {
+    shader.attach_uniform(
+        "hist_eq_lut",
+        &HistEqLut {
+            tex: cmaps.get_hist_eq_tex(),
+            stored: cmaps.get_hist_eq_lut(),
+            lut,
+        },
+    )
+}
+
+use al_api::hips::CutLevels;
+impl SendUniforms for CutLevels {
+    fn attach_uniforms<'a>(&self, shader: &'a ShaderBound<'a>) -> &'a ShaderBound<'a> {
+        // Keep the high cut strictly above the low one so that the shader
+        // never divides by zero
+        let high = self.high.max(self.low + f32::EPSILON * self.low.abs().max(1.0));
+
+        shader
+            .attach_uniform("cut_low", &self.low)
+            .attach_uniform("cut_high", &high);
+
+        shader
+    }
+}
+
+// Send the transfer functions of the red, green and blue channels
+fn attach_channel_uniforms<'a>(
+    shader: &'a ShaderBound<'a>,
+    channels: [&TransferFunction; 3],
+) -> &'a ShaderBound<'a> {
+    let [r, g, b] = channels;
+
+    shader
+        .attach_uniform("H_r", r)
+        .attach_uniform("H_g", g)
+        .attach_uniform("H_b", b)
+        .attach_uniform("gamma_exp_r", &r.gamma_exp())
+        .attach_uniform("gamma_exp_g", &g.gamma_exp())
+        .attach_uniform("gamma_exp_b", &b.gamma_exp())
+        .attach_uniform("asinh_softening_r", &r.asinh_softening())
+        .attach_uniform("asinh_softening_g", &g.asinh_softening())
+        .attach_uniform("asinh_softening_b", &b.asinh_softening())
+        .attach_uniform("H_r_reversed", &(r.is_reversed() as u8 as f32))
+        .attach_uniform("H_g_reversed", &(g.is_reversed() as u8 as f32))
+        .attach_uniform("H_b_reversed", &(b.is_reversed() as u8 as f32))
+}
+
+impl SendUniforms for [TransferFunction; 3] {
+    fn attach_uniforms<'a>(&self, shader: &'a ShaderBound<'a>) -> &'a ShaderBound<'a> {
+        let [r, g, b] = self;
+        attach_channel_uniforms(shader, [r, g, b])
     }
 }
 
@@ -304,12 +428,16 @@ impl SendUniforms for ImageMetadata {
 
 impl SendUniforms for HiPSColor {
     fn attach_uniforms<'a>(&self, shader: &'a ShaderBound<'a>) -> &'a ShaderBound<'a> {
-        let reversed = self.reversed as u8 as f32;
+        let reversed = self.colormap().1 as u8 as f32;
 
+        attach_channel_uniforms(shader, self.channel_stretches());
         shader
-            .attach_uniform("H", &self.stretch)
+            .attach_uniforms_from(&self.stretch)
             .attach_uniform("min_value", &self.min_cut.unwrap_or(0.0))
             .attach_uniform("max_value", &self.max_cut.unwrap_or(1.0))
+            .attach_uniform("pixel_scale", &self.scale)
+            .attach_uniform("pixel_offset", &self.offset)
+            .attach_uniform("blank_as_transparent", &self.blank_as_transparent)
             .attach_uniform("k_gamma", &self.k_gamma)
             .attach_uniform("k_saturation", &self.k_saturation)
             .attach_uniform("k_brightness", &self.k_brightness)
@@ -326,14 +454,27 @@ impl SendUniformsWithParams<Colormaps> for HiPSColor {
         shader: &'a ShaderBound<'a>,
         cmaps: &Colormaps,
     ) -> &'a ShaderBound<'a> {
-        let reversed = self.reversed as u8 as f32;
-
-        let cmap = cmaps.get(&self.cmap_name.as_ref());
+        let (cmap_name, reversed) = self.colormap();
+        let reversed = reversed as u8 as f32;
+
+        let channels = self.channel_stretches();
+        // There is only one histogram equalization table, it is the one of
+        // the first function defining it
+        let lut = std::iter::once(&self.stretch)
+            .chain(channels.iter().copied())
+            .find_map(hist_eq_lut);
+
+        let cmap = cmaps.get(cmap_name);
+        attach_channel_uniforms(shader, channels);
+        attach_hist_eq_lut(shader, cmaps, lut);
         shader
             .attach_uniforms_with_params_from(cmap, cmaps)
-            .attach_uniform("H", &self.stretch)
+            .attach_uniforms_from(&self.stretch)
             .attach_uniform("min_value", &self.min_cut.unwrap_or(0.0))
             .attach_uniform("max_value", &self.max_cut.unwrap_or(1.0))
+            .attach_uniform("pixel_scale", &self.scale)
+            .attach_uniform("pixel_offset", &self.offset)
+            .attach_uniform("blank_as_transparent", &self.blank_as_transparent)
             .attach_uniform("k_gamma", &self.k_gamma)
             .attach_uniform("k_saturation", &self.k_saturation)
             .attach_uniform("k_brightness", &self.k_brightness)
@@ -154,7 +154,14 @@ impl GlWrapper for BlendCfg {
 
         f()?;
 
+        // Restore the default blending state
         gl.blend_equation(blend_func_f(&BlendFunc::FuncAdd));
+        gl.blend_func_separate(
+            WebGlRenderingCtx::SRC_ALPHA,
+            WebGlRenderingCtx::ONE,
+            WebGlRenderingCtx::ONE,
+            WebGlRenderingCtx::ONE,
+        );
 
         Ok(())
     }
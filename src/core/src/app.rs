@@ -1,3 +1,6 @@
+use crate::camera::history::ViewSnapshot;
+use crate::state::ViewerState;
+use crate::renderable::blink::{Blink, BlinkMode};
 use crate::renderable::ImageLayer;
 use crate::{
     //async_task::{BuildCatalogIndex, ParseTableTask, TaskExecutor, TaskResult, TaskType},
@@ -11,8 +14,13 @@ use crate::{
         lonlat::{LonLat, LonLatT},
     },
     renderable::grid::ProjetedGrid,
+    renderable::limb::Limb,
+    renderable::ruler::Ruler,
     renderable::Layers,
-    renderable::{catalog::Manager, moc::MOCRenderer},
+    renderable::{
+        catalog::{DisplayMode, Manager, Source},
+        moc::MOCRenderer,
+    },
     shader::ShaderManager,
     tile_fetcher::TileFetcherQueue,
     time::DeltaTime,
@@ -63,6 +71,10 @@ pub struct App {
 
     // The grid renderable
     grid: ProjetedGrid,
+    // The ruler measuring distances
+    ruler: Ruler,
+    // The boundary of the projection
+    limb: Limb,
     // The moc renderable
     moc: MOCRenderer,
     // Catalog manager
@@ -161,10 +173,12 @@ impl App {
         let time_start_blending = Time::now();
 
         // Catalog definition
-        let manager = Manager::new(&gl, &mut shaders, &camera, &resources)?;
+        let manager = Manager::new(&gl, &mut shaders, &camera, &resources, aladin_div)?;
 
         // Grid definition
         let grid = ProjetedGrid::new(gl.clone(), aladin_div)?;
+        let ruler = Ruler::new(&gl, aladin_div)?;
+        let limb = Limb::new(&gl);
 
         // Variable storing the location to move to
         let inertia = None;
@@ -220,6 +234,8 @@ impl App {
             request_redraw,
             // The grid renderable
             grid,
+            ruler,
+            limb,
             // MOCs renderable
             moc,
             // The catalog renderable
@@ -535,9 +551,22 @@ impl App {
         return self.inertia.is_some();
     }
 
-    pub(crate) fn update(&mut self, _dt: DeltaTime) -> Result<bool, JsValue> {
+    pub(crate) fn update(&mut self, dt: DeltaTime) -> Result<bool, JsValue> {
         self.start_time_frame = Time::now();
 
+        if self.camera.update(dt.as_millis() as f64, &self.projection) {
+            // Always request for new tiles while moving
+            self.request_for_new_tiles = true;
+        }
+
+        if self.layers.update_blink(dt) {
+            self.request_redraw = true;
+        }
+
+        if self.layers.update_fades(dt) {
+            self.request_redraw = true;
+        }
+
         //let available_tiles = self.run_tasks(dt)?;
         if let Some(inertia) = self.inertia.as_mut() {
             inertia.apply(&mut self.camera, &self.projection);
@@ -642,7 +671,7 @@ impl App {
                                             Some(image)
                                         };
                                         use al_core::image::ImageType;
-                                        use fitsrs::fits::Fits;
+                                        use al_core::image::fits::Fits;
                                         use std::io::Cursor;
                                         if let Some(image) = image.as_ref() {
                                             match &*image.lock().unwrap_abort() {
@@ -658,39 +687,10 @@ impl App {
 
                                                         let mut bytes_reader =
                                                             Cursor::new(raw_bytes.as_slice());
-                                                        let Fits { hdu } =
-                                                            Fits::from_reader(&mut bytes_reader)
-                                                                .map_err(|_| {
-                                                                    JsValue::from_str(
-                                                                        "Parsing fits error",
-                                                                    )
-                                                                })?;
-
-                                                        let header = hdu.get_header();
-                                                        let bscale = if let Some(
-                                                            fitsrs::card::Value::Float(bscale),
-                                                        ) = header.get(b"BSCALE  ")
-                                                        {
-                                                            *bscale as f32
-                                                        } else {
-                                                            1.0
-                                                        };
-                                                        let bzero = if let Some(
-                                                            fitsrs::card::Value::Float(bzero),
-                                                        ) = header.get(b"BZERO   ")
-                                                        {
-                                                            *bzero as f32
-                                                        } else {
-                                                            0.0
-                                                        };
-                                                        let blank = if let Some(
-                                                            fitsrs::card::Value::Float(blank),
-                                                        ) = header.get(b"BLANK   ")
-                                                        {
-                                                            *blank as f32
-                                                        } else {
-                                                            std::f32::NAN
-                                                        };
+                                                        let Fits {
+                                                            bscale, bzero, blank, ..
+                                                        } = Fits::from_byte_slice(&mut bytes_reader)?;
+                                                        let blank = blank.unwrap_or(std::f32::NAN);
 
                                                         cfg.set_fits_metadata(bscale, bzero, blank);
                                                     }
@@ -819,9 +819,9 @@ impl App {
         // Finally update the camera that reset the flag camera changed
         //if has_camera_moved {
         // Catalogues update
-        /*if let Some(view) = self.layers.get_view() {
-            self.manager.update(&self.camera, view);
-        }*/
+        if self.manager.update(&self.camera, &self.projection)? {
+            self.request_redraw = true;
+        }
         //}
 
         // Check for async retrieval
@@ -861,7 +861,9 @@ impl App {
     }
 
     pub(crate) fn draw_grid_labels(&mut self) -> Result<(), JsValue> {
-        self.grid.draw_labels()
+        self.grid.draw_labels()?;
+        self.ruler.draw_label(&self.camera, &self.projection)?;
+        self.manager.draw_labels(&self.camera)
     }
 
     pub(crate) fn draw(&mut self, force_render: bool) -> Result<(), JsValue> {
@@ -934,35 +936,7 @@ impl App {
             self.gl
                 .clear(web_sys::WebGl2RenderingContext::COLOR_BUFFER_BIT);
 
-            self.layers.draw(
-                &mut self.camera,
-                &mut self.shaders,
-                &self.colormaps,
-                &self.projection,
-            )?;
-
-            // Draw the catalog
-            //let fbo_view = &self.fbo_view;
-            //catalogs.draw(&gl, shaders, camera, colormaps, fbo_view)?;
-            //catalogs.draw(&gl, shaders, camera, colormaps, None, self.projection)?;
-            self.moc.draw(
-                &mut self.camera,
-                &self.projection,
-                &mut self.shaders,
-                //&mut self.line_renderer,
-            )?;
-
-            //self.line_renderer.begin();
-            //Time::measure_perf("moc draw", || {
-
-            //    Ok(())
-            //})?;
-
-            self.grid
-                .draw(&self.camera, &self.projection, &mut self.shaders)?;
-            //self.line_renderer.end();
-            //self.line_renderer
-            //    .draw(&mut self.shaders, &self.camera, &self.projection)?;
+            self.draw_scene()?;
 
             //let dpi  = self.camera.get_dpi();
             //ui.draw(&gl, dpi)?;
@@ -979,6 +953,67 @@ impl App {
         Ok(())
     }
 
+    // Draw the layers, the mocs and the grid onto the framebuffer bound
+    fn draw_scene(&mut self) -> Result<(), JsValue> {
+        self.layers.draw(
+            &mut self.camera,
+            &mut self.shaders,
+            &self.colormaps,
+            &self.projection,
+        )?;
+        // Smooth out the edge of the layers
+        self.limb
+            .draw(&self.camera, &self.projection, &mut self.shaders)?;
+
+        // Draw the catalog
+        self.manager.draw(&mut self.shaders, &self.camera)?;
+        self.moc.draw(
+            &mut self.camera,
+            &self.projection,
+            &mut self.shaders,
+            //&mut self.line_renderer,
+        )?;
+
+        //self.line_renderer.begin();
+        //Time::measure_perf("moc draw", || {
+
+        //    Ok(())
+        //})?;
+
+        self.grid
+            .draw(&self.camera, &self.projection, &mut self.shaders)?;
+        self.ruler
+            .draw(&self.camera, &self.projection, &mut self.shaders)?;
+        //self.line_renderer.end();
+        //self.line_renderer
+        //    .draw(&mut self.shaders, &self.camera, &self.projection)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn render_offscreen(&mut self, width: u32, height: u32) -> Result<Vec<u8>, JsValue> {
+        let fbo = FrameBufferObject::new(&self.gl, width as usize, height as usize)?;
+
+        // Render the view with the size of the framebuffer
+        let dpi = self.camera.get_dpi();
+        let screen_size = self.camera.get_screen_size();
+        self.camera
+            .set_render_size(width as f32, height as f32, &self.projection);
+
+        let res = fbo.draw_onto(|| self.draw_scene(), None);
+
+        // Restore the size of the canvas, its viewport and its scissor
+        self.camera.set_screen_size(
+            screen_size.x / dpi,
+            screen_size.y / dpi,
+            &self.projection,
+        );
+        self.request_redraw = true;
+        res?;
+
+        fbo.read_pixels()
+    }
+
     pub(crate) fn remove_layer(&mut self, layer: &str) -> Result<(), JsValue> {
         self.layers
             .remove_layer(layer, &mut self.camera, &self.projection)?;
@@ -1004,6 +1039,32 @@ impl App {
         Ok(())
     }
 
+    pub(crate) fn set_blink(
+        &mut self,
+        layer_a: &str,
+        layer_b: &str,
+        period: DeltaTime,
+        mode: BlinkMode,
+    ) -> Result<(), JsValue> {
+        let blink = Blink::new(layer_a.to_string(), layer_b.to_string(), period, mode)
+            .map_err(JsValue::from_str)?;
+        self.layers.set_blink(blink)?;
+
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn stop_blink(&mut self) {
+        self.layers.stop_blink();
+
+        self.request_redraw = true;
+    }
+
+    pub(crate) fn set_tile_fade_duration(&mut self, duration: DeltaTime) {
+        self.layers.set_tile_fade_duration(duration);
+    }
+
     pub(crate) fn add_image_hips(&mut self, hips_cfg: HiPSCfg) -> Result<(), JsValue> {
         let hips =
             self.layers
@@ -1282,6 +1343,32 @@ impl App {
         self.layers.get_layer_cfg(layer)
     }
 
+    pub(crate) fn get_viewer_state(&self) -> ViewerState {
+        let mut state = ViewerState {
+            projection: self.projection.name().to_string(),
+            ..Default::default()
+        };
+        self.camera.save_state(&mut state);
+        self.layers.save_state(&mut state);
+
+        state
+    }
+
+    pub(crate) fn set_viewer_state(&mut self, state: &ViewerState) -> Result<(), JsValue> {
+        let projection = ProjectionType::from_name(&state.projection)
+            .ok_or_else(|| JsValue::from_str("Not a valid projection name"))?;
+        self.set_projection(projection)?;
+
+        self.camera.restore_state(state, &self.projection);
+        self.layers
+            .restore_state(state, &mut self.camera, &self.projection)?;
+
+        self.request_for_new_tiles = true;
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
     pub(crate) fn set_hips_url(&mut self, cdid: &String, new_url: String) -> Result<(), JsValue> {
         self.layers.set_survey_url(cdid, new_url.clone())?;
 
@@ -1293,6 +1380,17 @@ impl App {
         Ok(())
     }
 
+    pub(crate) fn set_layer_hist_eq_lut(&mut self, layer: String, lut: &[f32]) -> Result<(), JsValue> {
+        let mut meta = self.layers.get_layer_cfg(&layer)?;
+        // The reversal of the current function is kept
+        meta.color.stretch = meta
+            .color
+            .stretch
+            .with_hist_eq_lut(lut)
+            .map_err(|err| JsValue::from_str(&err))?;
+        self.set_image_survey_color_cfg(layer, meta)
+    }
+
     pub(crate) fn set_image_survey_color_cfg(
         &mut self,
         layer: String,
@@ -1387,7 +1485,7 @@ impl App {
 
         // launch the new tile requests
         self.request_for_new_tiles = true;
-        //self.manager.set_kernel_size(&self.camera);
+        self.manager.set_kernel_size(&self.camera);
 
         self.request_redraw = true;
     }
@@ -1428,6 +1526,124 @@ impl App {
         Ok(())
     }
 
+    pub(crate) fn set_catalog_cluster_threshold(
+        &mut self,
+        name: String,
+        px: f32,
+    ) -> Result<(), JsValue> {
+        let catalog = self.manager.get_mut_catalog(&name).map_err(|e| {
+            let err: JsValue = e.into();
+            err
+        })?;
+        catalog.set_cluster_threshold(px);
+
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn set_catalog_marker_size_range(
+        &mut self,
+        name: String,
+        min_px: f32,
+        max_px: f32,
+    ) -> Result<(), JsValue> {
+        let catalog = self.manager.get_mut_catalog(&name).map_err(|e| {
+            let err: JsValue = e.into();
+            err
+        })?;
+        catalog.set_marker_size_range(min_px, max_px)?;
+
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn set_catalog_display_mode(&mut self, name: String, mode: String) -> Result<(), JsValue> {
+        let mode = match mode.as_str() {
+            "markers" => DisplayMode::Markers,
+            "heatmap" => DisplayMode::Heatmap,
+            _ => return Err(JsValue::from_str(&format!("{:?} is not a catalog display mode", mode))),
+        };
+
+        let catalog = self.manager.get_mut_catalog(&name).map_err(|e| {
+            let err: JsValue = e.into();
+            err
+        })?;
+        catalog.set_display_mode(mode);
+
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn set_catalog_heatmap_bin_size(&mut self, name: String, px: f32) -> Result<(), JsValue> {
+        let catalog = self.manager.get_mut_catalog(&name).map_err(|e| {
+            let err: JsValue = e.into();
+            err
+        })?;
+        catalog.set_heatmap_bin_size(px)?;
+
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn push_catalog_sources(&mut self, name: String, sources: &[f64]) -> Result<(), JsValue> {
+        if sources.len() % 2 != 0 {
+            return Err(JsValue::from_str("The sources must be given as (ra, dec) pairs"));
+        }
+
+        let sources = sources
+            .chunks_exact(2)
+            .map(|radec| {
+                let (ra, dec) = (radec[0].to_radians() as f32, radec[1].to_radians() as f32);
+                Source::new(LonLatT::new(Angle(ra), Angle(dec)))
+            })
+            .collect::<Vec<_>>();
+        self.manager
+            .push_sources(&name, &sources, &mut self.camera, &self.projection);
+
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn get_catalog_source_at(
+        &self,
+        name: String,
+        pos: &Vector2<f64>,
+        max_px: f32,
+    ) -> Result<Option<Box<[f64]>>, JsValue> {
+        let ndc = crate::math::projection::screen_to_ndc_space(pos, &self.camera);
+        let lonlat = self
+            .manager
+            .nearest_source(&name, ndc, max_px, &self.camera, &self.projection)
+            .map_err(|e| {
+                let err: JsValue = e.into();
+                err
+            })?;
+
+        Ok(lonlat.map(|lonlat| {
+            Box::new([
+                (lonlat.lon().0 as f64).to_degrees(),
+                (lonlat.lat().0 as f64).to_degrees(),
+            ]) as Box<[f64]>
+        }))
+    }
+
+    pub(crate) fn set_catalog_epoch(&mut self, name: String, epoch: f64) -> Result<(), JsValue> {
+        let catalog = self.manager.get_mut_catalog(&name).map_err(|e| {
+            let err: JsValue = e.into();
+            err
+        })?;
+        catalog.set_epoch(epoch);
+
+        self.request_redraw = true;
+
+        Ok(())
+    }
+
     pub(crate) fn set_grid_cfg(&mut self, cfg: GridCfg) -> Result<(), JsValue> {
         self.grid.set_cfg(cfg, &self.camera, &self.projection)?;
         self.request_redraw = true;
@@ -1435,6 +1651,34 @@ impl App {
         Ok(())
     }
 
+    /// Measure the distance between two positions in the icrs frame
+    ///
+    /// Returns the distance in degrees
+    pub(crate) fn set_ruler(&mut self, start: LonLatT<f64>, end: LonLatT<f64>) -> f64 {
+        let distance = self.ruler.set(start, end).distance;
+        self.request_redraw = true;
+
+        distance.to_radians().to_degrees()
+    }
+
+    /// Move the second end of the ruler, None if there is no ruler
+    pub(crate) fn move_ruler_end(&mut self, end: LonLatT<f64>) -> Option<f64> {
+        let distance = self.ruler.set_end(end)?.distance;
+        self.request_redraw = true;
+
+        Some(distance.to_radians().to_degrees())
+    }
+
+    pub(crate) fn remove_ruler(&mut self) {
+        self.ruler.clear();
+        self.request_redraw = true;
+    }
+
+    pub(crate) fn show_projection_limb(&mut self, show: bool) {
+        self.limb.set_enabled(show);
+        self.request_redraw = true;
+    }
+
     pub(crate) fn set_coo_system(&mut self, coo_system: CooSystem) {
         self.camera.set_coo_system(coo_system, &self.projection);
         self.request_for_new_tiles = true;
@@ -1457,6 +1701,10 @@ impl App {
             .map(|model_pos| model_pos.lonlat())
     }
 
+    pub(crate) fn screen_to_ndc(&self, pos: &Vector2<f64>) -> Vector2<f64> {
+        crate::math::projection::screen_to_ndc_space(pos, &self.camera)
+    }
+
     pub(crate) fn screen_to_clip(&self, pos: &Vector2<f64>) -> Vector2<f64> {
         // Select the HiPS layer rendered lastly
         crate::math::projection::screen_to_clip_space(pos, &self.camera)
@@ -1482,6 +1730,7 @@ impl App {
     pub(crate) fn set_center(&mut self, lonlat: &LonLatT<f64>) {
         self.prev_cam_position = self.camera.get_center().truncate();
 
+        self.camera.stop_animation();
         self.camera.set_center(lonlat, &self.projection);
         self.request_for_new_tiles = true;
 
@@ -1489,6 +1738,68 @@ impl App {
         self.inertia = None;
     }
 
+    pub(crate) fn fit_region(&mut self, lon1: f64, lon2: f64, lat1: f64, lat2: f64, padding: f64) {
+        self.prev_cam_position = self.camera.get_center().truncate();
+
+        self.camera.stop_animation();
+        self.camera
+            .fit_region(lon1, lon2, lat1, lat2, padding, &self.projection);
+        self.request_for_new_tiles = true;
+        self.request_redraw = true;
+
+        // And stop the current inertia as well if there is one
+        self.inertia = None;
+    }
+
+    pub(crate) fn animate_to(&mut self, lonlat: LonLatT<f64>, fov: Angle<f64>, duration_ms: f64) {
+        self.prev_cam_position = self.camera.get_center().truncate();
+
+        self.camera.animate_to(lonlat, fov, duration_ms);
+        // Stop the current inertia as well if there is one
+        self.inertia = None;
+        self.request_redraw = true;
+    }
+
+    /// Go back to the previous view of the navigation history
+    ///
+    /// Returns whether there was a view to go back to
+    pub(crate) fn undo_view(&mut self, duration_ms: f64) -> Result<bool, JsValue> {
+        let view = self.camera.undo(duration_ms, &self.projection);
+        self.move_to_history_view(view)
+    }
+
+    /// Go forward to the view last undone
+    ///
+    /// Returns whether there was a view to go forward to
+    pub(crate) fn redo_view(&mut self, duration_ms: f64) -> Result<bool, JsValue> {
+        let view = self.camera.redo(duration_ms, &self.projection);
+        self.move_to_history_view(view)
+    }
+
+    fn move_to_history_view(&mut self, view: Option<ViewSnapshot>) -> Result<bool, JsValue> {
+        let view = match view {
+            Some(view) => view,
+            None => return Ok(false),
+        };
+
+        if view.projection != self.projection.name() {
+            if let Some(projection) = ProjectionType::from_name(view.projection) {
+                self.set_projection(projection)?;
+            }
+        }
+
+        self.prev_cam_position = self.camera.get_center().truncate();
+        // Stop the current inertia as well if there is one
+        self.inertia = None;
+        self.request_redraw = true;
+
+        Ok(true)
+    }
+
+    pub(crate) fn set_view_history_length(&mut self, length: usize) {
+        self.camera.set_history_capacity(length);
+    }
+
     pub(crate) fn move_mouse(&mut self, s1x: f32, s1y: f32, s2x: f32, s2y: f32) {
         if self.dragging {
             let from_mouse_pos = [s1x, s1y];
@@ -1510,6 +1821,7 @@ impl App {
         self.dragging = true;
 
         self.inertia = None;
+        self.camera.stop_animation();
         self.request_for_new_tiles = true;
         self.out_of_fov = false;
     }
@@ -1586,6 +1898,16 @@ impl App {
         self.camera.get_center_pos_angle()
     }
 
+    pub(crate) fn cursor_info(&self, ndc: Vector2<f64>) -> Option<crate::camera::cursor::CursorInfo> {
+        self.camera.cursor_info(ndc, &self.projection)
+    }
+
+    pub(crate) fn get_view_wcs(&self) -> Result<crate::camera::wcs::WcsKeywords, JsValue> {
+        self.camera
+            .current_wcs(&self.projection)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
     pub(crate) fn set_fov(&mut self, fov: Angle<f64>) {
         // For the moment, no animation is triggered.
         // The fov is directly set
@@ -0,0 +1,172 @@
+use cgmath::InnerSpace;
+use cgmath::Vector3;
+
+use crate::coo_space::XYZModel;
+use crate::math::angle::Angle;
+use crate::math::lonlat::LonLat;
+use crate::LonLatT;
+
+/// A camera move towards a target position and aperture
+///
+/// The center follows the great circle arc from its start position to the
+/// target, i.e. the shortest way, and the aperture is interpolated logarithmically
+/// so that zooming looks uniform. Both are eased in and out.
+pub struct CameraAnimation {
+    start: XYZModel<f64>,
+    // unit vector orthogonal to start towards the target
+    u: XYZModel<f64>,
+    // angular distance between start and the target
+    omega: f64,
+
+    log_start_aperture: f64,
+    log_target_aperture: f64,
+
+    // in milliseconds
+    duration: f64,
+    elapsed: f64,
+}
+
+impl CameraAnimation {
+    /// # Arguments
+    ///
+    /// * ``start`` - The position the camera starts from
+    /// * ``start_aperture`` - The aperture the camera starts from
+    /// * ``target`` - The position to reach
+    /// * ``target_aperture`` - The aperture to reach
+    /// * ``duration`` - Duration of the animation in milliseconds
+    pub fn new(
+        start: &LonLatT<f64>,
+        start_aperture: Angle<f64>,
+        target: &LonLatT<f64>,
+        target_aperture: Angle<f64>,
+        duration: f64,
+    ) -> Self {
+        let start: XYZModel<f64> = start.vector();
+        let target: XYZModel<f64> = target.vector();
+
+        let omega = crate::math::vector::angle3(&start, &target).to_radians();
+        let u = target - start * omega.cos();
+        let u = if u.magnitude2() > 1e-20 {
+            u.normalize()
+        } else {
+            // start and target are the same or antipodal, go through the
+            // north pole if the way matters
+            let north = Vector3::new(0.0, 1.0, 0.0);
+            let u = north - start * start.dot(north);
+            if u.magnitude2() > 1e-20 {
+                u.normalize()
+            } else {
+                Vector3::new(0.0, 0.0, 1.0)
+            }
+        };
+
+        Self {
+            start,
+            u,
+            omega,
+            log_start_aperture: start_aperture.to_radians().ln(),
+            log_target_aperture: target_aperture.to_radians().ln(),
+            duration: duration.max(0.0),
+            elapsed: 0.0,
+        }
+    }
+
+    /// Advance the animation of ``dt`` milliseconds
+    ///
+    /// Returns whether the animation is still running
+    pub fn update(&mut self, dt: f64) -> bool {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        self.is_running()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.elapsed < self.duration
+    }
+
+    /// The current position and aperture
+    pub fn get(&self) -> (LonLatT<f64>, Angle<f64>) {
+        let t = if self.duration > 0.0 {
+            ease_in_out(self.elapsed / self.duration)
+        } else {
+            1.0
+        };
+
+        let theta = t * self.omega;
+        let center = self.start * theta.cos() + self.u * theta.sin();
+        let aperture = (self.log_start_aperture + t * (self.log_target_aperture - self.log_start_aperture)).exp();
+
+        (center.lonlat(), Angle(aperture))
+    }
+}
+
+// Cubic ease in and out, from 0 at t = 0 to 1 at t = 1 with null derivatives at both ends
+#[inline]
+fn ease_in_out(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::angle::ToAngle;
+
+    fn lonlat(lon: f64, lat: f64) -> LonLatT<f64> {
+        LonLatT::new(lon.to_radians().to_angle(), lat.to_radians().to_angle())
+    }
+
+    #[test]
+    fn animation_ends_on_the_target() {
+        let target = lonlat(83.6, 22.0);
+        let mut anim = CameraAnimation::new(
+            &lonlat(10.0, -30.0),
+            Angle(180.0_f64.to_radians()),
+            &target,
+            Angle(0.5_f64.to_radians()),
+            1000.0,
+        );
+
+        assert!(anim.update(400.0));
+        assert!(anim.update(400.0));
+        assert!(!anim.update(400.0));
+
+        let (center, aperture) = anim.get();
+        let d = crate::math::lonlat::angular_distance(&center, &target);
+        assert!(d.to_radians() < 1e-9);
+        assert!((aperture.to_radians() - 0.5_f64.to_radians()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn animation_across_ra_0_takes_the_short_way() {
+        let mut anim = CameraAnimation::new(
+            &lonlat(358.0, 0.0),
+            Angle(1.0_f64.to_radians()),
+            &lonlat(4.0, 0.0),
+            Angle(4.0_f64.to_radians()),
+            1000.0,
+        );
+
+        for _ in 0..10 {
+            anim.update(100.0);
+
+            let (center, _) = anim.get();
+            // longitude in [-180; 180[
+            let lon = (center.lon().to_radians().to_degrees() + 180.0).rem_euclid(360.0) - 180.0;
+            assert!((-2.0 - 1e-9..=4.0 + 1e-9).contains(&lon), "{}", lon);
+        }
+
+        // half way, the aperture is the geometric mean
+        let mut anim = CameraAnimation::new(
+            &lonlat(358.0, 0.0),
+            Angle(1.0_f64.to_radians()),
+            &lonlat(4.0, 0.0),
+            Angle(4.0_f64.to_radians()),
+            1000.0,
+        );
+        anim.update(500.0);
+        let (center, aperture) = anim.get();
+        let d = crate::math::lonlat::angular_distance(&center, &lonlat(1.0, 0.0));
+        assert!(d.to_radians() < 1e-9);
+        assert!((aperture.to_radians() - 2.0_f64.to_radians()).abs() < 1e-12);
+    }
+}
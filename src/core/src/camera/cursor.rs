@@ -0,0 +1,88 @@
+use serde::Serialize;
+
+use al_api::coo_system::CooSystem;
+
+use crate::math::angle::{format_angle, Angle, AngleFormat};
+use crate::math::lonlat::LonLat;
+use crate::math::TWICE_PI;
+use crate::LonLatT;
+
+/// What is displayed in the tooltip following the cursor
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CursorInfo {
+    /// The frame of the coordinates, i.e. the one of the view
+    pub frame: String,
+    /// In degrees, the longitude being in [0, 360[
+    pub lon: f64,
+    pub lat: f64,
+    /// The longitude in HMS for the equatorial frames, in DMS otherwise
+    pub lon_fmt: String,
+    /// The latitude in DMS
+    pub lat_fmt: String,
+    /// The angular size of a pixel along the x and y screen directions, in arcseconds
+    pub px_scale: (f64, f64),
+}
+
+impl CursorInfo {
+    /// Gather the info of a sky position under the cursor
+    ///
+    /// # Arguments
+    ///
+    /// * ``lonlat`` - The position under the cursor, in the frame of the view
+    /// * ``px_scale`` - The angular size of a pixel along the x and y screen directions, in radians
+    /// * ``frame`` - The frame of the view
+    /// * ``aperture`` - The aperture of the view, giving the precision of the formatted coordinates
+    pub fn new(lonlat: &LonLatT<f64>, px_scale: (f64, f64), frame: CooSystem, aperture: Angle<f64>) -> Self {
+        let lon = Angle(lonlat.lon().to_radians().rem_euclid(TWICE_PI));
+        let lat = lonlat.lat();
+
+        let lon_fmt = match frame {
+            CooSystem::ICRS => format_angle(lon, AngleFormat::hms(aperture)),
+            _ => format_angle(lon, AngleFormat::dms(aperture)),
+        };
+        let lat_fmt = format_angle(lat, AngleFormat::dms(aperture));
+
+        let to_arcsec = |rad: f64| rad.to_degrees() * 3600.0;
+
+        Self {
+            frame: format!("{:?}", frame),
+            lon: lon.to_radians().to_degrees(),
+            lat: lat.to_radians().to_degrees(),
+            lon_fmt,
+            lat_fmt,
+            px_scale: (to_arcsec(px_scale.0), to_arcsec(px_scale.1)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coo_space::XYClip;
+    use crate::math::projection::Projection;
+    use crate::ProjectionType;
+    use cgmath::{Matrix4, Rad};
+
+    #[test]
+    fn info_at_the_center_of_the_view() {
+        let projection = ProjectionType::Sin(mapproj::zenithal::sin::Sin);
+        // A view centered on (150°, +30°)
+        let w2m = Matrix4::from_angle_y(Rad(150.0_f64.to_radians())) * Matrix4::from_angle_x(Rad(-30.0_f64.to_radians()));
+        let unproject = |clip: XYClip<f64>| {
+            projection
+                .clip_to_world_space(&clip)
+                .map(|world_pos| (w2m * world_pos).lonlat())
+        };
+        let aperture = Angle(1.0_f64.to_radians());
+
+        let center = unproject(XYClip::new(0.0, 0.0)).unwrap();
+        let info = CursorInfo::new(&center, (1e-5, 1e-5), CooSystem::ICRS, aperture);
+        assert_eq!(info.lon_fmt, "10h00m00.0s");
+        assert_eq!(info.lat_fmt, "+30°00'00\"");
+        assert!((info.lon - 150.0).abs() < 1e-9);
+
+        let info = CursorInfo::new(&center, (1e-5, 1e-5), CooSystem::GAL, aperture);
+        assert_eq!(info.lon_fmt, "+150°00'00\"");
+    }
+}
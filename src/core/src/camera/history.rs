@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+
+use crate::math::angle::Angle;
+use crate::math::lonlat;
+use crate::time::{DeltaTime, Time};
+use crate::LonLatT;
+
+const DEFAULT_CAPACITY: usize = 50;
+// Duration the view must stay still to be recorded
+const DEBOUNCE: DeltaTime = DeltaTime::from_millis(500.0);
+// Changes of the view below which it is considered the same, relative to the aperture
+const CENTER_TOLERANCE: f64 = 0.05;
+const APERTURE_TOLERANCE: f64 = 0.05;
+const ROTATION_TOLERANCE: f64 = 0.5 * std::f64::consts::PI / 180.0;
+
+/// A view recorded in the navigation history
+#[derive(Clone, Copy, Debug)]
+pub struct ViewSnapshot {
+    /// The center of the view, in the icrs frame
+    pub center: LonLatT<f64>,
+    pub aperture: Angle<f64>,
+    pub rotation: Angle<f64>,
+    /// The name of the projection, see ``ProjectionType::name``
+    pub projection: &'static str,
+}
+
+impl ViewSnapshot {
+    // Whether the two views are too close for the change to be recorded
+    fn is_close(&self, other: &Self) -> bool {
+        let aperture = self.aperture.to_radians().min(other.aperture.to_radians());
+        let d_center = lonlat::angular_distance(&self.center, &other.center).to_radians();
+        let d_aperture = (self.aperture.to_radians() / other.aperture.to_radians()).ln().abs();
+        let d_rotation = (self.rotation.to_radians() - other.rotation.to_radians()).sin().abs();
+
+        self.projection == other.projection
+            && d_center <= CENTER_TOLERANCE * aperture
+            && d_aperture <= APERTURE_TOLERANCE
+            && d_rotation <= ROTATION_TOLERANCE
+    }
+}
+
+/// The views the user has navigated through, to step back and forth between them
+///
+/// A view is recorded once it has stayed still for a moment so that the
+/// intermediate views of a drag or a zoom are not. The oldest views are
+/// dropped beyond the capacity of the history.
+pub struct NavigationHistory {
+    // The views recorded, the last one being the current view
+    past: VecDeque<ViewSnapshot>,
+    // The views undone, the last one being the next to redo
+    future: Vec<ViewSnapshot>,
+    capacity: usize,
+
+    // The view waiting to be still for long enough, with the time it was reached
+    candidate: Option<(ViewSnapshot, Time)>,
+}
+
+impl NavigationHistory {
+    pub fn new() -> Self {
+        Self {
+            past: VecDeque::new(),
+            future: vec![],
+            capacity: DEFAULT_CAPACITY,
+            candidate: None,
+        }
+    }
+
+    /// Set the max number of views recorded, at least one
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+
+        while self.past.len() > self.capacity {
+            self.past.pop_front();
+        }
+        self.future.truncate(self.capacity);
+    }
+
+    /// Look at the current view
+    ///
+    /// It is recorded when it has not changed significantly for a moment and
+    /// differs from the last view recorded. Recording a view discards the views undone.
+    pub fn observe(&mut self, view: ViewSnapshot, now: Time) {
+        let (candidate, since) = match self.candidate {
+            Some((candidate, since)) if candidate.is_close(&view) => (candidate, since),
+            // The view is moving
+            _ => {
+                self.candidate = Some((view, now));
+                return;
+            }
+        };
+
+        if now - since < DEBOUNCE {
+            return;
+        }
+
+        if self.past.back().map_or(true, |current| !current.is_close(&candidate)) {
+            self.future.clear();
+            self.past.push_back(candidate);
+
+            if self.past.len() > self.capacity {
+                self.past.pop_front();
+            }
+        }
+    }
+
+    /// Step back to the previous view recorded, None if there is none
+    pub fn undo(&mut self) -> Option<ViewSnapshot> {
+        if self.past.len() < 2 {
+            return None;
+        }
+
+        let current = self.past.pop_back()?;
+        self.future.push(current);
+
+        self.past.back().copied()
+    }
+
+    /// Step forward to the view undone last, None if there is none
+    pub fn redo(&mut self) -> Option<ViewSnapshot> {
+        let view = self.future.pop()?;
+        self.past.push_back(view);
+
+        Some(view)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::angle::ToAngle;
+
+    fn view(lon: f64, aperture: f64) -> ViewSnapshot {
+        ViewSnapshot {
+            center: LonLatT::new(lon.to_radians().to_angle(), 0.0_f64.to_angle()),
+            aperture: Angle(aperture.to_radians()),
+            rotation: Angle(0.0),
+            projection: "SIN",
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_three_moves() {
+        let mut history = NavigationHistory::new();
+
+        // Three moves, each view staying still for a second
+        let views = [view(10.0, 20.0), view(40.0, 20.0), view(40.0, 5.0)];
+        let mut t = 0.0;
+        for v in views {
+            for _ in 0..10 {
+                history.observe(v, Time(t));
+                t += 100.0;
+            }
+        }
+
+        let same = |a: Option<ViewSnapshot>, b: ViewSnapshot| a.map_or(false, |a| a.is_close(&b));
+        history.undo();
+        assert!(same(history.undo(), views[0]));
+        // Nothing recorded before the first view
+        assert!(history.undo().is_none());
+
+        assert!(same(history.redo(), views[1]));
+
+        // Coming back to the view undone does not record it again
+        for _ in 0..10 {
+            history.observe(views[1], Time(t));
+            t += 100.0;
+        }
+        assert!(same(history.redo(), views[2]));
+        assert!(history.redo().is_none());
+    }
+}
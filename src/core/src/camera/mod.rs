@@ -1,4 +1,8 @@
+pub mod animation;
+pub mod cursor;
+pub mod history;
 pub mod viewport;
+pub mod wcs;
 use crate::math::lonlat::LonLat;
 use crate::math::projection::coo_space::XYZWModel;
 pub use viewport::CameraViewPort;
@@ -18,6 +18,7 @@ impl ViewHpxCells {
         let hpx_cells = [
             HpxCells::new(CooSystem::ICRS),
             HpxCells::new(CooSystem::GAL),
+            HpxCells::new(CooSystem::ECL),
         ];
 
         Self {
@@ -11,7 +11,8 @@ const ID_R: &Matrix4<f64> = &Matrix4::new(
     -1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
 );
 
-use super::{fov::FieldOfView, view_hpx_cells::ViewHpxCells};
+use crate::state::ViewerState;
+use super::{animation::CameraAnimation, cursor::CursorInfo, fov::FieldOfView, history::{NavigationHistory, ViewSnapshot}, view_hpx_cells::ViewHpxCells, wcs::WcsKeywords};
 use crate::healpix::cell::HEALPixCell;
 use crate::healpix::coverage::HEALPixCoverage;
 use crate::math::angle::ToAngle;
@@ -21,6 +22,9 @@ use cgmath::{Matrix4, Vector2};
 pub struct CameraViewPort {
     // The field of view angle
     aperture: Angle<f64>,
+    // The range the aperture is constrained to
+    aperture_min: Angle<f64>,
+    aperture_max: Angle<f64>,
     // The rotation of the camera
     center: Vector4<f64>,
     w2m_rot: Rotation<f64>,
@@ -66,6 +70,14 @@ pub struct CameraViewPort {
     // Time when the camera has moved
     time_last_move: Time,
 
+    // The move towards a target currently done, if any
+    animation: Option<CameraAnimation>,
+    // The views the user has navigated through
+    history: NavigationHistory,
+    // The precision of the projection forced by the user. None to
+    // choose it from the aperture
+    forced_precision: Option<Precision>,
+
     // A reference to the WebGL2 context
     gl: WebGlContext,
     coo_sys: CooSystem,
@@ -79,6 +91,9 @@ use crate::{
     math::{angle::Angle, projection::Projection, rotation::Rotation},
 };
 
+use crate::coo_space::XYNDC;
+use crate::renderable::line::Precision;
+use crate::math::lonlat::LonLat;
 use crate::LonLatT;
 use cgmath::{SquareMatrix, Vector4};
 use wasm_bindgen::JsCast;
@@ -97,6 +112,9 @@ impl CameraViewPort {
         let last_user_action = UserAction::Starting;
 
         let aperture = Angle(projection.aperture_start());
+        // No restriction by default
+        let aperture_min = Angle(0.0);
+        let aperture_max = Angle(f64::INFINITY);
 
         let w2m = Matrix4::identity();
         let m2w = w2m;
@@ -137,6 +155,8 @@ impl CameraViewPort {
         CameraViewPort {
             // The field of view angle
             aperture,
+            aperture_min,
+            aperture_max,
             center,
             // The rotation of the cameraq
             w2m_rot,
@@ -177,6 +197,9 @@ impl CameraViewPort {
             coo_sys,
             // a flag telling if the viewport has a reversed longitude axis
             reversed_longitude,
+            animation: None,
+            history: NavigationHistory::new(),
+            forced_precision: None,
         }
     }
 
@@ -214,6 +237,53 @@ impl CameraViewPort {
         self.view_hpx_cells.get_cells(depth, frame)
     }
 
+    /// The HEALPix cells at ``order`` intersecting the view
+    ///
+    /// A cell is visible when its center or one of its corners projects on the
+    /// screen. The cells straddling the boundary of the projection are then kept
+    /// as soon as one corner projects, as well as the cell containing the center of
+    /// the view, which can be bigger than the view.
+    ///
+    /// * Remark
+    /// The cells are given in the coordinate system of the camera. All the cells of
+    /// the order are walked so it is meant for the low orders, e.g. for prefetching
+    pub fn visible_cells(&self, order: u8, projection: &ProjectionType) -> Vec<u64> {
+        let center = self.center.lonlat();
+        let center_idx = ::healpix::nested::hash(order, center.lon().0, center.lat().0);
+
+        visible_cells_with(order, center_idx, &|lonlat| {
+            if projection.is_in_valid_domain(lonlat, self) {
+                crate::math::lonlat::proj(lonlat, projection, self)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Estimate the fraction of the sky visible in the view, between [0, 1]
+    ///
+    /// The centers of the HEALPix cells intersecting the view are tested, the order
+    /// being chosen so that the view is sampled by about a few thousands of cells
+    pub fn visible_sky_fraction(&self, projection: &ProjectionType) -> f64 {
+        // Number of cells sampled along the aperture
+        const NUM_SAMPLES_PER_APERTURE: f64 = 64.0;
+
+        let cell_size = self.aperture.to_radians() / NUM_SAMPLES_PER_APERTURE;
+        let order = (crate::healpix::utils::MEAN_HPX_CELL_RES[0] / cell_size)
+            .log2()
+            .ceil()
+            .clamp(3.0, 29.0) as u8;
+
+        let cells = self.get_hpx_cells(order, self.get_coo_system());
+        sky_fraction_with(order, cells.into_iter(), &|lonlat| {
+            if projection.is_in_valid_domain(lonlat, self) {
+                crate::math::lonlat::proj(lonlat, projection, self)
+            } else {
+                None
+            }
+        })
+    }
+
     pub fn is_raytracing(&self, proj: &ProjectionType) -> bool {
         // Check whether the tile depth is 0 for square projection
         // definition domains i.e. Mercator
@@ -225,10 +295,12 @@ impl CameraViewPort {
         match proj {
             ProjectionType::Tan(_) => self.aperture >= 100.0_f64.to_radians().to_angle(),
             ProjectionType::Mer(_) => self.aperture >= 120.0_f64.to_radians().to_angle(),
+            ProjectionType::Car(_) => self.aperture >= 120.0_f64.to_radians().to_angle(),
             ProjectionType::Stg(_) => self.aperture >= 200.0_f64.to_radians().to_angle(),
             ProjectionType::Sin(_) => false,
             ProjectionType::Ait(_) => self.aperture >= 100.0_f64.to_radians().to_angle(),
             ProjectionType::Mol(_) => self.aperture >= 100.0_f64.to_radians().to_angle(),
+            ProjectionType::Hammer(_) => self.aperture >= 100.0_f64.to_radians().to_angle(),
             ProjectionType::Zea(_) => self.aperture >= 140.0_f64.to_radians().to_angle(),
         }
     }
@@ -271,8 +343,32 @@ impl CameraViewPort {
     }
 
     pub fn set_screen_size(&mut self, width: f32, height: f32, projection: &ProjectionType) {
-        self.width = (width as f32) * self.dpi;
-        self.height = (height as f32) * self.dpi;
+        self.set_render_size((width as f32) * self.dpi, (height as f32) * self.dpi, projection);
+
+        // Update the size of the canvas
+        let canvas = self
+            .gl
+            .canvas()
+            .unwrap_abort()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap_abort();
+
+        canvas.set_width(self.width as u32);
+        canvas.set_height(self.height as u32);
+        // Once the canvas size is changed, we have to set the viewport as well
+        self.gl
+            .viewport(0, 0, self.width as i32, self.height as i32);
+        // Once it is done, recompute the scissor
+        self.recompute_scissor();
+    }
+
+    /// Set the size in pixels of the surface rendered
+    ///
+    /// Contrary to `set_screen_size`, the size is not scaled by the dpi and
+    /// the canvas is left untouched. It is used to render offscreen
+    pub fn set_render_size(&mut self, width: f32, height: f32, projection: &ProjectionType) {
+        self.width = width;
+        self.height = height;
 
         self.aspect = width / height;
         // Compute the new clip zoom factor
@@ -290,22 +386,6 @@ impl CameraViewPort {
             &Vector2::new(-1.0, -1.0),
             self,
         ));
-
-        // Update the size of the canvas
-        let canvas = self
-            .gl
-            .canvas()
-            .unwrap_abort()
-            .dyn_into::<web_sys::HtmlCanvasElement>()
-            .unwrap_abort();
-
-        canvas.set_width(self.width as u32);
-        canvas.set_height(self.height as u32);
-        // Once the canvas size is changed, we have to set the viewport as well
-        self.gl
-            .viewport(0, 0, self.width as i32, self.height as i32);
-        // Once it is done, recompute the scissor
-        self.recompute_scissor();
     }
 
     pub fn compute_ndc_to_clip_factor(&mut self, proj: &ProjectionType) {
@@ -325,7 +405,30 @@ impl CameraViewPort {
         self.set_aperture(self.aperture, proj);
     }
 
-    pub fn set_aperture(&mut self, aperture: Angle<f64>, proj: &ProjectionType) {
+    /// Restrict the aperture to the range [min; max]
+    ///
+    /// The current aperture is clamped to that new range and returned
+    pub fn set_aperture_bounds(
+        &mut self,
+        min: Angle<f64>,
+        max: Angle<f64>,
+        proj: &ProjectionType,
+    ) -> Angle<f64> {
+        self.aperture_min = min;
+        self.aperture_max = max.max(min);
+
+        self.set_aperture(self.aperture, proj)
+    }
+
+    pub fn get_aperture_bounds(&self) -> (Angle<f64>, Angle<f64>) {
+        (self.aperture_min, self.aperture_max)
+    }
+
+    /// Set the aperture, clamped to the aperture bounds
+    ///
+    /// Returns the aperture actually set
+    pub fn set_aperture(&mut self, aperture: Angle<f64>, proj: &ProjectionType) -> Angle<f64> {
+        let aperture = bound_aperture(aperture, self.aperture_min, self.aperture_max, proj);
         // Checking if we are zooming or unzooming
         // This is used internaly for the raytracer to compute
         // blending between tiles and their parents (or children)
@@ -340,42 +443,18 @@ impl CameraViewPort {
         let _can_unzoom_more = match proj {
             ProjectionType::Tan(_)
             | ProjectionType::Mer(_)
+            | ProjectionType::Car(_)
             //| ProjectionType::Air(_)
             | ProjectionType::Stg(_) => false,
-            //| ProjectionType::Car(_)
             //| ProjectionType::Cea(_)
             //| ProjectionType::Cyp(_)
             //| ProjectionType::Hpx(_) => false,
             _ => true,
         };
 
-        let aperture_start: Angle<f64> = ArcDeg(proj.aperture_start()).into();
-
-        self.aperture = aperture.min(aperture_start);
+        self.aperture = aperture;
         // Compute the new clip zoom factor
-        let a = aperture.abs();
-
-        let v0 = math::lonlat::radec_to_xyzw(-a / 2.0, Angle(0.0));
-        let v1 = math::lonlat::radec_to_xyzw(a / 2.0, Angle(0.0));
-
-        // Vertex in the WCS of the FOV
-        self.clip_zoom_factor = if self.width < self.height {
-            if let (Some(p0), Some(p1)) =
-                (proj.world_to_clip_space(&v0), proj.world_to_clip_space(&v1))
-            {
-                (0.5 * (p1.x - p0.x).abs()).min(1.0)
-            } else {
-                1.0
-            }
-        } else {
-            if let (Some(p0), Some(p1)) =
-                (proj.world_to_clip_space(&v0), proj.world_to_clip_space(&v1))
-            {
-                (0.5 * (p1.x - p0.x).abs()).min(1.0)
-            } else {
-                1.0
-            }
-        };
+        self.clip_zoom_factor = clip_zoom_factor(self.aperture, proj);
 
         //console_log(&format!("clip factor {:?}", self.aperture));
 
@@ -406,6 +485,8 @@ impl CameraViewPort {
             self.get_coo_system(),
             proj,
         );
+
+        self.aperture
     }
 
     fn compute_texture_depth(&mut self) {
@@ -457,6 +538,19 @@ impl CameraViewPort {
         self.texture_depth
     }
 
+    /// The HEALPix order of the tiles whose pixels best match the screen pixels
+    /// at the current aperture
+    ///
+    /// # Arguments
+    ///
+    /// * ``tile_width_px`` - The width of a tile in pixels
+    /// * ``min_order`` - The lowest order available, returned for the wide apertures
+    /// * ``max_order`` - The deepest order available, returned for the extreme zooms
+    pub fn best_healpix_order(&self, tile_width_px: u32, min_order: u8, max_order: u8) -> u8 {
+        let pixel_size_rad = self.get_aperture().to_radians() / (self.width as f64);
+        best_healpix_order(pixel_size_rad, tile_width_px, min_order, max_order)
+    }
+
     pub fn apply_rotation(
         &mut self,
         axis: &cgmath::Vector3<f64>,
@@ -475,32 +569,170 @@ impl CameraViewPort {
         let icrs_pos: Vector4<_> = lonlat.vector();
 
         let view_pos = CooSystem::ICRS.to(self.get_coo_system()) * icrs_pos;
-        let rot_to_center = Rotation::from_sky_position(&view_pos);
-
-        let phi = self.get_center_pos_angle();
-        let third_euler_rot = Rotation::from_axis_angle(&view_pos.truncate(), phi);
-
-        let rot = third_euler_rot * rot_to_center;
+        let rot = view_rotation(&view_pos, self.get_center_pos_angle());
 
         // Apply the rotation to the camera to go
         // to the next lonlat
-        self.set_rotation(&rot, proj);
+        self.set_w2m_rotation(&rot, proj);
     }
 
-    pub fn set_center_pos_angle(&mut self, phi: Angle<f64>, proj: &ProjectionType) {
-        let rot_to_center = Rotation::from_sky_position(&self.center);
-        let third_euler_rot = Rotation::from_axis_angle(&self.center.truncate(), phi);
+    /// Center the view on a sky box and zoom so that the box fills it
+    ///
+    /// The box spans eastward from ``lon1`` to ``lon2``, crossing RA=0 if ``lon2 < lon1``.
+    /// A box reaching a pole is centered on it with an aperture of 180 degrees.
+    ///
+    /// # Arguments
+    ///
+    /// * ``lon1``, ``lon2``, ``lat1``, ``lat2`` - The bounds of the box, in radians in the icrs frame
+    /// * ``padding`` - The factor the diagonal of the box is multiplied by to give the aperture
+    ///
+    /// Returns the aperture actually set, i.e. clamped to the aperture bounds
+    pub fn fit_region(
+        &mut self,
+        lon1: f64,
+        lon2: f64,
+        lat1: f64,
+        lat2: f64,
+        padding: f64,
+        proj: &ProjectionType,
+    ) -> Angle<f64> {
+        let (center, aperture) = fit_region(lon1, lon2, lat1, lat2, padding);
 
-        let total_rot = third_euler_rot * rot_to_center;
-        self.set_rotation(&total_rot, proj);
+        self.set_center(&center, proj);
+        self.set_aperture(aperture, proj)
     }
 
-    fn set_rotation(&mut self, rot: &Rotation<f64>, proj: &ProjectionType) {
+    /// Start moving the camera towards a target
+    ///
+    /// The move is done by successive calls to ``update``.
+    ///
+    /// # Arguments
+    ///
+    /// * ``target`` - The center to reach, given in the icrs frame
+    /// * ``aperture`` - The aperture to reach
+    /// * ``duration_ms`` - Duration of the move in milliseconds
+    pub fn animate_to(&mut self, target: LonLatT<f64>, aperture: Angle<f64>, duration_ms: f64) {
+        let center = (self.get_coo_system().to(CooSystem::ICRS) * self.center).lonlat();
+
+        self.animation = Some(CameraAnimation::new(
+            &center,
+            self.aperture,
+            &target,
+            aperture,
+            duration_ms,
+        ));
+    }
+
+    /// Advance the current camera animation of ``dt`` milliseconds
+    ///
+    /// When no animation is running, the view is recorded in the navigation history
+    /// once it has stayed still for a moment.
+    ///
+    /// Returns whether the animation is still running
+    pub fn update(&mut self, dt: f64, proj: &ProjectionType) -> bool {
+        if let Some(animation) = self.animation.as_mut() {
+            let running = animation.update(dt);
+            let (center, aperture) = animation.get();
+
+            self.set_center(&center, proj);
+            self.set_aperture(aperture, proj);
+
+            if !running {
+                self.animation = None;
+            }
+
+            running
+        } else {
+            let view = self.snapshot(proj);
+            self.history.observe(view, Time::now());
+
+            false
+        }
+    }
+
+    fn snapshot(&self, proj: &ProjectionType) -> ViewSnapshot {
+        ViewSnapshot {
+            center: (self.get_coo_system().to(CooSystem::ICRS) * self.center).lonlat(),
+            aperture: self.aperture,
+            rotation: self.get_center_pos_angle(),
+            projection: proj.name(),
+        }
+    }
+
+    /// Go back to the previous view of the navigation history
+    ///
+    /// The camera is rolled to the rotation of the view then moves towards it.
+    /// The projection of the view returned must be set by the caller.
+    ///
+    /// Returns the view moved to, None if there is no view to go back to
+    pub fn undo(&mut self, duration_ms: f64, proj: &ProjectionType) -> Option<ViewSnapshot> {
+        let view = self.history.undo()?;
+        self.move_to_snapshot(&view, duration_ms, proj);
+
+        Some(view)
+    }
+
+    /// Go forward to the view last undone, see ``undo``
+    pub fn redo(&mut self, duration_ms: f64, proj: &ProjectionType) -> Option<ViewSnapshot> {
+        let view = self.history.redo()?;
+        self.move_to_snapshot(&view, duration_ms, proj);
+
+        Some(view)
+    }
+
+    fn move_to_snapshot(&mut self, view: &ViewSnapshot, duration_ms: f64, proj: &ProjectionType) {
+        self.set_center_pos_angle(view.rotation, proj);
+        self.animate_to(view.center, view.aperture, duration_ms);
+    }
+
+    /// Set the max number of views kept in the navigation history
+    pub fn set_history_capacity(&mut self, capacity: usize) {
+        self.history.set_capacity(capacity);
+    }
+
+    /// Stop the current camera animation, the camera staying where it is
+    pub fn stop_animation(&mut self) {
+        self.animation = None;
+    }
+
+    /// Force the precision the lines given in the (lon, lat) space are projected with.
+    /// None to choose it from the aperture
+    pub fn set_precision(&mut self, precision: Option<Precision>) {
+        self.forced_precision = precision;
+    }
+
+    /// The precision the lines given in the (lon, lat) space are projected with
+    pub fn get_precision(&self) -> Precision {
+        self.forced_precision.unwrap_or_else(|| {
+            let px_angle = self.aperture.to_radians() / (self.width.max(self.height) as f64);
+            Precision::from_pixel_angle(px_angle)
+        })
+    }
+
+    pub fn set_center_pos_angle(&mut self, phi: Angle<f64>, proj: &ProjectionType) {
+        let total_rot = view_rotation(&self.center, phi);
+        self.set_w2m_rotation(&total_rot, proj);
+    }
+
+    fn set_w2m_rotation(&mut self, rot: &Rotation<f64>, proj: &ProjectionType) {
         self.w2m_rot = *rot;
 
         self.update_rot_matrices(proj);
     }
 
+    /// Set the roll of the view, i.e. its rotation around the center
+    ///
+    /// It is the position angle of the view. The sky is rotated in the
+    /// world space so that everything projected rotates consistently
+    pub fn set_rotation(&mut self, angle: Angle<f64>, proj: &ProjectionType) {
+        self.set_center_pos_angle(angle, proj);
+    }
+
+    /// The roll of the view, see `set_rotation`
+    pub fn get_rotation(&self) -> Angle<f64> {
+        self.get_center_pos_angle()
+    }
+
     pub fn get_field_of_view(&self) -> &FieldOfView {
         &self.fov
     }
@@ -511,7 +743,7 @@ impl CameraViewPort {
         // Create a rotation object from that position
         let new_rotation = Rotation::from_sky_position(&new_center);
         // Apply it to the center of the view
-        self.set_rotation(&new_rotation, proj);
+        self.set_w2m_rotation(&new_rotation, proj);
 
         // unregister the coo sys
         //self.view_hpx_cells.unregister_frame(self.coo_sys);
@@ -625,11 +857,157 @@ impl CameraViewPort {
     }
 
     pub fn get_center_pos_angle(&self) -> Angle<f64> {
-        (self.w2m.x.y).atan2(self.w2m.y.y).to_angle()
+        pos_angle(&self.w2m)
     }
+
+    /// The FITS WCS keywords of the view
+    ///
+    /// The scale of the pixels is the one at the center of the view. Only the
+    /// projections having a standard FITS code can be exported
+    pub fn current_wcs(&self, projection: &ProjectionType) -> Result<WcsKeywords, String> {
+        let code = super::wcs::projection_code(projection)
+            .ok_or_else(|| String::from("The projection has no standard FITS WCS code"))?;
+        let (sx, sy) = projection
+            .local_scale(XYNDC::new(0.0, 0.0), self)
+            .ok_or_else(|| String::from("The center of the view cannot be deprojected"))?;
+
+        // Angular size of a pixel, a NDC length of 2 spanning the whole canvas
+        let px_scale = (2.0 * sx / (self.width as f64), 2.0 * sy / (self.height as f64));
+
+        Ok(WcsKeywords::new(
+            code,
+            self.coo_sys,
+            &self.center.lonlat(),
+            self.get_center_pos_angle().to_radians(),
+            px_scale,
+            (self.width as u32, self.height as u32),
+            self.reversed_longitude,
+        ))
+    }
+
+    /// The info of the sky position under the cursor
+    ///
+    /// Returns None if the position lies outside the projection definition
+    /// domain, e.g. at the corners of an all-sky projection
+    ///
+    /// # Arguments
+    ///
+    /// * ``ndc`` - The position of the cursor in the normalized device space
+    /// * ``projection`` - The projection of the view
+    pub fn cursor_info(&self, ndc: XYNDC<f64>, projection: &ProjectionType) -> Option<CursorInfo> {
+        let lonlat = projection.unproject(&ndc, self)?;
+        let (sx, sy) = projection.local_scale(ndc, self)?;
+        let px_scale = (2.0 * sx / (self.width as f64), 2.0 * sy / (self.height as f64));
+
+        Some(CursorInfo::new(&lonlat, px_scale, self.coo_sys, self.get_aperture()))
+    }
+
+    /// Where a sky position is relative to the screen, e.g. to point an arrow towards it
+    ///
+    /// # Arguments
+    ///
+    /// * ``p`` - The sky position, given in the icrs frame
+    /// * ``projection`` - The projection of the view
+    pub fn is_visible(&self, p: &LonLatT<f64>, projection: &ProjectionType) -> Visibility {
+        let view_pos = coosys::apply_coo_system(CooSystem::ICRS, self.coo_sys, &p.vector::<Vector4<f64>>()).lonlat();
+
+        visibility_with(&view_pos, &|lonlat| crate::math::lonlat::proj(lonlat, projection, self))
+    }
+
+    /// The sky positions of the corners of the screen, in the icrs frame
+    ///
+    /// The corners are given from the top left one clockwise. A corner lying
+    /// outside the projection definition domain, e.g. in an all-sky view, is None
+    ///
+    /// # Arguments
+    ///
+    /// * ``projection`` - The projection of the view
+    pub fn view_corners(&self, projection: &ProjectionType) -> [Option<LonLatT<f64>>; 4] {
+        corners_with(&|ndc| {
+            projection.unproject(ndc, self).map(|lonlat| {
+                coosys::apply_coo_system(self.coo_sys, CooSystem::ICRS, &lonlat.vector::<Vector4<f64>>()).lonlat()
+            })
+        })
+    }
+
+    /// Write the view into a viewer state
+    ///
+    /// The projection and the layers are not part of the camera, they are left untouched
+    pub fn save_state(&self, state: &mut ViewerState) {
+        let center = coosys::apply_coo_system(self.coo_sys, CooSystem::ICRS, &self.center).lonlat();
+
+        state.center = [
+            center.lon().to_radians().rem_euclid(math::TWICE_PI).to_degrees(),
+            center.lat().to_radians().to_degrees(),
+        ];
+        state.fov = self.get_aperture().to_radians().to_degrees();
+        state.rotation = self.get_center_pos_angle().to_radians().to_degrees();
+        state.frame = self.coo_sys;
+    }
+
+    /// Move the view to the one of a viewer state
+    ///
+    /// The projection of the state must have been set before so that the aperture
+    /// is clamped to the one of the projection
+    pub fn restore_state(&mut self, state: &ViewerState, proj: &ProjectionType) {
+        self.set_coo_system(state.frame, proj);
+        self.set_aperture(Angle(state.fov.to_radians()), proj);
+
+        let rot = state_rotation(state);
+        self.set_w2m_rotation(&rot, proj);
+    }
+}
+
+// The rotation of the view centered on a position, given in the frame
+// of the view, and rolled by a position angle
+fn view_rotation(view_pos: &Vector4<f64>, phi: Angle<f64>) -> Rotation<f64> {
+    let rot_to_center = Rotation::from_sky_position(view_pos);
+    let third_euler_rot = Rotation::from_axis_angle(&view_pos.truncate(), phi);
+
+    third_euler_rot * rot_to_center
+}
+
+// The rotation of the view of a viewer state
+fn state_rotation(state: &ViewerState) -> Rotation<f64> {
+    let [lon, lat] = state.center;
+    let icrs_pos: Vector4<f64> = LonLatT::new(lon.to_radians().to_angle(), lat.to_radians().to_angle()).vector();
+    let view_pos = CooSystem::ICRS.to(state.frame) * icrs_pos;
+
+    view_rotation(&view_pos, Angle(state.rotation.to_radians()))
+}
+
+// The position angle of the view given its world to model matrix
+fn pos_angle(w2m: &Matrix4<f64>) -> Angle<f64> {
+    (w2m.x.y).atan2(w2m.y.y).to_angle()
 }
 use crate::ProjectionType;
 use cgmath::Matrix;
+
+fn clamp_aperture(aperture: Angle<f64>, min: Angle<f64>, max: Angle<f64>) -> Angle<f64> {
+    aperture.max(min).min(max)
+}
+
+// The aperture clamped to [min; max], without going beyond the start aperture of the
+// projection, i.e. the widest view it can show, even if `min` is above it
+fn bound_aperture(aperture: Angle<f64>, min: Angle<f64>, max: Angle<f64>, proj: &ProjectionType) -> Angle<f64> {
+    let aperture_start: Angle<f64> = ArcDeg(proj.aperture_start()).into();
+
+    clamp_aperture(aperture, min, max).min(aperture_start)
+}
+
+// Half the clip space width spanned by the `aperture` along the equator, at most 1
+fn clip_zoom_factor(aperture: Angle<f64>, proj: &ProjectionType) -> f64 {
+    let a = aperture.abs();
+
+    let v0 = math::lonlat::radec_to_xyzw(-a / 2.0, Angle(0.0));
+    let v1 = math::lonlat::radec_to_xyzw(a / 2.0, Angle(0.0));
+
+    if let (Some(p0), Some(p1)) = (proj.world_to_clip_space(&v0), proj.world_to_clip_space(&v1)) {
+        (0.5 * (p1.x - p0.x).abs()).min(1.0)
+    } else {
+        1.0
+    }
+}
 //use crate::coo_conversion::CooBaseFloat;
 impl CameraViewPort {
     // private methods
@@ -661,6 +1039,17 @@ impl CameraViewPort {
     }
 }
 
+/// Where a sky position is relative to the screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Visibility {
+    Visible,
+    /// Projected out of the screen. The position in the normalized device space
+    /// is the point of the screen edge towards it, i.e. where to draw an arrow
+    OffScreen(XYNDC<f64>),
+    /// Out of the projection definition domain, e.g. on the back of a zenithal projection
+    BehindProjection,
+}
+
 use al_core::shader::{SendUniforms, ShaderBound};
 impl SendUniforms for CameraViewPort {
     fn attach_uniforms<'a>(&self, shader: &'a ShaderBound<'a>) -> &'a ShaderBound<'a> {
@@ -671,3 +1060,326 @@ impl SendUniforms for CameraViewPort {
         shader
     }
 }
+
+// The size of a HEALPix cell is halved at each order. The order whose pixels
+// are the nearest to `pixel_size_rad` in log scale is looked for
+fn best_healpix_order(pixel_size_rad: f64, tile_width_px: u32, min_order: u8, max_order: u8) -> u8 {
+    let pixel_order = (crate::healpix::utils::MEAN_HPX_CELL_RES[0] / pixel_size_rad).log2();
+    let tile_order = (pixel_order - (tile_width_px.max(1) as f64).log2()).round();
+
+    // NaN, i.e. a null pixel size, gives min_order
+    tile_order.max(min_order as f64).min(max_order as f64) as u8
+}
+
+// The center and the aperture of a view showing the box
+fn fit_region(lon1: f64, lon2: f64, lat1: f64, lat2: f64, padding: f64) -> (LonLatT<f64>, Angle<f64>) {
+    use crate::math::{HALF_PI, PI};
+    // Latitudes closer than that to a pole are considered at the pole
+    const POLE_EPS: f64 = 1e-9;
+
+    let (lat_min, lat_max) = (lat1.min(lat2), lat1.max(lat2));
+    // The whole sky around the pole is shown
+    if lat_max >= HALF_PI - POLE_EPS {
+        return (LonLatT::new(0.0_f64.to_angle(), HALF_PI.to_angle()), PI.to_angle());
+    } else if lat_min <= -HALF_PI + POLE_EPS {
+        return (LonLatT::new(0.0_f64.to_angle(), (-HALF_PI).to_angle()), PI.to_angle());
+    }
+
+    let lon_len = crate::math::sph_geom::distance_from_two_lon(lon1, lon2);
+    let lon = (lon1 + 0.5 * lon_len).rem_euclid(crate::math::TWICE_PI);
+    let center = LonLatT::new(lon.to_angle(), (0.5 * (lat_min + lat_max)).to_angle());
+
+    // The diagonal. A box spanning more than half a turn has its opposite corners
+    // joined by the great circle going the other way
+    let diagonal = if lon_len >= PI {
+        PI
+    } else {
+        let c1 = LonLatT::new(lon1.to_angle(), lat_min.to_angle());
+        let c2 = LonLatT::new((lon1 + lon_len).to_angle(), lat_max.to_angle());
+        crate::math::lonlat::angular_distance(&c1, &c2).to_radians()
+    };
+
+    (center, (padding * diagonal).to_angle())
+}
+
+// The fraction of the cells of `order` whose center projects on the screen
+fn sky_fraction_with<P>(order: u8, cells: impl Iterator<Item = HEALPixCell>, proj: &P) -> f64
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let num_visible = cells
+        .filter(|cell| {
+            let (lon, lat) = cell.center();
+            proj(&LonLatT::new(lon.to_angle(), lat.to_angle()))
+                .map(|p| p.x.abs() <= 1.0 && p.y.abs() <= 1.0)
+                .unwrap_or(false)
+        })
+        .count();
+
+    (num_visible as f64) / ((12_u64 << (2 * (order as u64))) as f64)
+}
+
+fn corners_with<U>(unproject: &U) -> [Option<LonLatT<f64>>; 4]
+where
+    U: Fn(&XYNDC<f64>) -> Option<LonLatT<f64>>,
+{
+    [(-1.0, 1.0), (1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)].map(|(x, y)| unproject(&XYNDC::new(x, y)))
+}
+
+fn visibility_with<P>(p: &LonLatT<f64>, proj: &P) -> Visibility
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    match proj(p) {
+        None => Visibility::BehindProjection,
+        Some(ndc) => {
+            // The max norm is 1 on the edges of the screen
+            let norm = ndc.x.abs().max(ndc.y.abs());
+            if norm <= 1.0 {
+                Visibility::Visible
+            } else {
+                // Move it back along the line joining the center of the screen
+                Visibility::OffScreen(ndc / norm)
+            }
+        }
+    }
+}
+
+fn visible_cells_with<P>(order: u8, center_idx: u64, proj: &P) -> Vec<u64>
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let on_screen = |(lon, lat): (f64, f64)| {
+        proj(&LonLatT::new(lon.to_angle(), lat.to_angle()))
+            .map(|p| p.x.abs() <= 1.0 && p.y.abs() <= 1.0)
+            .unwrap_or(false)
+    };
+
+    HEALPixCell::allsky(order)
+        .filter(|cell| {
+            cell.idx() == center_idx
+                || on_screen(cell.center())
+                || cell.vertices().iter().any(|v| on_screen(*v))
+        })
+        .map(|cell| cell.idx())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::projection::clip_space_proj;
+
+    #[test]
+    fn over_zoom_settles_at_the_min_bound() {
+        let min: Angle<f64> = ArcDeg(1.0).into();
+        let max: Angle<f64> = ArcDeg(90.0).into();
+
+        let mut aperture: Angle<f64> = ArcDeg(10.0).into();
+        // Zoom until going past the min bound
+        for _ in 0..10 {
+            aperture = clamp_aperture(aperture * 0.5, min, max);
+        }
+        assert_eq!(aperture, min);
+
+        // Unzoom past the max bound
+        assert_eq!(clamp_aperture(ArcDeg(180.0).into(), min, max), max);
+        // Default bounds do not restrict the aperture
+        let a: Angle<f64> = ArcDeg(1e-6).into();
+        assert_eq!(clamp_aperture(a, Angle(0.0), Angle(f64::INFINITY)), a);
+    }
+
+    #[test]
+    fn min_bound_above_the_start_aperture() {
+        let projection = ProjectionType::Tan(mapproj::zenithal::tan::Tan);
+        let start: Angle<f64> = ArcDeg(projection.aperture_start()).into();
+        let (min, max): (Angle<f64>, Angle<f64>) = (ArcDeg(170.0).into(), ArcDeg(180.0).into());
+
+        // The bounds cannot widen the view beyond what the projection shows
+        for aperture in [10.0, 175.0, 360.0] {
+            assert_eq!(bound_aperture(ArcDeg(aperture).into(), min, max, &projection), start);
+        }
+        let aperture: Angle<f64> = ArcDeg(60.0).into();
+        assert_eq!(bound_aperture(aperture, Angle(0.0), Angle(f64::INFINITY), &projection), aperture);
+
+        // A wider aperture spans more of the clip space, up to all of it
+        let zoom = |deg: f64| clip_zoom_factor(ArcDeg(deg).into(), &projection);
+        assert!(zoom(20.0) < zoom(60.0));
+        assert!(zoom(start.to_radians().to_degrees()) <= 1.0);
+    }
+
+    #[test]
+    fn roll_of_90_degrees_swaps_the_screen_axes() {
+        use crate::math::angle::ToAngle;
+
+        let projection = ProjectionType::Tan(mapproj::zenithal::tan::Tan);
+        // The camera looks at (0, 0), i.e. the +Z axis. Roll around it
+        let roll = Rotation::from_axis_angle(&cgmath::Vector3::new(0.0, 0.0, 1.0), ArcDeg(90.0).into());
+        let w2m: Matrix4<f64> = (&roll).into();
+        let m2w = w2m.transpose();
+
+        let p: XYZWModel<f64> = LonLatT::new(2.0_f64.to_radians().to_angle(), 1.0_f64.to_radians().to_angle()).vector();
+        let c = projection.world_to_clip_space(&p).unwrap();
+        let c_rolled = projection.world_to_clip_space(&(m2w * p)).unwrap();
+
+        assert!((c_rolled.x.abs() - c.y.abs()).abs() < 1e-9);
+        assert!((c_rolled.y.abs() - c.x.abs()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn best_healpix_order_at_a_few_apertures() {
+        let order = |aperture: ArcDeg<f64>| {
+            let aperture: Angle<f64> = aperture.into();
+            // 1024 pixels wide screen and 512 pixels wide tiles
+            best_healpix_order(aperture.to_radians() / 1024.0, 512, 0, 11)
+        };
+
+        assert_eq!(order(ArcDeg(360.0)), 0);
+        assert_eq!(order(ArcDeg(180.0)), 0);
+        assert_eq!(order(ArcDeg(4.0)), 5);
+        assert_eq!(order(ArcDeg(1.0)), 7);
+        // Capped at the max order
+        assert_eq!(order(ArcDeg(1.0 / 3600.0)), 11);
+        // Raised to the min order
+        assert_eq!(best_healpix_order(1.0, 512, 3, 11), 3);
+    }
+
+    #[test]
+    fn fit_a_box_across_ra_0() {
+        let padding = 1.2;
+        // 20 degrees wide
+        let (center, aperture) = fit_region(350.0_f64.to_radians(), 10.0_f64.to_radians(), -5.0_f64.to_radians(), 5.0_f64.to_radians(), padding);
+        assert!(center.lon().to_radians().abs() < 1e-9 || (center.lon().to_radians() - crate::math::TWICE_PI).abs() < 1e-9);
+        assert!(center.lat().to_radians().abs() < 1e-9);
+
+        // Covering the box, its corners included, with the padding
+        let width = 20.0_f64.to_radians();
+        assert!(aperture.to_radians() > padding * width);
+        assert!(aperture.to_radians() < padding * width * 1.2);
+
+        // Snap to the pole
+        let (center, aperture) = fit_region(0.0, 1.0, 1.0, crate::math::HALF_PI, padding);
+        assert!((center.lat().to_radians() - crate::math::HALF_PI).abs() < 1e-9);
+        assert!((aperture.to_radians() - crate::math::PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sky_fraction_of_a_small_view() {
+        let projection = ProjectionType::Tan(mapproj::zenithal::tan::Tan);
+        // A 10 degrees wide square view centered on (0, 0)
+        let half = 5.0_f64.to_radians();
+        let edge: XYZWModel<f64> = LonLatT::new(half.to_angle(), 0.0_f64.to_angle()).vector();
+        let zoom = 1.0 / projection.world_to_clip_space(&edge).unwrap().x;
+        let proj = |lonlat: &LonLatT<f64>| projection.world_to_clip_space(&lonlat.vector()).map(|p| p * zoom);
+
+        // The cells around the view
+        let order = 8;
+        let cells = HEALPixCell::allsky(order).filter(|cell| {
+            let (lon, lat) = cell.center();
+            lat.abs() < 2.0 * half && (lon < 2.0 * half || lon > crate::math::TWICE_PI - 2.0 * half)
+        });
+        let fraction = sky_fraction_with(order, cells, &proj);
+
+        // Solid angle of the square of the tangent plane
+        let t = half.tan();
+        let area = 4.0 * (t * t / (1.0 + t * t)).asin();
+        let expected = area / (4.0 * crate::math::PI);
+        assert!((fraction - expected).abs() / expected < 0.03, "{} != {}", fraction, expected);
+    }
+
+    #[test]
+    fn corners_of_a_tan_and_an_aitoff_view() {
+        use crate::math::projection::Projection;
+
+        // A 10 degrees wide TAN view centered on (0, 0)
+        let projection = ProjectionType::Tan(mapproj::zenithal::tan::Tan);
+        let edge: XYZWModel<f64> = LonLatT::new(5.0_f64.to_radians().to_angle(), 0.0_f64.to_angle()).vector();
+        let zoom = projection.world_to_clip_space(&edge).unwrap().x;
+        let corners = corners_with(&|ndc: &XYNDC<f64>| {
+            projection
+                .clip_to_world_space_checked(&(*ndc * zoom))
+                .map(|world_pos| world_pos.lonlat())
+        });
+        assert!(corners.iter().all(|c| c.is_some()));
+        // The top left corner
+        let top_left = corners[0].unwrap();
+        assert!(top_left.lon().to_radians().sin() < 0.0 && top_left.lat().to_radians() > 0.0);
+
+        // The whole Aitoff projection fits the screen
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        let unproject = |ndc: &XYNDC<f64>| projection.clip_to_world_space_checked(ndc).map(|world_pos| world_pos.lonlat());
+        assert!(corners_with(&unproject).iter().all(|c| c.is_none()));
+        assert!(unproject(&XYNDC::new(0.0, 0.0)).is_some());
+    }
+
+    #[test]
+    fn allsky_view_shows_the_12_base_cells() {
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        // The whole projection fits the screen
+        let proj = clip_space_proj(&projection);
+
+        let cells = visible_cells_with(0, 0, &proj);
+        assert_eq!(cells, (0..12).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn viewer_state_round_trips_through_json() {
+        use crate::state::LayerState;
+
+        let state = ViewerState {
+            center: [150.0, 30.0],
+            fov: 20.0,
+            rotation: 45.0,
+            projection: String::from("TAN"),
+            frame: CooSystem::GAL,
+            layers: vec![LayerState {
+                layer: String::from("base"),
+                id: String::from("CDS/P/DSS2/color"),
+                transfer_function: String::from("asinh:0.1"),
+                hist_eq_lut: None,
+                colormap: String::from("viridis"),
+                reversed: true,
+                min_cut: Some(0.0),
+                max_cut: Some(100.0),
+                opacity: 0.5,
+            }],
+        };
+        let restored = ViewerState::from_json(&state.to_json().unwrap()).unwrap();
+        assert_eq!(restored, state);
+
+        // The view rebuilt from the state
+        let w2m: Matrix4<f64> = (&state_rotation(&restored)).into();
+        let center = coosys::apply_coo_system(CooSystem::GAL, CooSystem::ICRS, &w2m.z).lonlat();
+        assert!((center.lon().to_radians().to_degrees() - 150.0).abs() < 1e-9);
+        assert!((center.lat().to_radians().to_degrees() - 30.0).abs() < 1e-9);
+        assert!((pos_angle(&w2m).to_radians().to_degrees() - 45.0).abs() < 1e-9);
+
+        // The unknown fields are ignored and the missing ones take their default value
+        let state = ViewerState::from_json(r#"{"fov": 10, "zoomLevel": 3}"#).unwrap();
+        assert_eq!(state.fov, 10.0);
+        assert_eq!(state.projection, "SIN");
+    }
+
+    #[test]
+    fn visibility_of_the_center_and_of_a_far_position() {
+        let lonlat = |lon: f64| LonLatT::new(lon.to_radians().to_angle(), 0.0_f64.to_angle());
+
+        // The orthographic projection only shows the front hemisphere
+        let projection = ProjectionType::Sin(mapproj::zenithal::sin::Sin);
+        let proj = clip_space_proj(&projection);
+        assert_eq!(visibility_with(&lonlat(0.0), &proj), Visibility::Visible);
+        assert_eq!(visibility_with(&lonlat(170.0), &proj), Visibility::BehindProjection);
+
+        // The Aitoff projection shows the whole sky, zoomed in the far position gets out of the screen
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        let proj = |ll: &LonLatT<f64>| projection.world_to_clip_space(&ll.vector()).map(|p| p * 4.0);
+        assert_eq!(visibility_with(&lonlat(0.0), &proj), Visibility::Visible);
+        match visibility_with(&lonlat(170.0), &proj) {
+            Visibility::OffScreen(edge) => {
+                assert!((edge.x.abs() - 1.0).abs() < 1e-9);
+                assert!(edge.y.abs() < 1e-9);
+            }
+            v => panic!("{:?} instead of off screen", v),
+        }
+    }
+}
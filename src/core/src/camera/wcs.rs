@@ -0,0 +1,147 @@
+use serde::Serialize;
+
+use al_api::coo_system::CooSystem;
+
+use crate::LonLatT;
+use crate::ProjectionType;
+
+/// The FITS WCS keywords of the view
+///
+/// The pixels are the ones of the canvas, the first one being the bottom left one
+/// in the FITS convention. The reference point is the center of the view.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub struct WcsKeywords {
+    pub naxis1: u32,
+    pub naxis2: u32,
+    pub ctype1: String,
+    pub ctype2: String,
+    pub crpix1: f64,
+    pub crpix2: f64,
+    /// In degrees
+    pub crval1: f64,
+    pub crval2: f64,
+    /// In degrees per pixel
+    pub cd1_1: f64,
+    pub cd1_2: f64,
+    pub cd2_1: f64,
+    pub cd2_2: f64,
+}
+
+/// The FITS WCS code of a projection, None if it has no standard one
+pub fn projection_code(projection: &ProjectionType) -> Option<&'static str> {
+    match projection {
+        ProjectionType::Tan(_) => Some("TAN"),
+        ProjectionType::Sin(_) => Some("SIN"),
+        ProjectionType::Stg(_) => Some("STG"),
+        // The FITS AIT is the Hammer-Aitoff projection
+        ProjectionType::Ait(_) | ProjectionType::Hammer(_) => Some("AIT"),
+        ProjectionType::Mol(_) => Some("MOL"),
+        _ => None,
+    }
+}
+
+impl WcsKeywords {
+    /// Build the keywords of a view
+    ///
+    /// # Arguments
+    ///
+    /// * ``code`` - The FITS code of the projection, e.g. "TAN"
+    /// * ``frame`` - The frame of the view
+    /// * ``center`` - The sky position at the center of the view
+    /// * ``pos_angle`` - The position angle of the view, in radians
+    /// * ``px_scale`` - The angular size of a pixel along the x and y screen directions, in radians
+    /// * ``size`` - The size of the canvas in pixels
+    /// * ``reversed_longitude`` - Whether the longitudes grow to the left, as on the sky
+    pub fn new(
+        code: &str,
+        frame: CooSystem,
+        center: &LonLatT<f64>,
+        pos_angle: f64,
+        px_scale: (f64, f64),
+        size: (u32, u32),
+        reversed_longitude: bool,
+    ) -> Self {
+        let (lon, lat) = match frame {
+            CooSystem::ICRS => ("RA", "DEC"),
+            CooSystem::GAL => ("GLON", "GLAT"),
+            CooSystem::ECL => ("ELON", "ELAT"),
+        };
+        // The axis name is padded with dashes up to the projection code
+        let ctype = |axis: &str| format!("{:-<5}{}", axis, code);
+
+        let cdelt1 = if reversed_longitude { -px_scale.0 } else { px_scale.0 }.to_degrees();
+        let cdelt2 = px_scale.1.to_degrees();
+        let (s, c) = pos_angle.sin_cos();
+
+        Self {
+            naxis1: size.0,
+            naxis2: size.1,
+            ctype1: ctype(lon),
+            ctype2: ctype(lat),
+            // The center of the first pixel is 1
+            crpix1: 0.5 * (size.0 as f64) + 0.5,
+            crpix2: 0.5 * (size.1 as f64) + 0.5,
+            crval1: center.lon().to_radians().rem_euclid(crate::math::TWICE_PI).to_degrees(),
+            crval2: center.lat().to_radians().to_degrees(),
+            cd1_1: cdelt1 * c,
+            cd1_2: -cdelt2 * s,
+            cd2_1: cdelt1 * s,
+            cd2_2: cdelt2 * c,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::angle::ToAngle;
+
+    // Deproject a pixel with the gnomonic projection described by the keywords
+    fn tan_pix_to_world(wcs: &WcsKeywords, px: f64, py: f64) -> LonLatT<f64> {
+        let (dx, dy) = (px - wcs.crpix1, py - wcs.crpix2);
+        let x = (wcs.cd1_1 * dx + wcs.cd1_2 * dy).to_radians();
+        let y = (wcs.cd2_1 * dx + wcs.cd2_2 * dy).to_radians();
+
+        // Native spherical coordinates, the native longitude of the pole being 180 degrees
+        let phi = x.atan2(-y);
+        let theta = (1.0 / x.hypot(y)).atan();
+        let (ap, dp) = (wcs.crval1.to_radians(), wcs.crval2.to_radians());
+        let dphi = phi - crate::math::PI;
+
+        let lat = (theta.sin() * dp.sin() + theta.cos() * dp.cos() * dphi.cos()).asin();
+        let lon = ap
+            + (-theta.cos() * dphi.sin()).atan2(theta.sin() * dp.cos() - theta.cos() * dp.sin() * dphi.cos());
+
+        LonLatT::new(lon.to_angle(), lat.to_angle())
+    }
+
+    #[test]
+    fn tan_view_round_trips() {
+        let center = LonLatT::new(83.63_f64.to_radians().to_angle(), 22.01_f64.to_radians().to_angle());
+        let px_scale = 1.0_f64.to_radians() / 3600.0;
+        let wcs = WcsKeywords::new(
+            "TAN",
+            CooSystem::ICRS,
+            &center,
+            0.3,
+            (px_scale, px_scale),
+            (800, 600),
+            true,
+        );
+        assert_eq!(wcs.ctype1, "RA---TAN");
+        assert_eq!(wcs.ctype2, "DEC--TAN");
+
+        // The center pixel is the center of the view
+        let lonlat = tan_pix_to_world(&wcs, 400.5, 300.5);
+        let d = crate::math::lonlat::angular_distance(&lonlat, &center).to_radians();
+        assert!(d < 1e-12);
+
+        // and a pixel is a pixel whatever the direction
+        for (px, py) in [(500.5, 300.5), (400.5, 200.5), (329.79, 371.21)] {
+            let lonlat = tan_pix_to_world(&wcs, px, py);
+            let d = crate::math::lonlat::angular_distance(&lonlat, &center).to_radians();
+            assert!((d - 100.0 * px_scale).abs() < 1e-3 * px_scale, "{}", d / px_scale);
+        }
+    }
+}
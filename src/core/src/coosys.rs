@@ -1,4 +1,6 @@
-use cgmath::{BaseFloat, Vector4};
+use cgmath::{BaseFloat, Matrix, Matrix4, Vector4};
+
+use crate::math::angle::Angle;
 
 use al_api::coo_system::CooBaseFloat;
 use al_api::coo_system::CooSystem;
@@ -18,6 +20,91 @@ where
     c1_2_c2_mat * (*v)
 }
 
+/// Location of an observer on Earth at a given time
+///
+/// It defines the horizontal (alt-az) frame: its longitude is the azimuth,
+/// from the north towards the east, and its latitude the altitude above the horizon.
+///
+/// * Remark
+/// The equatorial frame of the date is approximated by the ICRS one, i.e. the
+/// precession and the nutation are ignored, as well as the atmospheric refraction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Observer {
+    // Geodetic latitude in radians
+    lat: f64,
+    // Local sidereal time as an angle in radians
+    lst: f64,
+}
+
+impl Observer {
+    pub fn new(lat: Angle<f64>, lst: Angle<f64>) -> Self {
+        Self {
+            lat: lat.to_radians(),
+            lst: lst.to_radians(),
+        }
+    }
+
+    /// The observer at a location and a time
+    ///
+    /// # Arguments
+    ///
+    /// * ``lat`` - The latitude of the observer
+    /// * ``lon`` - The longitude of the observer, positive towards the east
+    /// * ``jd`` - The UT1 julian date
+    pub fn from_location(lat: Angle<f64>, lon: Angle<f64>, jd: f64) -> Self {
+        let lst = greenwich_mean_sidereal_time(jd) + lon.to_radians();
+
+        Self {
+            lat: lat.to_radians(),
+            lst: lst.rem_euclid(2.0 * std::f64::consts::PI),
+        }
+    }
+
+    /// The rotation from the ICRS frame to the alt-az one
+    ///
+    /// The azimuth turning the opposite way of the right ascension, the
+    /// matrix is a reflection. Its inverse is its transpose
+    pub fn icrs_to_altaz(&self) -> Matrix4<f64> {
+        let (sin_lat, cos_lat) = self.lat.sin_cos();
+        let (sin_lst, cos_lst) = self.lst.sin_cos();
+
+        // The columns are the images of the x, y (the north pole)
+        // and z (the vernal equinox) axis
+        Matrix4::new(
+            cos_lst,
+            cos_lat * sin_lst,
+            -sin_lat * sin_lst,
+            0.0,
+            0.0,
+            sin_lat,
+            cos_lat,
+            0.0,
+            -sin_lst,
+            cos_lat * cos_lst,
+            -sin_lat * cos_lst,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        )
+    }
+
+    /// The rotation from the alt-az frame to the ICRS one
+    pub fn altaz_to_icrs(&self) -> Matrix4<f64> {
+        self.icrs_to_altaz().transpose()
+    }
+}
+
+// Greenwich mean sidereal time, in radians, at a UT1 julian date (IAU 1982)
+fn greenwich_mean_sidereal_time(jd: f64) -> f64 {
+    let d = jd - 2451545.0;
+    let t = d / 36525.0;
+    let gmst_deg = 280.46061837 + 360.98564736629 * d + 0.000387933 * t * t - t * t * t / 38710000.0;
+
+    gmst_deg.rem_euclid(360.0).to_radians()
+}
+
 mod tests {
     #[allow(unused_macros)]
     macro_rules! assert_delta {
@@ -83,4 +170,86 @@ mod tests {
         assert_delta!(gal_lon_deg, 0.0, 1e-3);
         assert_delta!(gal_lat_deg, 0.0, 1e-3);
     }
+
+    #[test]
+    fn ecliptic_pole_to_j2000() {
+        use super::CooSystem;
+        use crate::math::lonlat::LonLat;
+        use crate::ArcDeg;
+        use crate::LonLatT;
+
+        let ecl_pole: LonLatT<f64> = LonLatT::new(ArcDeg(0.0).into(), ArcDeg(90.0).into());
+        let j2000_lonlat =
+            super::apply_coo_system(CooSystem::ECL, CooSystem::ICRS, &ecl_pole.vector()).lonlat();
+
+        let j2000_lon_deg = j2000_lonlat.lon().0.to_degrees();
+        let j2000_lat_deg = j2000_lonlat.lat().0.to_degrees();
+
+        assert!((j2000_lon_deg - 270.0).abs() < 1e-6);
+        assert!((j2000_lat_deg - 66.56070889).abs() < 1e-6);
+
+        // The vernal equinox is shared by both frames
+        let equinox: LonLatT<f64> = LonLatT::new(ArcDeg(0.0).into(), ArcDeg(0.0).into());
+        let ecl_lonlat =
+            super::apply_coo_system(CooSystem::ICRS, CooSystem::ECL, &equinox.vector()).lonlat();
+        assert!(ecl_lonlat.lat().0.abs() < 1e-12);
+    }
+
+    #[test]
+    fn j2000_ecl_roundtrip() {
+        use super::CooSystem;
+        use crate::math::lonlat::LonLat;
+        use crate::ArcDeg;
+        use crate::LonLatT;
+        use cgmath::InnerSpace;
+
+        // 1 mas in radians
+        let mas = (1e-3 / 3600.0_f64).to_radians();
+
+        for (lon, lat) in [(0.0, 0.0), (83.63, 22.01), (266.4, -28.94), (10.68, 41.27)] {
+            let lonlat: LonLatT<f64> = LonLatT::new(ArcDeg(lon).into(), ArcDeg(lat).into());
+            let icrs_pos = lonlat.vector();
+
+            let ecl_pos = super::apply_coo_system(CooSystem::ICRS, CooSystem::ECL, &icrs_pos);
+            let res = super::apply_coo_system(CooSystem::ECL, CooSystem::ICRS, &ecl_pos);
+            assert!((res - icrs_pos).magnitude() < mas);
+
+            // Galactic to ecliptic is the same as passing through ICRS
+            let gal_pos = super::apply_coo_system(CooSystem::ICRS, CooSystem::GAL, &icrs_pos);
+            let direct = super::apply_coo_system(CooSystem::GAL, CooSystem::ECL, &gal_pos);
+            assert!((direct - ecl_pos).magnitude() < mas);
+        }
+    }
+
+    #[test]
+    fn zenith_is_the_pole_of_the_altaz_frame() {
+        use super::Observer;
+        use crate::math::lonlat::LonLat;
+        use crate::ArcDeg;
+        use crate::LonLatT;
+        use cgmath::{InnerSpace, Vector4};
+
+        let observer = Observer::new(ArcDeg(48.5).into(), ArcDeg(120.0).into());
+
+        // The zenith has the latitude of the observer for declination
+        // and its local sidereal time for right ascension
+        let zenith: LonLatT<f64> = LonLatT::new(ArcDeg(120.0).into(), ArcDeg(48.5).into());
+        let altaz = observer.icrs_to_altaz() * zenith.vector::<Vector4<f64>>();
+        assert!((altaz.lonlat().lat().0.to_degrees() - 90.0).abs() < 1e-9);
+
+        let pole: LonLatT<f64> = LonLatT::new(ArcDeg(0.0).into(), ArcDeg(90.0).into());
+        let icrs = observer.altaz_to_icrs() * pole.vector::<Vector4<f64>>();
+        assert!((icrs - zenith.vector::<Vector4<f64>>()).magnitude() < 1e-12);
+
+        // The celestial north pole is due north at the altitude of the observer latitude
+        let altaz = (observer.icrs_to_altaz() * pole.vector::<Vector4<f64>>()).lonlat();
+        assert!((altaz.lat().0.to_degrees() - 48.5).abs() < 1e-9);
+        assert!(altaz.lon().0.abs() < 1e-9);
+
+        // A star rising in the east, i.e. at an hour angle of -90 degrees on the equator
+        let star: LonLatT<f64> = LonLatT::new(ArcDeg(210.0).into(), ArcDeg(0.0).into());
+        let altaz = (observer.icrs_to_altaz() * star.vector::<Vector4<f64>>()).lonlat();
+        assert!(altaz.lat().0.abs() < 1e-9);
+        assert!((altaz.lon().0.to_degrees() - 90.0).abs() < 1e-9);
+    }
 }
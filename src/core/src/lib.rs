@@ -105,6 +105,7 @@ mod inertia;
 pub mod math;
 pub mod renderable;
 mod shader;
+mod state;
 mod survey;
 mod tile_fetcher;
 mod time;
@@ -226,84 +227,39 @@ impl WebClient {
         Ok(())
     }
 
+    /// Render the current view into a RGBA buffer of a given size
+    ///
+    /// The view is drawn offscreen, the canvas being left unchanged. The grid
+    /// labels are not part of the image. It requires a WebGL2 context.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width in pixels of the image
+    /// * `height` - The height in pixels of the image
+    ///
+    /// # Return
+    ///
+    /// The RGBA pixels of the image, the rows being given from top to bottom
+    #[wasm_bindgen(js_name = renderOffscreen)]
+    pub fn render_offscreen(&mut self, width: u32, height: u32) -> Result<Vec<u8>, JsValue> {
+        self.app.render_offscreen(width, height)
+    }
+
     /// Set the type of projections
     ///
     /// # Arguments
     ///
-    /// * `name` - Can be aitoff, mollweide, arc, sinus, tan or mercator
+    /// * `name` - One of the names given by ``ProjectionType::NAMES``, e.g. "TAN"
     #[wasm_bindgen(js_name = setProjection)]
     pub fn set_projection(&mut self, projection: &str) -> Result<(), JsValue> {
-        match projection {
-            // Zenithal
-            "TAN" => self
-                .app
-                .set_projection(ProjectionType::Tan(mapproj::zenithal::tan::Tan::new())), /* Gnomonic projection      */
-            "STG" => self
-                .app
-                .set_projection(ProjectionType::Stg(mapproj::zenithal::stg::Stg::new())), /* Stereographic projection */
-            "SIN" => self
-                .app
-                .set_projection(ProjectionType::Sin(mapproj::zenithal::sin::Sin::new())), /* Orthographic		         */
-            "ZEA" => self
-                .app
-                .set_projection(ProjectionType::Zea(mapproj::zenithal::zea::Zea::new())), /* Equal-area 		         */
-            /*"FEYE" => self
-                .app
-                .set_projection(ProjectionType::Feye(mapproj::zenithal::feye::Feye::new())),
-            "AIR" => {
-                let air_proj = mapproj::zenithal::air::Air::new();
-                //air_proj.set_n_iter(10);
-                //air_proj.set_eps(1e-12);
-                self.app.set_projection(ProjectionType::Air(air_proj))
-            }*/
-            //"AZP",
-            /*"ARC" => self
-                .app
-                .set_projection(ProjectionType::Arc(mapproj::zenithal::arc::Arc::new())),
-            "NCP" => self
-                .app
-                .set_projection(ProjectionType::Ncp(mapproj::zenithal::ncp::Ncp::new())),*/
-            // Cylindrical
-            "MER" => self
-                .app
-                .set_projection(ProjectionType::Mer(mapproj::cylindrical::mer::Mer::new())),
-            /*"CAR" => self
-                .app
-                .set_projection(ProjectionType::Car(mapproj::cylindrical::car::Car::new())),
-            "CEA" => self
-                .app
-                .set_projection(ProjectionType::Cea(mapproj::cylindrical::cea::Cea::new())),
-            "CYP" => self
-                .app
-                .set_projection(ProjectionType::Cyp(mapproj::cylindrical::cyp::Cyp::new())),*/
-            // Pseudo-cylindrical
-            "AIT" => self
-                .app
-                .set_projection(ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new())),
-            /*"PAR" => self
-                .app
-                .set_projection(ProjectionType::Par(mapproj::pseudocyl::par::Par::new())),
-            "SFL" => self
-                .app
-                .set_projection(ProjectionType::Sfl(mapproj::pseudocyl::sfl::Sfl::new())),*/
-            "MOL" => {
-                let mut mol_proj = mapproj::pseudocyl::mol::Mol::new();
-                mol_proj.set_n_iter(10);
-                mol_proj.set_epsilon(1e-12);
-
-                self.app.set_projection(ProjectionType::Mol(mol_proj))
-            } // Conic
-            /*"COD" => self
-                .app
-                .set_projection(ProjectionType::Cod(mapproj::conic::cod::Cod::new())),
-            // Hybrid
-            "HPX" => self
-                .app
-                .set_projection(ProjectionType::Hpx(mapproj::hybrid::hpx::Hpx::new())),*/
-            _ => Err(JsValue::from_str(
-                "Not a valid projection name. AIT, ZEA, SIN, STG, TAN, MOL and MER are accepted",
-            )),
-        }
+        let projection = ProjectionType::from_name(projection).ok_or_else(|| {
+            JsValue::from_str(&format!(
+                "Not a valid projection name. {} are accepted",
+                ProjectionType::NAMES.join(", ")
+            ))
+        })?;
+
+        self.app.set_projection(projection)
     }
 
     /*
@@ -431,6 +387,49 @@ impl WebClient {
         self.app.swap_layers(&first_layer, &second_layer)
     }
 
+    /// Blink two layers to compare them
+    ///
+    /// # Arguments
+    ///
+    /// * `layer_a` - The layer shown first
+    /// * `layer_b` - The layer it is compared to
+    /// * `period_ms` - The duration, in ms, each layer is shown or a transition lasts
+    /// * `cross_fade` - Fade the layers into each other instead of alternating them
+    #[wasm_bindgen(js_name = setBlink)]
+    pub fn set_blink(
+        &mut self,
+        layer_a: String,
+        layer_b: String,
+        period_ms: f32,
+        cross_fade: bool,
+    ) -> Result<(), JsValue> {
+        let mode = if cross_fade {
+            renderable::blink::BlinkMode::CrossFade
+        } else {
+            renderable::blink::BlinkMode::Alternate
+        };
+
+        self.app
+            .set_blink(&layer_a, &layer_b, DeltaTime::from_millis(period_ms), mode)
+    }
+
+    /// Stop blinking the layers, they are drawn with their own opacity again
+    #[wasm_bindgen(js_name = stopBlink)]
+    pub fn stop_blink(&mut self) {
+        self.app.stop_blink();
+    }
+
+    /// Set the duration the HiPS tiles are faded in for once received
+    ///
+    /// # Arguments
+    ///
+    /// * `duration_ms` - The duration of the fade in ms, 0 to show the tiles at once
+    #[wasm_bindgen(js_name = setTileFadeDuration)]
+    pub fn set_tile_fade_duration(&mut self, duration_ms: f32) {
+        self.app
+            .set_tile_fade_duration(DeltaTime::from_millis(duration_ms));
+    }
+
     #[wasm_bindgen(js_name = setHiPSUrl)]
     pub fn set_hips_url(&mut self, cdid: String, new_url: String) -> Result<(), JsValue> {
         self.app.set_hips_url(&cdid, new_url)
@@ -449,6 +448,18 @@ impl WebClient {
         self.app.set_image_survey_color_cfg(layer, meta)
     }
 
+    /// Set a histogram equalization as the transfer function of a layer
+    ///
+    /// # Arguments
+    ///
+    /// * `layer` - The name of the layer
+    /// * `lut` - The 256 values of the table sampled on [0, 1]. They must be
+    ///   non-decreasing and are clamped to [0, 1]
+    #[wasm_bindgen(js_name = setLayerHistEqLut)]
+    pub fn set_layer_hist_eq_lut(&mut self, layer: String, lut: &[f32]) -> Result<(), JsValue> {
+        self.app.set_layer_hist_eq_lut(layer, lut)
+    }
+
     #[wasm_bindgen(js_name = setImageSurveyUrl)]
     pub fn set_survey_url(&mut self, cdid: String, new_url: String) -> Result<(), JsValue> {
         self.app.set_survey_url(&cdid, new_url)
@@ -594,6 +605,85 @@ impl WebClient {
         Ok(())
     }
 
+    /// Center and zoom the view so that a sky box fills it
+    ///
+    /// # Arguments
+    ///
+    /// * `ra_min`, `ra_max` - The right ascension bounds of the box in degrees. The box crosses RA=0 if `ra_max < ra_min`
+    /// * `dec_min`, `dec_max` - The declination bounds of the box in degrees
+    /// * `padding` - The factor the diagonal of the box is multiplied by to give the field of view, e.g. 1.1
+    #[wasm_bindgen(js_name = fitRegion)]
+    pub fn fit_region(
+        &mut self,
+        ra_min: f64,
+        ra_max: f64,
+        dec_min: f64,
+        dec_max: f64,
+        padding: f64,
+    ) -> Result<(), JsValue> {
+        let ra_min = ra_min.rem_euclid(360.0).to_radians();
+        let ra_max = ra_max.rem_euclid(360.0).to_radians();
+
+        self.app.fit_region(ra_min, ra_max, dec_min.to_radians(), dec_max.to_radians(), padding);
+
+        Ok(())
+    }
+
+    /// Move the view smoothly towards a location
+    ///
+    /// The center follows the shortest way on the sky while the field
+    /// of view is zoomed in or out
+    ///
+    /// # Arguments
+    ///
+    /// * `lon` - A longitude in degrees, in the ICRS coosys
+    /// * `lat` - A latitude in degrees, in the ICRS coosys
+    /// * `fov` - The field of view to reach in degrees
+    /// * `duration` - The duration of the move in milliseconds
+    #[wasm_bindgen(js_name = animateTo)]
+    pub fn animate_to(&mut self, lon: f64, lat: f64, fov: f64, duration: f64) -> Result<(), JsValue> {
+        let location = LonLatT::new(ArcDeg(lon).into(), ArcDeg(lat).into());
+
+        self.app.animate_to(location, ArcDeg(fov).into(), duration);
+
+        Ok(())
+    }
+
+    /// Go back to the previous view the user has navigated through
+    ///
+    /// The views are recorded once they have stayed still for a moment.
+    /// Returns false if there is no view to go back to
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - The duration of the move in milliseconds
+    #[wasm_bindgen(js_name = undoView)]
+    pub fn undo_view(&mut self, duration: f64) -> Result<bool, JsValue> {
+        self.app.undo_view(duration)
+    }
+
+    /// Go forward to the view last undone
+    ///
+    /// Returns false if there is no view to go forward to
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - The duration of the move in milliseconds
+    #[wasm_bindgen(js_name = redoView)]
+    pub fn redo_view(&mut self, duration: f64) -> Result<bool, JsValue> {
+        self.app.redo_view(duration)
+    }
+
+    /// Set the max number of views kept to go back to
+    ///
+    /// # Arguments
+    ///
+    /// * `length` - The number of views, at least one
+    #[wasm_bindgen(js_name = setViewHistoryLength)]
+    pub fn set_view_history_length(&mut self, length: usize) {
+        self.app.set_view_history_length(length);
+    }
+
     /// Get the center of the view
     ///
     /// This returns a javascript array of size 2.
@@ -872,6 +962,133 @@ impl WebClient {
         Ok(())
     }
 
+    /// Set the distance under which the markers of a catalog are clustered
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to apply this change to
+    /// * `px` - The distance in pixels
+    ///
+    /// # Panics
+    ///
+    /// If the catalog has not been found
+    #[wasm_bindgen(js_name = setCatalogClusterThreshold)]
+    pub fn set_catalog_cluster_threshold(
+        &mut self,
+        name_catalog: String,
+        px: f32,
+    ) -> Result<(), JsValue> {
+        self.app.set_catalog_cluster_threshold(name_catalog, px)?;
+
+        Ok(())
+    }
+
+    /// Set the bounds of the size of the markers of a catalog
+    ///
+    /// The markers scale inversely with the aperture of the view within these bounds
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to apply this change to
+    /// * `min_px` - The min size of the markers in pixels
+    /// * `max_px` - The max size of the markers in pixels
+    ///
+    /// # Panics
+    ///
+    /// If the catalog has not been found
+    #[wasm_bindgen(js_name = setCatalogMarkerSizeRange)]
+    pub fn set_catalog_marker_size_range(
+        &mut self,
+        name_catalog: String,
+        min_px: f32,
+        max_px: f32,
+    ) -> Result<(), JsValue> {
+        self.app.set_catalog_marker_size_range(name_catalog, min_px, max_px)?;
+
+        Ok(())
+    }
+
+    /// Set how the sources of a catalog are drawn
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to apply this change to
+    /// * `mode` - "markers" to draw a marker per source, the close ones being clustered,
+    ///   or "heatmap" to draw the density of the sources
+    #[wasm_bindgen(js_name = setCatalogDisplayMode)]
+    pub fn set_catalog_display_mode(&mut self, name_catalog: String, mode: String) -> Result<(), JsValue> {
+        self.app.set_catalog_display_mode(name_catalog, mode)
+    }
+
+    /// Set the resolution of the heatmap of a catalog
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to apply this change to
+    /// * `px` - The size of the bins in pixels, at least 1
+    #[wasm_bindgen(js_name = setCatalogHeatmapBinSize)]
+    pub fn set_catalog_heatmap_bin_size(&mut self, name_catalog: String, px: f32) -> Result<(), JsValue> {
+        self.app.set_catalog_heatmap_bin_size(name_catalog, px)
+    }
+
+    /// Add sources to a catalog
+    ///
+    /// The sources are integrated at the next frames, the ones in the view first
+    /// so that they are drawn without waiting for the whole catalog. The catalog is
+    /// created if it has not been added yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog
+    /// * `sources` - The positions of the sources in the ICRS frame, given as successive (ra, dec) pairs in degrees
+    #[wasm_bindgen(js_name = pushCatalogSources)]
+    pub fn push_catalog_sources(&mut self, name_catalog: String, sources: &[f64]) -> Result<(), JsValue> {
+        self.app.push_catalog_sources(name_catalog, sources)
+    }
+
+    /// Get the source of a catalog the closest to a position on screen, e.g. for hovering
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog
+    /// * `x` - The x pixel coordinate in the screen space
+    /// * `y` - The y pixel coordinate in the screen space
+    /// * `max_px` - The maximum distance in pixels between the source and the position
+    ///
+    /// # Returns
+    ///
+    /// The (ra, dec) position of the source in the ICRS frame, in degrees, or undefined if no source is close enough
+    #[wasm_bindgen(js_name = getCatalogSourceAt)]
+    pub fn get_catalog_source_at(
+        &self,
+        name_catalog: String,
+        x: f64,
+        y: f64,
+        max_px: f32,
+    ) -> Result<Option<Box<[f64]>>, JsValue> {
+        self.app
+            .get_catalog_source_at(name_catalog, &Vector2::new(x, y), max_px)
+    }
+
+    /// Set the epoch at which the sources of a catalog are displayed
+    ///
+    /// The sources are moved according to their proper motions
+    ///
+    /// # Arguments
+    ///
+    /// * `name_catalog` - The name of the catalog to apply this change to
+    /// * `epoch` - The epoch in Julian years, e.g. 2016.0
+    ///
+    /// # Panics
+    ///
+    /// If the catalog has not been found
+    #[wasm_bindgen(js_name = setCatalogEpoch)]
+    pub fn set_catalog_epoch(&mut self, name_catalog: String, epoch: f64) -> Result<(), JsValue> {
+        self.app.set_catalog_epoch(name_catalog, epoch)?;
+
+        Ok(())
+    }
+
     /// Project a line to the screen
     ///
     /// # Returns
@@ -954,6 +1171,35 @@ impl WebClient {
         Ok(())
     }
 
+    /// Create a colormap from color stops
+    ///
+    /// # Arguments
+    ///
+    /// * ``label`` - The name of the colormap, it can then be selected by that name
+    /// * ``positions`` - The positions in [0, 1] of the stops
+    /// * ``rgb`` - The RGB colors of the stops, three bytes per stop
+    #[wasm_bindgen(js_name = createColormapFromStops)]
+    pub fn add_colormap_from_stops(
+        &mut self,
+        label: String,
+        positions: Vec<f32>,
+        rgb: Vec<u8>,
+    ) -> Result<(), JsValue> {
+        if rgb.len() != 3 * positions.len() {
+            return Err(JsValue::from_str("Expected three color components per stop"));
+        }
+
+        let stops = positions
+            .into_iter()
+            .zip(rgb.chunks_exact(3))
+            .map(|(t, c)| (t, [c[0], c[1], c[2]]))
+            .collect::<Vec<_>>();
+
+        let cmap = Colormap::from_rgb(&label, &stops)?;
+        self.app.add_cmap(label, cmap)?;
+        Ok(())
+    }
+
     /// Get the image canvas where the webgl rendering is done
     #[wasm_bindgen(js_name = canvas)]
     pub fn get_gl_canvas(&mut self) -> Result<Option<js_sys::Object>, JsValue> {
@@ -983,6 +1229,57 @@ impl WebClient {
         Ok(pixel)
     }
 
+    /// Get the info to display in the tooltip following the cursor
+    ///
+    /// The coordinates are given in the frame of the view, formatted in HMS/DMS,
+    /// along with the angular size of a pixel there.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos_x` - The x screen coordinate in pixels
+    /// * `pos_y` - The y screen coordinate in pixels
+    ///
+    /// # Returns
+    ///
+    /// Null if the cursor is out of the projection, so that the tooltip can be hidden
+    #[wasm_bindgen(js_name = getCursorInfo)]
+    pub fn get_cursor_info(&self, pos_x: f64, pos_y: f64) -> Result<JsValue, JsValue> {
+        let ndc = self.app.screen_to_ndc(&Vector2::new(pos_x, pos_y));
+        let info = self.app.cursor_info(ndc);
+        Ok(serde_wasm_bindgen::to_value(&info)?)
+    }
+
+    /// Get the state of the viewer in JSON, to share or restore the view
+    ///
+    /// It gathers the view (center, fov, rotation, projection, frame) and
+    /// the color config of the image layers
+    #[wasm_bindgen(js_name = getViewerState)]
+    pub fn get_viewer_state(&self) -> Result<String, JsValue> {
+        self.app
+            .get_viewer_state()
+            .to_json()
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Restore a state of the viewer given in JSON
+    ///
+    /// The layers of the state must have been added before for their color
+    /// config to be applied. The unknown fields of the state are ignored
+    #[wasm_bindgen(js_name = setViewerState)]
+    pub fn set_viewer_state(&mut self, json: &str) -> Result<(), JsValue> {
+        let state = crate::state::ViewerState::from_json(json).map_err(|e| JsValue::from_str(&e))?;
+        self.app.set_viewer_state(&state)
+    }
+
+    /// Get the FITS WCS keywords of the current view
+    ///
+    /// Only the TAN, SIN, STG, AIT and MOL projections can be exported
+    #[wasm_bindgen(js_name = getViewWCS)]
+    pub fn get_view_wcs(&self) -> Result<JsValue, JsValue> {
+        let wcs = self.app.get_view_wcs()?;
+        Ok(serde_wasm_bindgen::to_value(&wcs)?)
+    }
+
     #[wasm_bindgen(js_name = getVisibleCells)]
     pub fn get_visible_cells(&self, depth: u8) -> Result<JsValue, JsValue> {
         let cells = self.app.get_visible_cells(depth);
@@ -999,6 +1296,56 @@ impl WebClient {
         self.app.draw_grid_labels()
     }
 
+    /// Draw a ruler measuring the distance between two positions
+    ///
+    /// Returns the distance in degrees
+    ///
+    /// # Arguments
+    ///
+    /// * `lon1` - The longitude of the first end in degrees, in the icrs frame
+    /// * `lat1` - The latitude of the first end in degrees, in the icrs frame
+    /// * `lon2` - The longitude of the second end in degrees, in the icrs frame
+    /// * `lat2` - The latitude of the second end in degrees, in the icrs frame
+    #[wasm_bindgen(js_name = setRuler)]
+    pub fn set_ruler(&mut self, lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+        let start = LonLatT::new(ArcDeg(lon1).into(), ArcDeg(lat1).into());
+        let end = LonLatT::new(ArcDeg(lon2).into(), ArcDeg(lat2).into());
+
+        self.app.set_ruler(start, end)
+    }
+
+    /// Move the second end of the ruler, e.g. while it is dragged
+    ///
+    /// Returns the new distance in degrees, undefined if there is no ruler
+    ///
+    /// # Arguments
+    ///
+    /// * `lon` - The longitude of the end in degrees, in the icrs frame
+    /// * `lat` - The latitude of the end in degrees, in the icrs frame
+    #[wasm_bindgen(js_name = moveRulerEnd)]
+    pub fn move_ruler_end(&mut self, lon: f64, lat: f64) -> Option<f64> {
+        let end = LonLatT::new(ArcDeg(lon).into(), ArcDeg(lat).into());
+
+        self.app.move_ruler_end(end)
+    }
+
+    #[wasm_bindgen(js_name = removeRuler)]
+    pub fn remove_ruler(&mut self) {
+        self.app.remove_ruler()
+    }
+
+    /// Draw the boundary of the projection, e.g. the ellipse of the Aitoff projection
+    ///
+    /// It smooths out the edge of the layers of the all-sky views
+    ///
+    /// # Arguments
+    ///
+    /// * `show` - Whether the boundary is drawn, it is by default
+    #[wasm_bindgen(js_name = showProjectionLimb)]
+    pub fn show_projection_limb(&mut self, show: bool) {
+        self.app.show_projection_limb(show)
+    }
+
     #[wasm_bindgen(js_name = parseVOTable)]
     pub fn parse_votable(&mut self, _s: &str) -> Result<JsValue, JsValue> {
         /*let votable: VOTableWrapper<votable::impls::mem::InMemTableDataRows> =
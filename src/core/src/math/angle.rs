@@ -363,6 +363,93 @@ impl FormatType for HMS {
     }
 }
 
+/// Sexagesimal formats of an angle
+///
+/// The precision is the number of decimals of the seconds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AngleFormat {
+    /// Hours, minutes and seconds of time, e.g. 12h34m56.7s
+    HMS { precision: usize },
+    /// Signed degrees, arcminutes and arcseconds, e.g. +30°15'07"
+    DMS { precision: usize },
+}
+
+impl AngleFormat {
+    /// HMS format with a precision fitting the aperture of the view
+    pub fn hms(aperture: Angle<f64>) -> Self {
+        // 1 second of time is 15 arcseconds
+        Self::HMS {
+            precision: Self::precision(aperture, 15.0),
+        }
+    }
+
+    /// DMS format with a precision fitting the aperture of the view
+    pub fn dms(aperture: Angle<f64>) -> Self {
+        Self::DMS {
+            precision: Self::precision(aperture, 1.0),
+        }
+    }
+
+    // Number of decimals needed to distinguish ~1/1000 of the aperture
+    fn precision(aperture: Angle<f64>, arcsec_per_unit: f64) -> usize {
+        const MAX_PRECISION: f64 = 3.0;
+
+        let aperture_arcsec = aperture.to_radians().to_degrees() * 3600.0;
+        let resolution = aperture_arcsec / (1000.0 * arcsec_per_unit);
+
+        (-resolution.log10()).ceil().clamp(0.0, MAX_PRECISION) as usize
+    }
+}
+
+/// Format an angle in sexagesimal
+///
+/// The rounding is done on the last displayed decimal so that it never produces
+/// 60 seconds or minutes. HMS wraps at 24h and DMS at 360°.
+pub fn format_angle(a: Angle<f64>, kind: AngleFormat) -> String {
+    let (value, precision) = match kind {
+        // in hours between [0; 24[
+        AngleFormat::HMS { precision } => (a.to_radians().to_degrees().rem_euclid(360.0) / 15.0, precision),
+        // in degrees
+        AngleFormat::DMS { precision } => (a.to_radians().to_degrees(), precision),
+    };
+
+    let units_per_sec = 10_u64.pow(precision as u32);
+    let units_per_min = 60 * units_per_sec;
+    let units_per_deg = 60 * units_per_min;
+
+    let mut units = (value.abs() * (units_per_deg as f64)).round() as u64;
+    let wrap = match kind {
+        AngleFormat::HMS { .. } => 24,
+        AngleFormat::DMS { .. } => 360,
+    };
+    if units >= wrap * units_per_deg {
+        units -= wrap * units_per_deg;
+    }
+
+    let d = units / units_per_deg;
+    let m = (units % units_per_deg) / units_per_min;
+    let s = units % units_per_min;
+
+    let seconds = if precision > 0 {
+        format!(
+            "{:02}.{:0p$}",
+            s / units_per_sec,
+            s % units_per_sec,
+            p = precision
+        )
+    } else {
+        format!("{:02}", s)
+    };
+
+    match kind {
+        AngleFormat::HMS { .. } => format!("{:02}h{:02}m{}s", d, m, seconds),
+        AngleFormat::DMS { .. } => {
+            let sign = if value < 0.0 && units > 0 { '-' } else { '+' };
+            format!("{}{:02}°{:02}'{}\"", sign, d, m, seconds)
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[repr(C)]
@@ -745,3 +832,49 @@ impl UniformType for Angle<f64> {
         gl.uniform1f(location, value.0 as f32);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{format_angle, AngleFormat, ArcDeg};
+
+    #[test]
+    fn format_hms() {
+        let ra = ArcDeg(188.73625).into();
+        assert_eq!(format_angle(ra, AngleFormat::HMS { precision: 1 }), "12h34m56.7s");
+
+        // RA=0 boundary
+        assert_eq!(format_angle(ArcDeg(0.0).into(), AngleFormat::HMS { precision: 0 }), "00h00m00s");
+        assert_eq!(format_angle(ArcDeg(-15.0).into(), AngleFormat::HMS { precision: 0 }), "23h00m00s");
+        assert_eq!(format_angle(ArcDeg(360.0).into(), AngleFormat::HMS { precision: 0 }), "00h00m00s");
+        // 23h59m59.96s rounds to the next day
+        let ra = ArcDeg(359.99983).into();
+        assert_eq!(format_angle(ra, AngleFormat::HMS { precision: 1 }), "00h00m00.0s");
+    }
+
+    #[test]
+    fn format_dms() {
+        let dec = ArcDeg(30.0 + 15.0 / 60.0 + 7.0 / 3600.0).into();
+        assert_eq!(format_angle(dec, AngleFormat::DMS { precision: 0 }), "+30°15'07\"");
+
+        // Negative declinations
+        let dec = ArcDeg(-(5.0 + 30.0 / 60.0)).into();
+        assert_eq!(format_angle(dec, AngleFormat::DMS { precision: 2 }), "-05°30'00.00\"");
+        let dec = ArcDeg(-0.5 / 3600.0).into();
+        assert_eq!(format_angle(dec, AngleFormat::DMS { precision: 1 }), "-00°00'00.5\"");
+        // -0.0 is displayed positive
+        let dec = ArcDeg(-0.01 / 3600.0).into();
+        assert_eq!(format_angle(dec, AngleFormat::DMS { precision: 0 }), "+00°00'00\"");
+
+        // 59.97" must not be rounded to 60"
+        let dec = ArcDeg(10.0 + 59.0 / 60.0 + 59.97 / 3600.0).into();
+        assert_eq!(format_angle(dec, AngleFormat::DMS { precision: 1 }), "+11°00'00.0\"");
+    }
+
+    #[test]
+    fn precision_adapts_to_the_aperture() {
+        assert_eq!(AngleFormat::dms(ArcDeg(180.0).into()), AngleFormat::DMS { precision: 0 });
+        assert_eq!(AngleFormat::dms(ArcDeg(0.1).into()), AngleFormat::DMS { precision: 1 });
+        assert_eq!(AngleFormat::dms(ArcDeg(1e-4).into()), AngleFormat::DMS { precision: 3 });
+        assert_eq!(AngleFormat::hms(ArcDeg(10.0).into()), AngleFormat::HMS { precision: 0 });
+    }
+}
@@ -1,6 +1,6 @@
 use crate::math::TWICE_PI;
 use crate::Abort;
-use cgmath::{BaseFloat, Matrix3, Rad, Vector3, Vector4};
+use cgmath::{BaseFloat, InnerSpace, Matrix3, Rad, Vector3, Vector4};
 
 pub trait LonLat<S: BaseFloat> {
     fn lon(&self) -> Angle<S>;
@@ -55,6 +55,39 @@ impl From<wcs::LonLat> for LonLatT<f64> {
     }
 }
 
+// Milliarcseconds in a radian
+const MAS_PER_RAD: f64 = 180.0 * 3600.0 * 1000.0 / std::f64::consts::PI;
+
+impl LonLatT<f64> {
+    /// Propagate the position of a source to another epoch from its proper motion
+    ///
+    /// The space motion is modelled without the parallax nor the radial velocity:
+    ///   u(t) = (u + t*mu) / |u + t*mu|
+    /// where mu is the proper motion vector lying in the plane tangent to the
+    /// position u. The motion in right ascension being given multiplied by cos(dec),
+    /// nothing diverges for the stars near the poles, they can go across them.
+    ///
+    /// # Arguments
+    ///
+    /// * ``pmra_cosdec`` - The proper motion in right ascension times cos(dec), in mas/yr
+    /// * ``pmdec`` - The proper motion in declination, in mas/yr
+    /// * ``epoch`` - The epoch of the position, in Julian years
+    /// * ``target_epoch`` - The epoch to propagate the position to, in Julian years
+    pub fn propagate(&self, pmra_cosdec: f64, pmdec: f64, epoch: f64, target_epoch: f64) -> Self {
+        let dt = target_epoch - epoch;
+        let (sa, ca) = self.lon().to_radians().sin_cos();
+        let (sd, cd) = self.lat().to_radians().sin_cos();
+
+        // The position and the unit vectors pointing to the east and the north
+        let u = Vector3::new(cd * sa, sd, cd * ca);
+        let east = Vector3::new(ca, 0.0, -sa);
+        let north = Vector3::new(-sd * sa, cd, -sd * ca);
+
+        let mu = (east * pmra_cosdec + north * pmdec) / MAS_PER_RAD;
+        (u + mu * dt).lonlat()
+    }
+}
+
 impl<S> LonLat<S> for LonLatT<S>
 where
     S: BaseFloat,
@@ -163,6 +196,179 @@ pub fn ang_between_lonlat<S: BaseFloat>(lonlat1: LonLatT<S>, lonlat2: LonLatT<S>
     )
 }
 
+/// Angular distance between two sky positions
+///
+/// The Vincenty formula is used, i.e. the atan2 of the sine and cosine of the
+/// distance. Contrary to the acos of the dot product, it keeps its precision for
+/// small distances, and so does it for the positions close to be antipodal.
+pub fn angular_distance(a: &LonLatT<f64>, b: &LonLatT<f64>) -> Angle<f64> {
+    let (s1, c1) = a.lat().to_radians().sin_cos();
+    let (s2, c2) = b.lat().to_radians().sin_cos();
+    let (sdlon, cdlon) = (b.lon().to_radians() - a.lon().to_radians()).sin_cos();
+
+    let x = c2 * sdlon;
+    let y = c1 * s2 - s1 * c2 * cdlon;
+
+    let sin_d = (x * x + y * y).sqrt();
+    let cos_d = s1 * s2 + c1 * c2 * cdlon;
+
+    Angle(sin_d.atan2(cos_d))
+}
+
+/// The angular distances to a fixed reference position
+///
+/// The unit vector of the reference is computed once so that measuring the
+/// distance to many positions, e.g. when cross-matching a catalog, only costs
+/// the vector of each position. Same precision as ``angular_distance``.
+#[derive(Clone, Copy, Debug)]
+pub struct SeparationField {
+    reference: Vector3<f64>,
+}
+
+impl SeparationField {
+    pub fn new(reference: &LonLatT<f64>) -> Self {
+        Self {
+            reference: reference.vector(),
+        }
+    }
+
+    /// Angular distance between the reference and a position
+    pub fn separation(&self, p: &LonLatT<f64>) -> Angle<f64> {
+        Angle(angle_between(&self.reference, &p.vector()))
+    }
+
+    /// Angular distances between the reference and each of the positions
+    pub fn separations(&self, positions: &[LonLatT<f64>]) -> Vec<Angle<f64>> {
+        positions.iter().map(|p| self.separation(p)).collect()
+    }
+}
+
+// Angle between two unit vectors, in radians
+fn angle_between(a: &Vector3<f64>, b: &Vector3<f64>) -> f64 {
+    let sin_d = a.cross(*b).magnitude();
+    let cos_d = a.dot(*b);
+
+    sin_d.atan2(cos_d)
+}
+
+// Slack on the radius of a cap for the positions on its border
+const CAP_TOLERANCE: f64 = 1e-12;
+
+// A spherical cap, given by its center and its angular radius in radians
+struct Cap {
+    center: Vector3<f64>,
+    radius: f64,
+}
+
+impl Cap {
+    fn contains(&self, v: &Vector3<f64>) -> bool {
+        angle_between(&self.center, v) <= self.radius + CAP_TOLERANCE
+    }
+
+    // The smallest cap having the two positions on its border, None if they are antipodal
+    fn from_two(a: &Vector3<f64>, b: &Vector3<f64>) -> Option<Self> {
+        let mid = a + b;
+        if mid.magnitude2() < CAP_TOLERANCE {
+            return None;
+        }
+
+        let center = mid.normalize();
+        Some(Self {
+            center,
+            radius: angle_between(&center, a),
+        })
+    }
+
+    // The cap not larger than a hemisphere having the three positions on its border,
+    // None if two of them coincide
+    fn from_three(a: &Vector3<f64>, b: &Vector3<f64>, c: &Vector3<f64>) -> Option<Self> {
+        let n = (b - a).cross(c - a);
+        if n.magnitude2() < CAP_TOLERANCE {
+            return None;
+        }
+
+        let center = if n.dot(*a) < 0.0 { -n } else { n }.normalize();
+        Some(Self {
+            center,
+            radius: angle_between(&center, a),
+        })
+    }
+}
+
+// Welzl's algorithm on the sphere, None if the positions do not lie in a hemisphere
+fn smallest_enclosing_cap(v: &[Vector3<f64>]) -> Option<Cap> {
+    let mut cap = Cap {
+        center: *v.first()?,
+        radius: 0.0,
+    };
+
+    for i in 1..v.len() {
+        if cap.contains(&v[i]) {
+            continue;
+        }
+
+        // v[i] is on the border of the cap enclosing v[..=i]
+        cap = Cap {
+            center: v[i],
+            radius: 0.0,
+        };
+        for j in 0..i {
+            if cap.contains(&v[j]) {
+                continue;
+            }
+
+            // v[i] and v[j] are on the border
+            cap = Cap::from_two(&v[i], &v[j])?;
+            for k in 0..j {
+                if !cap.contains(&v[k]) {
+                    cap = Cap::from_three(&v[i], &v[j], &v[k])?;
+                }
+            }
+        }
+    }
+
+    // The caps built are smaller than a hemisphere, one not enclosing all the
+    // positions means they are spread over more than that
+    if v.iter().all(|p| cap.contains(p)) {
+        Some(cap)
+    } else {
+        None
+    }
+}
+
+/// The smallest circle enclosing sky positions, e.g. to frame a group of sources
+///
+/// Returns its center, i.e. the position minimizing the maximum angular distance
+/// to the positions, and its angular radius. When the positions are spread over
+/// more than a hemisphere, their centroid is returned with the angular distance
+/// to the farthest of them, at most π.
+/// No positions give the origin with a null radius.
+pub fn enclosing_circle(points: &[LonLatT<f64>]) -> (LonLatT<f64>, Angle<f64>) {
+    let v = points.iter().map(|p| p.vector()).collect::<Vec<Vector3<f64>>>();
+    if v.is_empty() {
+        return (LonLatT::new(Angle(0.0), Angle(0.0)), Angle(0.0));
+    }
+
+    if let Some(cap) = smallest_enclosing_cap(&v) {
+        return (cap.center.lonlat(), Angle(cap.radius));
+    }
+
+    let sum = v.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, p| sum + p);
+    // The positions may cancel each other out, e.g. for antipodal ones
+    let centroid = if sum.magnitude2() > CAP_TOLERANCE {
+        sum.normalize()
+    } else {
+        v[0]
+    };
+    let radius = v
+        .iter()
+        .map(|p| angle_between(&centroid, p))
+        .fold(0.0, f64::max)
+        .min(std::f64::consts::PI);
+
+    (centroid.lonlat(), Angle(radius))
+}
+
 #[inline]
 pub fn xyz_to_radec<S: BaseFloat>(v: &Vector3<S>) -> (Angle<S>, Angle<S>) {
     let lon = Angle(v.x.atan2(v.z));
@@ -258,3 +464,122 @@ pub fn unproj_from_screen(
         .screen_to_model_space(&xy, camera)
         .map(|model_pos| model_pos.lonlat())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn angular_distance_small_and_antipodal() {
+        let arcsec = (1.0_f64 / 3600.0).to_radians();
+        let lonlat = |lon: f64, lat: f64| LonLatT::new(lon.to_angle(), lat.to_angle());
+
+        // 1 arcsec along a meridian and along the equator
+        let (lon, lat) = (1.0, 45.0_f64.to_radians());
+        let d = angular_distance(&lonlat(lon, lat), &lonlat(lon, lat + arcsec));
+        assert!((d.to_radians() - arcsec).abs() < 1e-9 * arcsec);
+
+        let d = angular_distance(&lonlat(lon, 0.0), &lonlat(lon + arcsec, 0.0));
+        assert!((d.to_radians() - arcsec).abs() < 1e-9 * arcsec);
+
+        // Coincident positions
+        assert_eq!(angular_distance(&lonlat(lon, lat), &lonlat(lon, lat)).to_radians(), 0.0);
+
+        // Antipodal positions
+        let d = angular_distance(&lonlat(0.0, 0.0), &lonlat(std::f64::consts::PI, 0.0));
+        assert_eq!(d.to_radians(), std::f64::consts::PI);
+        let d = angular_distance(&lonlat(0.3, 0.5), &lonlat(0.3 + std::f64::consts::PI, -0.5));
+        assert!((d.to_radians() - std::f64::consts::PI).abs() < 1e-12);
+    }
+
+    #[test]
+    fn separation_field_matches_angular_distance() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut lonlat = || {
+            LonLatT::new(
+                rng.gen_range(0.0..TWICE_PI).to_angle(),
+                rng.gen_range(-1.0_f64..1.0).asin().to_angle(),
+            )
+        };
+
+        let reference = lonlat();
+        let field = SeparationField::new(&reference);
+        let mut positions = (0..1000).map(|_| lonlat()).collect::<Vec<_>>();
+        // Close to the reference
+        positions.push(LonLatT::new(reference.lon(), reference.lat() + Angle(1e-9)));
+
+        let separations = field.separations(&positions);
+        for (p, d) in positions.iter().zip(separations) {
+            let expected = angular_distance(&reference, p).to_radians();
+            assert!((d.to_radians() - expected).abs() < 1e-12 + 1e-9 * expected);
+        }
+    }
+
+    #[test]
+    fn enclosing_circle_of_three_points() {
+        let lonlat = |lon: f64, lat: f64| LonLatT::new(lon.to_radians().to_angle(), lat.to_radians().to_angle());
+
+        // Three points at +60° of latitude 120° apart, plus one inside
+        let points = [lonlat(0.0, 60.0), lonlat(10.0, 80.0), lonlat(120.0, 60.0), lonlat(240.0, 60.0)];
+        let (center, radius) = enclosing_circle(&points);
+        assert!((center.lat().to_radians() - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+        assert!((radius.to_radians() - 30.0_f64.to_radians()).abs() < 1e-9);
+
+        // Two of them are enough across RA=0
+        let (center, radius) = enclosing_circle(&[lonlat(350.0, 0.0), lonlat(5.0, 1.0), lonlat(20.0, 0.0)]);
+        assert!(angular_distance(&center, &lonlat(5.0, 0.0)).to_radians() < 1e-9);
+        assert!((radius.to_radians() - 15.0_f64.to_radians()).abs() < 1e-9);
+
+        // Spread over the whole sky
+        let points = [
+            lonlat(0.0, 0.0),
+            lonlat(90.0, 0.0),
+            lonlat(180.0, 0.0),
+            lonlat(270.0, 0.0),
+            lonlat(0.0, 90.0),
+            lonlat(0.0, -90.0),
+        ];
+        let (center, radius) = enclosing_circle(&points);
+        assert!(points.iter().all(|p| angular_distance(&center, p).to_radians() <= radius.to_radians() + 1e-9));
+        assert!((radius.to_radians() - std::f64::consts::PI).abs() < 1e-9);
+    }
+
+    #[test]
+    fn barnard_star_over_10_years() {
+        // Barnard's star, Gaia DR3 at J2016.0
+        let (pmra_cosdec, pmdec) = (-801.551, 10362.394);
+        let p0 = LonLatT::new(
+            269.4520769_f64.to_radians().to_angle(),
+            4.6933649_f64.to_radians().to_angle(),
+        );
+        let p1 = p0.propagate(pmra_cosdec, pmdec, 2016.0, 2026.0);
+
+        let to_mas = |a: f64| a * MAS_PER_RAD;
+        let dra_cosdec = to_mas(p1.lon().to_radians() - p0.lon().to_radians()) * p0.lat().cos();
+        let ddec = to_mas(p1.lat().to_radians() - p0.lat().to_radians());
+
+        // About 10.4 arcsec/yr, mostly to the north
+        assert!((dra_cosdec - 10.0 * pmra_cosdec).abs() < 10.0);
+        assert!((ddec - 10.0 * pmdec).abs() < 10.0);
+
+        // Going back in time gives the starting position
+        let p2 = p1.propagate(pmra_cosdec, pmdec, 2026.0, 2016.0);
+        assert!(to_mas((p2.lat() - p0.lat()).to_radians()).abs() < 10.0);
+    }
+
+    #[test]
+    fn propagation_across_the_pole() {
+        let p0 = LonLatT::new(0.0_f64.to_angle(), (89.9999_f64).to_radians().to_angle());
+        // 10 arcsec/yr to the north for a century, i.e. ~0.28 deg
+        let p1 = p0.propagate(0.0, 10000.0, 2000.0, 2100.0);
+
+        assert!(p1.lon().to_radians().is_finite() && p1.lat().to_radians().is_finite());
+        // the star went across the pole
+        let lon = p1.lon().to_radians();
+        assert!((lon - std::f64::consts::PI).abs() < 1e-6);
+        assert!((p1.lat().to_radians() - (90.0 - 0.2777_f64 + 0.0001).to_radians()).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,47 @@
+use super::Projection;
+use crate::coo_space::{XYClip, XYZWWorld};
+
+use cgmath::Vector4;
+
+/// The Hammer equal-area projection
+///
+/// The whole sky maps to the ellipse of the plane whose semi axes are `2*sqrt(2)`
+/// and `sqrt(2)`. It is scaled to the unit disk of the clip space, the longitudes
+/// increasing towards the left as for the other all-sky projections.
+pub struct Hammer;
+
+impl Projection for Hammer {
+    fn clip_to_world_space(&self, xy: &XYClip<f64>) -> Option<XYZWWorld<f64>> {
+        // Out of the ellipse
+        let r2 = xy.x * xy.x + xy.y * xy.y;
+        if r2 > 1.0 {
+            return None;
+        }
+
+        // Coordinates of the ellipse of semi axes 2*sqrt(2) and sqrt(2)
+        let (x, y) = (-2.0 * std::f64::consts::SQRT_2 * xy.x, std::f64::consts::SQRT_2 * xy.y);
+        let z = (1.0 - r2 * 0.5).sqrt();
+
+        let lon = 2.0 * (z * x).atan2(2.0 * (2.0 * z * z - 1.0));
+        let lat = (z * y).clamp(-1.0, 1.0).asin();
+
+        Some(Vector4::new(
+            lat.cos() * lon.sin(),
+            lat.sin(),
+            lat.cos() * lon.cos(),
+            1.0,
+        ))
+    }
+
+    fn world_to_clip_space(&self, xyzw: &XYZWWorld<f64>) -> Option<XYClip<f64>> {
+        let norm = (xyzw.x * xyzw.x + xyzw.y * xyzw.y + xyzw.z * xyzw.z).sqrt();
+        let (x, y, z) = (xyzw.x / norm, xyzw.y / norm, xyzw.z / norm);
+
+        let half_lon = 0.5 * x.atan2(z);
+        let cos_lat = (x * x + z * z).sqrt();
+        // Always >= 1 as the half longitude is in [-PI/2, PI/2]
+        let w = (1.0 + cos_lat * half_lon.cos()).sqrt();
+
+        Some(XYClip::new(-cos_lat * half_lon.sin() / w, y / w))
+    }
+}
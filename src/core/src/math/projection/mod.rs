@@ -13,11 +13,16 @@ use crate::domain::sdf::ProjDefType;
 use coo_space::XYZWModel;
 //use crate::num_traits::FloatConst;
 use crate::math::PI;
+use crate::math::angle::Angle;
 
+use cgmath::InnerSpace;
 use cgmath::Vector2;
+use cgmath::Vector3;
+use cgmath::Matrix4;
 
 pub mod coo_space;
 pub mod domain;
+pub mod hammer;
 
 use domain::{basic, full::FullScreen};
 
@@ -101,6 +106,13 @@ use al_api::coo_system::CooSystem;
 
 use crate::coo_space::{XYClip, XYZWWorld};
 
+/// Latitude in radians, i.e. ~85.05°, beyond which the Mercator projection is not defined
+///
+/// The Mercator ordinate going to infinity at the poles, the map is cut so that it
+/// is a square, as done by the web maps. The clip space ordinate is then in [-1, 1]
+/// and the deprojection of the latitudes beyond returns None.
+pub const MER_MAX_LAT: f64 = 1.4844222297453324;
+
 pub enum ProjectionType {
     // Zenithal projections
     /* TAN,      Gnomonic projection        */
@@ -127,6 +139,8 @@ pub enum ProjectionType {
     Ait(mapproj::pseudocyl::ait::Ait),
     // MOL,      Mollweide                  */
     Mol(mapproj::pseudocyl::mol::Mol),
+    // Hammer equal-area                    */
+    Hammer(hammer::Hammer),
     // PAR,                                 */
     //Par(mapproj::pseudocyl::par::Par),
     // SFL,                                 */
@@ -135,8 +149,8 @@ pub enum ProjectionType {
     // Cylindrical projections
     // MER,      Mercator                   */
     Mer(mapproj::cylindrical::mer::Mer),
-    // CAR,                                 */
-    //Car(mapproj::cylindrical::car::Car),
+    // CAR,      Plate carrée               */
+    Car(mapproj::cylindrical::car::Car),
     // CEA,                                 */
     //Cea(mapproj::cylindrical::cea::Cea),
     // CYP,                                 */
@@ -152,6 +166,53 @@ pub enum ProjectionType {
 
 use crate::math::lonlat::LonLat;
 impl ProjectionType {
+    /// The names accepted by ``from_name``
+    pub const NAMES: &'static [&'static str] =
+        &["TAN", "STG", "SIN", "ZEA", "MER", "CAR", "AIT", "HAMMER", "MOL"];
+
+    /// Parse a projection from its name, e.g. "TAN"
+    ///
+    pub fn from_name(name: &str) -> Option<Self> {
+        let projection = match name {
+            // Zenithal
+            "TAN" => ProjectionType::Tan(mapproj::zenithal::tan::Tan::new()),
+            "STG" => ProjectionType::Stg(mapproj::zenithal::stg::Stg::new()),
+            "SIN" => ProjectionType::Sin(mapproj::zenithal::sin::Sin::new()),
+            "ZEA" => ProjectionType::Zea(mapproj::zenithal::zea::Zea::new()),
+            // Cylindrical
+            "MER" => ProjectionType::Mer(mapproj::cylindrical::mer::Mer::new()),
+            "CAR" => ProjectionType::Car(mapproj::cylindrical::car::Car::new()),
+            // Pseudo-cylindrical
+            "AIT" => ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new()),
+            "HAMMER" => ProjectionType::Hammer(hammer::Hammer),
+            "MOL" => {
+                let mut mol_proj = mapproj::pseudocyl::mol::Mol::new();
+                mol_proj.set_n_iter(10);
+                mol_proj.set_epsilon(1e-12);
+
+                ProjectionType::Mol(mol_proj)
+            }
+            _ => return None,
+        };
+
+        Some(projection)
+    }
+
+    /// The name of the projection, as parsed by ``from_name``
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProjectionType::Tan(_) => "TAN",
+            ProjectionType::Stg(_) => "STG",
+            ProjectionType::Sin(_) => "SIN",
+            ProjectionType::Zea(_) => "ZEA",
+            ProjectionType::Mer(_) => "MER",
+            ProjectionType::Car(_) => "CAR",
+            ProjectionType::Ait(_) => "AIT",
+            ProjectionType::Mol(_) => "MOL",
+            ProjectionType::Hammer(_) => "HAMMER",
+        }
+    }
+
     pub fn north_pole_celestial_space(&self, camera: &CameraViewPort) -> LonLatT<f64> {
         // This is always defined
         let np_world = self.north_pole_world_space();
@@ -208,6 +269,230 @@ impl ProjectionType {
             .map(|world_pos| camera.get_w2m() * world_pos)
     }
 
+    /// Normalized device to sky position deprojection
+    ///
+    /// Returns None if the position lies outside the projection
+    /// definition domain (e.g. the corners of an Aitoff frame)
+    ///
+    /// # Arguments
+    ///
+    /// * ``ndc`` - The position in the normalized device space
+    /// * ``camera`` - The camera object
+    pub fn unproject(&self, ndc: &XYNDC<f64>, camera: &CameraViewPort) -> Option<LonLatT<f64>> {
+        let clip_pos = ndc_to_clip_space(ndc, camera);
+        self.clip_to_world_space_checked(&clip_pos)
+            .map(|world_pos| (camera.get_w2m() * world_pos).lonlat())
+    }
+
+    /// Angular size of a unit step in the normalized device space
+    ///
+    /// It is computed by finite differences of ``unproject`` around the
+    /// position. The two sizes differ where the projection is not conformal,
+    /// e.g. near the poles of the Mercator projection.
+    ///
+    /// Returns None if the position lies outside the projection definition domain
+    ///
+    /// # Arguments
+    ///
+    /// * ``ndc`` - The position in the normalized device space
+    /// * ``camera`` - The camera object
+    ///
+    /// # Returns
+    ///
+    /// The angular sizes in radians of a NDC unit along the x and y screen directions
+    pub fn local_scale(&self, ndc: XYNDC<f64>, camera: &CameraViewPort) -> Option<(f64, f64)> {
+        let ndc_to_clip = camera.get_ndc_to_clip();
+        let clip_zoom_factor = camera.get_clip_zoom_factor();
+
+        let clip_pos = ndc_to_clip_space(&ndc, camera);
+        self.local_scale_clip_space(&clip_pos).map(|(sx, sy)| {
+            (
+                sx * ndc_to_clip.x * clip_zoom_factor,
+                sy * ndc_to_clip.y * clip_zoom_factor,
+            )
+        })
+    }
+
+    /// Sky positions along a vertical line of the screen
+    ///
+    /// The column is sampled every few pixels from the top to the bottom of the
+    /// screen and the samples are unprojected, e.g. for a crosshair to label the
+    /// coordinates it spans. The samples out of the projection definition domain are skipped
+    ///
+    /// # Arguments
+    ///
+    /// * ``ndc_x`` - The abscissa of the column in the normalized device space
+    /// * ``camera`` - The camera object
+    ///
+    /// # Returns
+    ///
+    /// The (ordinate in the normalized device space, sky position) pairs of the samples, from the top
+    pub fn screen_column_to_lonlat(&self, ndc_x: f32, camera: &CameraViewPort) -> Vec<(f32, LonLatT<f64>)> {
+        // Distance between two samples
+        const STEP_PX: f32 = 4.0;
+
+        let num_steps = ((camera.get_screen_size().y / STEP_PX).ceil() as usize).max(1);
+        sample_column_with(ndc_x, num_steps, |ndc| self.unproject(ndc, camera))
+    }
+
+    /// The directions of the north and the east at a position of the screen
+    ///
+    /// They are computed by finite differences of ``unproject`` so that they
+    /// follow the roll of the camera and the distortion of the projection.
+    ///
+    /// Returns None if the position lies outside the projection definition domain
+    /// or on a pole, where the north is not defined
+    ///
+    /// # Arguments
+    ///
+    /// * ``ndc_origin`` - The position in the normalized device space
+    /// * ``size_px`` - The length of the arrows on the screen, in pixels
+    /// * ``camera`` - The camera object
+    pub fn compass(&self, ndc_origin: XYNDC<f64>, size_px: f32, camera: &CameraViewPort) -> Option<Compass> {
+        compass_with(ndc_origin, size_px, &camera.get_screen_size(), |ndc| {
+            self.unproject(ndc, camera)
+        })
+    }
+
+    /// The angular distance covered by a move of some pixels on the screen, e.g.
+    /// for measurement tools to tell how many arcseconds a pixel spans there
+    ///
+    /// The two screen positions are unprojected so that the anisotropy of the
+    /// projection is taken into account. Returns None if one of them is out of the
+    /// valid domain of the projection.
+    ///
+    /// # Arguments
+    ///
+    /// * ``ndc`` - The position the move starts from, in the normalized device space
+    /// * ``dx_px`` - The move along the x screen axis, in pixels
+    /// * ``dy_px`` - The move along the y screen axis, in pixels, towards the bottom of the screen
+    /// * ``camera`` - The camera object
+    pub fn pixels_to_angle(&self, ndc: &XYNDC<f64>, dx_px: f32, dy_px: f32, camera: &CameraViewPort) -> Option<Angle<f64>> {
+        pixels_to_angle_with(ndc, dx_px, dy_px, &camera.get_screen_size(), |ndc| {
+            self.unproject(ndc, camera)
+        })
+    }
+
+    /// The boundary of the projection definition domain, e.g. the ellipse of an
+    /// Aitoff projection
+    ///
+    /// It is found by bisection along rays cast from the center of the projection
+    /// and can be drawn as a smooth curve or used to mask what lies outside.
+    /// The boundary does not depend on the view.
+    ///
+    /// # Returns
+    ///
+    /// A closed loop of clip space positions, its last vertex being the first one.
+    /// None if the domain is not bounded, e.g. for the gnomonic projection
+    pub fn limb(&self) -> Option<Vec<XYClip<f64>>> {
+        limb_with(|clip| self.clip_to_world_space_checked(clip).is_some())
+    }
+
+    // Angular sizes of a clip space unit step along the x and y axis. The world
+    // space being a rotation of the model one, the angles can be measured there
+    fn local_scale_clip_space(&self, clip_pos: &XYClip<f64>) -> Option<(f64, f64)> {
+        // Step of the finite differences, small enough for the scale to be local
+        // but not too much so that the angles measured keep being accurate
+        const H: f64 = 1e-6;
+
+        let lonlat = |p: XYClip<f64>| {
+            self.clip_to_world_space_checked(&p)
+                .map(|world_pos| world_pos.lonlat())
+        };
+        let center = lonlat(*clip_pos)?;
+
+        // Central difference, falling back to a one-sided one at the
+        // boundary of the projection
+        let scale = |dir: XYClip<f64>| -> f64 {
+            let dist = |p: &LonLatT<f64>| crate::math::lonlat::angular_distance(&center, p).to_radians();
+
+            match (lonlat(clip_pos + dir * H), lonlat(clip_pos - dir * H)) {
+                (Some(p1), Some(p2)) => (dist(&p1) + dist(&p2)) / (2.0 * H),
+                (Some(p), None) | (None, Some(p)) => dist(&p) / H,
+                (None, None) => 0.0,
+            }
+        };
+
+        Some((scale(XYClip::new(1.0, 0.0)), scale(XYClip::new(0.0, 1.0))))
+    }
+
+    // Deproject a clip space position and discard it if it does not project
+    // back to itself, i.e. if it is not part of the projection domain
+    pub(crate) fn clip_to_world_space_checked(&self, clip_pos: &XYClip<f64>) -> Option<XYZWWorld<f64>> {
+        const EPS: f64 = 1e-6;
+
+        let world_pos = self.clip_to_world_space(clip_pos)?;
+        let reproj_pos = self.world_to_clip_space(&world_pos)?;
+
+        if (reproj_pos - clip_pos).magnitude2() < EPS * EPS {
+            Some(world_pos)
+        } else {
+            None
+        }
+    }
+
+    /// Tell whether a sky position can be projected, i.e. whether ``math::lonlat::proj``
+    /// would return Some for it
+    ///
+    /// # Arguments
+    ///
+    /// * ``ll`` - The sky position
+    /// * ``camera`` - The camera object
+    pub fn is_in_valid_domain(&self, ll: &LonLatT<f64>, camera: &CameraViewPort) -> bool {
+        let pos_world_space = camera.get_m2w() * ll.vector::<XYZWModel<f64>>();
+        self.is_in_valid_domain_world_space(&pos_world_space)
+    }
+
+    // The NDC conversion being always defined, a position is valid as soon
+    // as it can be projected in the clip space
+    fn is_in_valid_domain_world_space(&self, pos_world_space: &XYZWWorld<f64>) -> bool {
+        self.world_to_clip_space(pos_world_space).is_some()
+    }
+
+    /// Blend the projections of a world position by two projections
+    ///
+    /// It is used to morph the view when switching from a projection to another one.
+    /// The returned weight is the opacity to give to the position: it fades out
+    /// when the position is only defined in one of the two projections
+    ///
+    /// # Arguments
+    ///
+    /// * ``from`` - The projection at t = 0
+    /// * ``to`` - The projection at t = 1
+    /// * ``t`` - The interpolation parameter between [0, 1]
+    /// * ``pos_world_space`` - The position in the world space
+    /// * ``camera`` - The camera object
+    pub fn lerp(
+        from: &ProjectionType,
+        to: &ProjectionType,
+        t: f64,
+        pos_world_space: &XYZWWorld<f64>,
+        camera: &CameraViewPort,
+    ) -> Option<(XYNDC<f64>, f32)> {
+        Self::lerp_clip_space(from, to, t, pos_world_space)
+            .map(|(pos_clip_space, alpha)| (clip_to_ndc_space(&pos_clip_space, camera), alpha))
+    }
+
+    fn lerp_clip_space(
+        from: &ProjectionType,
+        to: &ProjectionType,
+        t: f64,
+        pos_world_space: &XYZWWorld<f64>,
+    ) -> Option<(XYClip<f64>, f32)> {
+        let t = t.max(0.0).min(1.0);
+
+        match (
+            from.world_to_clip_space(pos_world_space),
+            to.world_to_clip_space(pos_world_space),
+        ) {
+            (Some(p1), Some(p2)) => Some((p1 * (1.0 - t) + p2 * t, 1.0)),
+            // Only defined in one projection, it is faded with the weight of that projection
+            (Some(p1), None) => Some((p1, (1.0 - t) as f32)),
+            (None, Some(p2)) => Some((p2, t as f32)),
+            (None, None) => None,
+        }
+    }
+
     pub fn model_to_screen_space(
         &self,
         pos_model_space: &XYZWModel<f64>,
@@ -260,6 +545,45 @@ impl ProjectionType {
         self.world_to_clip_space(&pos_world_space)
     }
 
+    /// Project a batch of sky positions to the NDC space
+    ///
+    /// The camera matrices are fetched once for the whole batch. Each position
+    /// gives the same result as ``math::lonlat::proj``, i.e. None for the
+    /// positions out of the projection domain.
+    ///
+    /// # Arguments
+    ///
+    /// * ``points`` - The sky positions given in the frame of the camera
+    /// * ``camera`` - The camera object
+    pub fn proj_batch(
+        &self,
+        points: &[LonLatT<f64>],
+        camera: &CameraViewPort,
+    ) -> Vec<Option<XYNDC<f64>>> {
+        let ndc_to_clip = camera.get_ndc_to_clip() * camera.get_clip_zoom_factor();
+        self.proj_batch_with(points, camera.get_m2w(), &ndc_to_clip)
+    }
+
+    fn proj_batch_with(
+        &self,
+        points: &[LonLatT<f64>],
+        m2w: &Matrix4<f64>,
+        ndc_to_clip: &Vector2<f64>,
+    ) -> Vec<Option<XYNDC<f64>>> {
+        points
+            .iter()
+            .map(|lonlat| {
+                let pos_world_space = m2w * lonlat.vector::<XYZWModel<f64>>();
+                self.world_to_clip_space(&pos_world_space).map(|pos_clip_space| {
+                    Vector2::new(
+                        pos_clip_space.x / ndc_to_clip.x,
+                        pos_clip_space.y / ndc_to_clip.y,
+                    )
+                })
+            })
+            .collect()
+    }
+
     /// World to screen space projection
 
     /// World to screen space transformation
@@ -331,6 +655,8 @@ impl ProjectionType {
             ProjectionType::Ait(_) => 2.0,
             // MOL,      Mollweide                  */
             ProjectionType::Mol(_) => 2.0,
+            // Hammer equal-area                    */
+            ProjectionType::Hammer(_) => 2.0,
             // PAR,                                 */
             //ProjectionType::Par(_) => 2.0,
             // SFL,                                 */
@@ -339,8 +665,8 @@ impl ProjectionType {
             // Cylindrical projections
             // MER,      Mercator                   */
             ProjectionType::Mer(_) => 1.0,
-            // CAR,                                 */
-            //ProjectionType::Car(_) => 1.0,
+            // CAR,      Plate carrée               */
+            ProjectionType::Car(_) => 2.0,
             // CEA,                                 */
             //ProjectionType::Cea(_) => 1.0,
             // CYP,                                 */
@@ -382,6 +708,8 @@ impl ProjectionType {
             ProjectionType::Ait(_) => 360.0,
             // MOL,      Mollweide                  */
             ProjectionType::Mol(_) => 360.0,
+            // Hammer equal-area                    */
+            ProjectionType::Hammer(_) => 360.0,
             // PAR,                                 */
             //ProjectionType::Par(_) => 360.0,
             // SFL,                                 */
@@ -390,8 +718,8 @@ impl ProjectionType {
             // Cylindrical projections
             // MER,      Mercator                   */
             ProjectionType::Mer(_) => 360.0,
-            // CAR,                                 */
-            //ProjectionType::Car(_) => 360.0,
+            // CAR,      Plate carrée               */
+            ProjectionType::Car(_) => 360.0,
             // CEA,                                 */
             //ProjectionType::Cea(_) => 360.0,
             // CYP,                                 */
@@ -463,6 +791,11 @@ impl ProjectionType {
                 const ELLIPSE: ProjDefType = ProjDefType::Disk(basic::disk::Disk { radius: 1.0 });
                 &ELLIPSE
             }
+            // Hammer equal-area                    */
+            ProjectionType::Hammer(_) => {
+                const ELLIPSE: ProjDefType = ProjDefType::Disk(basic::disk::Disk { radius: 1.0 });
+                &ELLIPSE
+            }
             // PAR,                                 */
             /*ProjectionType::Par(_) => {
                 const PAR: ProjDefType = ProjDefType::Par(Par);
@@ -479,11 +812,13 @@ impl ProjectionType {
             ProjectionType::Mer(_) => {
                 const FULL_SCREEN: ProjDefType = ProjDefType::FullScreen(FullScreen);
                 &FULL_SCREEN
-            } // CAR,                                 */
-              /*ProjectionType::Car(_) => {
+            }
+              // CAR,      Plate carrée               */
+              ProjectionType::Car(_) => {
+                  // The whole sphere maps to the rectangle of the clip space
                   const FULL_SCREEN: ProjDefType = ProjDefType::FullScreen(FullScreen);
                   &FULL_SCREEN
-              }*/
+              }
               // CEA,                                 */
               /*ProjectionType::Cea(_) => {
                   const FULL_SCREEN: ProjDefType = ProjDefType::FullScreen(FullScreen);
@@ -539,6 +874,8 @@ impl Projection for ProjectionType {
             ProjectionType::Ait(ait) => ait.clip_to_world_space(xy),
             // MOL,      Mollweide                  */
             ProjectionType::Mol(mol) => mol.clip_to_world_space(xy),
+            // Hammer equal-area                    */
+            ProjectionType::Hammer(hammer) => hammer.clip_to_world_space(xy),
             // PAR,                                 */
             //ProjectionType::Par(par) => par.clip_to_world_space(xy),
             // SFL,                                 */
@@ -546,9 +883,15 @@ impl Projection for ProjectionType {
 
             // Cylindrical projections
             // MER,      Mercator                   */
-            ProjectionType::Mer(mer) => mer.clip_to_world_space(xy),
-            // CAR,                                 */
-            //ProjectionType::Car(car) => car.clip_to_world_space(xy),
+            ProjectionType::Mer(mer) => {
+                if xy.y.abs() <= 1.0 {
+                    mer.clip_to_world_space(xy)
+                } else {
+                    None
+                }
+            }
+            // CAR,      Plate carrée               */
+            ProjectionType::Car(car) => car.clip_to_world_space(xy),
             // CEA,                                 */
             //ProjectionType::Cea(cea) => cea.clip_to_world_space(xy),
             // CYP,                                 */
@@ -595,6 +938,8 @@ impl Projection for ProjectionType {
             ProjectionType::Ait(ait) => ait.world_to_clip_space(xyzw),
             // MOL,      Mollweide                  */
             ProjectionType::Mol(mol) => mol.world_to_clip_space(xyzw),
+            // Hammer equal-area                    */
+            ProjectionType::Hammer(hammer) => hammer.world_to_clip_space(xyzw),
             // PAR,                                 */
             //ProjectionType::Par(par) => par.world_to_clip_space(xyzw),
             // SFL,                                 */
@@ -602,9 +947,11 @@ impl Projection for ProjectionType {
 
             // Cylindrical projections
             // MER,      Mercator                   */
-            ProjectionType::Mer(mer) => mer.world_to_clip_space(xyzw),
-            // CAR,                                 */
-            //ProjectionType::Car(car) => car.world_to_clip_space(xyzw),
+            ProjectionType::Mer(mer) => mer
+                .world_to_clip_space(xyzw)
+                .filter(|xy| xy.y.abs() <= 1.0),
+            // CAR,      Plate carrée               */
+            ProjectionType::Car(car) => car.world_to_clip_space(xyzw),
             // CEA,                                 */
             //ProjectionType::Cea(cea) => cea.world_to_clip_space(xyzw),
             // CYP,                                 */
@@ -649,6 +996,10 @@ impl UniformType for ProjectionType {
             // Cylindrical projections
             // MER,      Mercator                   */
             ProjectionType::Mer(_) => gl.uniform1i(location, 6),
+            // CAR,      Plate carrée               */
+            ProjectionType::Car(_) => gl.uniform1i(location, 7),
+            // Hammer equal-area                    */
+            ProjectionType::Hammer(_) => gl.uniform1i(location, 8),
         }
     }
 }
@@ -768,6 +1119,158 @@ where
     }
 }
 
+/// The projection of a sky position straight to the clip space, i.e. as seen by a
+/// camera looking at the (lon=0, lat=0) point and showing the whole projection
+///
+/// It is given to the ``*_with`` variants of the tessellation functions to test them
+/// without any camera
+#[cfg(test)]
+pub(crate) fn clip_space_proj(projection: &ProjectionType) -> impl Fn(&LonLatT<f64>) -> Option<XYClip<f64>> + '_ {
+    move |lonlat| projection.world_to_clip_space(&lonlat.vector())
+}
+
+/// Same as ``clip_space_proj`` for a position given as a unit vector
+#[cfg(test)]
+pub(crate) fn clip_space_proj_xyz(projection: &ProjectionType) -> impl Fn(&coo_space::XYZModel<f64>) -> Option<XYClip<f64>> + '_ {
+    move |v| projection.world_to_clip_space(&v.extend(1.0))
+}
+
+// Same as `ProjectionType::pixels_to_angle` but does the unprojection with `unproject`
+fn pixels_to_angle_with<U>(ndc: &XYNDC<f64>, dx_px: f32, dy_px: f32, screen_size: &Vector2<f32>, unproject: U) -> Option<Angle<f64>>
+where
+    U: Fn(&XYNDC<f64>) -> Option<LonLatT<f64>>,
+{
+    // The y axis of the screen points downwards whereas the one of the NDC space points upwards
+    let d_ndc = XYNDC::new(
+        2.0 * dx_px as f64 / screen_size.x as f64,
+        -2.0 * dy_px as f64 / screen_size.y as f64,
+    );
+
+    let start = unproject(ndc)?;
+    let end = unproject(&(ndc + d_ndc))?;
+
+    Some(crate::math::lonlat::angular_distance(&start, &end))
+}
+
+// Same as `ProjectionType::limb` but tells whether a clip space position is
+// in the projection definition domain with `is_in`
+fn limb_with<F>(is_in: F) -> Option<Vec<XYClip<f64>>>
+where
+    F: Fn(&XYClip<f64>) -> bool,
+{
+    const NUM_RAYS: usize = 360;
+    // Distance the rays are cast to, beyond the domain of all the bounded projections
+    const MAX_RADIUS: f64 = 4.0;
+    const EPS: f64 = 1e-7;
+
+    let center = XYClip::new(0.0, 0.0);
+    if !is_in(&center) {
+        return None;
+    }
+
+    let mut limb = Vec::with_capacity(NUM_RAYS + 1);
+    for i in 0..NUM_RAYS {
+        let theta = 2.0 * PI * (i as f64) / (NUM_RAYS as f64);
+        let dir = XYClip::new(theta.cos(), theta.sin());
+        if is_in(&(dir * MAX_RADIUS)) {
+            return None;
+        }
+
+        // The position at r_in is in the domain contrary to the one at r_out
+        let (mut r_in, mut r_out) = (0.0, MAX_RADIUS);
+        while r_out - r_in > EPS {
+            let r = 0.5 * (r_in + r_out);
+
+            if is_in(&(dir * r)) {
+                r_in = r;
+            } else {
+                r_out = r;
+            }
+        }
+
+        limb.push(dir * r_in);
+    }
+    // Close the loop
+    limb.push(limb[0]);
+
+    Some(limb)
+}
+
+/// A north/east compass drawn on the screen
+#[derive(Clone, Copy, Debug)]
+pub struct Compass {
+    pub origin: XYNDC<f64>,
+    /// The arrow towards increasing declinations, in the normalized device space
+    pub north: Vector2<f64>,
+    /// The arrow towards increasing right ascensions, in the normalized device space
+    pub east: Vector2<f64>,
+}
+
+// The compass at a position, the arrows being the inverse of the jacobian of
+// the unprojection applied to the north and east unit vectors of the sky
+fn compass_with<U>(ndc_origin: XYNDC<f64>, size_px: f32, screen_size: &Vector2<f32>, unproject: U) -> Option<Compass>
+where
+    U: Fn(&XYNDC<f64>) -> Option<LonLatT<f64>>,
+{
+    // Step of the finite differences in the normalized device space
+    const H: f64 = 1e-6;
+
+    let center = unproject(&ndc_origin)?;
+    let (lon, lat) = (center.lon().to_radians(), center.lat().to_radians());
+    if lat.cos() < 1e-9 {
+        return None;
+    }
+
+    // The local basis of the sky at the position
+    let east = Vector3::new(lon.cos(), 0.0, -lon.sin());
+    let north = Vector3::new(-lat.sin() * lon.sin(), lat.cos(), -lat.sin() * lon.cos());
+
+    // The move on the sky, projected on the local basis, for a unit step along a screen axis
+    let derivative = |dir: Vector2<f64>| -> Option<Vector2<f64>> {
+        let v = |p: XYNDC<f64>| unproject(&p).map(|ll| ll.vector::<Vector3<f64>>());
+        let (v1, v2) = (v(ndc_origin + dir * H)?, v(ndc_origin - dir * H)?);
+        let dv = (v1 - v2) / (2.0 * H);
+
+        Some(Vector2::new(dv.dot(east), dv.dot(north)))
+    };
+    let d_x = derivative(Vector2::new(1.0, 0.0))?;
+    let d_y = derivative(Vector2::new(0.0, 1.0))?;
+
+    let det = d_x.x * d_y.y - d_y.x * d_x.y;
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let to_ndc = |sky: Vector2<f64>| Vector2::new(d_y.y * sky.x - d_y.x * sky.y, d_x.x * sky.y - d_x.y * sky.x) / det;
+
+    // Scale the arrows to the length asked on the screen
+    let px_per_ndc = Vector2::new(0.5 * screen_size.x as f64, 0.5 * screen_size.y as f64);
+    let arrow = |ndc: Vector2<f64>| {
+        let len_px = Vector2::new(ndc.x * px_per_ndc.x, ndc.y * px_per_ndc.y).magnitude();
+        ndc * (size_px as f64 / len_px)
+    };
+
+    Some(Compass {
+        origin: ndc_origin,
+        north: arrow(to_ndc(Vector2::new(0.0, 1.0))),
+        east: arrow(to_ndc(Vector2::new(1.0, 0.0))),
+    })
+}
+
+// Unproject `num_steps + 1` evenly spaced positions of the column from the top to the bottom of the screen
+fn sample_column_with<U>(ndc_x: f32, num_steps: usize, unproject: U) -> Vec<(f32, LonLatT<f64>)>
+where
+    U: Fn(&XYNDC<f64>) -> Option<LonLatT<f64>>,
+{
+    (0..=num_steps)
+        .filter_map(|i| {
+            let ndc_y = 1.0 - 2.0 * (i as f64) / (num_steps as f64);
+            let lonlat = unproject(&XYNDC::new(ndc_x as f64, ndc_y))?;
+
+            Some((ndc_y as f32, lonlat))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -845,11 +1348,11 @@ mod tests {
             "./../img/mer.jpg",
             ProjectionType::Mer(mapproj::cylindrical::mer::Mer),
         );
-        /*generate_projection_map(
-            "./../img/car.png",
+        generate_projection_map(
+            "./../img/car.jpg",
             ProjectionType::Car(mapproj::cylindrical::car::Car),
         );
-        generate_projection_map(
+        /*generate_projection_map(
             "./../img/cea.png",
             ProjectionType::Cea(mapproj::cylindrical::cea::Cea::new()),
         );
@@ -885,4 +1388,565 @@ mod tests {
             ProjectionType::Hpx(mapproj::hybrid::hpx::Hpx),
         );*/
     }
+
+    fn projections() -> Vec<super::ProjectionType> {
+        use super::ProjectionType;
+
+        vec![
+            ProjectionType::Tan(mapproj::zenithal::tan::Tan),
+            ProjectionType::Stg(mapproj::zenithal::stg::Stg),
+            ProjectionType::Sin(mapproj::zenithal::sin::Sin),
+            ProjectionType::Zea(mapproj::zenithal::zea::Zea),
+            ProjectionType::Ait(mapproj::pseudocyl::ait::Ait),
+            ProjectionType::Mol(mapproj::pseudocyl::mol::Mol::new()),
+            ProjectionType::Mer(mapproj::cylindrical::mer::Mer),
+            ProjectionType::Car(mapproj::cylindrical::car::Car),
+        ]
+    }
+
+    #[test]
+    fn unproject_proj_roundtrip() {
+        use super::*;
+        use crate::math::angle::ToAngle;
+
+        for projection in projections() {
+            for lon in (-170..=170).step_by(20) {
+                for lat in (-80..=80).step_by(20) {
+                    let lonlat = LonLatT::new(
+                        (lon as f64).to_radians().to_angle(),
+                        (lat as f64).to_radians().to_angle(),
+                    );
+                    let xyzw: XYZWWorld<f64> = lonlat.vector();
+
+                    // The position may not be defined, e.g. behind zenithal projections
+                    if let Some(clip_pos) = projection.world_to_clip_space(&xyzw) {
+                        // The camera looking at the (0, 0) point, the world space is the model one
+                        let res = projection
+                            .clip_to_world_space_checked(&clip_pos)
+                            .unwrap();
+
+                        let d = crate::math::vector::angle3(&xyzw.truncate(), &res.truncate());
+                        assert!(d.to_radians() < 1e-6);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unproject_outside_of_the_projection() {
+        use super::*;
+
+        // The corners of the Aitoff and Mollweide ellipses
+        for projection in [
+            ProjectionType::Ait(mapproj::pseudocyl::ait::Ait),
+            ProjectionType::Mol(mapproj::pseudocyl::mol::Mol::new()),
+        ] {
+            let corner = XYClip::new(0.99, 0.99);
+            assert!(projection.clip_to_world_space_checked(&corner).is_none());
+        }
+    }
+
+    #[test]
+    fn equal_area_local_scale_is_constant() {
+        use super::*;
+
+        for projection in [
+            ProjectionType::Mol(mapproj::pseudocyl::mol::Mol::new()),
+            ProjectionType::Zea(mapproj::zenithal::zea::Zea),
+        ] {
+            let area = |clip_pos: XYClip<f64>| {
+                let (sx, sy) = projection.local_scale_clip_space(&clip_pos).unwrap();
+                sx * sy
+            };
+            let center = area(XYClip::new(0.0, 0.0));
+
+            // Along the axes, the screen directions are orthogonal on the sky, so that
+            // the product of the scales is the area of a pixel
+            for t in [0.1, 0.3, 0.5, 0.7] {
+                for clip_pos in [XYClip::new(t, 0.0), XYClip::new(0.0, t), XYClip::new(-t, 0.0)] {
+                    let a = area(clip_pos);
+                    assert!((a - center).abs() < 1e-3 * center, "{} {}", a, center);
+                }
+            }
+
+            // The sky is stretched differently along both directions
+            let (sx, sy) = projection.local_scale_clip_space(&XYClip::new(0.7, 0.0)).unwrap();
+            assert!((sx - sy).abs() > 1e-2 * sx);
+        }
+
+        // Outside of the projection
+        let projection = ProjectionType::Mol(mapproj::pseudocyl::mol::Mol::new());
+        assert!(projection.local_scale_clip_space(&XYClip::new(0.99, 0.99)).is_none());
+    }
+
+    #[test]
+    fn tan_follows_the_gnomonic_scaling() {
+        use super::*;
+        use crate::math::angle::ToAngle;
+
+        let projection = ProjectionType::Tan(mapproj::zenithal::tan::Tan);
+        let offset = |lon: f64, lat: f64| -> Option<f64> {
+            let xyzw: XYZWWorld<f64> =
+                LonLatT::new(lon.to_radians().to_angle(), lat.to_radians().to_angle()).vector();
+            projection
+                .world_to_clip_space(&xyzw)
+                .map(|clip_pos| clip_pos.magnitude())
+        };
+
+        // The distance to the tangent point grows as tan(theta)
+        let scale = offset(0.1, 0.0).unwrap() / 0.1_f64.to_radians().tan();
+        for theta in [0.5, 1.0, 5.0, 10.0, 20.0] {
+            let expected = scale * theta.to_radians().tan();
+
+            let along_lon = offset(theta, 0.0).unwrap();
+            let along_lat = offset(0.0, theta).unwrap();
+            assert!((along_lon - expected).abs() < 1e-9 * expected.max(1.0));
+            assert!((along_lat - expected).abs() < 1e-9 * expected.max(1.0));
+        }
+
+        // Only the visible hemisphere is projected
+        assert!(offset(89.0, 0.0).is_some());
+        assert!(offset(91.0, 0.0).is_none());
+        assert!(offset(180.0, 0.0).is_none());
+        assert!(offset(0.0, -95.0).is_none());
+    }
+
+    #[test]
+    fn stg_maps_circles_to_circles() {
+        use super::*;
+        use crate::math::angle::ToAngle;
+        use cgmath::Vector3;
+
+        let projection = ProjectionType::Stg(mapproj::zenithal::stg::Stg);
+
+        // A circle of 20 degrees radius far from the center of projection
+        let center: Vector3<f64> =
+            LonLatT::new(60.0_f64.to_radians().to_angle(), 30.0_f64.to_radians().to_angle())
+                .vector();
+        let e1 = center.cross(Vector3::new(0.0, 1.0, 0.0)).normalize();
+        let e2 = center.cross(e1);
+        let radius = 20.0_f64.to_radians();
+
+        let vertices = (0..36)
+            .map(|i| {
+                let phi = (i as f64) * 10.0_f64.to_radians();
+                let v = center * radius.cos() + (e1 * phi.cos() + e2 * phi.sin()) * radius.sin();
+                projection.world_to_clip_space(&v.extend(1.0)).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        // Circumcenter of the three first vertices
+        let (a, b, c) = (vertices[0], vertices[12], vertices[24]);
+        let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+        let o = XYClip::new(
+            (a.magnitude2() * (b.y - c.y) + b.magnitude2() * (c.y - a.y) + c.magnitude2() * (a.y - b.y)) / d,
+            (a.magnitude2() * (c.x - b.x) + b.magnitude2() * (a.x - c.x) + c.magnitude2() * (b.x - a.x)) / d,
+        );
+        let r = (a - o).magnitude();
+
+        for v in &vertices {
+            assert!(((*v - o).magnitude() - r).abs() < 1e-9 * r.max(1.0));
+        }
+    }
+
+    #[test]
+    fn stg_antipode_is_not_projected() {
+        use super::*;
+
+        let projection = ProjectionType::Stg(mapproj::zenithal::stg::Stg);
+        // The antipode of the (0, 0) center of projection
+        let antipode = XYZWWorld::new(0.0, 0.0, -1.0, 1.0);
+        assert!(projection.world_to_clip_space(&antipode).is_none());
+    }
+
+    #[test]
+    fn valid_domain_matches_proj() {
+        use super::*;
+        use crate::math::angle::ToAngle;
+
+        for projection in projections() {
+            let mut num_invalid = 0;
+            for lon in (0..360).step_by(3) {
+                for lat in (-90..=90).step_by(3) {
+                    let lonlat = LonLatT::new(
+                        (lon as f64).to_radians().to_angle(),
+                        (lat as f64).to_radians().to_angle(),
+                    );
+                    let xyzw: XYZWWorld<f64> = lonlat.vector();
+
+                    let valid = projection.is_in_valid_domain_world_space(&xyzw);
+                    assert_eq!(valid, projection.world_to_clip_space(&xyzw).is_some());
+
+                    num_invalid += (!valid) as usize;
+                }
+            }
+
+            // Zenithal projections do not project the whole sky
+            if let ProjectionType::Tan(_) | ProjectionType::Sin(_) = projection {
+                assert!(num_invalid > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn lerp_reproduces_the_endpoints() {
+        use super::*;
+        use crate::math::angle::ToAngle;
+
+        let from = ProjectionType::Sin(mapproj::zenithal::sin::Sin);
+        let to = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait);
+
+        for lon in (-170..=170).step_by(20) {
+            for lat in (-80..=80).step_by(20) {
+                let xyzw: XYZWWorld<f64> = LonLatT::new(
+                    (lon as f64).to_radians().to_angle(),
+                    (lat as f64).to_radians().to_angle(),
+                )
+                .vector();
+
+                let p1 = from.world_to_clip_space(&xyzw);
+                let p2 = to.world_to_clip_space(&xyzw).unwrap();
+
+                let (start, alpha) =
+                    ProjectionType::lerp_clip_space(&from, &to, 0.0, &xyzw).unwrap();
+                if let Some(p1) = p1 {
+                    assert_eq!(start, p1);
+                    assert_eq!(alpha, 1.0);
+                } else {
+                    // Behind the sinus hemisphere, the position is hidden at t = 0
+                    assert_eq!(alpha, 0.0);
+                }
+
+                let (end, alpha) =
+                    ProjectionType::lerp_clip_space(&from, &to, 1.0, &xyzw).unwrap();
+                assert_eq!(end, p2);
+                assert_eq!(alpha, 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn proj_batch_matches_the_single_point_projection() {
+        use super::*;
+        use crate::math::angle::ToAngle;
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let points = (0..1000)
+            .map(|_| {
+                let lon: f64 = rng.gen_range(0.0..crate::math::TWICE_PI);
+                let lat: f64 = rng.gen_range(-crate::math::HALF_PI..crate::math::HALF_PI);
+                LonLatT::new(lon.to_angle(), lat.to_angle())
+            })
+            .collect::<Vec<_>>();
+
+        // A rotated camera zooming in the view
+        let m2w: Matrix4<f64> = Matrix4::from_angle_y(cgmath::Rad(0.7)) * Matrix4::from_angle_x(cgmath::Rad(-0.3));
+        let ndc_to_clip = Vector2::new(2.0, 1.0) * 0.25;
+
+        for projection in [
+            ProjectionType::Sin(mapproj::zenithal::sin::Sin),
+            ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new()),
+            ProjectionType::Mer(mapproj::cylindrical::mer::Mer),
+        ] {
+            let batch = projection.proj_batch_with(&points, &m2w, &ndc_to_clip);
+            assert_eq!(batch.len(), points.len());
+            // Some positions are out of the domain of the orthographic projection
+            if let ProjectionType::Sin(_) = projection {
+                assert!(batch.iter().any(|p| p.is_none()));
+            }
+
+            for (lonlat, p) in points.iter().zip(batch) {
+                // What model_to_normalized_device_space does for one point
+                let expected = projection
+                    .world_to_clip_space(&(m2w * lonlat.vector::<XYZWModel<f64>>()))
+                    .map(|c| Vector2::new(c.x / ndc_to_clip.x, c.y / ndc_to_clip.y));
+                assert_eq!(p, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn orthographic_offsets_scale_as_sin() {
+        use super::*;
+        use crate::math::angle::ToAngle;
+
+        let projection = ProjectionType::Sin(mapproj::zenithal::sin::Sin);
+
+        // The camera looking at the (0, 0) point, the world space is the model one
+        for theta in [1e-4_f64, 1e-2, 0.1, 0.5, 1.2] {
+            for (lon, lat) in [(theta, 0.0), (0.0, theta), (-theta, 0.0)] {
+                let xyzw: XYZWWorld<f64> = LonLatT::new(lon.to_angle(), lat.to_angle()).vector();
+                let clip_pos = projection.world_to_clip_space(&xyzw).unwrap();
+
+                assert!((clip_pos.magnitude() - theta.sin()).abs() < 1e-12);
+            }
+        }
+
+        // The far hemisphere cannot be projected
+        let far: XYZWWorld<f64> = LonLatT::new(2.0_f64.to_angle(), 0.0_f64.to_angle()).vector();
+        assert!(projection.world_to_clip_space(&far).is_none());
+        // and the positions out of the disk cannot be unprojected
+        assert!(projection.clip_to_world_space(&XYClip::new(0.8, 0.8)).is_none());
+    }
+
+    #[test]
+    fn hammer_is_equal_area() {
+        use super::*;
+        use crate::math::angle::ToAngle;
+        use cgmath::Vector3;
+
+        let projection = ProjectionType::from_name("HAMMER").unwrap();
+
+        // Area in the clip space of the cap of radius `r` around (lon, lat)
+        let clip_area = |lon: f64, lat: f64, r: f64| {
+            let c: Vector3<f64> = LonLatT::new(lon.to_angle(), lat.to_angle()).vector();
+            let e1 = c.cross(Vector3::new(0.0, 1.0, 0.0)).normalize();
+            let e2 = c.cross(e1);
+
+            const N: usize = 2000;
+            let boundary = (0..N)
+                .map(|i| {
+                    let phi = (i as f64) * crate::math::TWICE_PI / (N as f64);
+                    let v = c * r.cos() + (e1 * phi.cos() + e2 * phi.sin()) * r.sin();
+                    projection.world_to_clip_space(&v.extend(1.0)).unwrap()
+                })
+                .collect::<Vec<_>>();
+
+            // Shoelace formula
+            let twice_area: f64 = boundary
+                .iter()
+                .zip(boundary.iter().cycle().skip(1))
+                .map(|(a, b)| a.x * b.y - a.y * b.x)
+                .sum();
+            0.5 * twice_area.abs()
+        };
+
+        // The whole sky, of area 4*PI, fills the unit disk of the clip space
+        for (lon, lat, r) in [(0.0, 0.0, 0.2), (1.0, 0.5, 0.3), (-2.0, -1.0, 0.1)] {
+            let sky_area = crate::math::TWICE_PI * (1.0 - f64::cos(r));
+            let expected = sky_area * PI / (4.0 * PI);
+
+            assert!((clip_area(lon, lat, r) - expected).abs() < 1e-3 * expected);
+        }
+
+        // The corners of the clip space are out of the ellipse
+        for (x, y) in [(0.9, 0.9), (-0.9, 0.9), (0.9, -0.9), (-0.9, -0.9)] {
+            assert!(projection.clip_to_world_space(&XYClip::new(x, y)).is_none());
+        }
+    }
+
+    #[test]
+    fn hammer_unprojects_its_projections() {
+        use super::*;
+        use crate::math::angle::ToAngle;
+
+        let projection = ProjectionType::Hammer(hammer::Hammer);
+        for (lon, lat) in [(0.0_f64, 0.0_f64), (1.0, 0.5), (-3.0, -1.2), (3.1, 1.5)] {
+            let xyzw: XYZWWorld<f64> = LonLatT::new(lon.to_angle(), lat.to_angle()).vector();
+            let clip = projection.world_to_clip_space(&xyzw).unwrap();
+            // The whole sky lies in the ellipse
+            assert!(clip.magnitude() <= 1.0);
+
+            let back = projection.clip_to_world_space(&clip).unwrap();
+            assert!((back - xyzw).magnitude() < 1e-9);
+        }
+
+        // The longitudes increase towards the left
+        let east: XYZWWorld<f64> = LonLatT::new(0.5_f64.to_angle(), 0.0_f64.to_angle()).vector();
+        assert!(projection.world_to_clip_space(&east).unwrap().x < 0.0);
+    }
+
+    #[test]
+    fn column_of_an_aitoff_view() {
+        use super::*;
+
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        let unproject = |ndc: &XYNDC<f64>| {
+            projection
+                .clip_to_world_space_checked(ndc)
+                .map(|world_pos| world_pos.lonlat())
+        };
+
+        // The central meridian: the latitudes decrease down the column and are
+        // symmetric about the center of the screen
+        let samples = sample_column_with(0.0, 100, unproject);
+        assert!(!samples.is_empty());
+        assert!(samples.windows(2).all(|s| s[1].1.lat().to_radians() < s[0].1.lat().to_radians()));
+        for ((y1, top), (y2, bottom)) in samples.iter().zip(samples.iter().rev()) {
+            assert!((y1 + y2).abs() < 1e-6);
+            // The longitude is not defined at the poles
+            assert!(top.lon().to_radians().abs() < 1e-9 || top.lat().to_radians().abs() > 1.5707);
+            assert!((top.lat().to_radians() + bottom.lat().to_radians()).abs() < 1e-9);
+        }
+
+        // Off the center, the longitudes are symmetric about the equator and grow towards the poles
+        let samples = sample_column_with(0.5, 100, unproject);
+        let n = samples.len();
+        assert!(n > 2);
+        for i in 0..n {
+            let (lon1, lon2) = (samples[i].1.lon().to_radians(), samples[n - 1 - i].1.lon().to_radians());
+            assert!((lon1 - lon2).abs() < 1e-9);
+        }
+        let lon = |i: usize| samples[i].1.lon().to_radians().abs();
+        assert!(lon(0) > lon(n / 2));
+    }
+
+    #[test]
+    fn compass_follows_the_camera_roll() {
+        use super::*;
+        use cgmath::Rad;
+
+        let projection = ProjectionType::Sin(mapproj::zenithal::sin::Sin::new());
+        let screen_size = Vector2::new(800.0, 800.0);
+        // A view centered on (150°, +30°) rolled around its center
+        let compass = |roll: f64| {
+            let w2m = Matrix4::from_angle_y(Rad(150.0_f64.to_radians()))
+                * Matrix4::from_angle_x(Rad(-30.0_f64.to_radians()))
+                * Matrix4::from_angle_z(Rad(roll.to_radians()));
+            let unproject = |ndc: &XYNDC<f64>| {
+                projection
+                    .clip_to_world_space_checked(ndc)
+                    .map(|world_pos| (w2m * world_pos).lonlat())
+            };
+
+            compass_with(XYNDC::new(0.0, 0.0), 40.0, &screen_size, unproject).unwrap()
+        };
+
+        let (c1, c2) = (compass(0.0), compass(90.0));
+        let angle = |v: Vector2<f64>| v.y.atan2(v.x);
+        let d_angle = (angle(c2.north) - angle(c1.north)).rem_euclid(2.0 * PI);
+        assert!((d_angle - 0.5 * PI).abs() < 1e-3 || (d_angle - 1.5 * PI).abs() < 1e-3);
+
+        // The arrows are 40 pixels long and perpendicular at the center of the view
+        for c in [c1, c2] {
+            let len_px = |v: Vector2<f64>| (v * 400.0).magnitude();
+            assert!((len_px(c.north) - 40.0).abs() < 1e-6);
+            assert!((len_px(c.east) - 40.0).abs() < 1e-6);
+            assert!(c.north.normalize().dot(c.east.normalize()).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn pixels_to_angle_at_the_center_and_near_the_limb() {
+        use super::*;
+
+        let projection = ProjectionType::Sin(mapproj::zenithal::sin::Sin::new());
+        // A NDC unit spans 500 pixels
+        let screen_size = Vector2::new(1000.0, 1000.0);
+        let unproject = |ndc: &XYNDC<f64>| {
+            projection
+                .clip_to_world_space_checked(ndc)
+                .map(|world_pos| world_pos.lonlat())
+        };
+        let angle = |x: f64, dx_px: f32, dy_px: f32| {
+            pixels_to_angle_with(&XYNDC::new(x, 0.0), dx_px, dy_px, &screen_size, unproject).map(|a| a.to_radians())
+        };
+
+        // The plate scale of the orthographic projection at its center, 500 pixels per radian
+        let d = angle(0.0, 10.0, 0.0).unwrap();
+        assert!((d - 0.02_f64.asin()).abs() < 1e-12);
+        assert!((angle(0.0, 0.0, -10.0).unwrap() - d).abs() < 1e-12);
+        assert!((angle(0.0, 6.0, 8.0).unwrap() - d).abs() < 1e-9);
+
+        // Near the limb, the radial moves are stretched but the tangential ones are not
+        let (radial, tangential) = (angle(0.98, -1.0, 0.0).unwrap(), angle(0.98, 0.0, 1.0).unwrap());
+        assert!((radial - (0.98_f64.asin() - 0.978_f64.asin())).abs() < 1e-9);
+        assert!(radial > 4.0 * tangential);
+        assert!((tangential - 0.002).abs() < 1e-4);
+
+        // Moving out of the disk
+        assert!(angle(0.98, 20.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn plate_carree_is_separable() {
+        use super::*;
+        use crate::math::angle::ToAngle;
+
+        let projection = ProjectionType::Car(mapproj::cylindrical::car::Car::new());
+        let proj = |lon: f64, lat: f64| {
+            let lonlat = LonLatT::new(lon.to_radians().to_angle(), lat.to_radians().to_angle());
+            projection.world_to_clip_space(&lonlat.vector()).unwrap()
+        };
+
+        for lon in (-170..=170).step_by(20) {
+            for lat in (-80..=80).step_by(20) {
+                let (lon, lat) = (lon as f64, lat as f64);
+                let p = proj(lon, lat);
+
+                // x only depends on the longitude and y on the latitude, both linearly
+                assert!((p.x - proj(lon, 0.0).x).abs() < 1e-9);
+                assert!((p.y - proj(0.0, lat).y).abs() < 1e-9);
+                assert!((p.x.abs() - lon.abs() / 180.0).abs() < 1e-9);
+                assert!((p.y.abs() - lat.abs() / 90.0).abs() < 1e-9);
+            }
+        }
+
+        // The whole sphere is mapped, the poles stretching to the top and bottom edges
+        assert!((proj(60.0, 90.0).y - 1.0).abs() < 1e-9);
+        assert!((proj(60.0, -90.0).y + 1.0).abs() < 1e-9);
+        assert!(projection.clip_to_world_space_checked(&XYClip::new(0.99, 0.99)).is_some());
+    }
+
+    #[test]
+    fn aitoff_limb_is_a_closed_ellipse() {
+        use super::*;
+
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        let limb = projection.limb().unwrap();
+        assert_eq!(limb.first(), limb.last());
+
+        // On a square screen, the y axis of the clip space is scaled by the bounds size ratio
+        let ratio = projection.bounds_size_ratio();
+        let (mut min, mut max) = (XYNDC::new(f64::MAX, f64::MAX), XYNDC::new(f64::MIN, f64::MIN));
+        for clip in &limb {
+            let ndc = XYNDC::new(clip.x, clip.y / ratio);
+            min = XYNDC::new(min.x.min(ndc.x), min.y.min(ndc.y));
+            max = XYNDC::new(max.x.max(ndc.x), max.y.max(ndc.y));
+        }
+        let aspect = (max.x - min.x) / (max.y - min.y);
+        assert!((aspect - 2.0).abs() < 1e-3, "{}", aspect);
+
+        // Not bounded
+        assert!(ProjectionType::Tan(mapproj::zenithal::tan::Tan).limb().is_none());
+    }
+
+    #[test]
+    fn mercator_is_conformal_and_clamped() {
+        use super::*;
+        use crate::math::angle::ToAngle;
+
+        let projection = ProjectionType::Mer(mapproj::cylindrical::mer::Mer::new());
+
+        for (x, y) in [(0.0, 0.0), (0.5, 0.3), (-0.8, -0.6), (0.2, 0.95)] {
+            let (sx, sy) = projection.local_scale_clip_space(&XYClip::new(x, y)).unwrap();
+            assert!((sx - sy).abs() / sx < 1e-4);
+        }
+
+        // The top edge of the map is the max latitude
+        let edge = projection.clip_to_world_space(&XYClip::new(0.0, 1.0)).unwrap().lonlat();
+        assert!((edge.lat().to_radians() - MER_MAX_LAT).abs() < 1e-9);
+
+        // Beyond, nothing is defined
+        assert!(projection.clip_to_world_space_checked(&XYClip::new(0.0, 1.01)).is_none());
+        assert!(projection.clip_to_world_space_checked(&XYClip::new(0.3, -1.2)).is_none());
+        for lat in [86.0_f64, -88.0, 90.0] {
+            let lonlat = LonLatT::new(0.0_f64.to_angle(), lat.to_radians().to_angle());
+            assert!(projection.world_to_clip_space(&lonlat.vector()).is_none());
+        }
+    }
+
+    #[test]
+    fn every_listed_name_is_accepted() {
+        use super::*;
+
+        for name in ProjectionType::NAMES {
+            let projection = ProjectionType::from_name(name).unwrap();
+            assert!(ProjectionType::from_name(projection.name()).is_some());
+        }
+        assert!(ProjectionType::from_name("ARC").is_none());
+    }
 }
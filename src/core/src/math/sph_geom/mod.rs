@@ -2,7 +2,8 @@ pub mod bbox;
 pub mod region;
 pub mod great_circle_arc;
 
-use super::{PI, TWICE_PI};
+use super::{HALF_PI, PI, TWICE_PI};
+use crate::LonLatT;
 
 #[inline]
 pub fn is_in_lon_range(lon0: f64, lon1: f64, lon2: f64) -> bool {
@@ -59,3 +60,236 @@ pub fn distance_from_two_lon(lon1: f64, lon2: f64) -> f64 {
     }
 }
 
+// Returns the signed longitude difference from lon1 to lon2 taking the
+// shortest way, i.e. going across RA=0 when it is shorter. It lies in [-PI; PI]
+#[inline]
+pub(crate) fn delta_lon(lon1: f64, lon2: f64) -> f64 {
+    let dlon = (lon2 - lon1) % TWICE_PI;
+    if dlon > PI {
+        dlon - TWICE_PI
+    } else if dlon < -PI {
+        dlon + TWICE_PI
+    } else {
+        dlon
+    }
+}
+
+/// Signed area of a spherical polygon on the unit sphere
+///
+/// The area is positive when the vertices are given counter-clockwise, i.e. the
+/// polygon lying on the left of its edges as seen from outside the sphere. The
+/// edges are great circle arcs, the polygon being implicitely closed.
+///
+/// The polygon is decomposed into the triangles formed by each edge and the
+/// north pole. Polygons crossing RA=0 and polygons containing a pole are handled.
+pub fn signed_spherical_area(vertices: &[LonLatT<f64>]) -> f64 {
+    if vertices.len() < 3 {
+        return 0.0;
+    }
+
+    // tan of half the colatitude
+    let tan_half_colat = |v: &LonLatT<f64>| ((HALF_PI - v.lat().to_radians()) * 0.5).tan();
+
+    vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(v1, v2)| {
+            let dlon = delta_lon(v1.lon().to_radians(), v2.lon().to_radians());
+            let t = tan_half_colat(v1) * tan_half_colat(v2);
+
+            // Signed area of the triangle (north pole, v1, v2)
+            2.0 * (t * dlon.sin()).atan2(1.0 + t * dlon.cos())
+        })
+        .sum()
+}
+
+/// Tell whether a position lies inside a spherical polygon
+///
+/// The number of edges crossed by the meridian arc going from the position to
+/// the north pole is counted, the edges being great circle arcs. The longitude
+/// range of an edge is tested with ``is_in_lon_range`` so that polygons crossing
+/// RA=0 are handled. A polygon turning around a pole contains that pole, i.e.
+/// the one of the hemisphere where most of its vertices lie.
+pub fn is_in_polygon(lonlat: &LonLatT<f64>, vertices: &[LonLatT<f64>]) -> bool {
+    if vertices.len() < 3 {
+        return false;
+    }
+
+    let (lon0, lat0) = (lonlat.lon().to_radians(), lonlat.lat().to_radians());
+    let edges = || vertices.iter().zip(vertices.iter().cycle().skip(1));
+
+    let num_crossings = edges()
+        .filter(|(v1, v2)| {
+            let (lon1, lon2) = (v1.lon().to_radians(), v2.lon().to_radians());
+            if !is_in_lon_range(lon0, lon1, lon2) {
+                return false;
+            }
+
+            // Latitude of the great circle of the edge at lon0
+            let n = v1.vector::<cgmath::Vector3<f64>>().cross(v2.vector());
+            let lat = (-(n.x * lon0.sin() + n.z * lon0.cos())).atan2(n.y);
+            let lat = if lat > HALF_PI {
+                lat - PI
+            } else if lat < -HALF_PI {
+                lat + PI
+            } else {
+                lat
+            };
+
+            lat > lat0
+        })
+        .count();
+
+    // A polygon turning around a pole has a winding of +/-2*PI
+    let winding: f64 = edges()
+        .map(|(v1, v2)| delta_lon(v1.lon().to_radians(), v2.lon().to_radians()))
+        .sum();
+    let north_pole_inside =
+        winding.abs() > PI && vertices.iter().map(|v| v.lat().to_radians()).sum::<f64>() > 0.0;
+
+    (num_crossings % 2 == 1) != north_pole_inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_in_lon_range;
+
+    fn in_range(lon0: f64, lon1: f64, lon2: f64) -> bool {
+        is_in_lon_range(lon0.to_radians(), lon1.to_radians(), lon2.to_radians())
+    }
+
+    // Number of polygon edges crossed by the northward meridian arc starting
+    // from (lon0, lat0). The latitude of an edge is linearly interpolated
+    fn num_crossings(lon0: f64, lat0: f64, vertices: &[(f64, f64)]) -> usize {
+        vertices
+            .iter()
+            .zip(vertices.iter().cycle().skip(1))
+            .filter(|(&(lon1, lat1), &(lon2, lat2))| {
+                if !in_range(lon0, lon1, lon2) {
+                    return false;
+                }
+
+                let wrap = |d: f64| (d + 540.0) % 360.0 - 180.0;
+                let t = wrap(lon0 - lon1) / wrap(lon2 - lon1);
+                lat1 + t * (lat2 - lat1) > lat0
+            })
+            .count()
+    }
+
+    #[test]
+    fn lon_range_non_wrapping() {
+        // [lon1; lon2[ whatever the order of the vertices
+        assert!(in_range(10.0, 10.0, 30.0));
+        assert!(!in_range(30.0, 10.0, 30.0));
+        assert!(in_range(10.0, 30.0, 10.0));
+        assert!(!in_range(30.0, 30.0, 10.0));
+
+        assert!(in_range(20.0, 10.0, 30.0));
+        assert!(!in_range(5.0, 10.0, 30.0));
+        assert!(!in_range(35.0, 30.0, 10.0));
+    }
+
+    #[test]
+    fn lon_range_wrapping() {
+        // Crossing RA=0: [350; 360[ U [0; 10[
+        assert!(in_range(350.0, 350.0, 10.0));
+        assert!(!in_range(10.0, 350.0, 10.0));
+        assert!(in_range(350.0, 10.0, 350.0));
+        assert!(!in_range(10.0, 10.0, 350.0));
+
+        assert!(in_range(0.0, 350.0, 10.0));
+        assert!(in_range(355.0, 10.0, 350.0));
+        assert!(in_range(5.0, 10.0, 350.0));
+        assert!(!in_range(180.0, 350.0, 10.0));
+    }
+
+    #[test]
+    fn odd_intersections_at_a_vertex_shared_with_a_ra0_crossing_segment() {
+        // The vertex at lon=350 is part of a segment not crossing RA=0 (340 -> 350)
+        // and of a segment crossing it (350 -> 20). A point inside the polygon sharing
+        // the longitude of that vertex must intersect it an odd number of times
+        let polygon = [(340.0, -10.0), (350.0, 10.0), (20.0, 10.0), (20.0, -10.0)];
+        assert_eq!(num_crossings(350.0, 0.0, &polygon), 1);
+
+        let reversed = polygon.iter().rev().cloned().collect::<Vec<_>>();
+        assert_eq!(num_crossings(350.0, 0.0, &reversed), 1);
+
+        // Outside the polygon
+        assert_eq!(num_crossings(350.0, 20.0, &polygon), 0);
+        assert_eq!(num_crossings(350.0, -20.0, &polygon) % 2, 0);
+    }
+
+    fn polygon(vertices: &[(f64, f64)]) -> Vec<crate::LonLatT<f64>> {
+        use crate::math::angle::ToAngle;
+
+        vertices
+            .iter()
+            .map(|(lon, lat)| crate::LonLatT::new(lon.to_radians().to_angle(), lat.to_radians().to_angle()))
+            .collect()
+    }
+
+    #[test]
+    fn point_in_polygon_across_ra0_and_around_a_pole() {
+        use super::is_in_polygon;
+
+        let square = polygon(&[(355.0, -5.0), (5.0, -5.0), (5.0, 5.0), (355.0, 5.0)]);
+        let p = |lon: f64, lat: f64| polygon(&[(lon, lat)])[0];
+        assert!(is_in_polygon(&p(0.0, 0.0), &square));
+        assert!(is_in_polygon(&p(358.0, 4.0), &square));
+        assert!(!is_in_polygon(&p(10.0, 0.0), &square));
+        assert!(!is_in_polygon(&p(180.0, 0.0), &square));
+
+        // A square around the north pole, whatever the orientation
+        let cap = polygon(&[(0.0, 80.0), (90.0, 80.0), (180.0, 80.0), (270.0, 80.0)]);
+        let reversed = cap.iter().rev().cloned().collect::<Vec<_>>();
+        for cap in [cap, reversed] {
+            assert!(is_in_polygon(&p(0.0, 90.0), &cap));
+            assert!(is_in_polygon(&p(45.0, 85.0), &cap));
+            assert!(!is_in_polygon(&p(45.0, 70.0), &cap));
+            assert!(!is_in_polygon(&p(200.0, -89.0), &cap));
+        }
+
+        // around the south pole
+        let cap = polygon(&[(0.0, -80.0), (90.0, -80.0), (180.0, -80.0), (270.0, -80.0)]);
+        assert!(is_in_polygon(&p(123.0, -85.0), &cap));
+        assert!(!is_in_polygon(&p(123.0, 0.0), &cap));
+    }
+
+    #[test]
+    fn area_of_a_small_square() {
+        use super::signed_spherical_area;
+
+        let side = 1.0_f64.to_radians();
+        for vertices in [
+            [(10.0, 0.0), (11.0, 0.0), (11.0, 1.0), (10.0, 1.0)],
+            // across RA=0
+            [(359.5, 0.0), (0.5, 0.0), (0.5, 1.0), (359.5, 1.0)],
+        ] {
+            let square = polygon(&vertices);
+
+            let area = signed_spherical_area(&square);
+            assert!((area - side * side).abs() < 1e-3 * side * side);
+
+            let reversed = square.into_iter().rev().collect::<Vec<_>>();
+            assert!((signed_spherical_area(&reversed) + area).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn area_of_a_polygon_enclosing_the_north_pole() {
+        use super::signed_spherical_area;
+
+        // A ring of constant latitude, close to the spherical cap
+        let lat = 80.0_f64;
+        let ring = polygon(&(0..360).map(|lon| (lon as f64, lat)).collect::<Vec<_>>());
+
+        let cap = 2.0 * std::f64::consts::PI * (1.0 - lat.to_radians().sin());
+        let area = signed_spherical_area(&ring);
+        assert!((area - cap).abs() < 1e-3 * cap);
+
+        // The edges of a square go closer to the pole than the parallel
+        let square = polygon(&[(0.0, lat), (90.0, lat), (180.0, lat), (270.0, lat)]);
+        let area = signed_spherical_area(&square);
+        assert!(area > 0.0 && area < cap);
+    }
+}
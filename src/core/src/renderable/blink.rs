@@ -0,0 +1,114 @@
+use crate::time::DeltaTime;
+
+/// How the two layers of a blink comparison succeed each other
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlinkMode {
+    /// Only one of the layers is drawn at a time, each during a period
+    Alternate,
+    /// The layers fade into each other, one transition lasting a period
+    CrossFade,
+}
+
+/// Comparison of two image layers by blinking them
+///
+/// The layers keep their own color config (transfer function, colormap, ...),
+/// only their opacity is modulated.
+pub struct Blink {
+    pub layer_a: String,
+    pub layer_b: String,
+    // Duration of one period in ms
+    period: f32,
+    mode: BlinkMode,
+    // Time elapsed in the current cycle, i.e. two periods, in ms
+    elapsed: f32,
+}
+
+impl Blink {
+    pub fn new(layer_a: String, layer_b: String, period: DeltaTime, mode: BlinkMode) -> Result<Self, &'static str> {
+        if !(period.as_millis() > 0.0) {
+            return Err("The blink period must be positive");
+        }
+
+        Ok(Self {
+            layer_a,
+            layer_b,
+            period: period.as_millis(),
+            mode,
+            elapsed: 0.0,
+        })
+    }
+
+    /// Move forward by `dt`
+    ///
+    /// The blink only depends on the time elapsed, not on the number of frames drawn
+    pub fn update(&mut self, dt: DeltaTime) {
+        self.elapsed = (self.elapsed + dt.as_millis().max(0.0)) % (2.0 * self.period);
+    }
+
+    /// The weight of the second layer, between [0, 1], the first one being weighted by its complement
+    pub fn blend_factor(&self) -> f32 {
+        let t = self.elapsed / self.period;
+
+        match self.mode {
+            BlinkMode::Alternate => {
+                if t < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            // Go from A to B during the first period and back to A during the second
+            BlinkMode::CrossFade => {
+                if t < 1.0 {
+                    t
+                } else {
+                    2.0 - t
+                }
+            }
+        }
+    }
+
+    /// The factor the opacity of a layer is multiplied by
+    pub fn opacity_factor(&self, layer: &str) -> f32 {
+        if layer == self.layer_a {
+            1.0 - self.blend_factor()
+        } else if layer == self.layer_b {
+            self.blend_factor()
+        } else {
+            1.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cross_fade_is_frame_rate_independent() {
+        let period = DeltaTime::from_millis(1000.0);
+
+        // Reach the half period with frames of various durations
+        for frame in [16.0, 33.3, 100.0] {
+            let mut blink = Blink::new("a".to_string(), "b".to_string(), period, BlinkMode::CrossFade).unwrap();
+
+            let num_frames = (500.0 / frame) as usize;
+            for _ in 0..num_frames {
+                blink.update(DeltaTime::from_millis(frame));
+            }
+            blink.update(DeltaTime::from_millis(500.0 - (num_frames as f32) * frame));
+
+            assert!((blink.blend_factor() - 0.5).abs() < 1e-3);
+            assert!((blink.opacity_factor("a") - 0.5).abs() < 1e-3);
+            assert_eq!(blink.opacity_factor("c"), 1.0);
+        }
+
+        let mut blink = Blink::new("a".to_string(), "b".to_string(), period, BlinkMode::Alternate).unwrap();
+        blink.update(DeltaTime::from_millis(500.0));
+        assert_eq!(blink.blend_factor(), 0.0);
+        blink.update(DeltaTime::from_millis(1000.0));
+        assert_eq!(blink.blend_factor(), 1.0);
+
+        assert!(Blink::new("a".to_string(), "b".to_string(), DeltaTime::zero(), BlinkMode::Alternate).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use cgmath::InnerSpace;
+use cgmath::Vector2;
+
+use crate::coo_space::XYNDC;
+
+/// A group of sources drawn as one marker
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cluster {
+    /// The position of the marker, i.e. the barycenter of the sources, in the NDC space
+    pub ndc_pos: XYNDC<f64>,
+    /// The number of sources. A cluster of one source is drawn as a simple marker
+    pub count: usize,
+    /// The size of the marker relative to the one of the catalog, the biggest of
+    /// the ones of its sources
+    pub size: f32,
+}
+
+struct Seed {
+    // the first source of the cluster, in pixels
+    pos_px: Vector2<f64>,
+    sum_px: Vector2<f64>,
+    count: usize,
+    size: f32,
+}
+
+/// Group the sources closer than ``threshold_px`` pixels on the screen
+///
+/// The screen is binned into a grid of cells of ``threshold_px`` pixels. A source
+/// joins the first cluster of its neighbouring cells whose first source is within
+/// ``threshold_px``, otherwise it starts a new one. The sources out of the screen
+/// are discarded.
+///
+/// # Arguments
+///
+/// * ``sources`` - The positions of the sources in the NDC space
+/// * ``threshold_px`` - The distance in pixels under which two sources are merged
+/// * ``screen_size`` - The size of the screen in pixels
+pub fn cluster<I>(sources: I, threshold_px: f32, screen_size: &Vector2<f32>) -> Vec<Cluster>
+where
+    I: IntoIterator<Item = XYNDC<f64>>,
+{
+    let mut clustering = Clustering::new(threshold_px, screen_size);
+    clustering.push(sources);
+
+    clustering.clusters()
+}
+
+/// The clustering of sources given by chunks
+///
+/// Pushing a chunk only touches the bins its sources fall in, the clusters
+/// already built elsewhere are kept as is. Pushing the sources in several chunks
+/// gives the same clusters as pushing them all at once, in the same order.
+pub struct Clustering {
+    // half the screen size in pixels
+    sx: f64,
+    sy: f64,
+    threshold_px: f64,
+    bin_size: f64,
+
+    seeds: Vec<Seed>,
+    bins: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl Clustering {
+    /// Start an empty clustering
+    ///
+    /// # Arguments
+    ///
+    /// * ``threshold_px`` - The distance in pixels under which two sources are merged
+    /// * ``screen_size`` - The size of the screen in pixels
+    pub fn new(threshold_px: f32, screen_size: &Vector2<f32>) -> Self {
+        let threshold_px = (threshold_px as f64).max(0.0);
+        // Avoid a null bin size for a null threshold, no source is merged then
+        let bin_size = threshold_px.max(1.0);
+
+        Self {
+            sx: 0.5 * screen_size.x as f64,
+            sy: 0.5 * screen_size.y as f64,
+            threshold_px,
+            bin_size,
+            seeds: vec![],
+            bins: HashMap::new(),
+        }
+    }
+
+    /// Add sources, given by their positions in the NDC space
+    pub fn push<I>(&mut self, sources: I)
+    where
+        I: IntoIterator<Item = XYNDC<f64>>,
+    {
+        self.push_sized(sources.into_iter().map(|p| (p, 1.0)));
+    }
+
+    /// Add sources, given by their positions in the NDC space along with the size
+    /// of their marker relative to the one of the catalog
+    pub fn push_sized<I>(&mut self, sources: I)
+    where
+        I: IntoIterator<Item = (XYNDC<f64>, f32)>,
+    {
+        for (p, size) in sources {
+            if p.x.abs() > 1.0 || p.y.abs() > 1.0 {
+                continue;
+            }
+
+            let pos_px = Vector2::new(p.x * self.sx, p.y * self.sy);
+            let bin = (
+                (pos_px.x / self.bin_size).floor() as i64,
+                (pos_px.y / self.bin_size).floor() as i64,
+            );
+
+            let (seeds, bins, threshold_px) = (&mut self.seeds, &mut self.bins, self.threshold_px);
+            let mut neighbours = (-1..=1).flat_map(|i| (-1..=1).map(move |j| (bin.0 + i, bin.1 + j)));
+            let joined = neighbours.find_map(|b| {
+                bins.get(&b)?
+                    .iter()
+                    .copied()
+                    .find(|&s| (seeds[s].pos_px - pos_px).magnitude() <= threshold_px)
+            });
+
+            if let Some(s) = joined {
+                let seed = &mut seeds[s];
+                seed.sum_px += pos_px;
+                seed.count += 1;
+                seed.size = seed.size.max(size);
+            } else {
+                bins.entry(bin).or_default().push(seeds.len());
+                seeds.push(Seed {
+                    pos_px,
+                    sum_px: pos_px,
+                    count: 1,
+                    size,
+                });
+            }
+        }
+    }
+
+    /// The clusters of the sources pushed so far
+    pub fn clusters(&self) -> Vec<Cluster> {
+        self.seeds
+            .iter()
+            .map(|seed| {
+                let c = seed.sum_px / (seed.count as f64);
+
+                Cluster {
+                    ndc_pos: XYNDC::new(c.x / self.sx, c.y / self.sy),
+                    count: seed.count,
+                    size: seed.size,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sources_collapse_within_the_threshold() {
+        let screen_size = Vector2::new(800.0, 600.0);
+        // 10 pixels apart along the x axis
+        let a = XYNDC::new(0.0, 0.0);
+        let b = XYNDC::new(10.0 / 400.0, 0.0);
+
+        let clusters = cluster([a, b], 15.0, &screen_size);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].count, 2);
+        assert!((clusters[0].ndc_pos - (a + b) * 0.5).magnitude() < 1e-9);
+
+        let clusters = cluster([a, b], 5.0, &screen_size);
+        assert_eq!(clusters.len(), 2);
+        assert!(clusters.iter().all(|c| c.count == 1));
+
+        // Out of the screen sources are discarded
+        assert!(cluster([XYNDC::new(1.5, 0.0)], 15.0, &screen_size).is_empty());
+    }
+
+    #[test]
+    fn clustering_by_chunks() {
+        let screen_size = Vector2::new(800.0, 600.0);
+        let sources = (0..200)
+            .map(|i| {
+                let t = i as f64;
+                XYNDC::new((0.37 * t).sin() * 1.05, (0.11 * t).cos() * 0.95)
+            })
+            .collect::<Vec<_>>();
+
+        let mut by_chunks = Clustering::new(20.0, &screen_size);
+        by_chunks.push(sources[..80].iter().copied());
+        by_chunks.push(sources[80..].iter().copied());
+
+        assert_eq!(by_chunks.clusters(), cluster(sources, 20.0, &screen_size));
+    }
+
+    #[test]
+    fn cluster_marker_is_the_biggest_of_its_sources() {
+        let screen_size = Vector2::new(800.0, 600.0);
+        let mut clustering = Clustering::new(15.0, &screen_size);
+        clustering.push_sized([
+            (XYNDC::new(0.0, 0.0), 1.0),
+            (XYNDC::new(5.0 / 400.0, 0.0), 2.5),
+            (XYNDC::new(0.5, 0.5), 0.5),
+        ]);
+
+        let clusters = clustering.clusters();
+        assert_eq!(clusters.iter().map(|c| (c.count, c.size)).collect::<Vec<_>>(), [(2, 2.5), (1, 0.5)]);
+    }
+}
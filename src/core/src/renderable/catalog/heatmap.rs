@@ -0,0 +1,263 @@
+use crate::CameraViewPort;
+use crate::ProjectionType;
+use crate::ShaderManager;
+
+use al_api::hips::{CutLevels, TransferFunction};
+use al_core::colormap::Colormap;
+use al_core::image::format::RGBA8U;
+use al_core::texture::TEX_PARAMS;
+use al_core::{SliceData, Texture2D, VertexArrayObject, WebGlContext};
+use cgmath::Vector2;
+
+use wasm_bindgen::JsValue;
+use web_sys::WebGl2RenderingContext;
+
+use crate::coo_space::XYNDC;
+use crate::LonLatT;
+
+use super::manager::to_view_frame;
+
+const DEFAULT_BIN_PX: f32 = 8.0;
+
+/// The counts of sources in a grid of bins covering the screen
+///
+/// The bins are square in pixels, the grid is stored row by row from the top of the screen
+pub struct DensityGrid {
+    width: usize,
+    height: usize,
+    counts: Vec<u32>,
+}
+
+impl DensityGrid {
+    /// An empty grid of bins of ``bin_px`` pixels wide covering a screen of size ``screen_size``
+    pub fn new(bin_px: f32, screen_size: &Vector2<f32>) -> Self {
+        let bin_px = bin_px.max(1.0);
+        let width = ((screen_size.x / bin_px).ceil() as usize).max(1);
+        let height = ((screen_size.y / bin_px).ceil() as usize).max(1);
+
+        Self {
+            width,
+            height,
+            counts: vec![0; width * height],
+        }
+    }
+
+    /// Count sources given by their position in the NDC space
+    ///
+    /// The sources out of the screen or that could not be projected, i.e. None, are dropped
+    pub fn accumulate<I>(&mut self, sources: I)
+    where
+        I: IntoIterator<Item = Option<XYNDC<f64>>>,
+    {
+        let (w, h) = (self.width as f64, self.height as f64);
+
+        for p in sources.into_iter().flatten() {
+            if p.x.abs() > 1.0 || p.y.abs() > 1.0 {
+                continue;
+            }
+
+            let ix = ((0.5 * (p.x + 1.0) * w) as usize).min(self.width - 1);
+            let iy = ((0.5 * (1.0 - p.y) * h) as usize).min(self.height - 1);
+            self.counts[iy * self.width + ix] += 1;
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    /// The RGBA texels of the heatmap
+    ///
+    /// The counts are normalized by the densest bin, stretched by ``transfer`` and
+    /// colored by ``colormap``. The empty bins are transparent.
+    pub fn colorize(&self, transfer: &TransferFunction, colormap: &Colormap, reversed: bool) -> Vec<u8> {
+        let max = self.counts.iter().copied().max().unwrap_or(0);
+        let cut = CutLevels::new(0.0, max as f32);
+
+        self.counts
+            .iter()
+            .flat_map(|&count| {
+                if count == 0 {
+                    [0; 4]
+                } else {
+                    colormap.color_at(transfer.apply(cut.normalize(count as f32)), reversed)
+                }
+            })
+            .collect()
+    }
+}
+
+/// The density of the sources of a large catalog drawn as a heatmap
+///
+/// The sources are binned on screen at each update instead of being drawn one by one.
+pub struct Heatmap {
+    gl: WebGlContext,
+
+    // Size of the bins in pixels
+    bin_px: f32,
+    transfer: TransferFunction,
+    reversed: bool,
+    alpha: f32,
+
+    texture: Option<Texture2D>,
+    vao: VertexArrayObject,
+}
+
+impl Heatmap {
+    pub fn new(gl: &WebGlContext) -> Self {
+        // The quad covering the screen
+        let pos = [-1.0_f32, -1.0, 1.0, -1.0, 1.0, 1.0, -1.0, 1.0];
+        let uv = [0.0_f32, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0];
+        let indices = [0_u16, 1, 2, 0, 2, 3];
+
+        let mut vao = VertexArrayObject::new(gl);
+        #[cfg(feature = "webgl2")]
+        vao.bind_for_update()
+            .add_array_buffer_single(
+                2,
+                "ndc_pos",
+                WebGl2RenderingContext::STATIC_DRAW,
+                SliceData(pos.as_ref()),
+            )
+            .add_array_buffer_single(
+                2,
+                "uv",
+                WebGl2RenderingContext::STATIC_DRAW,
+                SliceData(uv.as_ref()),
+            )
+            .add_element_buffer(
+                WebGl2RenderingContext::STATIC_DRAW,
+                SliceData(indices.as_ref()),
+            )
+            .unbind();
+
+        Self {
+            gl: gl.clone(),
+            bin_px: DEFAULT_BIN_PX,
+            transfer: TransferFunction::Log,
+            reversed: false,
+            alpha: 1.0,
+            texture: None,
+            vao,
+        }
+    }
+
+    /// Set the size of the bins in pixels
+    pub fn set_bin_size(&mut self, px: f32) -> Result<(), &'static str> {
+        if !(px >= 1.0) {
+            return Err("The bins of the heatmap must be at least one pixel wide");
+        }
+
+        self.bin_px = px;
+        Ok(())
+    }
+
+    pub fn get_bin_size(&self) -> f32 {
+        self.bin_px
+    }
+
+    pub fn set_transfer_function(&mut self, transfer: TransferFunction) {
+        self.transfer = transfer;
+    }
+
+    pub fn set_reversed(&mut self, reversed: bool) {
+        self.reversed = reversed;
+    }
+
+    pub fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+
+    /// Bin the sources, given by their ICRS position, in the current view and color
+    /// the bins with ``colormap``
+    ///
+    /// The sources out of the valid domain of the projection are dropped
+    pub fn update(
+        &mut self,
+        sources: Vec<LonLatT<f64>>,
+        colormap: &Colormap,
+        camera: &CameraViewPort,
+        projection: &ProjectionType,
+    ) -> Result<(), JsValue> {
+        let positions = to_view_frame(sources, camera.get_coo_system());
+
+        let mut grid = DensityGrid::new(self.bin_px, &camera.get_screen_size());
+        grid.accumulate(projection.proj_batch(&positions, camera));
+
+        let texels = grid.colorize(&self.transfer, colormap, self.reversed);
+        self.texture = Some(Texture2D::create_from_raw_pixels::<RGBA8U>(
+            &self.gl,
+            grid.width() as i32,
+            grid.height() as i32,
+            TEX_PARAMS,
+            Some(&texels),
+        )?);
+
+        Ok(())
+    }
+
+    pub fn draw(&self, shaders: &mut ShaderManager) -> Result<(), JsValue> {
+        let texture = match &self.texture {
+            Some(texture) if self.alpha > 0.0 => texture,
+            _ => return Ok(()),
+        };
+
+        // Blended by the catalog manager
+        crate::shader::get_shader(&self.gl, shaders, "image_base.vert", "image_sampler.frag")?
+            .bind(&self.gl)
+            .attach_uniform("tex", texture)
+            .attach_uniform("opacity", &self.alpha)
+            .bind_vertex_array_object_ref(&self.vao)
+            .draw_elements_with_i32(
+                WebGl2RenderingContext::TRIANGLES,
+                None,
+                WebGl2RenderingContext::UNSIGNED_SHORT,
+                0,
+            );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn uniform_and_clustered_densities() {
+        let mut rng = StdRng::seed_from_u64(7);
+        // 10x10 bins
+        let screen_size = Vector2::new(200.0, 200.0);
+
+        let mut uniform = DensityGrid::new(20.0, &screen_size);
+        uniform.accumulate((0..100_000).map(|_| Some(XYNDC::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))));
+        // Sources dropped
+        uniform.accumulate([None, Some(XYNDC::new(1.5, 0.0))]);
+
+        assert_eq!(uniform.counts().len(), 100);
+        assert_eq!(uniform.counts().iter().sum::<u32>(), 100_000);
+        assert!(uniform.counts().iter().all(|&c| (c as f64 - 1000.0).abs() < 200.0));
+
+        // A hotspot in the bin of the top right quadrant at (0.3, 0.3)
+        let mut clustered = DensityGrid::new(20.0, &screen_size);
+        clustered.accumulate((0..10_000).map(|_| {
+            Some(XYNDC::new(0.3 + rng.gen_range(-0.05..0.05), 0.3 + rng.gen_range(-0.05..0.05)))
+        }));
+        clustered.accumulate((0..1000).map(|_| Some(XYNDC::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)))));
+
+        let counts = clustered.counts();
+        let (hot, max) = counts.iter().enumerate().max_by_key(|(_, c)| **c).unwrap();
+        assert_eq!((hot % 10, hot / 10), (6, 3));
+        assert!(*max > 50 * counts[0]);
+    }
+}
@@ -3,15 +3,16 @@ use crate::ShaderManager;
 use al_api::coo_system::CooSystem;
 use al_api::resources::Resources;
 
+use al_api::color::ColorRGBA;
 use al_core::colormap::Colormap;
-use al_core::Colormaps;
-use al_core::FrameBufferObject;
 use al_core::{VecData, VertexArrayObject, WebGlContext};
 
+use crate::renderable::text::TextRenderManager;
+use crate::renderable::Renderer;
 use crate::ProjectionType;
 use std::collections::HashMap;
 
-use web_sys::WebGl2RenderingContext;
+use web_sys::{HtmlElement, WebGl2RenderingContext};
 
 #[derive(Debug)]
 pub enum Error {
@@ -31,13 +32,12 @@ const _NUM_SHAPES: usize = 5;
 pub struct Manager {
     gl: WebGlContext,
     //kernels: HashMap<&'static str, Texture2D>,
-    fbo: FrameBufferObject,
-
-    // VAOs
-    vertex_array_object_screen: VertexArrayObject,
+    // Draws the number of sources of the clusters
+    text_renderer: TextRenderManager,
 
     catalogs: HashMap<String, Catalog>,
-    kernel_size: Vector2<f32>,
+    // Size of a pixel in the NDC space
+    px_size: Vector2<f32>,
 }
 
 impl Manager {
@@ -46,6 +46,7 @@ impl Manager {
         _shaders: &mut ShaderManager,
         camera: &CameraViewPort,
         resources: &Resources,
+        aladin_div: &HtmlElement,
     ) -> Result<Self, JsValue> {
         // Load the texture of the gaussian kernel
         let _kernel_filename = resources.get_filename("kernel").unwrap_abort();
@@ -96,81 +97,19 @@ impl Manager {
             ),
         ]
         .into();*/
-        // Create the VAO for the screen
-        let vertex_array_object_screen = {
-            let vertices = [
-                -1.0_f32, -1.0_f32, 0.0_f32, 0.0_f32, 1.0_f32, -1.0_f32, 1.0_f32, 0.0_f32, 1.0_f32,
-                1.0_f32, 1.0_f32, 1.0_f32, -1.0_f32, 1.0_f32, 0.0_f32, 1.0_f32,
-            ];
-            let _position = [
-                -1.0_f32, -1.0_f32, 1.0_f32, -1.0_f32, 1.0_f32, 1.0_f32, -1.0_f32, 1.0_f32,
-            ];
-            let _uv = [
-                0.0_f32, 0.0_f32, 1.0_f32, 0.0_f32, 1.0_f32, 1.0_f32, 0.0_f32, 1.0_f32,
-            ];
-
-            let indices = [0_u16, 1, 2, 0, 2, 3];
-
-            let mut vao = VertexArrayObject::new(gl);
-            //let shader = Colormap::get_catalog_shader(gl, shaders)?;
-            #[cfg(feature = "webgl2")]
-            vao.bind_for_update()
-                // Store the screen and uv of the billboard in a VBO
-                .add_array_buffer(
-                    "vertices",
-                    4 * std::mem::size_of::<f32>(),
-                    &[2, 2],
-                    &[0, 2 * std::mem::size_of::<f32>()],
-                    WebGl2RenderingContext::STATIC_DRAW,
-                    SliceData(&vertices),
-                )
-                // Set the element buffer
-                .add_element_buffer(
-                    WebGl2RenderingContext::STATIC_DRAW,
-                    SliceData(indices.as_ref()),
-                )
-                // Unbind the buffer
-                .unbind();
-            #[cfg(feature = "webgl1")]
-            vao.bind_for_update()
-                // Store the screen and uv of the billboard in a VBO
-                .add_array_buffer(
-                    2,
-                    "position",
-                    WebGl2RenderingContext::STATIC_DRAW,
-                    SliceData(position.as_ref()),
-                )
-                .add_array_buffer(
-                    2,
-                    "uv",
-                    WebGl2RenderingContext::STATIC_DRAW,
-                    SliceData(uv.as_ref()),
-                )
-                // Set the element buffer
-                .add_element_buffer(
-                    WebGl2RenderingContext::STATIC_DRAW,
-                    SliceData(indices.as_ref()),
-                )
-                // Unbind the buffer
-                .unbind();
-            vao
-        };
-
         let catalogs = HashMap::new();
-        let kernel_size = Vector2::new(0.0, 0.0);
+        let px_size = Vector2::new(0.0, 0.0);
 
-        let fbo = FrameBufferObject::new(gl, 768, 768).unwrap_abort();
+        let text_renderer = TextRenderManager::new(aladin_div)?;
 
         let gl = gl.clone();
         let mut manager = Manager {
             gl,
             //kernels,
-            fbo,
-
-            vertex_array_object_screen,
+            text_renderer,
 
             catalogs,
-            kernel_size,
+            px_size,
         };
 
         manager.set_kernel_size(camera);
@@ -179,17 +118,22 @@ impl Manager {
     }
 
     // Private method adding a catalog into the manager
+    //
+    // The values of the fields of the sources are given in the order of ``fields``,
+    // the names of the numeric columns of the catalog
     pub fn add_catalog<P: Projection>(
         &mut self,
         name: String,
-        sources: Box<[LonLatT<f32>]>,
+        sources: Box<[Source]>,
+        fields: Vec<String>,
+        proper_motions: Option<ProperMotions>,
         colormap: Colormap,
         camera: &mut CameraViewPort,
         proj: &ProjectionType,
     ) {
         // Create the HashMap storing the source indices with respect to the
         // HEALPix cell at depth 7 in which they are contained
-        let catalog = Catalog::new::<P>(&self.gl, colormap, sources);
+        let catalog = Catalog::new::<P>(&self.gl, colormap, sources, fields, proper_motions);
 
         // Update the number of sources loaded
         //self.num_sources += num_instances_in_catalog as usize;
@@ -204,6 +148,46 @@ impl Manager {
         // at depth 7
     }
 
+    /// The source of a catalog the closest to a position on screen, see ``Catalog::nearest``
+    ///
+    /// Returns its position in the catalog, in the icrs frame
+    pub fn nearest_source(
+        &self,
+        name: &str,
+        ndc: XYNDC<f64>,
+        max_px: f32,
+        camera: &CameraViewPort,
+        projection: &ProjectionType,
+    ) -> Result<Option<LonLatT<f32>>, Error> {
+        let catalog = self.catalogs.get(name).ok_or(Error::CatalogNotPresent {
+            message: format!("{} catalog is not present!", name),
+        })?;
+
+        Ok(catalog
+            .nearest(ndc, max_px, camera, projection)
+            .map(|idx| catalog.sources[idx].lonlat))
+    }
+
+    /// Add sources to a catalog, see ``Catalog::push_sources``
+    ///
+    /// The catalog is created, without fields nor proper motions, if it has not been added yet
+    pub fn push_sources(
+        &mut self,
+        name: &str,
+        chunk: &[Source],
+        camera: &mut CameraViewPort,
+        proj: &ProjectionType,
+    ) {
+        if !self.catalogs.contains_key(name) {
+            let colormap = Colormap::new("viridis", colorgrad::viridis());
+            self.add_catalog::<ProjectionType>(name.to_string(), Box::new([]), vec![], None, colormap, camera, proj);
+        }
+
+        if let Some(catalog) = self.catalogs.get_mut(name) {
+            catalog.push_sources(chunk);
+        }
+    }
+
     pub fn remove_catalog<P: Projection>(
         &mut self,
         name: String,
@@ -218,7 +202,7 @@ impl Manager {
 
     pub fn set_kernel_size(&mut self, camera: &CameraViewPort) {
         let size = camera.get_screen_size();
-        self.kernel_size = Vector2::new(32.0 / size.x, 32.0 / size.y);
+        self.px_size = Vector2::new(2.0 / size.x, 2.0 / size.y);
     }
 
     pub fn get_mut_catalog(&mut self, name: &str) -> Result<&mut Catalog, Error> {
@@ -227,42 +211,47 @@ impl Manager {
         })
     }
 
-    pub fn update(&mut self, camera: &mut CameraViewPort) {
-        // Render only the sources in the current field of view
-        // Cells that are of depth > 7 are not handled by the hashmap (limited to depth 7)
-        // For these cells, we draw all the sources lying in the ancestor cell of depth 7 containing
-        // this cell
-        //if camera.get_aperture() > P::RASTER_THRESHOLD_ANGLE {
-        if camera.get_field_of_view().is_allsky() {
-            let cells = crate::healpix::cell::ALLSKY_HPX_CELLS_D0;
+    /// Integrate the sources pushed and cluster the sources of the view
+    ///
+    /// Returns true if the markers or the heatmaps have changed, i.e. the catalogs have to be drawn again
+    pub fn update(&mut self, camera: &CameraViewPort, projection: &ProjectionType) -> Result<bool, JsValue> {
+        let mut changed = false;
+        for catalog in self.catalogs.values_mut() {
+            // Integrate the sources pushed since the last update
+            catalog.integrate_pending(camera, projection);
+            changed |= catalog.update(camera, projection)?;
+        }
 
-            for catalog in self.catalogs.values_mut() {
-                catalog.update(cells);
-            }
-        } else {
-            let depth = camera.get_texture_depth().min(7);
-            let cells = camera.get_hpx_cells(depth, CooSystem::ICRS);
+        Ok(changed)
+    }
 
-            for catalog in self.catalogs.values_mut() {
-                catalog.update(&cells);
-            }
+    pub fn draw(&self, shaders: &mut ShaderManager, camera: &CameraViewPort) -> Result<(), JsValue> {
+        self.gl.enable(WebGl2RenderingContext::BLEND);
+        for catalog in self.catalogs.values() {
+            catalog.draw(&self.gl, shaders, self, camera)?;
         }
+        self.gl.disable(WebGl2RenderingContext::BLEND);
+
+        Ok(())
     }
 
-    pub fn draw(
-        &self,
-        gl: &WebGlContext,
-        shaders: &mut ShaderManager,
-        camera: &CameraViewPort,
-        colormaps: &Colormaps,
-        fbo: Option<&FrameBufferObject>,
-        projection: &ProjectionType,
-    ) -> Result<(), JsValue> {
-        gl.enable(WebGl2RenderingContext::BLEND);
-        for catalog in self.catalogs.values() {
-            catalog.draw(gl, shaders, self, camera, colormaps, fbo, projection)?;
+    /// Draw the number of sources of the clusters onto the text canvas
+    pub fn draw_labels(&mut self, camera: &CameraViewPort) -> Result<(), JsValue> {
+        // The heatmaps have no cluster
+        for catalog in self.catalogs.values().filter(|c| c.display_mode == DisplayMode::Markers) {
+            self.text_renderer.set_color(&catalog.color());
+
+            self.text_renderer.begin();
+            for cluster in catalog.clusters.iter().filter(|c| c.count > 1) {
+                let position = crate::math::projection::ndc_to_screen_space(&cluster.ndc_pos, camera);
+                self.text_renderer.add_label(
+                    &cluster.count.to_string(),
+                    &Vector2::new(position.x as f32, position.y as f32),
+                    cgmath::Rad(0.0),
+                )?;
+            }
+            self.text_renderer.end();
         }
-        gl.disable(WebGl2RenderingContext::BLEND);
 
         Ok(())
     }
@@ -270,6 +259,104 @@ impl Manager {
 
 use crate::healpix::index_vector::IdxVec;
 use crate::LonLatT;
+use crate::math::angle::Angle;
+use crate::math::lonlat::LonLat;
+
+use super::cluster::{Cluster, Clustering};
+use super::heatmap::Heatmap;
+
+/// How the sources of a catalog are drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DisplayMode {
+    /// A marker per source, the close ones being clustered
+    Markers,
+    /// The density of the sources, for the catalogs too large to be clustered
+    Heatmap,
+}
+
+/// A source of a catalog, given by its ICRS position along with the values
+/// of the numeric fields of the catalog, e.g. its magnitude
+#[derive(Clone, Debug, PartialEq)]
+pub struct Source {
+    pub lonlat: LonLatT<f32>,
+    // In the order of the fields of the catalog, NaN when missing
+    fields: Box<[f32]>,
+}
+
+impl Source {
+    /// A source without field values
+    pub fn new(lonlat: LonLatT<f32>) -> Self {
+        Self::with_fields(lonlat, Box::new([]))
+    }
+
+    pub fn with_fields(lonlat: LonLatT<f32>, fields: Box<[f32]>) -> Self {
+        Self { lonlat, fields }
+    }
+
+    /// The value of the field of index ``idx``, see ``Catalog::field_index``
+    ///
+    /// None if the value is missing or NaN
+    pub fn field(&self, idx: usize) -> Option<f32> {
+        self.fields.get(idx).copied().filter(|v| !v.is_nan())
+    }
+}
+
+impl LonLat<f32> for Source {
+    #[inline]
+    fn lon(&self) -> Angle<f32> {
+        self.lonlat.lon()
+    }
+
+    #[inline]
+    fn lat(&self) -> Angle<f32> {
+        self.lonlat.lat()
+    }
+
+    #[inline]
+    fn lonlat(&self) -> LonLatT<f32> {
+        self.lonlat
+    }
+
+    #[inline]
+    fn from_lonlat(lonlat: &LonLatT<f32>) -> Self {
+        Source::new(*lonlat)
+    }
+}
+
+/// The proper motions of the sources of a catalog
+pub struct ProperMotions {
+    /// The (pmra * cos(dec), pmdec) of the sources in mas/yr, given
+    /// in the same order as the sources
+    pub pm: Box<[[f32; 2]]>,
+    /// The epoch of the positions of the sources, in Julian years
+    pub epoch: f64,
+}
+
+// A source sorted along with its proper motion
+#[derive(Clone)]
+struct SourcePm(Source, [f32; 2]);
+
+impl LonLat<f32> for SourcePm {
+    #[inline]
+    fn lon(&self) -> Angle<f32> {
+        self.0.lon()
+    }
+
+    #[inline]
+    fn lat(&self) -> Angle<f32> {
+        self.0.lat()
+    }
+
+    #[inline]
+    fn lonlat(&self) -> LonLatT<f32> {
+        self.0.lonlat
+    }
+
+    #[inline]
+    fn from_lonlat(lonlat: &LonLatT<f32>) -> Self {
+        SourcePm(Source::new(*lonlat), [0.0, 0.0])
+    }
+}
 
 pub struct Catalog {
     colormap: Colormap,
@@ -277,27 +364,129 @@ pub struct Catalog {
     index_vec: IdxVec,
     alpha: f32,
     strength: f32,
-    lonlat: Box<[LonLatT<f32>]>,
+    // Distance in pixels under which the markers are clustered
+    cluster_threshold: f32,
+    // Bounds of the size of the markers in pixels
+    marker_min_px: f32,
+    marker_max_px: f32,
+    sources: Box<[Source]>,
+    // The names of the numeric fields of the sources
+    fields: Vec<String>,
+    proper_motions: Option<ProperMotions>,
+    // The largest proper motion of the sources in mas/yr
+    max_pm: f32,
+    // The epoch at which the sources are displayed. None to display them
+    // at the epoch of the catalog
+    target_epoch: Option<f64>,
+    // The sources pushed that are not integrated yet
+    pending: Vec<Source>,
+    // The clustering of the integrated sources along with the view it has been done for
+    clustering: Option<(ClusteringView, Clustering)>,
+    // The clusters drawn, i.e. the ones of the last update
+    clusters: Vec<Cluster>,
+    // The predicate the sources shown must match, and whether each integrated
+    // source does not. Empty when there is no filter
+    filter: Option<Box<dyn Fn(&Source) -> bool>>,
+    hidden: Vec<bool>,
+    // The sizes of the markers given by a field of the sources, and the size
+    // of the marker of each integrated source relative to the catalog one. Empty
+    // when all the markers have the same size
+    size_by_field: Option<SizeByField>,
+    sizes: Vec<f32>,
+    display_mode: DisplayMode,
+    // The heatmap drawn in the heatmap display mode, along with the view its
+    // sources have been binned for. None when they have to be binned again
+    heatmap: Heatmap,
+    heatmap_view: Option<ClusteringView>,
     vertex_array_object_catalog: VertexArrayObject,
 }
+
+// What the clustering and the binning of the sources depend on
+#[derive(PartialEq)]
+struct ClusteringView {
+    w2m: Matrix4<f64>,
+    aperture: f64,
+    screen_size: Vector2<f32>,
+    projection: std::mem::Discriminant<ProjectionType>,
+    threshold_px: f32,
+    epoch: Option<f64>,
+    frame: CooSystem,
+}
+
+impl ClusteringView {
+    fn new(catalog: &Catalog, camera: &CameraViewPort, projection: &ProjectionType) -> Self {
+        Self {
+            w2m: *camera.get_w2m(),
+            aperture: camera.get_aperture().to_radians(),
+            screen_size: camera.get_screen_size(),
+            projection: std::mem::discriminant(projection),
+            threshold_px: catalog.cluster_threshold,
+            epoch: catalog.target_epoch,
+            frame: camera.get_coo_system(),
+        }
+    }
+}
+use crate::coo_space::XYNDC;
 use crate::healpix::cell::HEALPixCell;
-use crate::{camera::CameraViewPort, math::projection::Projection, utils};
+use crate::healpix::coverage::HEALPixCoverage;
+use crate::{camera::CameraViewPort, math::projection::Projection};
+use moclib::moc::{RangeMOCIntoIterator, RangeMOCIterator};
 use al_core::SliceData;
-use cgmath::Vector2;
+use cgmath::{Matrix4, Vector2};
 
-const MAX_SOURCES_PER_CATALOG: f32 = 50000.0;
+// Number of pushed sources out of the view integrated at each update
+const MAX_OFF_SCREEN_SOURCES_PER_UPDATE: usize = 10000;
+const DEFAULT_CLUSTER_THRESHOLD_PX: f32 = 20.0;
+// Size of the markers at the reference aperture, they scale inversely with the aperture
+const MARKER_SIZE_PX: f32 = 16.0;
+const MARKER_REF_APERTURE: f64 = 30.0 * std::f64::consts::PI / 180.0;
+const DEFAULT_MARKER_MIN_PX: f32 = 4.0;
+const DEFAULT_MARKER_MAX_PX: f32 = 32.0;
 
 use crate::Abort;
 impl Catalog {
     fn new<P: Projection>(
         gl: &WebGlContext,
         colormap: Colormap,
-        mut lonlat: Box<[LonLatT<f32>]>,
+        mut sources: Box<[Source]>,
+        fields: Vec<String>,
+        proper_motions: Option<ProperMotions>,
     ) -> Catalog {
         let alpha = 1_f32;
         let strength = 1_f32;
-        let index_vec = IdxVec::from_coo(&mut lonlat);
-        let num_instances = lonlat.len() as i32;
+
+        let proper_motions = proper_motions.filter(|pm| {
+            let valid = pm.pm.len() == sources.len();
+            if !valid {
+                al_core::log::console_warn("The number of proper motions does not match the number of sources, they are discarded.");
+            }
+
+            valid
+        });
+        // The proper motions are sorted along with the sources
+        let (index_vec, proper_motions) = if let Some(ProperMotions { pm, epoch }) = proper_motions {
+            let mut sorted = sources
+                .iter()
+                .zip(pm.iter())
+                .map(|(s, pm)| SourcePm(s.clone(), *pm))
+                .collect::<Vec<_>>();
+            let index_vec = IdxVec::from_coo(&mut sorted);
+
+            let pm = sorted.iter().map(|s| s.1).collect();
+            sources = sorted.into_iter().map(|s| s.0).collect();
+
+            (index_vec, Some(ProperMotions { pm, epoch }))
+        } else {
+            (IdxVec::from_coo(&mut sources), None)
+        };
+        let max_pm = proper_motions
+            .iter()
+            .flat_map(|ProperMotions { pm, .. }| pm.iter())
+            .map(|[pmra_cosdec, pmdec]| pmra_cosdec.hypot(*pmdec))
+            .filter(|pm| pm.is_finite())
+            .fold(0.0, f32::max);
+        // No marker is drawn until the first update
+        let num_instances = 0;
 
         //let sources = unsafe { utils::transmute_boxed_slice(sources) };
 
@@ -332,11 +521,20 @@ impl Catalog {
                     WebGl2RenderingContext::STATIC_DRAW,
                     SliceData(vertices.as_ref()),
                 )
-                // Store the cartesian position of the center of the source in the a instanced VBO
+                // Store the position of the marker in the NDC space in the a instanced VBO
+                .add_instanced_array_buffer(
+                    "ndc_pos",
+                    2 * std::mem::size_of::<f32>(),
+                    &[2],
+                    &[0],
+                    WebGl2RenderingContext::DYNAMIC_DRAW,
+                    SliceData(&[]),
+                )
+                // Store the size of the marker of the source
                 .add_instanced_array_buffer(
-                    "center",
-                    3 * std::mem::size_of::<f32>(),
-                    &[3],
+                    "size",
+                    std::mem::size_of::<f32>(),
+                    &[1],
                     &[0],
                     WebGl2RenderingContext::DYNAMIC_DRAW,
                     SliceData(&[]),
@@ -351,8 +549,8 @@ impl Catalog {
             #[cfg(feature = "webgl1")]
             vao.bind_for_update()
                 .add_instanced_array_buffer(
-                    3,
-                    "center",
+                    2,
+                    "ndc_pos",
                     WebGl2RenderingContext::DYNAMIC_DRAW,
                     SliceData(&[]),
                 )
@@ -369,7 +567,12 @@ impl Catalog {
                     WebGl2RenderingContext::STATIC_DRAW,
                     SliceData(uv.as_ref()),
                 )
-                // Store the cartesian position of the center of the source in the a instanced VBO
+                .add_instanced_array_buffer(
+                    1,
+                    "size",
+                    WebGl2RenderingContext::DYNAMIC_DRAW,
+                    SliceData(&[]),
+                )
                 // Set the element buffer
                 .add_element_buffer(
                     WebGl2RenderingContext::STATIC_DRAW,
@@ -383,10 +586,27 @@ impl Catalog {
         Self {
             alpha,
             strength,
+            cluster_threshold: DEFAULT_CLUSTER_THRESHOLD_PX,
+            marker_min_px: DEFAULT_MARKER_MIN_PX,
+            marker_max_px: DEFAULT_MARKER_MAX_PX,
             colormap,
             num_instances,
             index_vec,
-            lonlat,
+            sources,
+            fields,
+            proper_motions,
+            max_pm,
+            target_epoch: None,
+            pending: vec![],
+            clustering: None,
+            clusters: vec![],
+            filter: None,
+            hidden: vec![],
+            size_by_field: None,
+            sizes: vec![],
+            display_mode: DisplayMode::Markers,
+            heatmap: Heatmap::new(gl),
+            heatmap_view: None,
 
             vertex_array_object_catalog,
         }
@@ -398,68 +618,414 @@ impl Catalog {
 
     pub fn set_colormap(&mut self, colormap: Colormap) {
         self.colormap = colormap;
+        self.heatmap_view = None;
     }
 
     pub fn set_alpha(&mut self, alpha: f32) {
         self.alpha = alpha;
+        self.heatmap.set_alpha(alpha);
     }
 
-    fn get_total_num_sources_in_fov(&self, cells: &[HEALPixCell]) -> usize {
-        let mut total_sources = 0;
+    /// Draw the sources as markers or as a density heatmap
+    pub fn set_display_mode(&mut self, mode: DisplayMode) {
+        self.display_mode = mode;
+        self.heatmap_view = None;
+    }
 
-        for cell in cells {
-            let sources_idx = self.index_vec.get_item_indices_inside_hpx_cell(cell);
-            total_sources += (sources_idx.end - sources_idx.start) as usize;
+    /// Set the size in pixels of the bins of the heatmap
+    pub fn set_heatmap_bin_size(&mut self, px: f32) -> Result<(), &'static str> {
+        self.heatmap.set_bin_size(px)?;
+        self.heatmap_view = None;
+
+        Ok(())
+    }
+
+    pub fn set_cluster_threshold(&mut self, px: f32) {
+        self.cluster_threshold = px;
+    }
+
+    /// Set the bounds of the size of the markers in pixels
+    pub fn set_marker_size_range(&mut self, min_px: f32, max_px: f32) -> Result<(), &'static str> {
+        if !(min_px > 0.0 && min_px <= max_px) {
+            return Err("The min marker size must be positive and not greater than the max one");
         }
 
-        total_sources
-    }
-
-    // Cells are of depth <= 7
-    fn update(&mut self, cells: &[HEALPixCell]) {
-        let num_sources_in_fov = self.get_total_num_sources_in_fov(cells) as f32;
-        // reset the sources in the frame
-        let mut sources: Vec<_> = vec![];
-        // depth < 7
-        for cell in cells {
-            let delta_depth = (7_i8 - cell.depth() as i8).max(0);
-
-            for c in cell.get_children_cells(delta_depth as u8) {
-                // Define the total number of sources being in this kernel depth tile
-                let sources_in_cell = self.index_vec.get_item_indices_inside_hpx_cell(&c);
-                let num_sources_in_kernel_cell =
-                    (sources_in_cell.end - sources_in_cell.start) as usize;
-                if num_sources_in_kernel_cell > 0 {
-                    let num_sources = (((num_sources_in_kernel_cell as f32) / num_sources_in_fov)
-                        * MAX_SOURCES_PER_CATALOG) as usize;
-
-                    let mut idx = self.index_vec.get_item_indices_inside_hpx_cell(&c);
-                    if num_sources < idx.end - idx.start {
-                        // use a selection of num_sources items
-                        idx = idx.start..(idx.start + num_sources);
-                    }
-
-                    sources.extend(&self.lonlat[idx]);
-                }
+        self.marker_min_px = min_px;
+        self.marker_max_px = max_px;
+
+        Ok(())
+    }
+
+    /// The size of the markers in pixels for a view of aperture ``aperture``
+    ///
+    /// The markers get smaller as the view zooms out so that dense catalogs do not
+    /// saturate the screen. This does not depend on the clustering of the sources.
+    pub fn marker_size(&self, aperture: Angle<f64>) -> f32 {
+        marker_size_with(aperture, self.marker_min_px, self.marker_max_px)
+    }
+
+    /// Display the sources at ``epoch``, in Julian years
+    ///
+    /// The sources are moved according to their proper motions if the catalog has some
+    pub fn set_epoch(&mut self, epoch: f64) {
+        self.target_epoch = Some(epoch);
+    }
+
+    /// The index of the field ``name`` in the values of the sources, see ``Source::field``
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|f| f == name)
+    }
+
+    /// Show only the sources matching ``f``, e.g. the ones brighter than a magnitude
+    /// given by one of their fields
+    ///
+    /// The other ones are hidden, i.e. neither drawn, clustered nor picked, but
+    /// kept so that the filter can be changed without loading the catalog again.
+    /// The sources integrated later are filtered too.
+    pub fn set_filter(&mut self, f: Box<dyn Fn(&Source) -> bool>) {
+        self.filter = Some(f);
+        self.apply_filter();
+    }
+
+    /// Show all the sources again
+    pub fn clear_filter(&mut self) {
+        self.filter = None;
+        self.apply_filter();
+    }
+
+    /// Scale the markers of the sources by one of their fields, e.g. draw the
+    /// brighter stars bigger
+    ///
+    /// The size is combined with the scaling of the markers with the aperture of
+    /// the view, the size given being the one at the reference aperture. The
+    /// sources with a missing or NaN value, or whose size is not positive, are
+    /// drawn with the size of the markers of the catalog.
+    ///
+    /// # Arguments
+    ///
+    /// * ``field`` - The name of the numeric field, e.g. "Vmag"
+    /// * ``size`` - The size in pixels of the marker of a source given its value
+    pub fn set_size_by_field(&mut self, field: &str, size: Box<dyn Fn(f32) -> f32>) -> Result<(), String> {
+        let field = self
+            .field_index(field)
+            .ok_or_else(|| format!("{:?} is not a numeric field of the catalog", field))?;
+
+        self.size_by_field = Some(SizeByField { field, size });
+        self.apply_size_by_field();
+
+        Ok(())
+    }
+
+    /// Draw all the markers with the same size again
+    pub fn clear_size_by_field(&mut self) {
+        self.size_by_field = None;
+        self.apply_size_by_field();
+    }
+
+    fn apply_size_by_field(&mut self) {
+        sizes_with(&mut self.sizes, &self.sources, self.size_by_field.as_ref());
+        self.clustering = None;
+    }
+
+    fn size(&self, idx: usize) -> f32 {
+        self.sizes.get(idx).copied().unwrap_or(1.0)
+    }
+
+    fn apply_filter(&mut self) {
+        hide_with(&mut self.hidden, &self.sources, self.filter.as_deref());
+        self.clustering = None;
+        self.heatmap_view = None;
+    }
+
+    fn is_hidden(&self, idx: usize) -> bool {
+        self.hidden.get(idx).copied().unwrap_or(false)
+    }
+
+    // The largest angular distance in radians between the position of a source
+    // in the catalog and the one displayed
+    fn max_pm_displacement(&self) -> f64 {
+        match (&self.proper_motions, self.target_epoch) {
+            (Some(ProperMotions { epoch, .. }), Some(target_epoch)) => {
+                let mas = (self.max_pm as f64) * (target_epoch - epoch).abs();
+                (mas / 3_600_000.0).to_radians()
+            }
+            _ => 0.0,
+        }
+    }
+
+    // The position of a source at the epoch displayed
+    fn position(&self, idx: usize) -> LonLatT<f64> {
+        let ll = self.sources[idx].lonlat;
+        let lonlat = LonLatT::new(Angle(ll.lon().0 as f64), Angle(ll.lat().0 as f64));
+
+        match (&self.proper_motions, self.target_epoch) {
+            (Some(ProperMotions { pm, epoch }), Some(target_epoch)) => {
+                let [pmra_cosdec, pmdec] = pm[idx];
+                lonlat.propagate(pmra_cosdec as f64, pmdec as f64, *epoch, target_epoch)
+            }
+            _ => lonlat,
+        }
+    }
+
+    /// The markers of the sources on screen, the ones closer than the cluster
+    /// threshold being merged into one marker labeled with their count
+    ///
+    /// The clustering is kept until the view changes, the sources integrated
+    /// meanwhile being added to it. The sources that cannot be projected are skipped
+    pub fn clusters(&mut self, camera: &CameraViewPort, projection: &ProjectionType) -> Vec<Cluster> {
+        let view = ClusteringView::new(self, camera, projection);
+
+        match &self.clustering {
+            Some((v, clustering)) if *v == view => clustering.clusters(),
+            _ => {
+                let positions = (0..self.sources.len())
+                    .filter(|&idx| !self.is_hidden(idx))
+                    .map(|idx| self.position(idx))
+                    .collect::<Vec<_>>();
+                let positions = to_view_frame(positions, camera.get_coo_system());
+                let sizes = (0..self.sources.len())
+                    .filter(|&idx| !self.is_hidden(idx))
+                    .map(|idx| self.size(idx));
+                let sources = projection
+                    .proj_batch(&positions, camera)
+                    .into_iter()
+                    .zip(sizes)
+                    .filter_map(|(p, size)| Some((p?, size)));
+
+                let mut clustering = Clustering::new(self.cluster_threshold, &camera.get_screen_size());
+                clustering.push_sized(sources);
+                let clusters = clustering.clusters();
+
+                self.clustering = Some((view, clustering));
+                clusters
+            }
+        }
+    }
+
+    /// The source the closest to a position on screen
+    ///
+    /// Only the sources of the HEALPix cells around the position are looked at. The
+    /// HEALPix index of the sources being rebuilt whenever sources are added, it
+    /// always covers the whole catalog. The sources being indexed at their position
+    /// in the catalog, the cells looked at are widened by the largest displacement
+    /// of the sources due to their proper motions.
+    ///
+    /// # Arguments
+    ///
+    /// * ``ndc`` - The position, e.g. of the cursor, in the NDC space
+    /// * ``max_px`` - The distance in pixels beyond which the sources are ignored
+    ///
+    /// # Returns
+    ///
+    /// The index of the source, None if no source is projected within ``max_px``
+    pub fn nearest(&self, ndc: XYNDC<f64>, max_px: f32, camera: &CameraViewPort, projection: &ProjectionType) -> Option<usize> {
+        let view_frame = camera.get_coo_system();
+        // The sources are indexed in the icrs frame
+        let center = projection.unproject(&ndc, camera)?;
+        let center = crate::coosys::apply_coo_system(view_frame, CooSystem::ICRS, &center.vector()).lonlat();
+        let (sx, sy) = projection.local_scale(ndc, camera)?;
+
+        // Angular radius of the search, doubled to cover the distortion of the
+        // projection within it
+        let size = camera.get_screen_size();
+        let px_angle = (2.0 * sx / size.x as f64).max(2.0 * sy / size.y as f64);
+        let radius = 2.0 * (max_px as f64) * px_angle + self.max_pm_displacement();
+
+        let cone = HEALPixCoverage::from_cone(&center, radius, 7);
+        let indices = (&cone.0)
+            .into_range_moc_iter()
+            .cells()
+            .flat_map(|cell| {
+                self.index_vec
+                    .get_item_indices_inside_hpx_cell(&HEALPixCell(cell.depth, cell.idx))
+            })
+            .filter(|&idx| !self.is_hidden(idx))
+            .collect::<Vec<_>>();
+        let positions = indices.iter().map(|&idx| self.position(idx)).collect::<Vec<_>>();
+        let positions = to_view_frame(positions, view_frame);
+
+        // The sources out of the projection valid domain are not projected
+        let candidates = indices
+            .into_iter()
+            .zip(projection.proj_batch(&positions, camera));
+        nearest_with(candidates, ndc, max_px, &size)
+    }
+
+    /// Add sources to the catalog
+    ///
+    /// The sources are integrated at the next update, the ones in the view first so that
+    /// their markers are drawn without waiting for the whole catalog to be loaded. The
+    /// sources pushed have no proper motion.
+    pub fn push_sources(&mut self, chunk: &[Source]) {
+        self.pending.extend_from_slice(chunk);
+    }
+
+    // Integrate the sources in the view that have been pushed and a batch of
+    // the other ones. The remaining ones are kept for the next updates
+    fn integrate_pending(&mut self, camera: &CameraViewPort, projection: &ProjectionType) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let view_frame = camera.get_coo_system();
+        let ndc_pos = |s: &Source| {
+            let lonlat = LonLatT::new(Angle(s.lon().0 as f64), Angle(s.lat().0 as f64));
+            let lonlat = crate::coosys::apply_coo_system(CooSystem::ICRS, view_frame, &lonlat.vector()).lonlat();
+
+            if projection.is_in_valid_domain(&lonlat, camera) {
+                crate::math::lonlat::proj(&lonlat, projection, camera)
+            } else {
+                None
+            }
+        };
+        let is_visible = |s: &Source| {
+            ndc_pos(s)
+                .map(|p| p.x.abs() <= 1.0 && p.y.abs() <= 1.0)
+                .unwrap_or(false)
+        };
+
+        let (mut sources, mut off_screen): (Vec<_>, Vec<_>) = self.pending.drain(..).partition(is_visible);
+        let num_off_screen = off_screen.len().min(MAX_OFF_SCREEN_SOURCES_PER_UPDATE);
+        self.pending = off_screen.split_off(num_off_screen);
+        sources.append(&mut off_screen);
+
+        self.insert_sources(&sources);
+
+        // Only the bins of the new sources are updated when the view has not changed.
+        // The sources pushed having no proper motion, they are at their catalog position
+        let view = ClusteringView::new(self, camera, projection);
+        let filter = self.filter.as_deref();
+        let size_by_field = self.size_by_field.as_ref();
+        match &mut self.clustering {
+            Some((v, clustering)) if *v == view => {
+                let sources = sources
+                    .iter()
+                    .filter(|s| filter.map_or(true, |f| f(s)))
+                    .collect::<Vec<_>>();
+                let positions = sources
+                    .iter()
+                    .map(|s| LonLatT::new(Angle(s.lon().0 as f64), Angle(s.lat().0 as f64)))
+                    .collect::<Vec<_>>();
+                let positions = to_view_frame(positions, view_frame);
+                let sizes = sources
+                    .iter()
+                    .map(|s| size_by_field.map_or(1.0, |f| f.relative_size(s)));
+
+                clustering.push_sized(
+                    projection
+                        .proj_batch(&positions, camera)
+                        .into_iter()
+                        .zip(sizes)
+                        .filter_map(|(p, size)| Some((p?, size))),
+                );
             }
+            _ => self.clustering = None,
+        }
+    }
+
+    // Merge sources into the ones of the catalog, keeping them sorted by HEALPix cell
+    fn insert_sources(&mut self, sources: &[Source]) {
+        if let Some(ProperMotions { pm, .. }) = &mut self.proper_motions {
+            let catalog = self
+                .sources
+                .iter()
+                .zip(pm.iter())
+                .map(|(s, pm)| SourcePm(s.clone(), *pm))
+                .collect::<Vec<_>>();
+            let sources = sources
+                .iter()
+                .map(|s| SourcePm(s.clone(), [0.0, 0.0]))
+                .collect::<Vec<_>>();
+
+            let mut merged = merge_sorted(&catalog, &sources);
+            self.index_vec = IdxVec::from_coo(&mut merged);
+
+            *pm = merged.iter().map(|s| s.1).collect();
+            self.sources = merged.into_iter().map(|s| s.0).collect();
+        } else {
+            let mut merged = merge_sorted(&self.sources, sources);
+            self.index_vec = IdxVec::from_coo(&mut merged);
+
+            self.sources = merged.into_boxed_slice();
         }
-        self.num_instances = sources.len() as i32;
-        let sources = unsafe { utils::transmute_vec::<LonLatT<f32>, f32>(sources).unwrap() };
+
+        // The sources have been reordered
+        hide_with(&mut self.hidden, &self.sources, self.filter.as_deref());
+        sizes_with(&mut self.sizes, &self.sources, self.size_by_field.as_ref());
+        self.heatmap_view = None;
+    }
+
+    // Draw the clusters or the heatmap of the current view. Returns true if they have changed
+    fn update(&mut self, camera: &CameraViewPort, projection: &ProjectionType) -> Result<bool, JsValue> {
+        if self.display_mode == DisplayMode::Heatmap {
+            return self.update_heatmap(camera, projection);
+        }
+
+        let clusters = self.clusters(camera, projection);
+        // Nothing to send to the GPU when the clusters have not changed
+        if clusters == self.clusters {
+            return Ok(false);
+        }
+
+        let ndc_pos = clusters
+            .iter()
+            .flat_map(|c| [c.ndc_pos.x as f32, c.ndc_pos.y as f32])
+            .collect::<Vec<_>>();
+        let sizes = clusters.iter().map(|c| c.size).collect::<Vec<_>>();
+        self.num_instances = clusters.len() as i32;
+        self.clusters = clusters;
 
         // Update the vertex buffer
         #[cfg(feature = "webgl1")]
         self.vertex_array_object_catalog
             .bind_for_update()
-            .update_instanced_array("center", VecData(&sources));
+            .update_instanced_array("ndc_pos", VecData(&ndc_pos))
+            .update_instanced_array("size", VecData(&sizes));
 
         #[cfg(feature = "webgl2")]
         self.vertex_array_object_catalog
             .bind_for_update()
             .update_instanced_array(
-                "center",
+                "ndc_pos",
                 WebGl2RenderingContext::DYNAMIC_DRAW,
-                VecData(&sources),
+                VecData(&ndc_pos),
+            )
+            .update_instanced_array(
+                "size",
+                WebGl2RenderingContext::DYNAMIC_DRAW,
+                VecData(&sizes),
             );
+
+        Ok(true)
+    }
+
+    // Bin the sources of the current view unless they have been binned for it already
+    fn update_heatmap(&mut self, camera: &CameraViewPort, projection: &ProjectionType) -> Result<bool, JsValue> {
+        let view = ClusteringView::new(self, camera, projection);
+        if self.heatmap_view.as_ref() == Some(&view) {
+            return Ok(false);
+        }
+
+        let positions = (0..self.sources.len())
+            .filter(|&idx| !self.is_hidden(idx))
+            .map(|idx| self.position(idx))
+            .collect::<Vec<_>>();
+        self.heatmap.update(positions, &self.colormap, camera, projection)?;
+        self.heatmap_view = Some(view);
+
+        Ok(true)
+    }
+
+    // The color of the markers and of the labels of the clusters
+    fn color(&self) -> ColorRGBA {
+        let [r, g, b, _] = self.colormap.color_at(1.0, false);
+
+        ColorRGBA {
+            r: r as f32 / 255.0,
+            g: g as f32 / 255.0,
+            b: b as f32 / 255.0,
+            a: self.alpha,
+        }
     }
 
     fn draw(
@@ -468,93 +1034,258 @@ impl Catalog {
         shaders: &mut ShaderManager,
         manager: &Manager, // catalog manager
         camera: &CameraViewPort,
-        colormaps: &Colormaps,
-        fbo: Option<&FrameBufferObject>,
-        projection: &ProjectionType,
     ) -> Result<(), JsValue> {
+        if self.display_mode == DisplayMode::Heatmap {
+            return self.heatmap.draw(shaders);
+        }
+
         // If the catalog is transparent, simply discard the draw
-        if self.alpha > 0_f32 {
-            // Render to the FRAMEBUFFER
-            // Render the scene
-            manager.fbo.draw_onto(
-                || {
-                    gl.clear_color(0.0, 0.0, 0.0, 1.0);
-                    gl.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
-
-                    let shader = match projection {
-                        ProjectionType::Sin(_) => {
-                            crate::shader::get_shader(gl, shaders, "CatalogOrtVS", "CatalogOrtFS")
-                        }
-                        ProjectionType::Ait(_) => {
-                            crate::shader::get_shader(gl, shaders, "CatalogAitVS", "CatalogFS")
-                        }
-                        ProjectionType::Mer(_) => {
-                            crate::shader::get_shader(gl, shaders, "CatalogMerVS", "CatalogFS")
-                        }
-                        ProjectionType::Mol(_) => {
-                            crate::shader::get_shader(gl, shaders, "CatalogMolVS", "CatalogFS")
-                        }
-                        /*ProjectionType::Arc(_) => {
-                            crate::shader::get_shader(gl, shaders, "CatalogArcVS", "CatalogFS")
-                        }*/
-                        ProjectionType::Tan(_) => {
-                            crate::shader::get_shader(gl, shaders, "CatalogTanVS", "CatalogFS")
-                        }
-                        /*ProjectionType::Hpx(_) => {
-                            crate::shader::get_shader(gl, shaders, "CatalogHpxVS", "CatalogFS")
-                        }*/
-                        _ => todo!(),
-                    }?;
-                    let shader_bound = shader.bind(gl);
-
-                    shader_bound
-                        .attach_uniforms_from(camera)
-                        // Attach catalog specialized uniforms
-                        //.attach_uniform("kernel_texture", &manager.kernels["gaussian"]) // Gaussian kernel texture
-                        .attach_uniform("strength", &self.strength) // Strengh of the kernel
-                        .attach_uniform("current_time", &utils::get_current_time())
-                        .attach_uniform("kernel_size", &manager.kernel_size)
-                        .bind_vertex_array_object_ref(&self.vertex_array_object_catalog)
-                        .draw_elements_instanced_with_i32(
-                            WebGl2RenderingContext::TRIANGLES,
-                            0,
-                            self.num_instances as i32,
-                        );
-                    Ok(())
-                },
-                fbo,
-            )?;
-
-            // Render to the heatmap to the screen
-            {
-                // Set the camera
-                let size = camera.get_screen_size();
-                gl.viewport(0, 0, size.x as i32, size.y as i32);
-
-                let shader = crate::shader::get_shader(
-                    gl,
-                    shaders,
-                    "ColormapCatalogVS",
-                    "ColormapCatalogFS",
-                )?;
-                //self.colormap.get_shader(gl, shaders);
-                let shaderbound = shader.bind(gl);
-                shaderbound
-                    .attach_uniform("texture_fbo", &manager.fbo.texture) // FBO density texture computed just above
-                    .attach_uniform("alpha", &self.alpha) // Alpha channel
-                    .attach_uniforms_with_params_from(&self.colormap, colormaps)
-                    .attach_uniforms_from(colormaps)
-                    .attach_uniform("reversed", &0.0_f32)
-                    .bind_vertex_array_object_ref(&manager.vertex_array_object_screen)
-                    .draw_elements_with_i32(
-                        WebGl2RenderingContext::TRIANGLES,
-                        None,
-                        WebGl2RenderingContext::UNSIGNED_SHORT,
-                        0,
-                    );
-            }
+        if self.alpha > 0_f32 && self.num_instances > 0 {
+            let kernel_size = manager.px_size * self.marker_size(camera.get_aperture());
+
+            crate::shader::get_shader(gl, shaders, "catalogs_marker.vert", "catalogs_marker.frag")?
+                .bind(gl)
+                .attach_uniform("u_color", &self.color())
+                .attach_uniform("strength", &self.strength)
+                .attach_uniform("kernel_size", &kernel_size)
+                .bind_vertex_array_object_ref(&self.vertex_array_object_catalog)
+                .draw_elements_instanced_with_i32(
+                    WebGl2RenderingContext::TRIANGLES,
+                    0,
+                    self.num_instances,
+                );
         }
 
         Ok(())
     }
 }
+
+// The size of the markers, inversely proportional to the aperture and clamped to [min_px, max_px]
+fn marker_size_with(aperture: Angle<f64>, min_px: f32, max_px: f32) -> f32 {
+    let size = (MARKER_SIZE_PX as f64) * MARKER_REF_APERTURE / aperture.to_radians().max(1e-12);
+
+    (size as f32).clamp(min_px, max_px)
+}
+
+// The index of the candidate the closest to `ndc` on screen, within `max_px` pixels
+fn nearest_with<I>(candidates: I, ndc: XYNDC<f64>, max_px: f32, screen_size: &Vector2<f32>) -> Option<usize>
+where
+    I: IntoIterator<Item = (usize, Option<XYNDC<f64>>)>,
+{
+    // a NDC length of 2 spans the whole screen
+    let (sx, sy) = (0.5 * screen_size.x as f64, 0.5 * screen_size.y as f64);
+
+    candidates
+        .into_iter()
+        .filter_map(|(idx, p)| {
+            let p = p?;
+            let d = ((p.x - ndc.x) * sx).hypot((p.y - ndc.y) * sy);
+
+            (d <= max_px as f64).then(|| (d, idx))
+        })
+        .min_by(|(d1, _), (d2, _)| d1.total_cmp(d2))
+        .map(|(_, idx)| idx)
+}
+
+// The size of the markers given by a field of the sources
+struct SizeByField {
+    // The index of the field in the values of the sources
+    field: usize,
+    size: Box<dyn Fn(f32) -> f32>,
+}
+
+impl SizeByField {
+    // The size of the marker of a source relative to the catalog one
+    fn relative_size(&self, source: &Source) -> f32 {
+        let px = source.field(self.field).map(|value| (self.size)(value));
+
+        match px {
+            Some(px) if px.is_finite() && px > 0.0 => px / MARKER_SIZE_PX,
+            // The default size
+            _ => 1.0,
+        }
+    }
+}
+
+// The sizes of the markers of the sources relative to the catalog one, reset in place
+fn sizes_with(sizes: &mut Vec<f32>, sources: &[Source], size_by_field: Option<&SizeByField>) {
+    sizes.clear();
+
+    if let Some(size_by_field) = size_by_field {
+        sizes.extend(sources.iter().map(|s| size_by_field.relative_size(s)));
+    }
+}
+
+// The positions of sources given in the icrs frame, in the frame of the view
+pub(super) fn to_view_frame(positions: Vec<LonLatT<f64>>, view_frame: CooSystem) -> Vec<LonLatT<f64>> {
+    if view_frame == CooSystem::ICRS {
+        positions
+    } else {
+        positions
+            .into_iter()
+            .map(|p| crate::coosys::apply_coo_system(CooSystem::ICRS, view_frame, &p.vector()).lonlat())
+            .collect()
+    }
+}
+
+// Mark the sources not matching the filter as hidden. The flags are reset in place
+// so that changing the filter does not reallocate them.
+fn hide_with(hidden: &mut Vec<bool>, sources: &[Source], filter: Option<&dyn Fn(&Source) -> bool>) {
+    hidden.clear();
+
+    if let Some(f) = filter {
+        hidden.extend(sources.iter().map(|s| !f(s)));
+    }
+}
+
+// Merge sources into a list of sources sorted by HEALPix cell, as done by `IdxVec::from_coo`.
+// Only the new sources are sorted, the result is kept sorted so that building its index
+// vector does not need to sort it again
+fn merge_sorted<T>(sorted: &[T], sources: &[T]) -> Vec<T>
+where
+    T: LonLat<f32> + Clone,
+{
+    let hash = |s: &T| {
+        let lonlat = s.lonlat();
+        healpix::nested::hash(7, lonlat.lon().to_radians() as f64, lonlat.lat().to_radians() as f64)
+    };
+
+    let mut sources = sources.iter().map(|s| (hash(s), s.clone())).collect::<Vec<_>>();
+    sources.sort_by_key(|(h, _)| *h);
+
+    let mut merged = Vec::with_capacity(sorted.len() + sources.len());
+    let mut sources = sources.into_iter().peekable();
+    for s in sorted {
+        let h = hash(s);
+        while let Some((_, new)) = sources.next_if(|(h_new, _)| *h_new < h) {
+            merged.push(new);
+        }
+        merged.push(s.clone());
+    }
+    merged.extend(sources.map(|(_, s)| s));
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_source_to_the_cursor() {
+        let screen_size = Vector2::new(800.0, 600.0);
+        // A cluster of sources a few pixels apart, one not projected
+        let px = |x: f64, y: f64| Some(XYNDC::new(x / 400.0, y / 300.0));
+        let candidates = vec![
+            (3, px(100.0, 100.0)),
+            (7, px(104.0, 101.0)),
+            (8, px(98.0, 95.0)),
+            (9, None),
+            (12, px(110.0, 100.0)),
+        ];
+        let cursor = XYNDC::new(103.0 / 400.0, 100.0 / 300.0);
+
+        assert_eq!(nearest_with(candidates.clone(), cursor, 10.0, &screen_size), Some(7));
+        // Far from all the sources
+        let cursor = XYNDC::new(-0.5, -0.5);
+        assert_eq!(nearest_with(candidates, cursor, 10.0, &screen_size), None);
+    }
+
+    #[test]
+    fn clusters_in_a_galactic_view() {
+        use super::super::cluster::cluster;
+        use crate::math::projection::Projection;
+        use cgmath::InnerSpace;
+
+        // Sources around the galactic center, given in the icrs frame
+        let deg = |lon: f64, lat: f64| LonLatT::new(Angle(lon.to_radians()), Angle(lat.to_radians()));
+        let positions = vec![deg(266.405, -28.936), deg(266.41, -28.94), deg(266.40, -28.93)];
+
+        // A galactic view centered on the galactic center
+        let projection = ProjectionType::Sin(mapproj::zenithal::sin::Sin);
+        let positions = to_view_frame(positions, CooSystem::GAL);
+        let sources = positions
+            .iter()
+            .filter_map(|p| projection.world_to_clip_space(&p.vector()))
+            .collect::<Vec<_>>();
+
+        let clusters = cluster(sources, 20.0, &Vector2::new(800.0, 600.0));
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].count, 3);
+        // Drawn at the center of the view, where the same sources are without conversion
+        assert!(clusters[0].ndc_pos.magnitude() < 1e-3);
+        // Left as is in an icrs view
+        let p = to_view_frame(vec![deg(10.0, 20.0)], CooSystem::ICRS)[0];
+        assert_eq!((p.lon().0, p.lat().0), (10.0_f64.to_radians(), 20.0_f64.to_radians()));
+    }
+
+    #[test]
+    fn filter_hides_the_sources_not_matching() {
+        // A catalog of two fields, the magnitude being the second one
+        let source = |dec: f32, mag: f32| {
+            Source::with_fields(LonLatT::new(Angle(0.5), Angle(dec.to_radians())), Box::new([dec, mag]))
+        };
+        let sources = [
+            source(-30.0, 12.1),
+            source(10.0, 16.4),
+            source(45.0, 14.9),
+            source(-5.0, f32::NAN),
+            source(60.0, 18.0),
+        ];
+        let mag = 1;
+        let brighter_than_15 = move |s: &Source| s.field(mag).map_or(false, |m| m < 15.0);
+        let north = |s: &Source| s.lat().0 > 0.0;
+
+        let mut hidden = vec![];
+        hide_with(&mut hidden, &sources, Some(&brighter_than_15));
+        assert_eq!(hidden, [false, true, false, true, true]);
+
+        // Changing the filter reuses the flags
+        let ptr = hidden.as_ptr();
+        hide_with(&mut hidden, &sources, Some(&north));
+        assert_eq!(hidden, [true, false, false, true, false]);
+        assert_eq!(hidden.as_ptr(), ptr);
+
+        // Without filter, all the sources are shown
+        hide_with(&mut hidden, &sources, None);
+        assert!((0..sources.len()).all(|i| !hidden.get(i).copied().unwrap_or(false)));
+    }
+
+    #[test]
+    fn brighter_sources_get_bigger_markers() {
+        // The magnitude is the only field of the sources
+        let source = |fields: &[f32]| Source::with_fields(LonLatT::new(Angle(0.5), Angle(0.2)), fields.into());
+        let sources = [source(&[8.0]), source(&[12.0]), source(&[f32::NAN]), source(&[])];
+
+        // 2 pixels less per magnitude, 24 pixels at magnitude 8
+        let size_by_field = SizeByField {
+            field: 0,
+            size: Box::new(|mag: f32| 24.0 - 2.0 * (mag - 8.0)),
+        };
+        let mut sizes = vec![];
+        sizes_with(&mut sizes, &sources, Some(&size_by_field));
+
+        assert!((sizes[0] / sizes[1] - 24.0 / 16.0).abs() < 1e-6);
+        // NaN and missing values
+        assert_eq!(&sizes[2..], [1.0, 1.0]);
+
+        // Combined with the size of the markers of the catalog at the reference aperture
+        let catalog_size = marker_size_with(Angle(MARKER_REF_APERTURE), 4.0, 32.0);
+        assert!((catalog_size * sizes[0] - 24.0).abs() < 1e-4);
+
+        sizes_with(&mut sizes, &sources, None);
+        assert!(sizes.is_empty());
+    }
+
+    #[test]
+    fn marker_size_scales_with_the_aperture() {
+        let size = |deg: f64| marker_size_with(Angle(deg.to_radians()), 4.0, 32.0);
+
+        assert!(size(10.0) > size(60.0));
+        assert!((size(60.0) - 8.0).abs() < 1e-4);
+        // Clamped when zoomed far in or out
+        assert_eq!(size(1.0), 32.0);
+        assert_eq!(size(360.0), 4.0);
+        assert_eq!(marker_size_with(Angle(60.0_f64.to_radians()), 10.0, 32.0), 10.0);
+    }
+}
@@ -1,2 +1,5 @@
+pub mod cluster;
+pub mod heatmap;
+pub mod selection;
 mod manager;
-pub use manager::{Catalog, Manager};
+pub use manager::{Catalog, DisplayMode, Manager, ProperMotions, Source};
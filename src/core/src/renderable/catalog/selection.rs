@@ -0,0 +1,85 @@
+use crate::CameraViewPort;
+use crate::ProjectionType;
+
+use crate::coo_space::XYNDC;
+use crate::math::sph_geom;
+
+use crate::LonLatT;
+
+/// The indices of the sources enclosed by a polygon drawn on the screen, e.g. a lasso
+///
+/// The vertices of the polygon are unprojected to the sky, the ones out of the
+/// projection being discarded, and the sources are tested against the spherical
+/// polygon formed. Polygons crossing RA=0 or going around a pole are handled.
+///
+/// # Arguments
+///
+/// * ``screen_poly`` - The vertices of the polygon in the NDC space
+/// * ``sources`` - The positions of the sources
+/// * ``camera`` - The camera object
+/// * ``projection`` - The projection of the view
+pub fn sources_in_polygon(
+    screen_poly: &[XYNDC<f64>],
+    sources: &[LonLatT<f64>],
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Vec<usize> {
+    sources_in_polygon_with(screen_poly, sources, &|xy| {
+        crate::math::lonlat::unproj(xy, projection, camera)
+    })
+}
+
+// Same as `sources_in_polygon` but the vertices are unprojected by `unproj`
+fn sources_in_polygon_with<U>(screen_poly: &[XYNDC<f64>], sources: &[LonLatT<f64>], unproj: &U) -> Vec<usize>
+where
+    U: Fn(&XYNDC<f64>) -> Option<LonLatT<f64>>,
+{
+    let vertices = screen_poly.iter().filter_map(unproj).collect::<Vec<_>>();
+    if vertices.len() < 3 {
+        return vec![];
+    }
+
+    sources
+        .iter()
+        .enumerate()
+        .filter(|(_, lonlat)| sph_geom::is_in_polygon(lonlat, &vertices))
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::angle::ToAngle;
+    use crate::math::lonlat::LonLat;
+    use crate::math::projection::Projection;
+
+    #[test]
+    fn sources_in_a_square_around_the_center() {
+        let projection = ProjectionType::Tan(mapproj::zenithal::tan::Tan);
+        // Unproject directly from the clip space, the center of the projection being (0, 0)
+        let unproj = |xy: &XYNDC<f64>| projection.clip_to_world_space(xy).map(|v| v.lonlat());
+
+        let (x, y) = (0.1, 0.1);
+        let square = [
+            XYNDC::new(-x, -y),
+            XYNDC::new(x, -y),
+            XYNDC::new(x, y),
+            XYNDC::new(-x, y),
+        ];
+
+        let lonlat = |lon: f64, lat: f64| LonLatT::new(lon.to_radians().to_angle(), lat.to_radians().to_angle());
+        let sources = [
+            lonlat(0.0, 0.0),
+            // inside, across RA=0
+            lonlat(359.0, 1.0),
+            // outside
+            lonlat(30.0, 0.0),
+            lonlat(180.0, 0.0),
+            lonlat(0.0, -40.0),
+            lonlat(1.0, -1.0),
+        ];
+
+        assert_eq!(sources_in_polygon_with(&square, &sources, &unproj), vec![0, 1, 5]);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::camera::CameraViewPort;
+use crate::ProjectionType;
+
+/// The camera state the projected grid geometry depends on
+#[derive(Clone, Copy, PartialEq)]
+pub struct GridCacheKey {
+    aperture: f64,
+    // Contains both the center and the rotation of the view
+    w2m: cgmath::Matrix4<f64>,
+    width: f32,
+    height: f32,
+    projection: std::mem::Discriminant<ProjectionType>,
+}
+
+impl GridCacheKey {
+    pub fn new(camera: &CameraViewPort, projection: &ProjectionType) -> Self {
+        Self {
+            aperture: camera.get_aperture().to_radians(),
+            w2m: *camera.get_w2m(),
+            width: camera.get_width(),
+            height: camera.get_height(),
+            projection: std::mem::discriminant(projection),
+        }
+    }
+}
+
+/// Keep a value computed for a key until the key changes
+pub struct GeometryCache<K, T> {
+    key: Option<K>,
+    value: T,
+}
+
+impl<K, T> GeometryCache<K, T>
+where
+    K: PartialEq,
+{
+    pub fn new(value: T) -> Self {
+        Self { key: None, value }
+    }
+
+    /// Tell whether the value must be recomputed for ``key``
+    pub fn is_dirty(&self, key: &K) -> bool {
+        self.key.as_ref() != Some(key)
+    }
+
+    /// Force the next access to recompute the value
+    pub fn invalidate(&mut self) {
+        self.key = None;
+    }
+
+    /// Get the cached value, it is recomputed with ``f`` only if the key changed
+    pub fn get_or_update<F>(&mut self, key: K, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        if self.is_dirty(&key) {
+            self.value = f();
+            self.key = Some(key);
+        }
+
+        &self.value
+    }
+
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GeometryCache;
+    use std::cell::Cell;
+
+    #[test]
+    fn static_camera_does_not_reproject() {
+        let num_calls = Cell::new(0);
+        let project = |aperture: f64| {
+            num_calls.set(num_calls.get() + 1);
+            vec![aperture; 4]
+        };
+
+        let mut cache = GeometryCache::new(vec![]);
+
+        // first frame
+        let key = (1.0_f64, 0.5_f64);
+        assert!(cache.is_dirty(&key));
+        cache.get_or_update(key, || project(key.0));
+        assert_eq!(num_calls.get(), 1);
+
+        // the camera did not move
+        assert!(!cache.is_dirty(&key));
+        let v = cache.get_or_update(key, || project(key.0));
+        assert_eq!(v.len(), 4);
+        assert_eq!(num_calls.get(), 1);
+
+        // the camera zoomed
+        let key = (0.5_f64, 0.5_f64);
+        assert!(cache.is_dirty(&key));
+        cache.get_or_update(key, || project(key.0));
+        assert_eq!(num_calls.get(), 2);
+
+        cache.invalidate();
+        assert!(cache.is_dirty(&key));
+    }
+}
@@ -7,7 +7,7 @@ use core::ops::Range;
 use crate::math::MINUS_HALF_PI;
 use crate::ProjectionType;
 
-use super::angle::SerializeFmt;
+use super::angle::{Angle, SerializeFmt};
 use crate::math::HALF_PI;
 
 pub fn get_intersecting_meridian(
@@ -15,6 +15,7 @@ pub fn get_intersecting_meridian(
     camera: &CameraViewPort,
     projection: &ProjectionType,
     fmt: &SerializeFmt,
+    limb_fade: Angle<f64>,
 ) -> Option<Meridian> {
     let fov = camera.get_field_of_view();
     if fov.contains_both_poles() {
@@ -25,6 +26,7 @@ pub fn get_intersecting_meridian(
             camera,
             projection,
             fmt,
+            limb_fade,
         );
         Some(meridian)
     } else {
@@ -39,6 +41,7 @@ pub fn get_intersecting_meridian(
                     camera,
                     projection,
                     fmt,
+                    limb_fade,
                 );
                 Some(meridian)
             }
@@ -56,7 +59,15 @@ pub fn get_intersecting_meridian(
                             lat1..MINUS_HALF_PI
                         };
 
-                        Meridian::new(lon, &lat, LabelOptions::OnSide, camera, projection, fmt)
+                        Meridian::new(
+                            lon,
+                            &lat,
+                            LabelOptions::OnSide,
+                            camera,
+                            projection,
+                            fmt,
+                            limb_fade,
+                        )
                     }
                     2 => {
                         // full intersection
@@ -73,6 +84,7 @@ pub fn get_intersecting_meridian(
                             camera,
                             projection,
                             fmt,
+                            limb_fade,
                         )
                     }
                     _ => {
@@ -100,11 +112,9 @@ pub fn get_intersecting_meridian(
                         let line_vertices = vertices.iter().zip(vertices.iter().skip(1))
                             .step_by(2)
                             .map(|(i1, i2)| {
-                                line::great_circle_arc::project(
-                                    lon,
-                                    i1.lat().to_radians(),
-                                    lon,
-                                    i2.lat().to_radians(),
+                                line::great_circle::project(
+                                    LonLatT::new(lon.to_angle(), i1.lat()),
+                                    LonLatT::new(lon.to_angle(), i2.lat()),
                                     camera,
                                     projection
                                 )
@@ -121,6 +131,7 @@ pub fn get_intersecting_meridian(
                             camera,
                             projection,
                             fmt,
+                            limb_fade,
                         )
                     }
                 };
@@ -133,8 +144,11 @@ pub fn get_intersecting_meridian(
 }
 
 pub struct Meridian {
+    lon: f64,
     // List of vertices
     vertices: Vec<[f32; 2]>,
+    // Alpha of the vertices, fading the meridian out near the limb
+    alphas: Vec<f32>,
     // Line vertices indices
     indices: Vec<Range<usize>>,
     label: Option<Label>,
@@ -148,16 +162,16 @@ impl Meridian {
         camera: &CameraViewPort,
         projection: &ProjectionType,
         fmt: &SerializeFmt,
+        limb_fade: Angle<f64>,
     ) -> Self {
         let label = Label::from_meridian(lon, lat, label_options, camera, projection, fmt);
 
-        // Draw the full parallel
-        let vertices = crate::renderable::line::great_circle_arc::project(
-            lon, lat.start, lon, lat.end, camera, projection,
+        // Draw the full meridian
+        let (vertices, alphas): (Vec<_>, Vec<_>) = crate::renderable::line::meridian::project_faded(
+            lon, lat.start, lat.end, limb_fade, camera, projection,
         )
         .into_iter()
-        .map(|v| [v.x as f32, v.y as f32])
-        .collect::<Vec<_>>();
+        .unzip();
 
         let mut start_idx = 0;
 
@@ -232,12 +246,19 @@ impl Meridian {
         };*/
 
         Self {
+            lon,
             vertices,
+            alphas,
             indices,
             label,
         }
     }
 
+    #[inline]
+    pub fn get_lon(&self) -> f64 {
+        self.lon
+    }
+
     #[inline]
     pub fn get_lines_vertices(&self) -> Vec<&[[f32; 2]]> {
         self.indices
@@ -246,6 +267,15 @@ impl Meridian {
             .collect()
     }
 
+    /// The alpha of the vertices of each line given by ``get_lines_vertices``
+    #[inline]
+    pub fn get_lines_alphas(&self) -> Vec<&[f32]> {
+        self.indices
+            .iter()
+            .map(|r| &self.alphas[r.start..r.end])
+            .collect()
+    }
+
     #[inline]
     pub fn get_label(&self) -> Option<&Label> {
         self.label.as_ref()
@@ -1,3 +1,4 @@
+pub mod cache;
 pub mod label;
 pub mod meridian;
 pub mod parallel;
@@ -9,39 +10,62 @@ use parallel::Parallel;
 
 use crate::camera::CameraViewPort;
 use crate::math::angle;
-use crate::math::HALF_PI;
+use crate::math::{HALF_PI, TWICE_PI};
+use crate::renderable::line::{self, LineStyle};
 use crate::ProjectionType;
-use al_api::color::ColorRGBA;
-use al_api::grid::GridCfg;
+use al_api::color::{ColorRGB, ColorRGBA};
+use al_api::grid::{GridCfg, LineWidthPolicy};
 use al_core::VertexArrayObject;
 use al_core::WebGlContext;
 use web_sys::WebGl2RenderingContext;
 
 use label::Label;
+
+// Default bounds of the width of the grid lines in pixels
+const MIN_THICKNESS_PX: f32 = 1.0;
+const MAX_THICKNESS_PX: f32 = 16.0;
+
 pub struct ProjetedGrid {
     // Properties
     pub color: ColorRGBA,
+    // Highlight the meridian of longitude 0 with that color
+    pub prime_meridian_color: Option<ColorRGB>,
     pub show_labels: bool,
     pub enabled: bool,
     pub label_scale: f32,
     thickness: f32,
+    width_policy: LineWidthPolicy,
+    // Bounds of the width of the lines in pixels
+    min_thickness: f32,
+    max_thickness: f32,
+    // Angular distance over which the lines fade out before the limb, 0 to disable the fade
+    limb_fade: angle::Angle<f64>,
 
     // Render Text Manager
     text_renderer: TextRenderManager,
     fmt: angle::SerializeFmt,
 
     //line_style: line::Style,
-    meridians: Vec<Meridian>,
-    parallels: Vec<Parallel>,
+    // The projected lines, only rebuilt when the camera moves
+    lines: GeometryCache<GridCacheKey, GridLines>,
 
     vao: VertexArrayObject,
+    // Same as `vao` with the alpha of the endpoints of the segments
+    faded_vao: VertexArrayObject,
     gl: WebGlContext,
 }
+
+#[derive(Default)]
+struct GridLines {
+    meridians: Vec<Meridian>,
+    parallels: Vec<Parallel>,
+}
 use crate::renderable::text::TextRenderManager;
 use crate::renderable::Renderer;
 use wasm_bindgen::JsValue;
 use web_sys::HtmlElement;
 
+use self::cache::{GeometryCache, GridCacheKey};
 use self::meridian::Meridian;
 
 impl ProjetedGrid {
@@ -60,52 +84,30 @@ impl ProjetedGrid {
         //let line_style = line::Style::None;
         let fmt = angle::SerializeFmt::DMS;
         let thickness = 2.0;
-        let meridians = Vec::new();
-        let parallels = Vec::new();
-
-        let mut vao = VertexArrayObject::new(&gl);
-        vao.bind_for_update()
-            // Store the cartesian position of the center of the source in the a instanced VBO
-            .add_instanced_array_buffer(
-                "ndc_pos",
-                4 * std::mem::size_of::<f32>(),
-                &[2, 2],
-                &[0, 2 * std::mem::size_of::<f32>()],
-                WebGl2RenderingContext::DYNAMIC_DRAW,
-                &[] as &[f32],
-            )
-            .add_array_buffer(
-                "vertices",
-                2 * std::mem::size_of::<f32>(),
-                &[2],
-                &[0],
-                WebGl2RenderingContext::STATIC_DRAW,
-                &[
-                    0_f32, -0.5_f32, 1_f32, -0.5_f32, 1_f32, 0.5_f32, 0_f32, 0.5_f32,
-                ] as &[f32],
-            )
-            // Set the element buffer
-            .add_element_buffer(
-                WebGl2RenderingContext::STATIC_DRAW,
-                &[0_u16, 1_u16, 2_u16, 0_u16, 2_u16, 3_u16] as &[u16],
-            )
-            // Unbind the buffer
-            .unbind();
+        let lines = GeometryCache::new(GridLines::default());
+
+        let vao = line::segments_vao(&gl, 2);
+        let faded_vao = line::segments_vao(&gl, 3);
 
         let grid = ProjetedGrid {
             color,
+            prime_meridian_color: None,
             //line_style,
             show_labels,
             enabled,
             label_scale,
             thickness,
+            width_policy: LineWidthPolicy::Fixed,
+            min_thickness: MIN_THICKNESS_PX,
+            max_thickness: MAX_THICKNESS_PX,
+            limb_fade: angle::Angle(0.0),
 
             text_renderer,
-            meridians,
-            parallels,
+            lines,
             fmt,
 
             vao,
+            faded_vao,
             gl,
         };
         // Initialize the vertices & labels
@@ -128,6 +130,11 @@ impl ProjetedGrid {
             label_size,
             enabled,
             fmt,
+            prime_meridian_color,
+            width_policy,
+            min_thickness,
+            max_thickness,
+            limb_fade,
         } = new_cfg;
 
         if let Some(color) = color {
@@ -145,17 +152,41 @@ impl ProjetedGrid {
             self.text_renderer.set_color(&self.color);
         }
 
+        if let Some(prime_meridian_color) = prime_meridian_color {
+            self.prime_meridian_color = Some(prime_meridian_color);
+        }
+
         if let Some(thickness) = thickness {
             // convert thickness in pixels to ndc
             self.thickness = thickness;
         }
 
+        if let Some(width_policy) = width_policy {
+            self.width_policy = width_policy;
+        }
+
+        if let Some(min_thickness) = min_thickness {
+            self.min_thickness = min_thickness;
+        }
+
+        if let Some(max_thickness) = max_thickness {
+            self.max_thickness = max_thickness;
+        }
+
+        if let Some(limb_fade) = limb_fade {
+            self.limb_fade = angle::Angle((limb_fade.max(0.0) as f64).to_radians());
+            // the alpha of the vertices have to be recomputed
+            self.lines.invalidate();
+        }
+
         if let Some(show_labels) = show_labels {
             self.show_labels = show_labels;
         }
 
         if let Some(fmt) = fmt {
             self.fmt = fmt.into();
+            // the labels have to be regenerated
+            self.lines.invalidate();
         }
 
         if let Some(label_size) = label_size {
@@ -174,13 +205,22 @@ impl ProjetedGrid {
         Ok(())
     }
 
+    /// Tell whether the grid lines have to be projected again for
+    /// that camera state
+    pub fn is_dirty(&self, camera: &CameraViewPort, projection: &ProjectionType) -> bool {
+        self.lines.is_dirty(&GridCacheKey::new(camera, projection))
+    }
+
     pub fn draw_labels(&mut self) -> Result<(), JsValue> {
         if self.enabled && self.show_labels {
-            let labels = self
-                .meridians
+            let GridLines {
+                meridians,
+                parallels,
+            } = self.lines.get();
+            let labels = meridians
                 .iter()
                 .filter_map(|m| m.get_label())
-                .chain(self.parallels.iter().filter_map(|p| p.get_label()));
+                .chain(parallels.iter().filter_map(|p| p.get_label()));
 
             //let dpi = camera.get_dpi();
             self.text_renderer.begin();
@@ -207,110 +247,288 @@ impl ProjetedGrid {
         shaders: &mut ShaderManager,
     ) -> Result<(), JsValue> {
         if self.enabled {
-            let fov = camera.get_field_of_view();
-            let bbox = fov.get_bounding_box();
-            let max_dim_px = camera.get_width().max(camera.get_height()) as f64;
-            let step_line_px = max_dim_px * 0.2;
-
-            // update meridians
-            self.meridians = {
-                // Select the good step with a binary search
-                let step_lon_precised =
-                    (bbox.get_lon_size() as f64) * step_line_px / (camera.get_width() as f64);
-                let step_lon = select_fixed_step(step_lon_precised);
-
-                // Add meridians
-                let start_lon = bbox.lon_min() - (bbox.lon_min() % step_lon);
-                let mut stop_lon = bbox.lon_max();
-                if bbox.all_lon() {
-                    stop_lon -= 1e-3;
-                }
+            let fmt = &self.fmt;
+            let limb_fade = self.limb_fade;
+            let GridLines {
+                meridians,
+                parallels,
+            } = self
+                .lines
+                .get_or_update(GridCacheKey::new(camera, projection), || {
+                    project_lines(camera, projection, fmt, limb_fade)
+                });
+
+            let thickness = line_width(
+                self.width_policy,
+                self.thickness,
+                camera.get_dpi(),
+                self.min_thickness,
+                self.max_thickness,
+            );
+            // The lines sharing a style are drawn together
+            let style = LineStyle::new(&self.color, thickness);
+            let prime_meridian_style = self.prime_meridian_color.map(|c| {
+                let color = ColorRGBA {
+                    r: c.r,
+                    g: c.g,
+                    b: c.b,
+                    a: self.color.a,
+                };
+                LineStyle::new(&color, thickness)
+            });
+
+            let lines = meridians
+                .iter()
+                .flat_map(|meridian| {
+                    let style = match prime_meridian_style {
+                        Some(s) if is_prime_meridian(meridian.get_lon()) => s,
+                        _ => style,
+                    };
+
+                    meridian
+                        .get_lines_vertices()
+                        .into_iter()
+                        .zip(meridian.get_lines_alphas())
+                        .map(move |(vertices, alphas)| (style, vertices, alphas))
+                })
+                .chain(parallels.iter().flat_map(|parallel| {
+                    parallel
+                        .get_lines_vertices()
+                        .into_iter()
+                        .zip(parallel.get_lines_alphas())
+                        .map(move |(vertices, alphas)| (style, vertices, alphas))
+                }));
 
-                let mut meridians = vec![];
-                let mut lon = start_lon;
-                while lon < stop_lon {
-                    if let Some(p) =
-                        meridian::get_intersecting_meridian(lon, camera, projection, &self.fmt)
-                    {
-                        meridians.push(p);
-                    }
-                    lon += step_lon;
+            self.gl.enable(WebGl2RenderingContext::BLEND);
+            if limb_fade.to_radians() > 0.0 {
+                for (style, buf) in line::group_by_style_faded(lines) {
+                    self.faded_vao.bind_for_update().update_instanced_array(
+                        "ndc_pos",
+                        WebGl2RenderingContext::DYNAMIC_DRAW,
+                        VecData(&buf),
+                    );
+
+                    let num_instances = buf.len() / 6;
+
+                    crate::shader::get_shader(&self.gl, shaders, "line_inst_ndc_faded.vert", "line_faded.frag")?
+                        .bind(&self.gl)
+                        .attach_uniforms_from(&style)
+                        .attach_uniform("u_width", &(camera.get_width()))
+                        .attach_uniform("u_height", &(camera.get_height()))
+                        .bind_vertex_array_object_ref(&self.faded_vao)
+                        .draw_elements_instanced_with_i32(
+                            WebGl2RenderingContext::TRIANGLES,
+                            0,
+                            num_instances as i32,
+                        );
                 }
-                meridians
-            };
+            } else {
+                let lines = lines.map(|(style, vertices, _)| (style, vertices));
+                for (style, buf) in line::group_by_style(lines) {
+                    self.vao.bind_for_update().update_instanced_array(
+                        "ndc_pos",
+                        WebGl2RenderingContext::DYNAMIC_DRAW,
+                        VecData(&buf),
+                    );
+
+                    let num_instances = buf.len() / 4;
+
+                    crate::shader::get_shader(&self.gl, shaders, "line_inst_ndc.vert", "line_base.frag")?
+                        .bind(&self.gl)
+                        .attach_uniforms_from(&style)
+                        .attach_uniform("u_width", &(camera.get_width()))
+                        .attach_uniform("u_height", &(camera.get_height()))
+                        .bind_vertex_array_object_ref(&self.vao)
+                        .draw_elements_instanced_with_i32(
+                            WebGl2RenderingContext::TRIANGLES,
+                            0,
+                            num_instances as i32,
+                        );
+                }
+            }
 
-            self.parallels = {
-                let step_lat_precised =
-                    (bbox.get_lat_size() as f64) * step_line_px / (camera.get_height() as f64);
-                let step_lat = select_fixed_step(step_lat_precised);
+            // Draw the parallels over, colored by the depth of recursion of their subdivision
+            #[cfg(feature = "debug_subdivision")]
+            {
+                let max_iter = crate::renderable::line::parallel_arc::SubdivisionParams::default().max_iter;
+                for depth in 0..=max_iter {
+                    let buf = parallels
+                        .iter()
+                        .flat_map(|parallel| parallel.get_segments_depth())
+                        .filter(|(_, d)| *d == depth)
+                        .flat_map(|(s, _)| s)
+                        .collect::<Vec<f32>>();
+                    if buf.is_empty() {
+                        continue;
+                    }
 
-                let mut start_lat = bbox.lat_min() - (bbox.lat_min() % step_lat);
-                if start_lat == -HALF_PI {
-                    start_lat += step_lat;
+                    self.vao.bind_for_update().update_instanced_array(
+                        "ndc_pos",
+                        WebGl2RenderingContext::DYNAMIC_DRAW,
+                        VecData(&buf),
+                    );
+
+                    crate::shader::get_shader(&self.gl, shaders, "line_inst_ndc.vert", "line_base.frag")?
+                        .bind(&self.gl)
+                        .attach_uniform("u_color", &depth_color(depth, max_iter))
+                        .attach_uniform("u_width", &(camera.get_width()))
+                        .attach_uniform("u_height", &(camera.get_height()))
+                        .attach_uniform("u_thickness", &thickness)
+                        .bind_vertex_array_object_ref(&self.vao)
+                        .draw_elements_instanced_with_i32(
+                            WebGl2RenderingContext::TRIANGLES,
+                            0,
+                            (buf.len() / 4) as i32,
+                        );
                 }
-                let stop_lat = bbox.lat_max();
-                let mut lat = start_lat;
+            }
 
-                let mut parallels = vec![];
-                while lat < stop_lat {
-                    if let Some(p) = parallel::get_intersecting_parallel(lat, camera, projection) {
-                        parallels.push(p);
-                    }
-                    lat += step_lat;
+            // Mark with a cross the points where the parallels have been split at the zero meridian
+            #[cfg(feature = "debug_seam")]
+            {
+                // Half size of the crosses
+                let (dx, dy) = (8.0 / camera.get_width(), 8.0 / camera.get_height());
+                let buf = parallels
+                    .iter()
+                    .flat_map(|parallel| parallel.get_seams())
+                    .flat_map(|p| {
+                        let (x, y) = (p.x as f32, p.y as f32);
+                        [x - dx, y, x + dx, y, x, y - dy, x, y + dy]
+                    })
+                    .collect::<Vec<f32>>();
+
+                if !buf.is_empty() {
+                    self.vao.bind_for_update().update_instanced_array(
+                        "ndc_pos",
+                        WebGl2RenderingContext::DYNAMIC_DRAW,
+                        VecData(&buf),
+                    );
+
+                    crate::shader::get_shader(&self.gl, shaders, "line_inst_ndc.vert", "line_base.frag")?
+                        .bind(&self.gl)
+                        .attach_uniform("u_color", &SEAM_COLOR)
+                        .attach_uniform("u_width", &(camera.get_width()))
+                        .attach_uniform("u_height", &(camera.get_height()))
+                        .attach_uniform("u_thickness", &thickness)
+                        .bind_vertex_array_object_ref(&self.vao)
+                        .draw_elements_instanced_with_i32(
+                            WebGl2RenderingContext::TRIANGLES,
+                            0,
+                            (buf.len() / 4) as i32,
+                        );
                 }
-                parallels
-            };
+            }
+            self.gl.disable(WebGl2RenderingContext::BLEND);
+        }
 
-            // update the line buffers
-            let paths = self
-                .meridians
-                .iter()
-                .map(|meridian| meridian.get_lines_vertices())
-                .chain(
-                    self.parallels
-                        .iter()
-                        .map(|parallel| parallel.get_lines_vertices()),
-                )
-                .flatten();
+        Ok(())
+    }
+}
 
-            let mut buf: Vec<f32> = vec![];
+// The width of the lines in pixels, clamped to [min, max]
+fn line_width(policy: LineWidthPolicy, thickness: f32, dpr: f32, min: f32, max: f32) -> f32 {
+    let width = match policy {
+        LineWidthPolicy::Fixed => thickness,
+        LineWidthPolicy::DevicePixelRatio => thickness * dpr,
+    };
 
-            for vertices in paths {
-                let vertices = vertices.as_ref();
-                let path_vertices_buf_iter = vertices
-                    .iter()
-                    .zip(vertices.iter().skip(1))
-                    .map(|(a, b)| [a[0], a[1], b[0], b[1]])
-                    .flatten();
+    width.clamp(min, max.max(min))
+}
+
+#[inline]
+fn is_prime_meridian(lon: f64) -> bool {
+    let lon = lon.rem_euclid(TWICE_PI);
+    lon < 1e-6 || TWICE_PI - lon < 1e-6
+}
+
+// Color of the crosses marking where the parallels are split at the zero meridian
+#[cfg(feature = "debug_seam")]
+const SEAM_COLOR: ColorRGBA = ColorRGBA {
+    r: 1.0,
+    g: 0.0,
+    b: 1.0,
+    a: 1.0,
+};
+
+// From green for no recursion to red for the max depth of recursion
+#[cfg(feature = "debug_subdivision")]
+fn depth_color(depth: usize, max_iter: usize) -> ColorRGBA {
+    let t = (depth as f32) / (max_iter.max(1) as f32);
+
+    ColorRGBA {
+        r: t,
+        g: 1.0 - t,
+        b: 0.0,
+        a: 1.0,
+    }
+}
+
+fn project_lines(
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+    fmt: &angle::SerializeFmt,
+    limb_fade: angle::Angle<f64>,
+) -> GridLines {
+    let fov = camera.get_field_of_view();
+    let bbox = fov.get_bounding_box();
+    let max_dim_px = camera.get_width().max(camera.get_height()) as f64;
+    let step_line_px = max_dim_px * 0.2;
+
+    // update meridians
+    let meridians = {
+        // Select the good step with a binary search
+        let step_lon_precised =
+            (bbox.get_lon_size() as f64) * step_line_px / (camera.get_width() as f64);
+        let step_lon = select_fixed_step(step_lon_precised);
+
+        // Add meridians
+        let start_lon = bbox.lon_min() - (bbox.lon_min() % step_lon);
+        let mut stop_lon = bbox.lon_max();
+        if bbox.all_lon() {
+            stop_lon -= 1e-3;
+        }
 
-                buf.extend(path_vertices_buf_iter);
+        let mut meridians = vec![];
+        let mut lon = start_lon;
+        while lon < stop_lon {
+            if let Some(p) = meridian::get_intersecting_meridian(lon, camera, projection, fmt, limb_fade) {
+                meridians.push(p);
             }
+            lon += step_lon;
+        }
+        meridians
+    };
 
-            self.vao.bind_for_update().update_instanced_array(
-                "ndc_pos",
-                WebGl2RenderingContext::DYNAMIC_DRAW,
-                VecData(&buf),
-            );
+    let parallels = {
+        let step_lat_precised =
+            (bbox.get_lat_size() as f64) * step_line_px / (camera.get_height() as f64);
+        let step_lat = select_fixed_step(step_lat_precised);
 
-            let num_instances = buf.len() / 4;
+        let mut start_lat = bbox.lat_min() - (bbox.lat_min() % step_lat);
+        if start_lat == -HALF_PI {
+            start_lat += step_lat;
+        }
+        let stop_lat = bbox.lat_max();
+        let mut lat = start_lat;
+
+        let mut parallels = vec![];
+        while lat < stop_lat {
+            if !parallel::parallel_intersects_view(lat, camera, projection) {
+                lat += step_lat;
+                continue;
+            }
 
-            self.gl.enable(WebGl2RenderingContext::BLEND);
-            crate::shader::get_shader(&self.gl, shaders, "line_inst_ndc.vert", "line_base.frag")?
-                .bind(&self.gl)
-                .attach_uniform("u_color", &self.color)
-                .attach_uniform("u_width", &(camera.get_width()))
-                .attach_uniform("u_height", &(camera.get_height()))
-                .attach_uniform("u_thickness", &self.thickness)
-                .bind_vertex_array_object_ref(&self.vao)
-                .draw_elements_instanced_with_i32(
-                    WebGl2RenderingContext::TRIANGLES,
-                    0,
-                    num_instances as i32,
-                );
-            self.gl.disable(WebGl2RenderingContext::BLEND);
+            if let Some(p) = parallel::get_intersecting_parallel(lat, camera, projection, limb_fade) {
+                parallels.push(p);
+            }
+            lat += step_lat;
         }
+        parallels
+    };
 
-        Ok(())
+    GridLines {
+        meridians,
+        parallels,
     }
 }
 
@@ -349,10 +567,27 @@ const GRID_STEPS: &[f64] = &[
     0.034906586,
     0.08726647,
     0.17453293,
+    0.2617994,
     0.34906585,
+    0.5235988,
     std::f64::consts::FRAC_PI_4,
 ];
 
+// Number of grid lines aimed at across the aperture
+const NUM_LINES_PER_APERTURE: f64 = 4.0;
+
+/// The longitude and latitude spacings of the grid lines for an aperture
+///
+/// The spacings are round values of degrees, arcminutes or arcseconds (e.g. 15°,
+/// 10', 2") giving about four lines across the view. They only change when the
+/// aperture crosses the midpoint of two consecutive spacings so that the grid
+/// stays the same for small zooms.
+pub fn auto_grid_step(aperture: angle::Angle<f64>) -> (angle::Angle<f64>, angle::Angle<f64>) {
+    let step = select_fixed_step(aperture.to_radians() / NUM_LINES_PER_APERTURE);
+
+    (angle::Angle(step), angle::Angle(step))
+}
+
 fn select_fixed_step(fov: f64) -> f64 {
     match GRID_STEPS.binary_search_by(|v| {
         v.partial_cmp(&fov)
@@ -377,3 +612,37 @@ fn select_fixed_step(fov: f64) -> f64 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::angle::ArcDeg;
+
+    #[test]
+    fn auto_grid_step_at_a_few_apertures() {
+        let (lon_step, lat_step) = auto_grid_step(ArcDeg(60.0).into());
+        assert!((lon_step.to_radians().to_degrees() - 15.0).abs() < 1e-5);
+        assert_eq!(lon_step, lat_step);
+
+        // Arcsecond steps for a 1' aperture
+        let (lon_step, _) = auto_grid_step(ArcDeg(1.0 / 60.0).into());
+        let arcsec = lon_step.to_radians().to_degrees() * 3600.0;
+        assert!(arcsec < 60.0);
+        assert!((arcsec - 15.0).abs() < 1e-3);
+
+        // Capped for the allsky views
+        let (lon_step, _) = auto_grid_step(ArcDeg(360.0).into());
+        assert_eq!(lon_step.to_radians(), std::f64::consts::FRAC_PI_4);
+    }
+
+    #[test]
+    fn line_width_is_clamped() {
+        assert_eq!(line_width(LineWidthPolicy::Fixed, 2.0, 3.0, 1.0, 16.0), 2.0);
+        assert_eq!(line_width(LineWidthPolicy::DevicePixelRatio, 2.0, 1.5, 1.0, 16.0), 3.0);
+
+        // Extreme device pixel ratios
+        assert_eq!(line_width(LineWidthPolicy::DevicePixelRatio, 2.0, 100.0, 1.0, 16.0), 16.0);
+        assert_eq!(line_width(LineWidthPolicy::DevicePixelRatio, 2.0, 0.01, 1.0, 16.0), 1.0);
+        assert_eq!(line_width(LineWidthPolicy::DevicePixelRatio, 2.0, 0.0, 1.0, 16.0), 1.0);
+    }
+}
@@ -3,6 +3,7 @@ use crate::math::projection::ProjectionType;
 use crate::math::sph_geom::region::Intersection;
 use crate::CameraViewPort;
 
+use crate::math::angle::Angle;
 use crate::math::lonlat::LonLat;
 use crate::math::{PI, TWICE_PI};
 
@@ -11,10 +12,44 @@ use crate::renderable::line;
 
 use core::ops::Range;
 
+// Margin taken on the radius of the visible cone. The projections distort
+// the angles towards the borders of the view
+const VIEW_CONE_MARGIN: f64 = 1.5;
+
+/// Cheap test telling whether a parallel may be seen by the camera
+///
+/// The view is bounded by the cone centered on the camera center whose
+/// radius is the half diagonal of the view. The test is conservative: a
+/// parallel passing close to the view may be kept but a visible one is never
+/// rejected, even if its lon range endpoints lie outside the view.
+pub fn parallel_intersects_view(
+    lat: f64,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> bool {
+    let aperture = camera.get_aperture().to_radians();
+    if camera.is_allsky() || aperture >= projection.aperture_start().to_radians() {
+        return true;
+    }
+
+    let r = (camera.get_height() / camera.get_width()) as f64;
+    let radius = 0.5 * aperture * (1.0 + r * r).sqrt() * VIEW_CONE_MARGIN;
+
+    let center_lat = camera.get_center().lat().to_radians();
+    parallel_intersects_cone(lat, center_lat, radius)
+}
+
+// The closest point of a parallel to the cone center is on the meridian of
+// the center so the parallel intersects the cone iff that point falls in it
+fn parallel_intersects_cone(lat: f64, center_lat: f64, radius: f64) -> bool {
+    radius >= PI || (lat - center_lat).abs() <= radius
+}
+
 pub fn get_intersecting_parallel(
     lat: f64,
     camera: &CameraViewPort,
     projection: &ProjectionType,
+    limb_fade: Angle<f64>,
 ) -> Option<Parallel> {
     let fov = camera.get_field_of_view();
     if fov.get_bounding_box().get_lon_size() > PI {
@@ -28,6 +63,7 @@ pub fn get_intersecting_parallel(
             camera,
             LabelOptions::Centered,
             projection,
+            limb_fade,
         ))
     } else {
         // Longitude fov < PI
@@ -43,6 +79,7 @@ pub fn get_intersecting_parallel(
                     camera,
                     LabelOptions::Centered,
                     projection,
+                    limb_fade,
                 ))
             }
             Intersection::Intersect { vertices } => {
@@ -65,6 +102,7 @@ pub fn get_intersecting_parallel(
                     camera,
                     LabelOptions::OnSide,
                     projection,
+                    limb_fade,
                 ))
             }
             Intersection::Empty => None,
@@ -75,9 +113,17 @@ pub fn get_intersecting_parallel(
 pub struct Parallel {
     // List of vertices
     vertices: Vec<[f32; 2]>,
+    // Alpha of the vertices, fading the parallel out near the limb
+    alphas: Vec<f32>,
     // Line vertices indices
     indices: Vec<Range<usize>>,
     label: Option<Label>,
+    // Depth of recursion of the subdivision each vertex has been emitted at
+    #[cfg(feature = "debug_subdivision")]
+    depths: Vec<usize>,
+    // Points where the parallel has been split at the zero meridian
+    #[cfg(feature = "debug_seam")]
+    seams: Vec<crate::coo_space::XYNDC<f64>>,
 }
 
 use super::label::LabelOptions;
@@ -89,24 +135,46 @@ impl Parallel {
         camera: &CameraViewPort,
         label_options: LabelOptions,
         projection: &ProjectionType,
+        limb_fade: Angle<f64>,
     ) -> Self {
         let label = Label::from_parallel(lat, lon, label_options, camera, projection);
 
+        #[cfg(not(feature = "debug_subdivision"))]
+        let project_arc = |lon1: f64, lon2: f64| {
+            line::parallel_arc::project_faded(lat, lon1, lon2, limb_fade, camera, projection)
+        };
+        #[cfg(feature = "debug_subdivision")]
+        let project_arc = |lon1: f64, lon2: f64| {
+            line::parallel_arc::project_with_depth(lat, lon1, lon2, camera, projection, &Default::default())
+        };
+
         // Draw the full parallel
         let vertices = if lon.end - lon.start > PI {
-            let mut vertices =
-                line::parallel_arc::project(lat, lon.start, lon.start + PI, camera, projection);
-            vertices.append(&mut line::parallel_arc::project(
-                lat,
-                lon.start + PI,
-                lon.end,
-                camera,
-                projection,
-            ));
+            let mut vertices = project_arc(lon.start, lon.start + PI);
+            vertices.append(&mut project_arc(lon.start + PI, lon.end));
 
             vertices
         } else {
-            line::parallel_arc::project(lat, lon.start, lon.end, camera, projection)
+            project_arc(lon.start, lon.end)
+        };
+        #[cfg(not(feature = "debug_subdivision"))]
+        let (vertices, alphas): (Vec<_>, Vec<_>) = vertices.into_iter().unzip();
+        #[cfg(feature = "debug_subdivision")]
+        let (vertices, depths): (Vec<_>, Vec<_>) = vertices.into_iter().unzip();
+        // The depths are debugged on opaque lines, not faded out near the limb
+        #[cfg(feature = "debug_subdivision")]
+        let alphas = {
+            let _ = limb_fade;
+            vec![1.0; vertices.len()]
+        };
+        #[cfg(feature = "debug_seam")]
+        let seams = if lon.end - lon.start > PI {
+            let mut seams = line::parallel_arc::project_seams(lat, lon.start, lon.start + PI, camera, projection);
+            seams.append(&mut line::parallel_arc::project_seams(lat, lon.start + PI, lon.end, camera, projection));
+
+            seams
+        } else {
+            line::parallel_arc::project_seams(lat, lon.start, lon.end, camera, projection)
         };
 
         /*let mut prev_v = [vertices[0].x as f32, vertices[0].y as f32];
@@ -155,11 +223,32 @@ impl Parallel {
 
         Self {
             vertices,
+            alphas,
             indices,
             label,
+            #[cfg(feature = "debug_subdivision")]
+            depths,
+            #[cfg(feature = "debug_seam")]
+            seams,
         }
     }
 
+    /// The points where the parallel has been split at the zero meridian
+    #[cfg(feature = "debug_seam")]
+    pub fn get_seams(&self) -> &[crate::coo_space::XYNDC<f64>] {
+        &self.seams
+    }
+
+    /// The segments of the parallel with the depth of recursion of
+    /// the subdivision they have been emitted at
+    #[cfg(feature = "debug_subdivision")]
+    pub fn get_segments_depth(&self) -> impl Iterator<Item = ([f32; 4], usize)> + '_ {
+        self.vertices
+            .chunks_exact(2)
+            .zip(self.depths.chunks_exact(2))
+            .map(|(s, d)| ([s[0][0], s[0][1], s[1][0], s[1][1]], d[0]))
+    }
+
     #[inline]
     pub fn get_lines_vertices(&self) -> Vec<&[[f32; 2]]> {
         self.indices
@@ -168,8 +257,38 @@ impl Parallel {
             .collect()
     }
 
+    /// The alpha of the vertices of each line given by ``get_lines_vertices``
+    #[inline]
+    pub fn get_lines_alphas(&self) -> Vec<&[f32]> {
+        self.indices
+            .iter()
+            .map(|range| &self.alphas[range.start..range.end])
+            .collect()
+    }
+
     #[inline]
     pub fn get_label(&self) -> Option<&Label> {
         self.label.as_ref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::parallel_intersects_cone;
+
+    #[test]
+    fn polar_parallels_culled_in_a_small_field() {
+        // A 1 degree field of a 16/9 screen near the equator
+        let r: f64 = 9.0 / 16.0;
+        let radius = 0.5 * 1.0_f64.to_radians() * (1.0 + r * r).sqrt() * super::VIEW_CONE_MARGIN;
+        let center_lat = 0.2_f64.to_radians();
+
+        for lat in [-80.0_f64, -60.0, 30.0, 60.0, 80.0] {
+            assert!(!parallel_intersects_cone(lat.to_radians(), center_lat, radius));
+        }
+        // The parallels crossing the view are kept
+        for lat in [0.0_f64, 0.5] {
+            assert!(parallel_intersects_cone(lat.to_radians(), center_lat, radius));
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use crate::healpix::cell::HEALPixCell;
+use crate::time::DeltaTime;
+
+pub const DEFAULT_FADE_DURATION: DeltaTime = DeltaTime::from_millis(200.0);
+
+/// The fading in of the tiles once they have been received
+///
+/// A tile is blended over the lower resolution tile drawn before it, its alpha
+/// ramping from 0 to 1. The time elapsed is advanced frame by frame and kept
+/// per tile so that the fade goes on while the camera moves.
+pub struct TileFades {
+    duration: DeltaTime,
+    // The time elapsed since the tiles still fading have been received, in ms
+    elapsed: HashMap<HEALPixCell, f32>,
+}
+
+impl TileFades {
+    pub fn new() -> Self {
+        Self {
+            duration: DEFAULT_FADE_DURATION,
+            elapsed: HashMap::new(),
+        }
+    }
+
+    /// Set the duration of the fade, 0 to show the tiles at once
+    pub fn set_duration(&mut self, duration: DeltaTime) {
+        self.duration = DeltaTime(duration.0.max(0.0));
+    }
+
+    pub fn get_duration(&self) -> DeltaTime {
+        self.duration
+    }
+
+    /// Start fading in a tile that has just been received
+    pub fn start(&mut self, cell: HEALPixCell) {
+        self.elapsed.insert(cell, 0.0);
+    }
+
+    /// Move the fades forward by `dt`
+    ///
+    /// Returns whether the alpha of some tiles has changed
+    pub fn update(&mut self, dt: DeltaTime) -> bool {
+        if self.elapsed.is_empty() {
+            return false;
+        }
+
+        let duration = self.duration.as_millis();
+        for elapsed in self.elapsed.values_mut() {
+            *elapsed += dt.as_millis();
+        }
+        self.elapsed.retain(|_, elapsed| *elapsed < duration);
+
+        true
+    }
+
+    pub fn is_fading(&self) -> bool {
+        !self.elapsed.is_empty()
+    }
+
+    /// The alpha of a tile, 1 once its fade is over
+    pub fn alpha(&self, cell: &HEALPixCell) -> f32 {
+        self.elapsed
+            .get(cell)
+            .map(|&elapsed| fade_alpha(elapsed, self.duration.as_millis()))
+            .unwrap_or(1.0)
+    }
+}
+
+/// The color output by the rasterizer fragment shaders for a tile
+///
+/// The tile is mixed with the one it replaces by its fade alpha, then the result
+/// is dimmed by the opacity of the layer, i.e. the ``opacity`` uniform, before
+/// being blended with the layers below following the blend mode of the layer.
+/// Keep it in sync with ``hips/rasterizer/*.frag``.
+///
+/// # Arguments
+///
+/// * ``start`` - The RGBA color of the tile replaced
+/// * ``end`` - The RGBA color of the tile fading in
+/// * ``tile_alpha`` - The alpha of the fade, see ``TileFades::alpha``
+/// * ``opacity`` - The opacity of the layer
+pub fn fragment_color(start: [f32; 4], end: [f32; 4], tile_alpha: f32, opacity: f32) -> [f32; 4] {
+    let mut color = [0.0; 4];
+    for (i, c) in color.iter_mut().enumerate() {
+        *c = start[i] + (end[i] - start[i]) * tile_alpha;
+    }
+    color[3] *= opacity;
+
+    color
+}
+
+fn fade_alpha(elapsed: f32, duration: f32) -> f32 {
+    if duration > 0.0 {
+        (elapsed / duration).clamp(0.0, 1.0)
+    } else {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_faded_in_halfway_through() {
+        let mut fades = TileFades::new();
+        fades.set_duration(DeltaTime::from_millis(400.0));
+
+        let cell = HEALPixCell(3, 42);
+        fades.start(cell);
+        assert_eq!(fades.alpha(&cell), 0.0);
+
+        // Frames of 16ms
+        for _ in 0..12 {
+            assert!(fades.update(DeltaTime::from_millis(16.0)));
+        }
+        fades.update(DeltaTime::from_millis(8.0));
+        assert!((fades.alpha(&cell) - 0.5).abs() < 1e-3);
+        // The other tiles are not fading
+        assert_eq!(fades.alpha(&HEALPixCell(3, 43)), 1.0);
+
+        fades.update(DeltaTime::from_millis(200.0));
+        assert_eq!(fades.alpha(&cell), 1.0);
+        assert!(!fades.is_fading());
+        assert!(!fades.update(DeltaTime::from_millis(16.0)));
+    }
+
+    #[test]
+    fn half_opaque_layer_over_a_background() {
+        use al_api::blend::{BlendCfg, BlendMode};
+
+        let background = [0.0, 0.0, 1.0, 1.0];
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let transparent = [0.0, 0.0, 0.0, 0.0];
+        let over = BlendCfg::from(BlendMode::Over);
+
+        // A tile faded in, of a layer at half opacity
+        let layer = fragment_color(transparent, red, 1.0, 0.5);
+        assert_eq!(layer, [1.0, 0.0, 0.0, 0.5]);
+        assert_eq!(over.blend(layer, background), [0.5, 0.0, 0.5]);
+
+        // The same tile halfway through its fade
+        let mut fades = TileFades::new();
+        fades.set_duration(DeltaTime::from_millis(400.0));
+        let cell = HEALPixCell(3, 42);
+        fades.start(cell);
+        fades.update(DeltaTime::from_millis(200.0));
+
+        let layer = fragment_color(transparent, red, fades.alpha(&cell), 0.5);
+        assert_eq!(layer[3], 0.25);
+        // Mixed with the transparent tile replaced, the color darkens too
+        let [r, g, b] = over.blend(layer, background);
+        assert!((r - 0.125).abs() < 1e-6 && g == 0.0 && (b - 0.75).abs() < 1e-6);
+        assert_eq!(BlendCfg::from(BlendMode::Add).blend(fragment_color(red, red, 0.0, 0.5), background), [0.5, 0.0, 1.0]);
+    }
+}
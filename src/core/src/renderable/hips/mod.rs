@@ -1,3 +1,4 @@
+pub mod fade;
 pub mod raytracing;
 mod triangulation;
 pub mod uv;
@@ -29,7 +30,9 @@ use crate::downloader::request::allsky::Allsky;
 use crate::healpix::{cell::HEALPixCell, coverage::HEALPixCoverage};
 use crate::math::lonlat::LonLat;
 use crate::renderable::utils::index_patch::DefaultPatchIndexIter;
-use crate::time::Time;
+use crate::time::{DeltaTime, Time};
+
+use fade::TileFades;
 
 use std::collections::HashSet;
 
@@ -375,8 +378,8 @@ pub struct HiPS {
     // layout (location = 2) in vec3 uv_end;
     uv_end: Vec<f32>,
     //#[cfg(feature = "webgl1")]
-    // layout (location = 3) in float time_tile_received;
-    time_tile_received: Vec<f32>,
+    // layout (location = 3) in float tile_alpha;
+    tile_alpha: Vec<f32>,
     //#[cfg(feature = "webgl1")]
     // layout (location = 4) in float m0;
     m0: Vec<f32>,
@@ -396,6 +399,11 @@ pub struct HiPS {
 
     // A buffer storing the cells in the view
     hpx_cells_in_view: Vec<HEALPixCell>,
+
+    // The tiles fading in since they have been received
+    fades: TileFades,
+    // Whether the alpha of the tiles fading in has changed since the vertices have been computed
+    fades_changed: bool,
 }
 
 impl HiPS {
@@ -406,7 +414,7 @@ impl HiPS {
         // layout (location = 1) in vec3 position;
         // layout (location = 2) in vec3 uv_start;
         // layout (location = 3) in vec3 uv_end;
-        // layout (location = 4) in float time_tile_received;
+        // layout (location = 4) in float tile_alpha;
         // layout (location = 5) in float m0;
         // layout (location = 6) in float m1;
         //let vertices = vec![0.0; MAX_NUM_FLOATS_TO_DRAW];
@@ -416,7 +424,7 @@ impl HiPS {
         let position = vec![];
         let uv_start = vec![];
         let uv_end = vec![];
-        let time_tile_received = vec![];
+        let tile_alpha = vec![];
         let m0 = vec![];
         let m1 = vec![];
         let idx_vertices = vec![];
@@ -443,9 +451,9 @@ impl HiPS {
             )
             .add_array_buffer_single(
                 1,
-                "time_tile_received",
+                "tile_alpha",
                 WebGl2RenderingContext::DYNAMIC_DRAW,
-                VecData::<f32>(&time_tile_received),
+                VecData::<f32>(&tile_alpha),
             )
             .add_array_buffer_single(
                 1,
@@ -487,9 +495,9 @@ impl HiPS {
             )
             .add_array_buffer(
                 1,
-                "time_tile_received",
+                "tile_alpha",
                 WebGl2RenderingContext::DYNAMIC_DRAW,
-                VecData::<f32>(&time_tile_received),
+                VecData::<f32>(&tile_alpha),
             )
             .add_array_buffer(
                 1,
@@ -529,7 +537,7 @@ impl HiPS {
             position,
             uv_start,
             uv_end,
-            time_tile_received,
+            tile_alpha,
             m0,
             m1,
 
@@ -537,6 +545,9 @@ impl HiPS {
 
             footprint_moc,
             hpx_cells_in_view,
+
+            fades: TileFades::new(),
+            fades_changed: false,
         })
     }
 
@@ -633,11 +644,26 @@ impl HiPS {
         let available_tiles = self.textures.reset_available_tiles();
         let new_cells_in_view = self.retrieve_cells_in_camera(camera);
 
-        if new_cells_in_view || available_tiles {
+        if new_cells_in_view || available_tiles || self.fades_changed {
             self.recompute_vertices(camera, projection);
         }
     }
 
+    /// Move the fading in of the tiles forward by `dt`
+    ///
+    /// Returns whether some tiles are fading in, i.e. the view needs to be redrawn
+    pub fn update_fades(&mut self, dt: DeltaTime) -> bool {
+        let changed = self.fades.update(dt);
+        self.fades_changed |= changed;
+
+        changed
+    }
+
+    /// Set the duration the tiles are faded in for once received
+    pub fn set_fade_duration(&mut self, duration: DeltaTime) {
+        self.fades.set_duration(duration);
+    }
+
     // returns a boolean if the view cells has changed with respect to the last frame
     pub fn retrieve_cells_in_camera(&mut self, camera: &CameraViewPort) -> bool {
         let cfg = self.textures.config();
@@ -735,7 +761,8 @@ impl HiPS {
         self.position.clear();
         self.uv_start.clear();
         self.uv_end.clear();
-        self.time_tile_received.clear();
+        self.tile_alpha.clear();
+        self.fades_changed = false;
         self.m0.clear();
         self.m1.clear();
         self.idx_vertices.clear();
@@ -840,9 +867,12 @@ impl HiPS {
                 {
                     let uv_0 = TileUVW::new(cell, starting_texture, cfg);
                     let uv_1 = TileUVW::new(cell, ending_texture, cfg);
-                    let start_time = ending_texture.start_time().as_millis();
+                    // The tile received last is blended over the one of lower resolution
+                    let alpha = self.fades.alpha(ending_texture.cell());
 
-                    let miss_0 = (starting_texture.is_missing()) as i32 as f32;
+                    // Nothing is drawn under a tile without any lower resolution one
+                    let no_fallback = std::ptr::eq(starting_texture, ending_texture);
+                    let miss_0 = (starting_texture.is_missing() || no_fallback) as i32 as f32;
                     let miss_1 = (ending_texture.is_missing()) as i32 as f32;
 
                     let num_subdivision = num_subdivision(cell, camera, projection);
@@ -894,7 +924,7 @@ impl HiPS {
                         self.uv_end.extend(uv_end);
                         self.m0.push(miss_0);
                         self.m1.push(miss_1);
-                        self.time_tile_received.push(start_time);
+                        self.tile_alpha.push(alpha);
 
                         pos.push([lon as f32, lat as f32]);
                     }
@@ -946,9 +976,9 @@ impl HiPS {
             VecData(&self.uv_end),
         )
         .update_array(
-            "time_tile_received",
+            "tile_alpha",
             WebGl2RenderingContext::DYNAMIC_DRAW,
-            VecData(&self.time_tile_received),
+            VecData(&self.tile_alpha),
         )
         .update_array(
             "m0",
@@ -1001,7 +1031,12 @@ impl HiPS {
         image: Option<I>,
         time_request: Time,
     ) -> Result<(), JsValue> {
-        self.textures.push(&cell, image, time_request)
+        self.textures.push(&cell, image, time_request)?;
+        // The tiles are drawn by texture, the texture containing the tile fades in again
+        let tex_cell = cell.get_texture_cell(self.get_config().delta_depth());
+        self.fades.start(tex_cell);
+
+        Ok(())
     }
 
     pub fn add_allsky(&mut self, allsky: Allsky) -> Result<(), JsValue> {
@@ -1062,15 +1097,15 @@ impl HiPS {
         let ImageMetadata {
             color,
             opacity,
-            blend_cfg,
             ..
         } = cfg;
+        let blend_cfg = cfg.blend();
 
         // Add starting fading
         //let fading = self.get_fading_factor();
         //let opacity = opacity * fading;
         // Get the colormap from the color
-        let cmap = colormaps.get(color.cmap_name.as_ref());
+        let cmap = colormaps.get(color.colormap().0);
 
         blend_cfg.enable(&self.gl, || {
             if raytracing {
@@ -1082,9 +1117,7 @@ impl HiPS {
                 shader
                     .attach_uniforms_from(camera)
                     .attach_uniforms_from(&self.textures)
-                    // send the cmap appart from the color config
-                    .attach_uniforms_with_params_from(cmap, colormaps)
-                    .attach_uniforms_from(color)
+                    .attach_uniforms_with_params_from(color, colormaps)
                     .attach_uniform("model", &w2v)
                     .attach_uniform("current_time", &utils::get_current_time())
                     .attach_uniform("opacity", opacity)
@@ -1097,7 +1130,7 @@ impl HiPS {
                 // The rasterizer has a buffer containing:
                 // - The vertices of the HEALPix cells for the most refined survey
                 // - The starting and ending uv for the blending animation
-                // - The time for each HEALPix cell at which the animation begins
+                // - The alpha of each HEALPix cell fading in
                 //
                 // Each of these data can be changed at different circumstances:
                 // - The vertices are changed if:
@@ -1110,12 +1143,9 @@ impl HiPS {
 
                 shader
                     .attach_uniforms_from(&self.textures)
-                    // send the cmap appart from the color config
-                    .attach_uniforms_with_params_from(cmap, colormaps)
-                    .attach_uniforms_from(color)
+                    .attach_uniforms_with_params_from(color, colormaps)
                     .attach_uniforms_from(camera)
                     .attach_uniform("inv_model", &v2w)
-                    .attach_uniform("current_time", &utils::get_current_time())
                     .attach_uniform("opacity", opacity)
                     .attach_uniform("u_proj", proj)
                     .attach_uniforms_from(colormaps)
@@ -653,9 +653,9 @@ impl Image {
         let ImageMetadata {
             color,
             opacity,
-            blend_cfg,
             ..
         } = cfg;
+        let blend_cfg = cfg.blend();
 
         let shader = match self.channel {
             ChannelType::RGBA8U => crate::shader::get_shader(
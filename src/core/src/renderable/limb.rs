@@ -0,0 +1,116 @@
+use crate::shader::ShaderManager;
+use crate::CameraViewPort;
+use crate::ProjectionType;
+
+use al_api::color::ColorRGBA;
+use al_core::{VecData, VertexArrayObject, WebGlContext};
+use wasm_bindgen::JsValue;
+use web_sys::WebGl2RenderingContext;
+
+use crate::coo_space::XYClip;
+use crate::renderable::line::{self, LineStyle};
+
+const DEFAULT_COLOR: ColorRGBA = ColorRGBA {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 0.5,
+};
+const DEFAULT_THICKNESS: f32 = 1.5;
+
+/// The boundary of the projection definition domain, e.g. the ellipse of an
+/// all-sky Aitoff view
+///
+/// It is drawn antialiased on top of the layers so that their edge, given by
+/// the tiles and the pixels of the screen, does not look jagged. The boundary
+/// is computed once per projection.
+pub struct Limb {
+    enabled: bool,
+    // The boundary, in the clip space, along with the name of the projection
+    // it has been computed for. None if the projection is not bounded
+    boundary: Option<(&'static str, Option<Vec<XYClip<f64>>>)>,
+
+    style: LineStyle,
+
+    vao: VertexArrayObject,
+    gl: WebGlContext,
+}
+
+impl Limb {
+    pub fn new(gl: &WebGlContext) -> Self {
+        Self {
+            enabled: true,
+            boundary: None,
+            style: LineStyle::new(&DEFAULT_COLOR, DEFAULT_THICKNESS),
+            vao: line::segments_vao(gl, 2),
+            gl: gl.clone(),
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn set_color(&mut self, color: &ColorRGBA) {
+        self.style = LineStyle::new(color, self.style.width);
+    }
+
+    /// The boundary of the projection domain in the clip space, e.g. to mask
+    /// what lies outside. See ``ProjectionType::limb``
+    pub fn get_boundary(&mut self, projection: &ProjectionType) -> Option<&[XYClip<f64>]> {
+        let name = projection.name();
+        if !matches!(&self.boundary, Some((n, _)) if *n == name) {
+            self.boundary = Some((name, projection.limb()));
+        }
+
+        self.boundary.as_ref().and_then(|(_, boundary)| boundary.as_deref())
+    }
+
+    pub fn draw(
+        &mut self,
+        camera: &CameraViewPort,
+        projection: &ProjectionType,
+        shaders: &mut ShaderManager,
+    ) -> Result<(), JsValue> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let boundary = match self.get_boundary(projection) {
+            Some(boundary) => boundary,
+            None => return Ok(()),
+        };
+
+        let buf = boundary
+            .windows(2)
+            .flat_map(|w| {
+                let a = crate::math::projection::clip_to_ndc_space(&w[0], camera);
+                let b = crate::math::projection::clip_to_ndc_space(&w[1], camera);
+
+                [a.x as f32, a.y as f32, b.x as f32, b.y as f32]
+            })
+            .collect::<Vec<f32>>();
+
+        self.vao.bind_for_update().update_instanced_array(
+            "ndc_pos",
+            WebGl2RenderingContext::DYNAMIC_DRAW,
+            VecData(&buf),
+        );
+
+        self.gl.enable(WebGl2RenderingContext::BLEND);
+        crate::shader::get_shader(&self.gl, shaders, "line_inst_ndc_aa.vert", "line_aa.frag")?
+            .bind(&self.gl)
+            .attach_uniforms_from(&self.style)
+            .attach_uniform("u_width", &(camera.get_width()))
+            .attach_uniform("u_height", &(camera.get_height()))
+            .bind_vertex_array_object_ref(&self.vao)
+            .draw_elements_instanced_with_i32(
+                WebGl2RenderingContext::TRIANGLES,
+                0,
+                (buf.len() / 4) as i32,
+            );
+        self.gl.disable(WebGl2RenderingContext::BLEND);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use crate::CameraViewPort;
+use crate::ProjectionType;
+
+use cgmath::Matrix4;
+
+use crate::coo_space::XYNDC;
+use crate::coosys::Observer;
+use crate::math::lonlat::LonLat;
+use crate::math::PI;
+use al_api::coo_system::CooSystem;
+
+use crate::LonLatT;
+
+use super::parallel_arc::{self, SubdivisionParams};
+
+// Lines of the horizontal frame of an observer
+//
+// The parallels and meridians of the alt-az frame are the lines of constant altitude
+// and azimuth. They are subdivided like the ones of the view frame, their positions
+// being rotated to the view frame before being projected.
+
+// Project the line of constant altitude between two azimuths
+//
+// * Remark
+// The azimuths go from az1 towards the east to az2 as for the parallels
+//
+// * Returns
+// A list of lines vertices
+pub fn project_altitude(
+    alt: f64,
+    az1: f64,
+    az2: f64,
+    observer: &Observer,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Vec<[f32; 2]> {
+    let altaz_to_view = altaz_to_view(observer, camera);
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+    let px_size = parallel_arc::ndc_pixel_size(camera);
+
+    let mut vertices = vec![];
+    let az_len = crate::math::sph_geom::distance_from_two_lon(az1, az2);
+    // The parallels are projected by halves
+    let bounds = if az_len > PI {
+        let az_mid = (az1 + 0.5 * az_len).rem_euclid(crate::math::TWICE_PI);
+        vec![(az1, az_mid), (az_mid, az2)]
+    } else {
+        vec![(az1, az2)]
+    };
+
+    for (az1, az2) in bounds {
+        parallel_arc::project_with(
+            &mut vertices,
+            alt,
+            az1,
+            az2,
+            d_alpha,
+            px_size,
+            &SubdivisionParams::default(),
+            &|lonlat| project_altaz(lonlat, &altaz_to_view, camera, projection),
+        );
+    }
+
+    vertices
+}
+
+// Project the line of constant azimuth between two altitudes
+//
+// * Returns
+// A list of lines vertices
+pub fn project_azimuth(
+    az: f64,
+    alt1: f64,
+    alt2: f64,
+    observer: &Observer,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Vec<XYNDC<f64>> {
+    let altaz_to_view = altaz_to_view(observer, camera);
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    super::meridian::project_with(az, alt1, alt2, d_alpha, &SubdivisionParams::default(), &|lonlat| {
+        project_altaz(lonlat, &altaz_to_view, camera, projection)
+    })
+}
+
+// Project the horizon, i.e. the line of null altitude. It is meant to be
+// drawn highlighted over the other lines of the alt-az grid
+//
+// * Returns
+// A list of lines vertices
+pub fn project_horizon(
+    observer: &Observer,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Vec<[f32; 2]> {
+    let mut vertices = project_altitude(0.0, 0.0, PI, observer, camera, projection);
+    vertices.append(&mut project_altitude(0.0, PI, 0.0, observer, camera, projection));
+
+    vertices
+}
+
+fn altaz_to_view(observer: &Observer, camera: &CameraViewPort) -> Matrix4<f64> {
+    CooSystem::ICRS.to(camera.get_coo_system()) * observer.altaz_to_icrs()
+}
+
+fn project_altaz(
+    lonlat: &LonLatT<f64>,
+    altaz_to_view: &Matrix4<f64>,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Option<XYNDC<f64>> {
+    let view_pos = altaz_to_view * lonlat.vector::<cgmath::Vector4<f64>>();
+    crate::math::lonlat::proj(&view_pos.lonlat(), projection, camera)
+}
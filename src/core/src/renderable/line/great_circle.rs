@@ -0,0 +1,277 @@
+use crate::CameraViewPort;
+use crate::ProjectionType;
+use cgmath::Vector3;
+
+use cgmath::InnerSpace;
+
+use crate::coo_space::XYNDC;
+use crate::coo_space::XYZModel;
+
+use crate::LonLatT;
+const MAX_ITERATION: usize = 5;
+
+// A great circle arc starting from `v1` of length `omega` radians. Its points
+// are given by spherical linear interpolation:
+//   v(t) = cos(t*omega)*v1 + sin(t*omega)*u with t in [0; 1]
+// where u is the unit vector orthogonal to v1 directed towards the end of the arc
+pub(crate) struct Arc {
+    v1: XYZModel<f64>,
+    u: XYZModel<f64>,
+    pub(crate) omega: f64,
+}
+
+impl Arc {
+    pub(crate) fn new(v1: XYZModel<f64>, v2: XYZModel<f64>) -> Self {
+        let omega = crate::math::vector::angle3(&v1, &v2).to_radians();
+
+        let u = v2 - v1 * omega.cos();
+        let u = if u.magnitude2() > 1e-20 {
+            u.normalize()
+        } else {
+            // v1 and v2 are antipodal, there is an infinity of great circles
+            // passing through them. We choose the one passing through the
+            // north pole (+Y in the model space). If v1 is itself a pole, it
+            // goes through the (lon=0, lat=0) point
+            let north = Vector3::new(0.0, 1.0, 0.0);
+            let u = north - v1 * v1.dot(north);
+            if u.magnitude2() > 1e-20 {
+                u.normalize()
+            } else {
+                Vector3::new(0.0, 0.0, 1.0)
+            }
+        };
+
+        Self { v1, u, omega }
+    }
+
+    pub(crate) fn at(&self, t: f64) -> XYZModel<f64> {
+        let theta = t * self.omega;
+        self.v1 * theta.cos() + self.u * theta.sin()
+    }
+}
+
+// Project the shortest path between two sky positions
+//
+// * Remark
+// The arc may exit and re-enter the projection domain. Each of its pieces
+// is clipped at the boundary of the projection
+//
+// * Returns
+// A list of lines vertices
+pub fn project(
+    p1: LonLatT<f64>,
+    p2: LonLatT<f64>,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Vec<XYNDC<f64>> {
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_with(p1.vector(), p2.vector(), d_alpha, &|v| {
+        projection.model_to_normalized_device_space(&v.extend(1.0), camera)
+    })
+}
+
+// The arc between `v1` and `v2` projected with `proj`, `d_alpha` being the
+// precision of the dichotomy at the boundary of the projection
+fn project_with<P>(v1: XYZModel<f64>, v2: XYZModel<f64>, d_alpha: f64, proj: &P) -> Vec<XYNDC<f64>>
+where
+    P: Fn(&XYZModel<f64>) -> Option<XYNDC<f64>>,
+{
+    let arc = Arc::new(v1, v2);
+    if arc.omega == 0.0 {
+        return vec![];
+    }
+    // the dichotomy precision along the arc
+    let dt = d_alpha / arc.omega;
+
+    project_curve(&|t| arc.at(t), dt, proj)
+}
+
+// Project a curve of the sphere parametrized by t in [0; 1]
+//
+// * Remark
+// The curve is first split into several pieces that are subdivided
+// independently, each of them being clipped at the boundary of the projection.
+//
+// * Returns
+// A list of lines vertices
+pub(super) fn project_curve<C, P>(curve: &C, dt: f64, proj: &P) -> Vec<XYNDC<f64>>
+where
+    C: Fn(f64) -> XYZModel<f64>,
+    P: Fn(&XYZModel<f64>) -> Option<XYNDC<f64>>,
+{
+    let mut vertices = vec![];
+
+    let num_vertices = 5;
+    let step = 1.0 / (num_vertices as f64);
+    for i in 0..num_vertices {
+        let t1 = (i as f64) * step;
+        let t2 = t1 + step;
+
+        let p1 = proj(&curve(t1));
+        let p2 = proj(&curve(t2));
+
+        match (p1, p2) {
+            (Some(_), Some(_)) => {
+                subdivide(&mut vertices, curve, t1, t2, proj, 0);
+            }
+            (None, Some(_)) => {
+                let (t1, t2) = sub_valid_domain(curve, t2, t1, dt, proj);
+                subdivide(&mut vertices, curve, t1, t2, proj, 0);
+            }
+            (Some(_), None) => {
+                let (t1, t2) = sub_valid_domain(curve, t1, t2, dt, proj);
+                subdivide(&mut vertices, curve, t1, t2, proj, 0);
+            }
+            (None, None) => {}
+        }
+    }
+
+    vertices
+}
+
+// Precondition:
+// * the point at valid_t can be projected whereas the one at invalid_t cannot
+fn sub_valid_domain<C, P>(curve: &C, valid_t: f64, invalid_t: f64, dt: f64, proj: &P) -> (f64, f64)
+where
+    C: Fn(f64) -> XYZModel<f64>,
+    P: Fn(&XYZModel<f64>) -> Option<XYNDC<f64>>,
+{
+    let mut t_valid = valid_t;
+    let mut t_invalid = invalid_t;
+    while (t_valid - t_invalid).abs() > dt {
+        let tm = (t_valid + t_invalid) * 0.5;
+        // check whether is it defined or not
+        if proj(&curve(tm)).is_some() {
+            t_valid = tm;
+        } else {
+            t_invalid = tm;
+        }
+    }
+
+    if valid_t > invalid_t {
+        (t_valid, valid_t)
+    } else {
+        (valid_t, t_valid)
+    }
+}
+
+fn subdivide<C, P>(
+    vertices: &mut Vec<XYNDC<f64>>,
+    curve: &C,
+    t1: f64,
+    t2: f64,
+    proj: &P,
+    iter: usize,
+) -> bool
+where
+    C: Fn(f64) -> XYZModel<f64>,
+    P: Fn(&XYZModel<f64>) -> Option<XYNDC<f64>>,
+{
+    let p1 = proj(&curve(t1));
+    let p2 = proj(&curve(t2));
+
+    if iter < MAX_ITERATION {
+        // Project them. We are always facing the camera
+        let tm = (t1 + t2) * 0.5;
+        let pm = proj(&curve(tm));
+
+        match (p1, pm, p2) {
+            (Some(p1), Some(pm), Some(p2)) => {
+                let ab = pm - p1;
+                let bc = p2 - pm;
+
+                let ab_u = ab.normalize();
+                let bc_u = bc.normalize();
+
+                let dot_abbc = crate::math::vector::dot(&ab_u, &bc_u);
+                let theta_abbc = dot_abbc.acos();
+
+                if theta_abbc.abs() < 5.0_f64.to_radians() {
+                    let det_abbc = crate::math::vector::det(&ab_u, &bc_u);
+
+                    if det_abbc.abs() < 1e-2 {
+                        vertices.push(p1);
+                        vertices.push(p2);
+                    } else {
+                        // not colinear but enough to stop
+                        vertices.push(p1);
+                        vertices.push(pm);
+
+                        vertices.push(pm);
+                        vertices.push(p2);
+                    }
+                } else {
+                    let ab_l = ab.magnitude2();
+                    let bc_l = bc.magnitude2();
+
+                    let r = (ab_l - bc_l).abs() / (ab_l + bc_l);
+
+                    if r > 0.8 {
+                        if ab_l < bc_l {
+                            vertices.push(p1);
+                            vertices.push(pm);
+                        } else {
+                            vertices.push(pm);
+                            vertices.push(p2);
+                        }
+                    } else {
+                        // Subdivide a->b and b->c
+                        if !subdivide(vertices, curve, t1, tm, proj, iter + 1) {
+                            vertices.push(p1);
+                            vertices.push(pm);
+                        }
+
+                        if !subdivide(vertices, curve, tm, t2, proj, iter + 1) {
+                            vertices.push(pm);
+                            vertices.push(p2);
+                        }
+                    }
+                }
+                true
+            }
+            _ => false,
+        }
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::angle::ToAngle;
+    use crate::math::projection::clip_space_proj_xyz;
+
+    #[test]
+    fn geodesic_vertex_chain_is_monotonic() {
+        let projection = ProjectionType::Mol(mapproj::pseudocyl::mol::Mol::new());
+        let proj = clip_space_proj_xyz(&projection);
+
+        let v1: XYZModel<f64> = LonLatT::new(0.0_f64.to_angle(), 0.0_f64.to_angle()).vector();
+        let v2: XYZModel<f64> =
+            LonLatT::new(90.0_f64.to_radians().to_angle(), 0.0_f64.to_angle()).vector();
+
+        let vertices = project_with(v1, v2, 1e-3, &proj);
+        assert!(!vertices.is_empty());
+
+        // The path follows the equator
+        let dx = vertices[vertices.len() - 1].x - vertices[0].x;
+        assert!(vertices.iter().all(|v| (v.y - vertices[0].y).abs() < 1e-6));
+        assert!(vertices
+            .windows(2)
+            .all(|w| (w[1].x - w[0].x) * dx >= 0.0));
+    }
+
+    #[test]
+    fn antipodal_points_go_through_the_north_pole() {
+        let v1: XYZModel<f64> = LonLatT::new(0.0_f64.to_angle(), 0.0_f64.to_angle()).vector();
+        let v2 = -v1;
+
+        let arc = Arc::new(v1, v2);
+        let vm = arc.at(0.5);
+
+        assert!((vm.y - 1.0).abs() < 1e-9);
+        assert!((arc.at(1.0) - v2).magnitude() < 1e-9);
+    }
+}
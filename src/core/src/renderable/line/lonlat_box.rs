@@ -0,0 +1,136 @@
+use crate::CameraViewPort;
+use crate::ProjectionType;
+
+use cgmath::InnerSpace;
+
+use crate::coo_space::XYNDC;
+use crate::math::{PI, TWICE_PI};
+use crate::LonLatT;
+
+use super::meridian;
+use super::parallel_arc::{self, SubdivisionParams};
+
+// * Remark
+//
+// - The box goes from lon1 to lon2 eastward, i.e. it crosses RA=0 when lon1 > lon2
+// - Latitudes between [-0.5*pi; 0.5*pi], lat1 < lat2
+// - The box is walked counter-clockwise on the sky: the lat1 parallel eastward,
+//   the lon2 meridian northward, the lat2 parallel westward and the lon1 meridian
+//   southward. The parts that cannot be projected are skipped, the polyline
+//   joining the two sides of them.
+//
+// * Returns
+// The vertices of a closed polyline, the last vertex being the first one
+pub fn project_lonlat_box(
+    lon1: f64,
+    lon2: f64,
+    lat1: f64,
+    lat2: f64,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Vec<XYNDC<f64>> {
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_with(lon1, lon2, lat1, lat2, d_alpha, parallel_arc::ndc_pixel_size(camera), &|lonlat| {
+        crate::math::lonlat::proj(lonlat, projection, camera)
+    })
+}
+
+// The closed outline of the box projected with `proj`, starting with its bottom
+// parallel from `lon1` to `lon2`
+fn project_with<P>(lon1: f64, lon2: f64, lat1: f64, lat2: f64, d_alpha: f64, px_size: f64, proj: &P) -> Vec<XYNDC<f64>>
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let (lon1, lon2) = (lon1.rem_euclid(TWICE_PI), lon2.rem_euclid(TWICE_PI));
+
+    // The parallel vertices being given in simple precision, vertices closer than
+    // EPS are considered the same
+    const EPS: f64 = 1e-6;
+
+    let mut polyline = vec![];
+    let mut append = |segments: Vec<XYNDC<f64>>| {
+        for p in segments {
+            match polyline.last() {
+                Some(last) if (p - *last).magnitude2() < EPS * EPS => {}
+                _ => polyline.push(p),
+            }
+        }
+    };
+
+    let params = SubdivisionParams::default();
+    let bottom = parallel(lat1, lon1, lon2, d_alpha, px_size, &params, proj);
+    let mut top = parallel(lat2, lon1, lon2, d_alpha, px_size, &params, proj);
+    top.reverse();
+
+    append(bottom);
+    append(meridian::project_with(lon2, lat1, lat2, d_alpha, &params, proj));
+    append(top);
+    append(meridian::project_with(lon1, lat2, lat1, d_alpha, &params, proj));
+
+    // close the loop
+    if let Some(first) = polyline.first().copied() {
+        append(vec![first]);
+    }
+
+    polyline
+}
+
+// The parallel projector handles spans up to PI, longer ones are projected in two halves
+fn parallel<P>(lat: f64, lon1: f64, lon2: f64, d_alpha: f64, px_size: f64, params: &SubdivisionParams, proj: &P) -> Vec<XYNDC<f64>>
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let lon_len = crate::math::sph_geom::distance_from_two_lon(lon1, lon2);
+
+    let mut vertices: Vec<[f32; 2]> = vec![];
+    if lon_len > PI {
+        let lon_mid = (lon1 + 0.5 * lon_len) % TWICE_PI;
+
+        parallel_arc::project_with(&mut vertices, lat, lon1, lon_mid, d_alpha, px_size, params, proj);
+        parallel_arc::project_with(&mut vertices, lat, lon_mid, lon2, d_alpha, px_size, params, proj);
+    } else {
+        parallel_arc::project_with(&mut vertices, lat, lon1, lon2, d_alpha, px_size, params, proj);
+    }
+
+    vertices
+        .iter()
+        .map(|v| XYNDC::new(v[0] as f64, v[1] as f64))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::angle::ToAngle;
+    use crate::math::projection::clip_space_proj;
+
+    #[test]
+    fn box_across_ra_0_closes() {
+        let projection = ProjectionType::Mol(mapproj::pseudocyl::mol::Mol::new());
+        let proj = clip_space_proj(&projection);
+        let at = |lon: f64, lat: f64| proj(&LonLatT::new(lon.to_radians().to_angle(), lat.to_radians().to_angle())).unwrap();
+
+        let (lon1, lon2) = (355.0_f64.to_radians(), 5.0_f64.to_radians());
+        let (lat1, lat2) = (-5.0_f64.to_radians(), 5.0_f64.to_radians());
+        let polyline = project_with(lon1, lon2, lat1, lat2, 1e-3, 0.0, &proj);
+        assert!(polyline.len() >= 5);
+
+        // closed
+        let (first, last) = (polyline[0], polyline[polyline.len() - 1]);
+        assert!((first - last).magnitude() < 1e-6);
+        assert!((first - at(355.0, -5.0)).magnitude() < 1e-6);
+
+        // The parallels of a pseudo-cylindrical projection are horizontal lines and
+        // its meridians are the most distant from the center at the equator
+        let (mut min, mut max) = (XYNDC::new(f64::MAX, f64::MAX), XYNDC::new(f64::MIN, f64::MIN));
+        for p in &polyline {
+            min = XYNDC::new(min.x.min(p.x), min.y.min(p.y));
+            max = XYNDC::new(max.x.max(p.x), max.y.max(p.y));
+        }
+        let (x1, x2) = (at(355.0, 0.0).x, at(5.0, 0.0).x);
+        let (y1, y2) = (at(0.0, -5.0).y, at(0.0, 5.0).y);
+        assert!((min.x - x1.min(x2)).abs() < 1e-4 && (max.x - x1.max(x2)).abs() < 1e-4);
+        assert!((min.y - y1.min(y2)).abs() < 1e-6 && (max.y - y1.max(y2)).abs() < 1e-6);
+    }
+}
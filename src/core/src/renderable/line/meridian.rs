@@ -0,0 +1,372 @@
+use crate::CameraViewPort;
+use crate::ProjectionType;
+
+use crate::math::angle::{Angle, ToAngle};
+use cgmath::InnerSpace;
+
+use crate::coo_space::XYNDC;
+
+use crate::LonLatT;
+
+use super::parallel_arc::{BoundingBox, FadedVertices, SubdivisionParams, Vertices};
+
+// * Remark
+//
+// - Meridian longitude between [0; 2\pi[
+// - Latitudes between [-0.5*pi; 0.5*pi]. The poles can be reached, the
+//   longitude being degenerated there, they are handled like any other
+//   latitude: if they cannot be projected, the nearest valid latitude is
+//   found by dichotomy
+//
+// * Returns
+// A list of lines vertices
+pub fn project(
+    lon: f64,
+    lat1: f64,
+    lat2: f64,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Vec<XYNDC<f64>> {
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_with(lon, lat1, lat2, d_alpha, &SubdivisionParams::default(), &|lonlat| {
+        crate::math::lonlat::proj(lonlat, projection, camera)
+    })
+}
+
+// Same as `project` but only returns the bounding box of the vertices, computed
+// while subdividing. None if nothing can be projected
+//
+// * Returns
+// The (min, max) corners of the box
+pub fn project_bbox(
+    lon: f64,
+    lat1: f64,
+    lat2: f64,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Option<(XYNDC<f64>, XYNDC<f64>)> {
+    let mut bbox = BoundingBox::default();
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_into(&mut bbox, lon, lat1, lat2, d_alpha, &SubdivisionParams::default(), &|lonlat| {
+        crate::math::lonlat::proj(lonlat, projection, camera)
+    });
+
+    bbox.corners()
+}
+
+// Same as `project` but each vertex is given with an alpha fading the meridian
+// out over the last `fade` before the points where it is clipped by the valid
+// domain of the projection. A null `fade` gives opaque vertices
+pub fn project_faded(
+    lon: f64,
+    lat1: f64,
+    lat2: f64,
+    fade: Angle<f64>,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Vec<([f32; 2], f32)> {
+    let mut vertices = FadedVertices::new(fade.to_radians(), 1.0);
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_into(&mut vertices, lon, lat1, lat2, d_alpha, &SubdivisionParams::default(), &|lonlat| {
+        crate::math::lonlat::proj(lonlat, projection, camera)
+    });
+
+    vertices.into_vertices()
+}
+
+impl Vertices for Vec<XYNDC<f64>> {
+    #[inline]
+    fn emit(&mut self, p: &XYNDC<f64>, _iter: usize) {
+        self.push(*p);
+    }
+}
+
+// The meridian at `lon` between `lat1` and `lat2` projected with `proj`
+pub(super) fn project_with<P>(lon: f64, lat1: f64, lat2: f64, d_alpha: f64, params: &SubdivisionParams, proj: &P) -> Vec<XYNDC<f64>>
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let mut vertices = vec![];
+    project_into(&mut vertices, lon, lat1, lat2, d_alpha, params, proj);
+
+    vertices
+}
+
+fn project_into<V, P>(vertices: &mut V, lon: f64, lat1: f64, lat2: f64, d_alpha: f64, params: &SubdivisionParams, proj: &P)
+where
+    V: Vertices,
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let v1 = proj(&LonLatT::new(lon.to_angle(), lat1.to_angle()));
+    let v2 = proj(&LonLatT::new(lon.to_angle(), lat2.to_angle()));
+
+    match (v1, v2) {
+        (Some(_v1), Some(_v2)) => {
+            subdivide_multi(vertices, lon, lat1, lat2, proj, params);
+        }
+        (None, Some(_v2)) => {
+            let (lat1, lat2) = sub_valid_domain(lon, lat2, lat1, d_alpha, proj);
+            vertices.clip_at(lat1);
+            subdivide_multi(vertices, lon, lat1, lat2, proj, params);
+        }
+        (Some(_v1), None) => {
+            let (lat1, lat2) = sub_valid_domain(lon, lat1, lat2, d_alpha, proj);
+            vertices.clip_at(lat2);
+            subdivide_multi(vertices, lon, lat1, lat2, proj, params);
+        }
+        (None, None) => {
+            // Both ends cannot be projected, e.g. the poles of the Mercator projection,
+            // but the middle of the meridian may still be
+            let lat_m = (lat1 + lat2) * 0.5;
+            if proj(&LonLatT::new(lon.to_angle(), lat_m.to_angle())).is_some() {
+                let (lat1, _) = sub_valid_domain(lon, lat_m, lat1, d_alpha, proj);
+                let (_, lat2) = sub_valid_domain(lon, lat_m, lat2, d_alpha, proj);
+                vertices.clip_at(lat1);
+                vertices.clip_at(lat2);
+                subdivide_multi(vertices, lon, lat1, lat2, proj, params);
+            }
+        }
+    }
+}
+
+// Precondition:
+// * valid_lat can be projected whereas invalid_lat cannot
+fn sub_valid_domain<P>(
+    lon: f64,
+    valid_lat: f64,
+    invalid_lat: f64,
+    d_alpha: f64,
+    proj: &P,
+) -> (f64, f64)
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let mut l_valid = valid_lat;
+    let mut l_invalid = invalid_lat;
+    while (l_valid - l_invalid).abs() > d_alpha {
+        let lm = (l_valid + l_invalid) * 0.5;
+        // check whether is it defined or not
+        let mid_lonlat = LonLatT::new(lon.to_angle(), lm.to_angle());
+        if proj(&mid_lonlat).is_some() {
+            l_valid = lm;
+        } else {
+            l_invalid = lm;
+        }
+    }
+
+    // l2 is invalid while l1 is valid
+    if valid_lat > invalid_lat {
+        (l_valid, valid_lat)
+    } else {
+        (valid_lat, l_valid)
+    }
+}
+
+fn subdivide_multi<V, P>(vertices: &mut V, lon: f64, lat_s: f64, lat_e: f64, proj: &P, params: &SubdivisionParams)
+where
+    V: Vertices,
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let num_vertices = 5;
+    let dlat = (lat_e - lat_s) / (num_vertices as f64);
+    for i in 0..num_vertices {
+        let lat1 = lat_s + (i as f64) * dlat;
+        let lat2 = lat1 + dlat;
+
+        subdivide(vertices, lon, lat1, lat2, proj, params, 0);
+    }
+}
+
+fn subdivide<V, P>(
+    vertices: &mut V,
+    lon: f64,
+
+    lat1: f64,
+    lat2: f64,
+
+    proj: &P,
+    params: &SubdivisionParams,
+    iter: usize,
+) -> bool
+where
+    V: Vertices,
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let p1 = proj(&LonLatT::new(lon.to_angle(), lat1.to_angle()));
+    let p2 = proj(&LonLatT::new(lon.to_angle(), lat2.to_angle()));
+
+    if iter < params.max_iter {
+        // Project them. We are always facing the camera
+        let lat0 = (lat1 + lat2) * 0.5;
+        let pm = proj(&LonLatT::new(lon.to_angle(), lat0.to_angle()));
+
+        match (p1, pm, p2) {
+            (Some(p1), Some(pm), Some(p2)) => {
+                let ab = pm - p1;
+                let bc = p2 - pm;
+
+                let ab_u = ab.normalize();
+                let bc_u = bc.normalize();
+
+                let dot_abbc = crate::math::vector::dot(&ab_u, &bc_u);
+                let theta_abbc = dot_abbc.acos();
+
+                if theta_abbc.abs() < params.max_angle.to_radians() {
+                    // Computed on the normalized vectors, the colinearity test does
+                    // not depend on the aperture
+                    let det_abbc = crate::math::vector::det(&ab_u, &bc_u);
+
+                    if det_abbc.abs() < params.colinear_eps {
+                        vertices.emit_at(&p1, lat1, iter);
+                        vertices.emit_at(&p2, lat2, iter);
+                    } else {
+                        // not colinear but enough to stop
+                        vertices.emit_at(&p1, lat1, iter);
+                        vertices.emit_at(&pm, lat0, iter);
+
+                        vertices.emit_at(&pm, lat0, iter);
+                        vertices.emit_at(&p2, lat2, iter);
+                    }
+                } else {
+                    let ab_l = ab.magnitude2();
+                    let bc_l = bc.magnitude2();
+
+                    let r = (ab_l - bc_l).abs() / (ab_l + bc_l);
+
+                    if r > params.short_ratio {
+                        if ab_l < bc_l {
+                            vertices.emit_at(&p1, lat1, iter);
+                            vertices.emit_at(&pm, lat0, iter);
+                        } else {
+                            vertices.emit_at(&pm, lat0, iter);
+                            vertices.emit_at(&p2, lat2, iter);
+                        }
+                    } else {
+                        // Subdivide a->b and b->c
+                        if !subdivide(vertices, lon, lat1, lat0, proj, params, iter + 1) {
+                            vertices.emit_at(&p1, lat1, iter);
+                            vertices.emit_at(&pm, lat0, iter);
+                        }
+
+                        if !subdivide(vertices, lon, lat0, lat2, proj, params, iter + 1) {
+                            vertices.emit_at(&pm, lat0, iter);
+                            vertices.emit_at(&p2, lat2, iter);
+                        }
+                    }
+                }
+                true
+            }
+            _ => false,
+        }
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::projection::clip_space_proj;
+    use crate::math::HALF_PI;
+
+    fn num_vertices(lon: f64, projection: &ProjectionType) -> usize {
+        let proj = clip_space_proj(projection);
+
+        project_with(lon, -HALF_PI, HALF_PI, 1e-3, &SubdivisionParams::default(), &proj).len()
+    }
+
+    #[test]
+    fn subdivision_grows_near_the_limb() {
+        let projections = [
+            ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new()),
+            ProjectionType::Mol(mapproj::pseudocyl::mol::Mol::new()),
+            ProjectionType::Hammer(crate::math::projection::hammer::Hammer),
+        ];
+
+        for projection in &projections {
+            let center = num_vertices(0.0, projection);
+            let limb = num_vertices(179.0_f64.to_radians(), projection);
+
+            // The central meridian is a straight line, no subdivision needed
+            assert_eq!(center, 10);
+            assert!(limb > center);
+        }
+    }
+
+    #[test]
+    fn plate_carree_meridians_reach_the_edges() {
+        let projection = ProjectionType::Car(mapproj::cylindrical::car::Car::new());
+        let proj = clip_space_proj(&projection);
+
+        // The meridians are all straight and the poles are lines, not points: every meridian
+        // runs from the bottom edge to the top one at its own abscissa
+        for lon in [0.0_f64, 90.0, 179.0] {
+            let vertices = project_with(lon.to_radians(), -HALF_PI, HALF_PI, 1e-3, &SubdivisionParams::default(), &proj);
+            assert_eq!(vertices.len(), num_vertices(0.0, &projection));
+
+            let (first, last) = (vertices.first().unwrap(), vertices.last().unwrap());
+            assert!((first.y + 1.0).abs() < 1e-6 && (last.y - 1.0).abs() < 1e-6);
+            assert!(vertices.iter().all(|v| (v.x.abs() - lon / 180.0).abs() < 1e-6));
+        }
+    }
+
+    #[test]
+    fn mercator_meridians_fade_out_near_the_max_latitude() {
+        let projection = ProjectionType::Mer(mapproj::cylindrical::mer::Mer::new());
+        let proj = clip_space_proj(&projection);
+
+        // Fade over the last 10 degrees before the clip points, at both ends
+        let mut vertices = FadedVertices::new(10.0_f64.to_radians(), 1.0);
+        project_into(&mut vertices, 0.5, -HALF_PI, HALF_PI, 1e-3, &SubdivisionParams::default(), &proj);
+        let vertices = vertices.into_vertices();
+
+        let alpha_at = |y: f64| {
+            vertices
+                .iter()
+                .min_by(|a, b| (a.0[1] as f64 - y).abs().total_cmp(&(b.0[1] as f64 - y).abs()))
+                .map(|(_, alpha)| *alpha)
+                .unwrap()
+        };
+        let top = vertices.iter().map(|(v, _)| v[1]).fold(f32::MIN, f32::max) as f64;
+        let bottom = vertices.iter().map(|(v, _)| v[1]).fold(f32::MAX, f32::min) as f64;
+
+        assert_eq!(alpha_at(0.0), 1.0);
+        assert!(alpha_at(top) < 0.2);
+        assert!(alpha_at(bottom) < 0.2);
+        assert!(vertices.iter().all(|(_, alpha)| (0.0..=1.0).contains(alpha)));
+    }
+
+    #[test]
+    fn mercator_meridians_stop_at_the_max_latitude() {
+        use crate::math::projection::MER_MAX_LAT;
+
+        let projection = ProjectionType::Mer(mapproj::cylindrical::mer::Mer::new());
+        let proj = clip_space_proj(&projection);
+
+        // None of the poles can be projected, the valid part of the meridian is found by dichotomy
+        let d_alpha = 1e-3;
+        let vertices = project_with(0.5, -HALF_PI, HALF_PI, d_alpha, &SubdivisionParams::default(), &proj);
+        assert!(!vertices.is_empty());
+
+        let top = proj(&LonLatT::new(0.5_f64.to_angle(), (MER_MAX_LAT - d_alpha).to_angle())).unwrap();
+        assert!(vertices.iter().all(|v| v.y.abs() <= 1.0));
+        assert!(vertices.last().unwrap().y >= top.y);
+    }
+
+    #[test]
+    fn subdivision_follows_the_params() {
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        let proj = clip_space_proj(&projection);
+        let num_vertices = |params: &SubdivisionParams| {
+            project_with(179.0_f64.to_radians(), -HALF_PI, HALF_PI, 1e-3, params, &proj).len()
+        };
+
+        // With one level of recursion, each of the 5 initial arcs gives at most two segments
+        let shallow = num_vertices(&SubdivisionParams { max_iter: 1, ..Default::default() });
+        assert!(shallow <= 20);
+        assert!(num_vertices(&SubdivisionParams::default()) > shallow);
+    }
+}
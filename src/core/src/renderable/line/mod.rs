@@ -1,6 +1,14 @@
 /// This module handles the lines rendering code
-pub mod great_circle_arc;
+pub mod great_circle;
+pub mod lonlat_box;
+pub mod meridian;
 pub mod parallel_arc;
+pub mod rhumb;
+pub mod small_circle;
+pub mod stroke;
+pub mod track;
+
+pub mod altaz;
 
 use crate::math::projection::ProjectionType;
 use crate::shader::ShaderManager;
@@ -27,6 +35,306 @@ pub enum Style {
     Dotted,
 }
 
+/// Dash pattern of a line, the lengths being given in pixels
+#[derive(Clone, Copy, Debug)]
+pub struct DashPattern {
+    pub dash: f32,
+    pub gap: f32,
+}
+
+use al_core::shader::{SendUniforms, ShaderBound};
+impl SendUniforms for DashPattern {
+    fn attach_uniforms<'a>(&self, shader: &'a ShaderBound<'a>) -> &'a ShaderBound<'a> {
+        shader
+            .attach_uniform("u_dash", &self.dash)
+            .attach_uniform("u_gap", &self.gap)
+    }
+}
+
+/// Color and width of a set of lines
+///
+/// The lines sharing the same style are drawn with one draw call, the style
+/// being sent to the shader as uniforms
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineStyle {
+    pub color: [f32; 4],
+    /// Width of the lines in pixels
+    pub width: f32,
+}
+
+impl LineStyle {
+    pub fn new(color: &ColorRGBA, width: f32) -> Self {
+        Self {
+            color: [color.r, color.g, color.b, color.a],
+            width,
+        }
+    }
+}
+
+impl SendUniforms for LineStyle {
+    fn attach_uniforms<'a>(&self, shader: &'a ShaderBound<'a>) -> &'a ShaderBound<'a> {
+        shader
+            .attach_uniform("u_color", &self.color)
+            .attach_uniform("u_thickness", &self.width)
+    }
+}
+
+/// Group lines by style
+///
+/// # Arguments
+///
+/// * ``lines`` - The lines vertices along with their style
+///
+/// # Returns
+///
+/// One entry per distinct style, in the order they first appear, with the
+/// segments of its lines laid out as `[x1, y1, x2, y2]` for the instanced line shader
+pub fn group_by_style<'a, I>(lines: I) -> Vec<(LineStyle, Vec<f32>)>
+where
+    I: IntoIterator<Item = (LineStyle, &'a [[f32; 2]])>,
+{
+    let mut groups: Vec<(LineStyle, Vec<f32>)> = vec![];
+
+    for (style, vertices) in lines {
+        let segments = vertices
+            .iter()
+            .zip(vertices.iter().skip(1))
+            .flat_map(|(a, b)| [a[0], a[1], b[0], b[1]]);
+
+        if let Some((_, buf)) = groups.iter_mut().find(|(s, _)| *s == style) {
+            buf.extend(segments);
+        } else {
+            groups.push((style, segments.collect()));
+        }
+    }
+
+    groups
+}
+
+/// Same as ``group_by_style`` for lines whose vertices are given with an alpha
+///
+/// # Arguments
+///
+/// * ``lines`` - The lines vertices along with their alpha and their style
+///
+/// # Returns
+///
+/// The segments are laid out as `[x1, y1, alpha1, x2, y2, alpha2]` for the faded instanced line shader
+pub fn group_by_style_faded<'a, I>(lines: I) -> Vec<(LineStyle, Vec<f32>)>
+where
+    I: IntoIterator<Item = (LineStyle, &'a [[f32; 2]], &'a [f32])>,
+{
+    let mut groups: Vec<(LineStyle, Vec<f32>)> = vec![];
+
+    for (style, vertices, alphas) in lines {
+        let vertices = vertices.iter().zip(alphas);
+        let segments = vertices
+            .clone()
+            .zip(vertices.skip(1))
+            .flat_map(|((a, a_alpha), (b, b_alpha))| [a[0], a[1], *a_alpha, b[0], b[1], *b_alpha]);
+
+        if let Some((_, buf)) = groups.iter_mut().find(|(s, _)| *s == style) {
+            buf.extend(segments);
+        } else {
+            groups.push((style, segments.collect()));
+        }
+    }
+
+    groups
+}
+
+/// The VAO of the segments drawn by the instanced line shaders
+///
+/// Each endpoint of the segments is given by ``dim`` floats, i.e. its position in
+/// the NDC space possibly followed by its alpha
+pub fn segments_vao(gl: &WebGlContext, dim: usize) -> VertexArrayObject {
+    let mut vao = VertexArrayObject::new(gl);
+    vao.bind_for_update()
+        // Store the cartesian position of the center of the source in the a instanced VBO
+        .add_instanced_array_buffer(
+            "ndc_pos",
+            2 * dim * std::mem::size_of::<f32>(),
+            &[dim, dim],
+            &[0, dim * std::mem::size_of::<f32>()],
+            WebGl2RenderingContext::DYNAMIC_DRAW,
+            &[] as &[f32],
+        )
+        .add_array_buffer(
+            "vertices",
+            2 * std::mem::size_of::<f32>(),
+            &[2],
+            &[0],
+            WebGl2RenderingContext::STATIC_DRAW,
+            &[
+                0_f32, -0.5_f32, 1_f32, -0.5_f32, 1_f32, 0.5_f32, 0_f32, 0.5_f32,
+            ] as &[f32],
+        )
+        // Set the element buffer
+        .add_element_buffer(
+            WebGl2RenderingContext::STATIC_DRAW,
+            &[0_u16, 1_u16, 2_u16, 0_u16, 2_u16, 3_u16] as &[u16],
+        )
+        // Unbind the buffer
+        .unbind();
+
+    vao
+}
+
+use crate::coo_space::XYNDC;
+
+/// Precision the lines given in the (lon, lat) space are projected with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precision {
+    /// Projected by the vertex shader. The positions and the rotation of the view
+    /// being given in f32, the lines jitter when zoomed deep
+    Single,
+    /// Projected on the CPU in f64, only the NDC positions, i.e. the offsets
+    /// to the center of the view, are converted to f32
+    Double,
+}
+
+impl Precision {
+    // Angular size of a pixel under which the f32 rounding of the unit vectors,
+    // about 6e-8 rad, becomes visible
+    const MIN_SINGLE_PIXEL_ANGLE: f64 = 1e-6;
+
+    /// The precision needed for pixels of angular size ``px_angle``, in radians
+    pub fn from_pixel_angle(px_angle: f64) -> Self {
+        if px_angle < Self::MIN_SINGLE_PIXEL_ANGLE {
+            Precision::Double
+        } else {
+            Precision::Single
+        }
+    }
+}
+
+/// Project lines given as segment pairs of (lon, lat) in double precision
+///
+/// The segments having an endpoint that cannot be projected are discarded.
+///
+/// # Arguments
+///
+/// * ``segments`` - The segments as (lon_a, lat_a, lon_b, lat_b) in radians
+/// * ``frame`` - The frame the positions are given in
+///
+/// # Returns
+///
+/// The segments as (x_a, y_a, x_b, y_b) in the NDC space
+pub fn project_segments(segments: &[f64], frame: CooSystem, camera: &CameraViewPort, projection: &ProjectionType) -> Vec<f32> {
+    use crate::math::lonlat::LonLat;
+
+    let view_frame = camera.get_coo_system();
+    project_segments_with(segments, &|lonlat| {
+        let xyzw = crate::coosys::apply_coo_system(frame, view_frame, &lonlat.vector());
+        crate::math::lonlat::proj(&xyzw.lonlat(), projection, camera)
+    })
+}
+
+// Same as `project_segments` but does the projection with `proj`
+fn project_segments_with<P>(segments: &[f64], proj: &P) -> Vec<f32>
+where
+    P: Fn(&crate::LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    use crate::math::angle::ToAngle;
+
+    let at = |lon: f64, lat: f64| proj(&crate::LonLatT::new(lon.to_angle(), lat.to_angle()));
+
+    segments
+        .chunks_exact(4)
+        .filter_map(|s| {
+            let a = at(s[0], s[1])?;
+            let b = at(s[2], s[3])?;
+
+            Some([a.x as f32, a.y as f32, b.x as f32, b.y as f32])
+        })
+        .flatten()
+        .collect()
+}
+
+/// Compute the arc length along lines vertices given as segment pairs
+///
+/// The length is accumulated in pixels. It goes on when a segment starts
+/// where the previous one ended and is reset where the line is broken.
+///
+/// # Arguments
+///
+/// * ``vertices`` - The segments vertices, in the NDC space
+/// * ``screen_size`` - The size of the screen in pixels
+pub fn arc_lengths(vertices: &[[f32; 2]], screen_size: &cgmath::Vector2<f32>) -> Vec<(XYNDC<f64>, f32)> {
+    let mut res = Vec::with_capacity(vertices.len());
+
+    let mut len = 0.0;
+    let mut prev: Option<&[f32; 2]> = None;
+    for (i, v) in vertices.iter().enumerate() {
+        if let Some(p) = prev {
+            // a NDC length of 2 spans the whole screen
+            let dx = (v[0] - p[0]) * 0.5 * screen_size.x;
+            let dy = (v[1] - p[1]) * 0.5 * screen_size.y;
+            let d = (dx * dx + dy * dy).sqrt();
+
+            if i % 2 == 1 {
+                // end of a segment
+                len += d;
+            } else if d > 1e-3 {
+                // start of a segment not connected to the previous one
+                len = 0.0;
+            }
+        }
+
+        res.push((XYNDC::new(v[0] as f64, v[1] as f64), len));
+        prev = Some(v);
+    }
+
+    res
+}
+
+/// Compute the screen tangent at lines vertices given as segment pairs
+///
+/// The segments are first chained into polylines, the tangent of an inner
+/// vertex being given by its two neighbours and the one of an endpoint by the
+/// segment it belongs to. The lines broken, e.g. at the boundary of the projection,
+/// are treated as separate polylines so that the tangents do not jump across the break.
+///
+/// # Arguments
+///
+/// * ``vertices`` - The segments vertices, in the NDC space
+/// * ``screen_size`` - The size of the screen in pixels
+///
+/// # Returns
+///
+/// The vertices of the polylines along with their unit tangent in the screen space.
+/// A null vector is given for the vertices of a degenerated polyline
+pub fn tangents(vertices: &[[f32; 2]], screen_size: &cgmath::Vector2<f32>) -> Vec<(XYNDC<f64>, cgmath::Vector2<f32>)> {
+    use cgmath::InnerSpace;
+
+    let segments = vertices
+        .iter()
+        .map(|v| XYNDC::new(v[0] as f64, v[1] as f64))
+        .collect::<Vec<_>>();
+    let (sx, sy) = (0.5 * screen_size.x, 0.5 * screen_size.y);
+
+    let mut res = Vec::with_capacity(vertices.len());
+    for polyline in stroke::polylines(&segments) {
+        let n = polyline.len();
+        for i in 0..n {
+            let a = polyline[i.saturating_sub(1)];
+            let b = polyline[(i + 1).min(n - 1)];
+
+            // a NDC length of 2 spans the whole screen
+            let d = cgmath::Vector2::new((b.x - a.x) as f32 * sx, (b.y - a.y) as f32 * sy);
+            let t = if d.magnitude2() > 0.0 {
+                d.normalize()
+            } else {
+                cgmath::Vector2::new(0.0, 0.0)
+            };
+
+            res.push((polyline[i], t));
+        }
+    }
+
+    res
+}
+
 pub struct RasterizedLineRenderer {
     gl: WebGlContext,
     vao: VertexArrayObject,
@@ -456,3 +764,209 @@ impl Renderer for RasterizedLineRenderer {
             );
     }
 }
+
+/// Project several independent lines
+///
+/// With the ``rayon`` feature, the lines are projected across threads. It stays
+/// single-threaded on wasm32 where threads are not available. Note that
+/// ``CameraViewPort`` is not ``Sync`` as it owns the WebGL context, ``project``
+/// must be given camera-free projection closures to be run in parallel.
+pub fn project_lines<T, F>(lines: &[T], project: F) -> Vec<Vec<XYNDC<f64>>>
+where
+    T: Sync,
+    F: Fn(&T) -> Vec<XYNDC<f64>> + Sync + Send,
+{
+    #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+    {
+        use rayon::prelude::*;
+        lines.par_iter().map(project).collect()
+    }
+
+    #[cfg(not(all(feature = "rayon", not(target_arch = "wasm32"))))]
+    {
+        lines.iter().map(project).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::arc_lengths;
+    use cgmath::InnerSpace;
+
+    #[test]
+    fn arc_length_across_a_wrapping_parallel() {
+        // A parallel drawn in two halves, the second one starting where the first ended
+        let half = |s: f32| {
+            (0..4)
+                .flat_map(move |i| {
+                    let x1 = s + (i as f32) * 0.1;
+                    let x2 = x1 + 0.1;
+                    [[x1, 0.2 * x1 * x1], [x2, 0.2 * x2 * x2]]
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut vertices = half(-0.4);
+        vertices.extend(half(0.0));
+
+        let screen_size = cgmath::Vector2::new(800.0, 600.0);
+        let lengths = arc_lengths(&vertices, &screen_size);
+
+        assert_eq!(lengths[0].1, 0.0);
+        for w in lengths.windows(2) {
+            // monotonic
+            assert!(w[1].1 >= w[0].1);
+        }
+        // continuous at the junction of the two halves
+        assert_eq!(lengths[7].1, lengths[8].1);
+
+        // The total length is the one of the polyline
+        let expected: f32 = vertices
+            .chunks_exact(2)
+            .map(|s| {
+                let dx = (s[1][0] - s[0][0]) * 400.0;
+                let dy = (s[1][1] - s[0][1]) * 300.0;
+                (dx * dx + dy * dy).sqrt()
+            })
+            .sum();
+        assert!((lengths.last().unwrap().1 - expected).abs() < 1e-2);
+    }
+
+    #[test]
+    fn project_lines_matches_serial_projection() {
+        use super::project_lines;
+        use crate::math::projection::clip_space_proj;
+        use crate::math::HALF_PI;
+        use crate::ProjectionType;
+
+        let projection = ProjectionType::Mol(mapproj::pseudocyl::mol::Mol::new());
+        let proj = clip_space_proj(&projection);
+
+        let lons = (0..24)
+            .map(|i| (i as f64) * 15.0_f64.to_radians())
+            .collect::<Vec<_>>();
+        let project = |lon: &f64| super::meridian::project_with(*lon, -HALF_PI, HALF_PI, 1e-3, &super::parallel_arc::SubdivisionParams::default(), &proj);
+
+        let serial = lons.iter().map(project).collect::<Vec<_>>();
+        let parallel = project_lines(&lons, project);
+
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn arc_length_reset_on_broken_lines() {
+        let vertices = [[0.0, 0.0], [0.5, 0.0], [-0.5, 0.5], [0.0, 0.5]];
+        let lengths = arc_lengths(&vertices, &cgmath::Vector2::new(2.0, 2.0));
+
+        assert!((lengths[1].1 - 0.5).abs() < 1e-6);
+        assert_eq!(lengths[2].1, 0.0);
+        assert!((lengths[3].1 - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn lines_are_grouped_by_style() {
+        use super::{group_by_style, LineStyle};
+        use al_api::color::ColorRGBA;
+
+        let color = ColorRGBA { r: 0.0, g: 1.0, b: 0.0, a: 0.5 };
+        let grid = LineStyle::new(&color, 2.0);
+        let prime_meridian = LineStyle::new(&ColorRGBA { r: 1.0, ..color }, 2.0);
+
+        let l1: &[[f32; 2]] = &[[0.0, 0.0], [0.1, 0.0], [0.2, 0.0]];
+        let l2: &[[f32; 2]] = &[[0.0, -0.5], [0.0, 0.5]];
+        let l3: &[[f32; 2]] = &[[0.5, 0.5], [0.6, 0.5]];
+
+        let groups = group_by_style([(grid, l1), (prime_meridian, l2), (grid, l3)]);
+        assert_eq!(groups.len(), 2);
+
+        assert_eq!(groups[0].0, grid);
+        // 2 segments for the first line and 1 for the last one
+        assert_eq!(groups[0].1.len(), 3 * 4);
+        assert_eq!(groups[1].0, prime_meridian);
+        assert_eq!(groups[1].1, vec![0.0, -0.5, 0.0, 0.5]);
+
+        // a single style gives a single draw call
+        assert_eq!(group_by_style([(grid, l1), (grid, l2)]).len(), 1);
+    }
+
+    #[test]
+    fn tangents_along_a_straight_line_are_constant() {
+        use super::tangents;
+
+        let screen_size = cgmath::Vector2::new(800.0, 600.0);
+        // Two segments along a straight line, then a broken one going elsewhere
+        let vertices = [
+            [-0.5, -0.5],
+            [0.0, 0.0],
+            [0.0, 0.0],
+            [0.5, 0.5],
+            [0.8, -0.8],
+            [0.9, -0.8],
+        ];
+        let res = tangents(&vertices, &screen_size);
+        assert_eq!(res.len(), 5);
+
+        // The NDC diagonal is not the screen one
+        let expected = cgmath::Vector2::new(400.0_f32, 300.0).normalize();
+        for (_, t) in &res[..3] {
+            assert!((t - expected).magnitude() < 1e-6);
+        }
+        // No jump across the break
+        for (_, t) in &res[3..] {
+            assert!((t - cgmath::Vector2::new(1.0, 0.0)).magnitude() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn positions_are_stable_at_1_arcmin() {
+        use super::project_segments_with;
+        use crate::math::angle::ToAngle;
+        use crate::math::projection::Projection;
+        use crate::{LonLatT, ProjectionType};
+        use cgmath::Vector2;
+
+        let projection = ProjectionType::Tan(mapproj::zenithal::tan::Tan);
+        let aperture = (1.0_f64 / 60.0).to_radians();
+        let width = 1000.0;
+        // Scale the clip space so that the aperture spans the NDC space
+        let half = LonLatT::new((0.5 * aperture).to_angle(), 0.0_f64.to_angle());
+        let zoom = 1.0 / projection.world_to_clip_space(&half.vector()).unwrap().x;
+
+        // The view centered on (lon0, 0)
+        let frame = |lon0: f64| {
+            move |lonlat: &LonLatT<f64>| {
+                let ll = LonLatT::new((lonlat.lon().to_radians() - lon0).to_angle(), lonlat.lat());
+                projection.world_to_clip_space(&ll.vector()).map(|p| p * zoom)
+            }
+        };
+
+        // Lines of a grid around a position far from the origin of the
+        // longitudes so that they are big numbers compared to the aperture
+        let lon0 = 2.5;
+        let proj1 = frame(lon0);
+        // The next frame, the view having moved by a tenth of pixel
+        let dlon = 0.1 * aperture / width;
+        let proj2 = frame(lon0 + dlon);
+
+        let (lon, lat1, lat2) = (lon0 + 0.1 * aperture, -0.4 * aperture, 0.4 * aperture);
+        let params = super::parallel_arc::SubdivisionParams::default();
+        let m1 = super::meridian::project_with(lon, lat1, lat2, aperture * 0.02, &params, &proj1);
+        let m2 = super::meridian::project_with(lon, lat1, lat2, aperture * 0.02, &params, &proj2);
+        assert_eq!(m1.len(), m2.len());
+
+        // Every vertex moves by the same sub pixel shift
+        let to_px = |p: Vector2<f64>| p * (0.5 * width);
+        let shift = to_px(m1[0] - m2[0]);
+        assert!((shift.x.abs() - 0.1).abs() < 1e-3, "{:?}", shift);
+        for (a, b) in m1.iter().zip(m2.iter()) {
+            assert!((to_px(*a - *b) - shift).magnitude() < 1e-3);
+        }
+
+        // The segments projected in double precision keep their position in f32
+        let segments = [lon0, 0.0, lon0 + 0.3 * aperture, 0.2 * aperture];
+        let (s1, s2) = (project_segments_with(&segments, &proj1), project_segments_with(&segments, &proj2));
+        for (a, b) in s1.chunks_exact(2).zip(s2.chunks_exact(2)) {
+            let d = Vector2::new((a[0] - b[0]) as f64, (a[1] - b[1]) as f64);
+            assert!((to_px(d).x.abs() - 0.1).abs() < 1e-2, "{:?}", to_px(d));
+        }
+    }
+}
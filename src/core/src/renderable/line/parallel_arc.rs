@@ -5,10 +5,56 @@ use cgmath::InnerSpace;
 use crate::math::angle::ToAngle;
 
 
-use crate::math::{TWICE_PI};
+use crate::math::{HALF_PI, PI, TWICE_PI};
 
+// Latitudes closer than that to a pole are considered at the pole
+const POLE_EPS: f64 = 1e-9;
+
+use crate::math::angle::{Angle, ArcDeg};
+use crate::coo_space::XYNDC;
+use crate::math::lonlat::LonLat;
 use crate::LonLatT;
-const MAX_ITERATION: usize = 4;
+use al_api::coo_system::CooSystem;
+
+/// Parameters driving the adaptive subdivision of the parallels and the meridians
+///
+/// Lowering them gives fewer vertices (e.g. for low-end devices), raising
+/// them gives smoother lines (e.g. for high resolution displays)
+#[derive(Clone, Copy, Debug)]
+pub struct SubdivisionParams {
+    /// Maximum depth of recursion of the subdivision
+    pub max_iter: usize,
+    /// Angle between two consecutive projected segments under which
+    /// they are considered smooth enough to stop the subdivision
+    pub max_angle: Angle<f64>,
+    /// Longitude span of the arcs a parallel is initially split into before subdividing.
+    /// A shorter parallel is split into fewer arcs
+    pub arc_lon: Angle<f64>,
+    /// Maximum number of arcs a parallel is initially split into
+    pub max_num_arcs: usize,
+    /// Two consecutive projected segments, once smooth enough, are merged into one
+    /// when the sine of their angle is lower than that. Lowering it keeps more
+    /// vertices along slightly curved lines, i.e. smoother lines at the cost of
+    /// more vertices to draw
+    pub colinear_eps: f64,
+    /// Relative difference of squared lengths, between two consecutive projected
+    /// segments, above which only the shorter one is kept instead of subdividing.
+    /// It happens where one of the segments crosses a discontinuity of the projection
+    pub short_ratio: f64,
+}
+
+impl Default for SubdivisionParams {
+    fn default() -> Self {
+        Self {
+            max_iter: 4,
+            max_angle: ArcDeg(5.0).into(),
+            arc_lon: ArcDeg(36.0).into(),
+            max_num_arcs: 10,
+            colinear_eps: 1e-2,
+            short_ratio: 0.8,
+        }
+    }
+}
 
 // * Remark
 // 
@@ -18,11 +64,422 @@ const MAX_ITERATION: usize = 4;
 // - (lon1 - lon2).abs() < PI
 //
 // * Returns
-// A list of lines vertices
-pub fn project(lat: f64, mut lon1: f64, lon2: f64, camera: &CameraViewPort, projection: &ProjectionType) -> Vec<[f32; 2]> {
+// A list of lines vertices, given as segment pairs. The segments are ordered by
+// increasing longitude from lon1 to lon2 and so are the two ends of each segment.
+// Across the zero meridian, the longitudes are counted from lon1 - 2pi so that
+// the order holds on both sides of the seam.
+pub fn project(lat: f64, lon1: f64, lon2: f64, camera: &CameraViewPort, projection: &ProjectionType) -> Vec<[f32; 2]> {
+    project_with_params(lat, lon1, lon2, camera, projection, &SubdivisionParams::default())
+}
+
+/// Where the label of a line is drawn and the value it reports
+pub struct LabelAnchor {
+    /// Position of the label in the render frame
+    pub position: XYNDC<f64>,
+    /// Sky position at the anchor point, given in the label frame
+    pub lonlat: LonLatT<f64>,
+}
+
+// Same as `project` but also returns the point where the label of the parallel
+// should be anchored, i.e. where the projected line is the most horizontal on screen.
+//
+// The anchor is computed in the render frame but the label value is given in `label_frame`.
+// A line of the render frame does not map to a constant value in another frame (e.g. a galactic
+// meridian crossing RA=0) so the value is the one at the actual anchor point.
+//
+// * Returns
+// The lines vertices and the anchor. The anchor is None if nothing has been projected
+pub fn project_with_label(lat: f64, lon1: f64, lon2: f64, camera: &CameraViewPort, projection: &ProjectionType, label_frame: CooSystem) -> (Vec<[f32; 2]>, Option<LabelAnchor>) {
+    let vertices = project(lat, lon1, lon2, camera, projection);
+    let anchor = label_anchor(&vertices).and_then(|position| {
+        let lonlat = projection.unproject(&position, camera)?;
+
+        Some(LabelAnchor {
+            position,
+            lonlat: to_label_frame(&lonlat, camera.get_coo_system(), label_frame),
+        })
+    });
+
+    (vertices, anchor)
+}
+
+fn to_label_frame(lonlat: &LonLatT<f64>, render_frame: CooSystem, label_frame: CooSystem) -> LonLatT<f64> {
+    crate::coosys::apply_coo_system(render_frame, label_frame, &lonlat.vector()).lonlat()
+}
+
+// Find the middle of the most horizontal segment. Falls back to the middle of
+// the line if all its segments are degenerated
+fn label_anchor(vertices: &[[f32; 2]]) -> Option<XYNDC<f64>> {
+    let segment_mid = |s: &[[f32; 2]]| {
+        XYNDC::new(
+            (s[0][0] as f64 + s[1][0] as f64) * 0.5,
+            (s[0][1] as f64 + s[1][1] as f64) * 0.5,
+        )
+    };
+
+    let horizontal = vertices
+        .chunks_exact(2)
+        .filter_map(|s| {
+            let dx = (s[1][0] - s[0][0]) as f64;
+            let dy = (s[1][1] - s[0][1]) as f64;
+            let len = (dx * dx + dy * dy).sqrt();
+
+            // slope of the segment
+            (len > 0.0).then(|| (dy.abs() / len, s))
+        })
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, s)| segment_mid(s));
+
+    horizontal.or_else(|| {
+        let first = vertices.first()?;
+        let last = vertices.last()?;
+        Some(segment_mid(&[*first, *last]))
+    })
+}
+
+// Same as `project` but also returns the arc length in pixels at each vertex
+// so that the line can be dashed. Parallels longer than PI are drawn as two
+// halves along which the arc length is accumulated continuously
+pub fn project_with_arc_length(lat: f64, lon1: f64, lon2: f64, camera: &CameraViewPort, projection: &ProjectionType) -> Vec<(XYNDC<f64>, f32)> {
+    let lon_len = crate::math::sph_geom::distance_from_two_lon(lon1, lon2);
+
+    let vertices = if lon_len > PI {
+        let lon_mid = (lon1 + PI) % TWICE_PI;
+
+        let mut vertices = project(lat, lon1, lon_mid, camera, projection);
+        vertices.append(&mut project(lat, lon_mid, lon2, camera, projection));
+        vertices
+    } else {
+        project(lat, lon1, lon2, camera, projection)
+    };
+
+    super::arc_lengths(&vertices, &camera.get_screen_size())
+}
+
+// Same as `project` but also returns the screen tangent at each vertex so that
+// oriented markers can be placed along the line. The halves of the parallels
+// longer than PI are chained into one polyline
+pub fn project_with_tangents(lat: f64, lon1: f64, lon2: f64, camera: &CameraViewPort, projection: &ProjectionType) -> Vec<(XYNDC<f64>, cgmath::Vector2<f32>)> {
+    let lon_len = crate::math::sph_geom::distance_from_two_lon(lon1, lon2);
+
+    let vertices = if lon_len > PI {
+        let lon_mid = (lon1 + PI) % TWICE_PI;
+
+        let mut vertices = project(lat, lon1, lon_mid, camera, projection);
+        vertices.append(&mut project(lat, lon_mid, lon2, camera, projection));
+        vertices
+    } else {
+        project(lat, lon1, lon2, camera, projection)
+    };
+
+    super::tangents(&vertices, &camera.get_screen_size())
+}
+
+// Sample the parallel every `step` of longitude, e.g. to place evenly spaced ticks.
+// Contrary to `project`, the points are not meant to be joined into a line.
+//
+// * Remark
+// The longitudes go from lon1 eastward to lon2, crossing RA=0 if lon2 < lon1. The steps
+// are counted from lon1 so that they stay evenly spaced across the seam.
+//
+// * Returns
+// One entry per step, lon1 and lon2 included if the span is a multiple of `step`.
+// An entry is None if its position cannot be projected
+pub fn sample_parallel(lat: f64, lon1: f64, lon2: f64, step: Angle<f64>, camera: &CameraViewPort, projection: &ProjectionType) -> Vec<Option<XYNDC<f64>>> {
+    sample_with(lat, lon1, lon2, step.to_radians(), &|lonlat| {
+        crate::math::lonlat::proj(lonlat, projection, camera)
+    })
+}
+
+// Same as `sample_parallel` but does the projection with `proj`
+fn sample_with<P>(lat: f64, lon1: f64, lon2: f64, step: f64, proj: &P) -> Vec<Option<XYNDC<f64>>>
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    if step <= 0.0 {
+        return vec![];
+    }
+
+    let lon_len = crate::math::sph_geom::distance_from_two_lon(lon1, lon2);
+    // Accept the last step despite the rounding errors
+    let num_steps = (lon_len / step + 1e-9).floor() as usize;
+
+    (0..=num_steps)
+        .map(|k| {
+            let lon = (lon1 + (k as f64) * step).rem_euclid(TWICE_PI);
+            proj(&LonLatT::new(lon.to_angle(), lat.to_angle()))
+        })
+        .collect()
+}
+
+// Same as `project` but with custom subdivision parameters
+pub fn project_with_params(lat: f64, lon1: f64, lon2: f64, camera: &CameraViewPort, projection: &ProjectionType, params: &SubdivisionParams) -> Vec<[f32; 2]> {
     let mut vertices = vec![];
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_with(&mut vertices, lat, lon1, lon2, d_alpha, ndc_pixel_size(camera), params, &|lonlat| {
+        crate::math::lonlat::proj(lonlat, projection, camera)
+    });
+
+    vertices
+}
+
+// Same as `project_with_params` but each vertex is given with the depth of
+// recursion of the subdivision it has been emitted at
+#[cfg(feature = "debug_subdivision")]
+pub fn project_with_depth(lat: f64, lon1: f64, lon2: f64, camera: &CameraViewPort, projection: &ProjectionType, params: &SubdivisionParams) -> Vec<([f32; 2], usize)> {
+    let mut vertices = vec![];
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_with(&mut vertices, lat, lon1, lon2, d_alpha, ndc_pixel_size(camera), params, &|lonlat| {
+        crate::math::lonlat::proj(lonlat, projection, camera)
+    });
+
+    vertices
+}
+
+// Same as `project` but each vertex is given with an alpha fading the line out
+// over the last `fade` before the points where it is clipped by the valid
+// domain of the projection, softening the limb. A null `fade` gives opaque vertices
+pub fn project_faded(lat: f64, lon1: f64, lon2: f64, fade: Angle<f64>, camera: &CameraViewPort, projection: &ProjectionType) -> Vec<([f32; 2], f32)> {
+    let mut vertices = FadedVertices::new(fade.to_radians(), lat.cos());
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_with(&mut vertices, lat, lon1, lon2, d_alpha, ndc_pixel_size(camera), &SubdivisionParams::default(), &|lonlat| {
+        crate::math::lonlat::proj(lonlat, projection, camera)
+    });
+
+    vertices.into_vertices()
+}
+
+// Same as `project_with_params` but the vertices shared by consecutive segments
+// are given once.
+//
+// * Returns
+// The lines vertices and the pairs of indices of the segments
+pub fn project_indexed(lat: f64, lon1: f64, lon2: f64, camera: &CameraViewPort, projection: &ProjectionType, params: &SubdivisionParams) -> (Vec<XYNDC<f64>>, Vec<u32>) {
+    let mut lines = IndexedLines::default();
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_with(&mut lines, lat, lon1, lon2, d_alpha, ndc_pixel_size(camera), params, &|lonlat| {
+        crate::math::lonlat::proj(lonlat, projection, camera)
+    });
+
+    (lines.vertices, lines.indices)
+}
+
+// Same as `project_with_params` but only returns the bounding box of the vertices,
+// computed while subdividing.
+//
+// * Returns
+// The (min, max) corners of the box, None if nothing can be projected
+pub fn project_bbox(lat: f64, lon1: f64, lon2: f64, camera: &CameraViewPort, projection: &ProjectionType, params: &SubdivisionParams) -> Option<(XYNDC<f64>, XYNDC<f64>)> {
+    let mut bbox = BoundingBox::default();
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_with(&mut bbox, lat, lon1, lon2, d_alpha, ndc_pixel_size(camera), params, &|lonlat| {
+        crate::math::lonlat::proj(lonlat, projection, camera)
+    });
+
+    bbox.corners()
+}
+
+// Receives the vertices emitted by the subdivision along with
+// the depth of recursion they have been emitted at
+//
+// The vertices are emitted by increasing abscissa, the ones of a segment
+// following each other
+pub(super) trait Vertices {
+    fn emit(&mut self, p: &XYNDC<f64>, iter: usize);
+
+    // Same as `emit` for a vertex at the abscissa `t` along the line, i.e.
+    // its longitude for a parallel and its latitude for a meridian
+    #[inline]
+    fn emit_at(&mut self, p: &XYNDC<f64>, _t: f64, iter: usize) {
+        self.emit(p, iter);
+    }
+
+    // Receives the abscissa where the line is clipped by the valid domain
+    // of the projection. Called before the vertices are emitted
+    fn clip_at(&mut self, _t: f64) {}
+
+    // Receives the projection of the point where the parallel is split
+    // because it crosses the zero meridian
+    #[cfg(any(test, feature = "debug_seam"))]
+    fn mark_seam(&mut self, _p: &XYNDC<f64>) {}
+}
+
+impl Vertices for Vec<[f32; 2]> {
+    #[inline]
+    fn emit(&mut self, p: &XYNDC<f64>, _iter: usize) {
+        self.push([p.x as f32, p.y as f32]);
+    }
+}
+
+#[derive(Default)]
+struct IndexedLines {
+    vertices: Vec<XYNDC<f64>>,
+    indices: Vec<u32>,
+}
+
+impl Vertices for IndexedLines {
+    fn emit(&mut self, p: &XYNDC<f64>, _iter: usize) {
+        let segment_start = self.indices.len() % 2 == 0;
+
+        match self.vertices.last() {
+            // The segment starts where the previous one ended
+            Some(last) if segment_start && last == p => {
+                self.indices.push((self.vertices.len() - 1) as u32);
+            }
+            _ => {
+                self.indices.push(self.vertices.len() as u32);
+                self.vertices.push(*p);
+            }
+        }
+    }
+}
+
+// The vertices along with their alpha, decreasing linearly to 0 over the last
+// `width` radians before the points where the line is clipped by the valid domain
+pub(super) struct FadedVertices {
+    vertices: Vec<([f32; 2], f32)>,
+    // Abscissas of the points where the line is clipped
+    clips: Vec<f64>,
+    width: f64,
+    // Angular distance covered per unit of abscissa, i.e. cos(lat) along a parallel
+    scale: f64,
+}
+
+impl FadedVertices {
+    pub(super) fn new(width: f64, scale: f64) -> Self {
+        Self {
+            vertices: vec![],
+            clips: vec![],
+            width,
+            scale,
+        }
+    }
+
+    pub(super) fn into_vertices(self) -> Vec<([f32; 2], f32)> {
+        self.vertices
+    }
+
+    fn alpha(&self, t: f64) -> f32 {
+        if self.width <= 0.0 {
+            return 1.0;
+        }
+
+        self.clips
+            .iter()
+            .map(|clip| ((t - clip).abs() * self.scale / self.width).min(1.0))
+            .fold(1.0, f64::min) as f32
+    }
+}
+
+impl Vertices for FadedVertices {
+    fn emit(&mut self, p: &XYNDC<f64>, _iter: usize) {
+        self.vertices.push(([p.x as f32, p.y as f32], 1.0));
+    }
+
+    fn emit_at(&mut self, p: &XYNDC<f64>, t: f64, _iter: usize) {
+        let alpha = self.alpha(t);
+        self.vertices.push(([p.x as f32, p.y as f32], alpha));
+    }
+
+    fn clip_at(&mut self, t: f64) {
+        self.clips.push(t);
+    }
+}
+
+// Only keeps the bounding box of the vertices
+#[derive(Default)]
+pub(super) struct BoundingBox {
+    corners: Option<(XYNDC<f64>, XYNDC<f64>)>,
+}
+
+impl BoundingBox {
+    pub(super) fn corners(&self) -> Option<(XYNDC<f64>, XYNDC<f64>)> {
+        self.corners
+    }
+}
+
+impl Vertices for BoundingBox {
+    fn emit(&mut self, p: &XYNDC<f64>, _iter: usize) {
+        self.corners = Some(match self.corners {
+            Some((min, max)) => (
+                XYNDC::new(min.x.min(p.x), min.y.min(p.y)),
+                XYNDC::new(max.x.max(p.x), max.y.max(p.y)),
+            ),
+            None => (*p, *p),
+        });
+    }
+}
+
+#[cfg(any(test, feature = "debug_subdivision"))]
+impl Vertices for Vec<([f32; 2], usize)> {
+    #[inline]
+    fn emit(&mut self, p: &XYNDC<f64>, iter: usize) {
+        self.push(([p.x as f32, p.y as f32], iter));
+    }
+}
+
+// Only keeps the points where the parallel is split at the zero meridian
+#[cfg(any(test, feature = "debug_seam"))]
+#[derive(Default)]
+struct Seams(Vec<XYNDC<f64>>);
+
+#[cfg(any(test, feature = "debug_seam"))]
+impl Vertices for Seams {
+    fn emit(&mut self, _p: &XYNDC<f64>, _iter: usize) {}
+
+    fn mark_seam(&mut self, p: &XYNDC<f64>) {
+        self.0.push(*p);
+    }
+}
+
+// Same as `project` but only returns the points where the parallel is split because it
+// crosses the zero meridian, to debug the handling of the seam
+#[cfg(feature = "debug_seam")]
+pub fn project_seams(lat: f64, lon1: f64, lon2: f64, camera: &CameraViewPort, projection: &ProjectionType) -> Vec<XYNDC<f64>> {
+    let mut seams = Seams::default();
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_with(&mut seams, lat, lon1, lon2, d_alpha, ndc_pixel_size(camera), &SubdivisionParams::default(), &|lonlat| {
+        crate::math::lonlat::proj(lonlat, projection, camera)
+    });
+
+    seams.0
+}
+
+// Size of a pixel in the NDC space
+pub(super) fn ndc_pixel_size(camera: &CameraViewPort) -> f64 {
+    2.0 / (camera.get_width().min(camera.get_height()) as f64)
+}
+
+// The parallel at `lat` from `lon1` to `lon2` projected with `proj` into `vertices`.
+//
+// A parallel at a pole is a point, as well as a parallel close enough to it for
+// all its vertices to hold in `px_size`. It is emitted as a single degenerated segment.
+pub(super) fn project_with<V, P>(vertices: &mut V, lat: f64, mut lon1: f64, lon2: f64, d_alpha: f64, px_size: f64, params: &SubdivisionParams, proj: &P)
+where
+    V: Vertices,
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    if HALF_PI - lat.abs() < POLE_EPS {
+        if let Some(p) = proj(&LonLatT::new(lon1.to_angle(), lat.to_angle())) {
+            vertices.emit(&p, 0);
+            vertices.emit(&p, 0);
+        }
+        return;
+    }
 
     let lon_len = crate::math::sph_geom::distance_from_two_lon(lon1, lon2);
+    if let Some(p) = collapsed_parallel(lat, lon1, lon_len, px_size, proj) {
+        vertices.emit(&p, 0);
+        vertices.emit(&p, 0);
+        return;
+    }
+
     let mut lon2 = lon1 + lon_len;
 
     // Can only cross the 0 meridian but not 0 and 180 ones
@@ -31,44 +488,82 @@ pub fn project(lat: f64, mut lon1: f64, lon2: f64, camera: &CameraViewPort, proj
         lon2 -= TWICE_PI;
         // lon1 is > PI because the lon len is <= PI
         lon1 -= TWICE_PI;
+
+        #[cfg(any(test, feature = "debug_seam"))]
+        if let Some(p) = proj(&LonLatT::new(0.0_f64.to_angle(), lat.to_angle())) {
+            vertices.mark_seam(&p);
+        }
     }
 
     // We know (lon1, lat) can be projected as it is a requirement of that method
-    let v1 = crate::math::lonlat::proj(&LonLatT::new(lon1.to_angle(), lat.to_angle()), projection, camera);
-    let v2 = crate::math::lonlat::proj(&LonLatT::new(lon2.to_angle(), lat.to_angle()), projection, camera);
+    let v1 = proj(&LonLatT::new(lon1.to_angle(), lat.to_angle()));
+    let v2 = proj(&LonLatT::new(lon2.to_angle(), lat.to_angle()));
 
     match (v1, v2) {
         (Some(_v1), Some(_v2)) => {
-            subdivide_multi(&mut vertices, lat, lon1, lon2, camera, projection);
+            subdivide_multi(vertices, lat, lon1, lon2, proj, params);
         },
         (None, Some(_v2)) => {
-            let (lon1, lon2) = sub_valid_domain(lat, lon2, lon1, projection, camera);
-            subdivide_multi(&mut vertices, lat, lon1, lon2, camera, projection);
+            let (lon1, lon2) = sub_valid_domain(lat, lon2, lon1, d_alpha, proj);
+            vertices.clip_at(lon1);
+            subdivide_multi(vertices, lat, lon1, lon2, proj, params);
         },
         (Some(_v1), None) => {
-            let (lon1, lon2) = sub_valid_domain(lat, lon1, lon2, projection, camera);
-            subdivide_multi(&mut vertices, lat, lon1, lon2, camera, projection);
+            let (lon1, lon2) = sub_valid_domain(lat, lon1, lon2, d_alpha, proj);
+            vertices.clip_at(lon2);
+            subdivide_multi(vertices, lat, lon1, lon2, proj, params);
         },
         (None, None) => {}
     }
+}
 
-    vertices
+// Close to a pole, the parallel projects into a tiny loop the subdivision does not
+// handle well. Returns the center of the parallel if it holds in `px_size`
+fn collapsed_parallel<P>(lat: f64, lon1: f64, lon_len: f64, px_size: f64, proj: &P) -> Option<XYNDC<f64>>
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    const NUM_SAMPLES: usize = 16;
+
+    let (mut min, mut max) = (XYNDC::new(f64::MAX, f64::MAX), XYNDC::new(f64::MIN, f64::MIN));
+    for i in 0..=NUM_SAMPLES {
+        let lon = lon1 + lon_len * (i as f64) / (NUM_SAMPLES as f64);
+        let p = proj(&LonLatT::new(lon.to_angle(), lat.to_angle()))?;
+
+        min = XYNDC::new(min.x.min(p.x), min.y.min(p.y));
+        max = XYNDC::new(max.x.max(p.x), max.y.max(p.y));
+
+        if max.x - min.x >= px_size || max.y - min.y >= px_size {
+            return None;
+        }
+    }
+
+    Some((min + max) * 0.5)
 }
 
 // Precondition:
 // * angular distance between valid_lon and invalid_lon is < PI
 // * valid_lon and invalid_lon are well defined, i.e. they can be between [-PI; PI] or [0, 2PI] depending
 //   whether they cross or not the zero meridian
-fn sub_valid_domain(lat: f64, valid_lon: f64, invalid_lon: f64, projection: &ProjectionType, camera: &CameraViewPort) -> (f64, f64) {
-    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+fn sub_valid_domain<P>(lat: f64, valid_lon: f64, invalid_lon: f64, d_alpha: f64, proj: &P) -> (f64, f64)
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    // Bound the number of dichotomy steps. When the invalid longitude is a single
+    // singular point (e.g. the antipode of the stereographic projection center),
+    // the interval may no longer shrink once it reaches the float precision
+    const MAX_DICHOTOMY_STEPS: usize = 64;
 
     let mut l_valid = valid_lon;
     let mut l_invalid = invalid_lon;
-    while (l_valid - l_invalid).abs() > d_alpha {
+    let mut step = 0;
+    while (l_valid - l_invalid).abs() > d_alpha && step < MAX_DICHOTOMY_STEPS {
+        step += 1;
+
         let lm = (l_valid + l_invalid)*0.5;
         // check whether is it defined or not
         let mid_lonlat = LonLatT::new(lm.to_angle(), lat.to_angle());
-        if let Some(_) = crate::math::lonlat::proj(&mid_lonlat, projection, camera) {
+        if proj(&mid_lonlat).is_some() {
             l_valid = lm;
         } else {
             l_invalid = lm;
@@ -83,44 +578,64 @@ fn sub_valid_domain(lat: f64, valid_lon: f64, invalid_lon: f64, projection: &Pro
     }
 }
 
-fn subdivide_multi(
-    vertices: &mut Vec<[f32; 2]>,
+fn subdivide_multi<V, P>(
+    vertices: &mut V,
     lat: f64,
 
     lon_s: f64,
     lon_e: f64, 
 
-    camera: &CameraViewPort,
-    projection: &ProjectionType,
-) {
-    let num_vertices = 5;
-    let dlon = (lon_e - lon_s) / (num_vertices as f64);
-    for i in 0..num_vertices {
+    proj: &P,
+    params: &SubdivisionParams,
+)
+where
+    V: Vertices,
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let num_arcs = num_initial_arcs(lon_s, lon_e, params);
+    let dlon = (lon_e - lon_s) / (num_arcs as f64);
+    for i in 0..num_arcs {
         let lon1 = lon_s + (i as f64) * dlon;
         let lon2 = lon1 + dlon;
 
-        subdivide(vertices, lat, lon1, lon2, camera, projection, 0);
+        subdivide(vertices, lat, lon1, lon2, proj, params, 0);
+    }
+}
+
+// Number of arcs the parallel between lon_s and lon_e is split into before
+// subdividing, proportional to its longitude span
+fn num_initial_arcs(lon_s: f64, lon_e: f64, params: &SubdivisionParams) -> usize {
+    let n = ((lon_e - lon_s).abs() / params.arc_lon.to_radians()).ceil();
+
+    if n.is_finite() {
+        (n as usize).clamp(1, params.max_num_arcs.max(1))
+    } else {
+        params.max_num_arcs.max(1)
     }
 }
 
-fn subdivide(
-    vertices: &mut Vec<[f32; 2]>,
+fn subdivide<V, P>(
+    vertices: &mut V,
     lat: f64,
 
     lon1: f64,
     lon2: f64,
 
-    camera: &CameraViewPort,
-    projection: &ProjectionType,
+    proj: &P,
+    params: &SubdivisionParams,
     iter: usize,
-) -> bool {
-    let p1 = crate::math::lonlat::proj(&LonLatT::new(lon1.to_angle(), lat.to_angle()), projection, camera);
-    let p2 = crate::math::lonlat::proj(&LonLatT::new(lon2.to_angle(), lat.to_angle()), projection, camera);
+) -> bool
+where
+    V: Vertices,
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let p1 = proj(&LonLatT::new(lon1.to_angle(), lat.to_angle()));
+    let p2 = proj(&LonLatT::new(lon2.to_angle(), lat.to_angle()));
 
-    if iter < MAX_ITERATION {
+    if iter < params.max_iter {
         // Project them. We are always facing the camera
         let lon0 = (lon1 + lon2)*0.5;
-        let pm = crate::math::lonlat::proj(&LonLatT::new(lon0.to_angle(), lat.to_angle()), projection, camera);
+        let pm = proj(&LonLatT::new(lon0.to_angle(), lat.to_angle()));
     
         match (p1, pm, p2) {
             (Some(p1), Some(pm), Some(p2)) => {
@@ -133,19 +648,19 @@ fn subdivide(
                 let dot_abbc = crate::math::vector::dot(&ab_u, &bc_u);
                 let theta_abbc = dot_abbc.acos();
 
-                if theta_abbc.abs() < 5.0_f64.to_radians() {
+                if theta_abbc.abs() < params.max_angle.to_radians() {
                     let det_abbc = crate::math::vector::det(&ab_u, &bc_u);
 
-                    if det_abbc.abs() < 1e-2 {
-                        vertices.push([p1.x as f32, p1.y as f32]);
-                        vertices.push([p2.x as f32, p2.y as f32]);
+                    if det_abbc.abs() < params.colinear_eps {
+                        vertices.emit_at(&p1, lon1, iter);
+                        vertices.emit_at(&p2, lon2, iter);
                     } else {
                         // not colinear but enough to stop
-                        vertices.push([p1.x as f32, p1.y as f32]);
-                        vertices.push([pm.x as f32, pm.y as f32]);
+                        vertices.emit_at(&p1, lon1, iter);
+                        vertices.emit_at(&pm, lon0, iter);
         
-                        vertices.push([pm.x as f32, pm.y as f32]);
-                        vertices.push([p2.x as f32, p2.y as f32]);
+                        vertices.emit_at(&pm, lon0, iter);
+                        vertices.emit_at(&p2, lon2, iter);
                     }
                 } else {
                     let ab_l = ab.magnitude2();
@@ -153,13 +668,13 @@ fn subdivide(
 
                     let r = (ab_l - bc_l).abs() / (ab_l + bc_l);
 
-                    if r > 0.8 {
+                    if r > params.short_ratio {
                         if ab_l < bc_l {
-                            vertices.push([p1.x as f32, p1.y as f32]);
-                            vertices.push([pm.x as f32, pm.y as f32]);
+                            vertices.emit_at(&p1, lon1, iter);
+                            vertices.emit_at(&pm, lon0, iter);
                         } else {
-                            vertices.push([pm.x as f32, pm.y as f32]);
-                            vertices.push([p2.x as f32, p2.y as f32]);
+                            vertices.emit_at(&pm, lon0, iter);
+                            vertices.emit_at(&p2, lon2, iter);
                         }
                     } else {
                         // Subdivide a->b and b->c
@@ -168,12 +683,12 @@ fn subdivide(
                             lat,
                             lon1,
                             lon0,
-                            camera,
-                            projection,
+                            proj,
+                            params,
                             iter + 1
                         ) {
-                            vertices.push([p1.x as f32, p1.y as f32]);
-                            vertices.push([pm.x as f32, pm.y as f32]);
+                            vertices.emit_at(&p1, lon1, iter);
+                            vertices.emit_at(&pm, lon0, iter);
                         }
 
                         if !subdivide(
@@ -181,12 +696,12 @@ fn subdivide(
                             lat,
                             lon0,
                             lon2,
-                            camera,
-                            projection,
+                            proj,
+                            params,
                             iter + 1
                         ) {
-                            vertices.push([pm.x as f32, pm.y as f32]);
-                            vertices.push([p2.x as f32, p2.y as f32]);
+                            vertices.emit_at(&pm, lon0, iter);
+                            vertices.emit_at(&p2, lon2, iter);
                         }
                     }
                 }
@@ -197,4 +712,318 @@ fn subdivide(
     } else {
         false
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::projection::clip_space_proj;
+
+    #[test]
+    fn parallels_close_to_the_pole_collapse() {
+
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        let proj = clip_space_proj(&projection);
+        // a screen of 1000 pixels
+        let px_size = 2e-3;
+
+        let lat = 89.99_f64.to_radians();
+        let mut vertices: Vec<[f32; 2]> = vec![];
+        project_with(&mut vertices, lat, 0.0, PI, 1e-3, px_size, &SubdivisionParams::default(), &proj);
+        assert_eq!(vertices.len(), 2);
+        assert_eq!(vertices[0], vertices[1]);
+
+        let pole = proj(&LonLatT::new(0.0_f64.to_angle(), HALF_PI.to_angle())).unwrap();
+        assert!((vertices[0][0] as f64 - pole.x).abs() < px_size);
+        assert!((vertices[0][1] as f64 - pole.y).abs() < px_size);
+
+        // At the pole
+        let mut vertices: Vec<[f32; 2]> = vec![];
+        project_with(&mut vertices, -HALF_PI, 0.0, PI, 1e-3, 0.0, &SubdivisionParams::default(), &proj);
+        assert_eq!(vertices.len(), 2);
+
+        // A parallel spanning more than a pixel is subdivided
+        let mut vertices: Vec<[f32; 2]> = vec![];
+        project_with(&mut vertices, 80.0_f64.to_radians(), 0.0, PI, 1e-3, px_size, &SubdivisionParams::default(), &proj);
+        assert!(vertices.len() > 2);
+    }
+
+    #[test]
+    fn hammer_parallels_stay_in_the_ellipse() {
+        use crate::math::projection::hammer::Hammer;
+
+        let projection = ProjectionType::Hammer(Hammer);
+        let proj = clip_space_proj(&projection);
+
+        for lat in [-60.0_f64, 0.0, 45.0] {
+            let mut vertices: Vec<[f32; 2]> = vec![];
+            project_with(&mut vertices, lat.to_radians(), 0.0, PI - 1e-6, 1e-3, 2e-3, &SubdivisionParams::default(), &proj);
+
+            let r = |v: &[f32; 2]| (v[0] as f64).hypot(v[1] as f64);
+            assert!(vertices.iter().all(|v| r(v) <= 1.0 + 1e-6));
+            // The parallel ends on the boundary of the ellipse
+            assert!((r(vertices.last().unwrap()) - 1.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn initial_arcs_proportional_to_the_span() {
+        let params = SubdivisionParams::default();
+
+        let short = num_initial_arcs(0.0, 1.0_f64.to_radians(), &params);
+        let long = num_initial_arcs(0.0, 170.0_f64.to_radians(), &params);
+        assert_eq!(short, 1);
+        assert_eq!(long, 5);
+
+        // capped for the full parallel
+        assert_eq!(num_initial_arcs(0.0, TWICE_PI, &params), params.max_num_arcs);
+        assert_eq!(num_initial_arcs(1.0, 1.0, &params), 1);
+    }
+
+    #[test]
+    fn label_anchor_on_most_horizontal_segment() {
+        let vertices = [
+            [0.0, 0.0],
+            [0.1, 0.3],
+            [0.1, 0.3],
+            [0.5, 0.35],
+            [0.5, 0.35],
+            [0.6, 0.7],
+        ];
+
+        let anchor = label_anchor(&vertices).unwrap();
+        assert!((anchor.x - 0.3).abs() < 1e-6);
+        assert!((anchor.y - 0.325).abs() < 1e-6);
+    }
+
+    #[test]
+    fn label_value_in_another_frame() {
+        // The galactic center
+        let gal = LonLatT::new(ArcDeg(0.0).into(), ArcDeg(0.0).into());
+        let icrs = to_label_frame(&gal, CooSystem::GAL, CooSystem::ICRS);
+
+        assert!((icrs.lon().to_radians().to_degrees() - 266.40506655).abs() < 1e-4);
+        assert!((icrs.lat().to_radians().to_degrees() - -28.93616241).abs() < 1e-4);
+
+        // Same frame
+        let res = to_label_frame(&gal, CooSystem::GAL, CooSystem::GAL);
+        assert!(res.lon().to_radians().abs() < 1e-12);
+        assert!(res.lat().to_radians().abs() < 1e-12);
+    }
+
+    #[test]
+    fn label_anchor_fallbacks() {
+        // Clipped parallel
+        assert!(label_anchor(&[]).is_none());
+
+        // Degenerated segments
+        let anchor = label_anchor(&[[0.2, 0.4], [0.2, 0.4]]).unwrap();
+        assert!((anchor.x - 0.2).abs() < 1e-6);
+        assert!((anchor.y - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn subdivision_goes_deeper_near_the_limb() {
+
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        let proj = clip_space_proj(&projection);
+        let max_depth = |lat: f64, lon1: f64, lon2: f64| {
+            let mut vertices: Vec<([f32; 2], usize)> = vec![];
+            project_with(&mut vertices, lat.to_radians(), lon1.to_radians(), lon2.to_radians(), 1e-3, 0.0, &SubdivisionParams::default(), &proj);
+
+            vertices.iter().map(|(_, iter)| *iter).max()
+        };
+
+        // The equator is a straight line
+        let center = max_depth(0.0, 0.0, 60.0).unwrap();
+        let limb = max_depth(70.0, 100.0, 179.0).unwrap();
+        assert_eq!(center, 0);
+        assert!(limb > center);
+    }
+
+    #[test]
+    fn indexed_lines_draw_the_same_segments() {
+
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        let proj = clip_space_proj(&projection);
+        let params = SubdivisionParams::default();
+
+        // A parallel curved near the limb, then one clipped by the boundary of the projection
+        for (lat, lon1, lon2) in [(70.0_f64, 100.0_f64, 179.0_f64), (30.0, 120.0, 240.0)] {
+            let (lat, lon1, lon2) = (lat.to_radians(), lon1.to_radians(), lon2.to_radians());
+
+            let mut flat: Vec<[f32; 2]> = vec![];
+            project_with(&mut flat, lat, lon1, lon2, 1e-3, 0.0, &params, &proj);
+            let mut indexed = IndexedLines::default();
+            project_with(&mut indexed, lat, lon1, lon2, 1e-3, 0.0, &params, &proj);
+
+            let segments = indexed
+                .indices
+                .iter()
+                .map(|&i| {
+                    let v = indexed.vertices[i as usize];
+                    [v.x as f32, v.y as f32]
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(segments, flat);
+            assert!(indexed.vertices.len() < flat.len());
+        }
+    }
+
+    #[test]
+    fn tighter_colinear_eps_gives_more_vertices() {
+
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        let proj = clip_space_proj(&projection);
+        let num_vertices = |params: &SubdivisionParams| {
+            let mut vertices: Vec<[f32; 2]> = vec![];
+            project_with(&mut vertices, 20.0_f64.to_radians(), 0.0, 90.0_f64.to_radians(), 1e-3, 0.0, params, &proj);
+
+            vertices.len()
+        };
+
+        let default = SubdivisionParams::default();
+        let tight = SubdivisionParams {
+            colinear_eps: 1e-5,
+            ..default
+        };
+        assert!(num_vertices(&tight) > num_vertices(&default));
+    }
+
+    #[test]
+    fn samples_across_ra_0() {
+
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        let proj = clip_space_proj(&projection);
+        let lat = 20.0_f64.to_radians();
+
+        // A 90 degrees span from RA=330 to RA=60
+        let samples = sample_with(lat, 330.0_f64.to_radians(), 60.0_f64.to_radians(), 30.0_f64.to_radians(), &proj);
+        assert_eq!(samples.len(), 4);
+
+        for (sample, lon) in samples.iter().zip([330.0_f64, 0.0, 30.0, 60.0]) {
+            let expected = proj(&LonLatT::new(lon.to_radians().to_angle(), lat.to_angle())).unwrap();
+            let p = sample.unwrap();
+            assert!((p - expected).magnitude() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn one_seam_marker_across_ra_0() {
+
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        let proj = clip_space_proj(&projection);
+        let seams = |lon1: f64, lon2: f64| {
+            let mut seams = Seams::default();
+            project_with(&mut seams, 20.0_f64.to_radians(), lon1.to_radians(), lon2.to_radians(), 1e-3, 2e-3, &SubdivisionParams::default(), &proj);
+
+            seams.0
+        };
+
+        let markers = seams(350.0, 10.0);
+        assert_eq!(markers.len(), 1);
+        let at_ra_0 = proj(&LonLatT::new(0.0_f64.to_angle(), 20.0_f64.to_radians().to_angle())).unwrap();
+        assert!((markers[0] - at_ra_0).magnitude() < 1e-12);
+
+        // Not crossing the zero meridian
+        assert!(seams(10.0, 30.0).is_empty());
+    }
+
+    // The abscissas of the vertices emitted
+    struct Abscissas(Vec<f64>);
+
+    impl Vertices for Abscissas {
+        fn emit(&mut self, _p: &XYNDC<f64>, _iter: usize) {}
+
+        fn emit_at(&mut self, _p: &XYNDC<f64>, t: f64, _iter: usize) {
+            self.0.push(t);
+        }
+    }
+
+    #[test]
+    fn vertices_ordered_by_increasing_longitude() {
+
+        let projection = ProjectionType::Sin(mapproj::zenithal::sin::Sin);
+        let proj = clip_space_proj(&projection);
+        let lat = 30.0_f64.to_radians();
+        let lons = |lon1: f64, lon2: f64| {
+            let mut lons = Abscissas(vec![]);
+            project_with(&mut lons, lat, lon1.to_radians(), lon2.to_radians(), 1e-3, 0.0, &SubdivisionParams::default(), &proj);
+
+            lons.0
+        };
+
+        // Fully visible, across the seam and clipped by the limb at RA=+/-90
+        for (lon1, lon2) in [(10.0, 80.0), (300.0, 60.0), (30.0, 170.0), (200.0, 340.0)] {
+            let lons = lons(lon1, lon2);
+            assert!(lons.len() >= 2 && lons.len() % 2 == 0);
+            assert!(lons.windows(2).all(|w| w[0] <= w[1]), "{:?}", (lon1, lon2));
+
+            // Within each side of the seam, the longitudes in [0, 2pi[ increase too
+            let wrapped = lons.iter().map(|lon| lon.rem_euclid(TWICE_PI)).collect::<Vec<_>>();
+            let seams = wrapped.windows(2).filter(|w| w[0] > w[1]).count();
+            assert_eq!(seams, if lon1 > lon2 { 1 } else { 0 });
+        }
+    }
+
+    #[test]
+    fn alpha_lowers_near_the_clip_point() {
+
+        let projection = ProjectionType::Sin(mapproj::zenithal::sin::Sin);
+        let proj = clip_space_proj(&projection);
+        let lat = 30.0_f64.to_radians();
+
+        // Going behind the limb at RA=90, faded over the last 10 degrees
+        let mut vertices = FadedVertices::new(10.0_f64.to_radians(), lat.cos());
+        project_with(&mut vertices, lat, 0.0, 120.0_f64.to_radians(), 1e-3, 0.0, &SubdivisionParams::default(), &proj);
+        let vertices = vertices.into_vertices();
+        assert!(!vertices.is_empty());
+
+        // The vertices are ordered from RA=0 to the limb
+        let (first, last) = (vertices.first().unwrap(), vertices.last().unwrap());
+        assert_eq!(first.1, 1.0);
+        assert!(last.1 < 0.05);
+        let interior = vertices.iter().filter(|(v, _)| v[0] < 0.5).map(|(_, a)| *a);
+        let limb = vertices.iter().filter(|(v, _)| v[0] > 0.86).map(|(_, a)| *a);
+        assert!(limb.fold(0.0, f32::max) < interior.fold(1.0, f32::min));
+
+        // Nothing faded without any clipping
+        let mut vertices = FadedVertices::new(10.0_f64.to_radians(), lat.cos());
+        project_with(&mut vertices, lat, 0.0, 60.0_f64.to_radians(), 1e-3, 0.0, &SubdivisionParams::default(), &proj);
+        assert!(vertices.into_vertices().iter().all(|(_, a)| *a == 1.0));
+    }
+
+    #[test]
+    fn bbox_bounds_a_centered_parallel() {
+
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        let proj = clip_space_proj(&projection);
+        let params = SubdivisionParams::default();
+        let (lat, lon1, lon2) = (30.0_f64.to_radians(), 330.0_f64.to_radians(), 30.0_f64.to_radians());
+
+        let mut vertices: Vec<[f32; 2]> = vec![];
+        project_with(&mut vertices, lat, lon1, lon2, 1e-3, 0.0, &params, &proj);
+        let mut bbox = BoundingBox::default();
+        project_with(&mut bbox, lat, lon1, lon2, 1e-3, 0.0, &params, &proj);
+        let (min, max) = bbox.corners().unwrap();
+
+        // Tight, i.e. every side of the box is reached by a vertex
+        let eps = 1e-6;
+        assert!(vertices.iter().all(|v| {
+            let (x, y) = (v[0] as f64, v[1] as f64);
+            x >= min.x - eps && x <= max.x + eps && y >= min.y - eps && y <= max.y + eps
+        }));
+        assert!(vertices.iter().any(|v| (v[0] as f64 - min.x).abs() < eps));
+        assert!(vertices.iter().any(|v| (v[0] as f64 - max.x).abs() < eps));
+        assert!(vertices.iter().any(|v| (v[1] as f64 - min.y).abs() < eps));
+        assert!(vertices.iter().any(|v| (v[1] as f64 - max.y).abs() < eps));
+        // Symmetric about the central meridian
+        assert!((min.x + max.x).abs() < eps);
+
+        // Nothing projected
+        let mut bbox = BoundingBox::default();
+        project_with(&mut bbox, lat, lon1, lon2, 1e-3, 0.0, &params, &|_: &LonLatT<f64>| None);
+        assert!(bbox.corners().is_none());
+    }
+}
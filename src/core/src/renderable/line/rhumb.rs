@@ -0,0 +1,150 @@
+use crate::CameraViewPort;
+use crate::ProjectionType;
+
+use crate::coo_space::XYNDC;
+use crate::coo_space::XYZModel;
+use crate::math::angle::ToAngle;
+use crate::math::{HALF_PI, PI, TWICE_PI};
+
+use crate::LonLatT;
+
+use super::great_circle;
+
+// A loxodrome reaches a pole only after an infinity of turns. The latitudes
+// are clamped to that value so that the mercator latitude stays finite
+const MAX_LAT: f64 = 89.99 * PI / 180.0;
+
+// A loxodrome is a straight line in the mercator coordinates (lon, psi) where
+// psi = ln(tan(pi/4 + lat/2)):
+//   lon(t) = lon1 + t*dlon and psi(t) = psi1 + t*dpsi with t in [0; 1]
+struct Loxodrome {
+    lon1: f64,
+    dlon: f64,
+    psi1: f64,
+    dpsi: f64,
+    // approximative angular length
+    len: f64,
+}
+
+impl Loxodrome {
+    fn new(p1: &LonLatT<f64>, p2: &LonLatT<f64>) -> Self {
+        let lat1 = p1.lat().to_radians().max(-MAX_LAT).min(MAX_LAT);
+        let lat2 = p2.lat().to_radians().max(-MAX_LAT).min(MAX_LAT);
+
+        let lon1 = p1.lon().to_radians();
+        // Take the shortest way in longitude, between ]-PI; PI]
+        let mut dlon = (p2.lon().to_radians() - lon1) % TWICE_PI;
+        if dlon > PI {
+            dlon -= TWICE_PI;
+        } else if dlon <= -PI {
+            dlon += TWICE_PI;
+        }
+
+        let psi1 = mercator_lat(lat1);
+        let dpsi = mercator_lat(lat2) - psi1;
+
+        let dlat = lat2 - lat1;
+        let dx = dlon * (0.5 * (lat1 + lat2)).cos();
+        let len = (dlat * dlat + dx * dx).sqrt();
+
+        Self {
+            lon1,
+            dlon,
+            psi1,
+            dpsi,
+            len,
+        }
+    }
+
+    fn at(&self, t: f64) -> XYZModel<f64> {
+        let lon = self.lon1 + t * self.dlon;
+        let lat = 2.0 * (self.psi1 + t * self.dpsi).exp().atan() - HALF_PI;
+
+        LonLatT::new(lon.to_angle(), lat.to_angle()).vector()
+    }
+}
+
+#[inline]
+fn mercator_lat(lat: f64) -> f64 {
+    (0.25 * PI + 0.5 * lat).tan().ln()
+}
+
+// Project the line of constant bearing between two sky positions
+//
+// * Remark
+// The shortest way in longitude is taken so that the line never does more than
+// half a turn around the poles. The positions closer to a pole than MAX_LAT are
+// moved to MAX_LAT: a loxodrome spirals endlessly around the pole it heads to.
+//
+// * Returns
+// A list of lines vertices
+pub fn project(
+    p1: LonLatT<f64>,
+    p2: LonLatT<f64>,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Vec<XYNDC<f64>> {
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_with(&p1, &p2, d_alpha, &|v| {
+        projection.model_to_normalized_device_space(&v.extend(1.0), camera)
+    })
+}
+
+// The loxodrome from `p1` to `p2` projected with `proj`, as a curve of the sphere
+fn project_with<P>(p1: &LonLatT<f64>, p2: &LonLatT<f64>, d_alpha: f64, proj: &P) -> Vec<XYNDC<f64>>
+where
+    P: Fn(&XYZModel<f64>) -> Option<XYNDC<f64>>,
+{
+    let loxodrome = Loxodrome::new(p1, p2);
+    if loxodrome.len == 0.0 {
+        return vec![];
+    }
+    // the dichotomy precision along the line
+    let dt = d_alpha / loxodrome.len;
+
+    great_circle::project_curve(&|t| loxodrome.at(t), dt, proj)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::lonlat::LonLat;
+    use crate::math::projection::{clip_space_proj_xyz, Projection};
+
+    #[test]
+    fn constant_latitude_follows_the_parallel() {
+        let projection = ProjectionType::Mol(mapproj::pseudocyl::mol::Mol::new());
+        let proj = clip_space_proj_xyz(&projection);
+
+        let lat = 30.0_f64.to_radians();
+        let p1 = LonLatT::new((-60.0_f64).to_radians().to_angle(), lat.to_angle());
+        let p2 = LonLatT::new(60.0_f64.to_radians().to_angle(), lat.to_angle());
+
+        let vertices = project_with(&p1, &p2, 1e-3, &proj);
+        assert!(!vertices.is_empty());
+
+        // The endpoints are the ones of the parallel
+        let v1 = proj(&p1.vector()).unwrap();
+        let v2 = proj(&p2.vector()).unwrap();
+        assert!((vertices[0] - v1).x.abs() < 1e-9 && (vertices[0] - v1).y.abs() < 1e-9);
+        let last = vertices[vertices.len() - 1];
+        assert!((last - v2).x.abs() < 1e-9 && (last - v2).y.abs() < 1e-9);
+
+        // and every vertex lies on it
+        for v in &vertices {
+            let lonlat = projection.clip_to_world_space(v).unwrap().lonlat();
+            assert!((lonlat.lat().to_radians() - lat).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn shortest_longitude_span() {
+        let p1 = LonLatT::new(170.0_f64.to_radians().to_angle(), 0.0_f64.to_angle());
+        let p2 = LonLatT::new((-170.0_f64).to_radians().to_angle(), 89.999_f64.to_radians().to_angle());
+
+        let loxodrome = Loxodrome::new(&p1, &p2);
+        assert!((loxodrome.dlon - 20.0_f64.to_radians()).abs() < 1e-9);
+        assert!(loxodrome.dpsi.is_finite());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::CameraViewPort;
+use crate::ProjectionType;
+use cgmath::Vector3;
+
+use cgmath::InnerSpace;
+
+use crate::coo_space::XYNDC;
+use crate::coo_space::XYZModel;
+use crate::math::angle::Angle;
+
+use crate::LonLatT;
+
+use super::great_circle;
+
+// The circle of angular radius `radius` around the `center` direction:
+//   v(t) = cos(radius)*center + sin(radius)*(cos(2*pi*t)*e1 + sin(2*pi*t)*e2) with t in [0; 1]
+// where (e1, e2) is an orthonormal basis of the plane orthogonal to center
+struct Cone {
+    center: XYZModel<f64>,
+    e1: XYZModel<f64>,
+    e2: XYZModel<f64>,
+    cos_r: f64,
+    sin_r: f64,
+}
+
+impl Cone {
+    fn new(center: XYZModel<f64>, radius: f64) -> Self {
+        // Take the axis the less aligned with the center to build the basis
+        let axis = if center.y.abs() < 0.9 {
+            Vector3::new(0.0, 1.0, 0.0)
+        } else {
+            Vector3::new(1.0, 0.0, 0.0)
+        };
+
+        let e1 = center.cross(axis).normalize();
+        let e2 = center.cross(e1);
+
+        Self {
+            center,
+            e1,
+            e2,
+            cos_r: radius.cos(),
+            sin_r: radius.sin(),
+        }
+    }
+
+    fn at(&self, t: f64) -> XYZModel<f64> {
+        let phi = t * crate::math::TWICE_PI;
+        self.center * self.cos_r + (self.e1 * phi.cos() + self.e2 * phi.sin()) * self.sin_r
+    }
+}
+
+// Project the circle of angular radius `radius` around `center`
+//
+// * Remark
+// The loop is closed, i.e. the last vertex equals the first one. If part of the
+// circle crosses the boundary of the projection, it is clipped there
+//
+// * Returns
+// A list of lines vertices
+pub fn project(
+    center: LonLatT<f64>,
+    radius: Angle<f64>,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Vec<XYNDC<f64>> {
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_with(center.vector(), radius.to_radians(), d_alpha, &|v| {
+        projection.model_to_normalized_device_space(&v.extend(1.0), camera)
+    })
+}
+
+// The circle of angular `radius` around `center` projected with `proj`.
+// A null radius gives no vertices
+fn project_with<P>(center: XYZModel<f64>, radius: f64, d_alpha: f64, proj: &P) -> Vec<XYNDC<f64>>
+where
+    P: Fn(&XYZModel<f64>) -> Option<XYNDC<f64>>,
+{
+    let cone = Cone::new(center, radius);
+
+    let circumference = crate::math::TWICE_PI * cone.sin_r.abs();
+    if circumference == 0.0 {
+        return vec![];
+    }
+    // the dichotomy precision along the circle
+    let dt = d_alpha / circumference;
+
+    great_circle::project_curve(&|t| cone.at(t), dt, proj)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::angle::ToAngle;
+    use crate::math::projection::clip_space_proj_xyz;
+
+    #[test]
+    fn small_circle_at_the_center_is_regular() {
+        let projection = ProjectionType::Sin(mapproj::zenithal::sin::Sin);
+        let proj = clip_space_proj_xyz(&projection);
+
+        let radius = 1.0_f64.to_radians();
+        let center: XYZModel<f64> = LonLatT::new(0.0_f64.to_angle(), 0.0_f64.to_angle()).vector();
+        let c = proj(&center).unwrap();
+
+        // Screen radius expected
+        let border: XYZModel<f64> = LonLatT::new(0.0_f64.to_angle(), radius.to_angle()).vector();
+        let expected = (proj(&border).unwrap() - c).magnitude();
+
+        let vertices = project_with(center, radius, 1e-3, &proj);
+        assert!(vertices.len() >= 8);
+
+        for v in &vertices {
+            let r = (*v - c).magnitude();
+            assert!((r - expected).abs() < 1e-3 * expected);
+        }
+
+        // the loop is closed
+        let first = vertices.first().unwrap();
+        let last = vertices.last().unwrap();
+        assert!((*first - *last).magnitude() < 1e-9);
+    }
+}
@@ -0,0 +1,204 @@
+use cgmath::InnerSpace;
+use cgmath::Vector2;
+
+use crate::coo_space::XYNDC;
+
+// The miter is replaced by a bevel when it would go farther than
+// MITER_LIMIT times the half width from the corner
+const MITER_LIMIT: f64 = 4.0;
+// Max angle between two vertices of a round join
+const ROUND_JOIN_STEP: f64 = std::f64::consts::PI / 8.0;
+// Segments shorter than this, in pixels, are discarded
+const MIN_SEGMENT_LENGTH_PX: f64 = 1e-6;
+
+/// How two consecutive segments of a thick line are joined
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineJoin {
+    Miter,
+    Bevel,
+    Round,
+}
+
+/// A vertex of the triangle strip of a thick line
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StrokeVertex {
+    pub ndc_pos: [f32; 2],
+    /// Signed distance to the center of the line, -1 and 1 being the two edges.
+    /// It is interpolated by the fragment shader to fade the edges
+    pub edge: f32,
+}
+
+/// Expand a polyline into the triangle strip of a line of constant screen width
+///
+/// * Remark
+/// Corners too sharp to be mitered, e.g. a line going back on itself, are beveled
+/// so that no spike is drawn. Consecutive duplicated vertices are ignored.
+///
+/// # Arguments
+///
+/// * ``polyline`` - The vertices of the line, in the NDC space
+/// * ``width`` - The width of the line in pixels
+/// * ``join`` - How the segments are joined
+/// * ``screen_size`` - The size of the screen in pixels
+pub fn stroke(
+    polyline: &[XYNDC<f64>],
+    width: f32,
+    join: LineJoin,
+    screen_size: &Vector2<f32>,
+) -> Vec<StrokeVertex> {
+    // Work in pixels so that the width is the same whatever the direction
+    let (sx, sy) = (0.5 * screen_size.x as f64, 0.5 * screen_size.y as f64);
+
+    let mut points: Vec<Vector2<f64>> = Vec::with_capacity(polyline.len());
+    for p in polyline {
+        let p = Vector2::new(p.x * sx, p.y * sy);
+        if points
+            .last()
+            .map(|last| (p - *last).magnitude() > MIN_SEGMENT_LENGTH_PX)
+            .unwrap_or(true)
+        {
+            points.push(p);
+        }
+    }
+
+    if points.len() < 2 {
+        return vec![];
+    }
+
+    let half_width = 0.5 * (width as f64);
+    let mut strip = Vec::with_capacity(2 * points.len());
+    let mut push = |p: Vector2<f64>, n: Vector2<f64>| {
+        let (a, b) = (p + n * half_width, p - n * half_width);
+
+        strip.push(StrokeVertex {
+            ndc_pos: [(a.x / sx) as f32, (a.y / sy) as f32],
+            edge: 1.0,
+        });
+        strip.push(StrokeVertex {
+            ndc_pos: [(b.x / sx) as f32, (b.y / sy) as f32],
+            edge: -1.0,
+        });
+    };
+
+    let normals = points
+        .windows(2)
+        .map(|s| {
+            let d = (s[1] - s[0]).normalize();
+            Vector2::new(-d.y, d.x)
+        })
+        .collect::<Vec<_>>();
+
+    push(points[0], normals[0]);
+    for (i, n) in normals.windows(2).enumerate() {
+        let (n1, n2) = (n[0], n[1]);
+        let p = points[i + 1];
+
+        let m = n1 + n2;
+        // cosine of the angle between the miter and the normals
+        let cos_m = if m.magnitude2() > 0.0 {
+            m.normalize().dot(n1)
+        } else {
+            0.0
+        };
+
+        match join {
+            LineJoin::Miter if cos_m > 1.0 / MITER_LIMIT => {
+                push(p, m.normalize() / cos_m);
+            }
+            LineJoin::Round => {
+                // Pivot the normal around the corner
+                let theta = (n1.x * n2.y - n1.y * n2.x).atan2(n1.dot(n2));
+                let num_steps = ((theta.abs() / ROUND_JOIN_STEP).ceil() as usize).max(1);
+
+                for k in 0..=num_steps {
+                    let (s, c) = (theta * (k as f64) / (num_steps as f64)).sin_cos();
+                    push(p, Vector2::new(c * n1.x - s * n1.y, s * n1.x + c * n1.y));
+                }
+            }
+            _ => {
+                // Bevel
+                push(p, n1);
+                push(p, n2);
+            }
+        }
+    }
+    push(points[points.len() - 1], normals[normals.len() - 1]);
+
+    strip
+}
+
+/// Group segment pairs, as returned by the line projections, into polylines
+///
+/// A new polyline is started wherever a segment does not start where the
+/// previous one ended, e.g. where a line has been split at the boundary of
+/// the projection. Joining across these splits would draw spurious strokes.
+pub fn polylines(segments: &[XYNDC<f64>]) -> Vec<Vec<XYNDC<f64>>> {
+    const EPS: f64 = 1e-9;
+
+    let mut lines: Vec<Vec<XYNDC<f64>>> = vec![];
+    for s in segments.chunks_exact(2) {
+        match lines.last_mut() {
+            Some(line) if (line[line.len() - 1] - s[0]).magnitude2() < EPS * EPS => {
+                line.push(s[1]);
+            }
+            _ => lines.push(vec![s[0], s[1]]),
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn horizontal_line_gives_a_quad_of_the_line_width() {
+        let screen_size = Vector2::new(800.0, 600.0);
+        let width = 4.0;
+
+        let line = [XYNDC::new(-0.5, 0.1), XYNDC::new(0.0, 0.1), XYNDC::new(0.5, 0.1)];
+        for join in [LineJoin::Miter, LineJoin::Bevel, LineJoin::Round] {
+            let strip = stroke(&line, width, join, &screen_size);
+            assert!(strip.len() >= 4);
+
+            for v in &strip {
+                let dy_px = (v.ndc_pos[1] - 0.1) * 0.5 * screen_size.y;
+                assert!((dy_px - 0.5 * width * v.edge).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn sharp_corners_are_beveled() {
+        let screen_size = Vector2::new(800.0, 600.0);
+        let width = 4.0;
+
+        // The line goes back on itself
+        let line = [XYNDC::new(-0.5, 0.0), XYNDC::new(0.5, 0.0), XYNDC::new(-0.5, 1e-4)];
+        let strip = stroke(&line, width, LineJoin::Miter, &screen_size);
+
+        for v in &strip {
+            let x_px = v.ndc_pos[0] * 0.5 * screen_size.x;
+            assert!(x_px <= 0.5 * 400.0 + width);
+        }
+    }
+
+    #[test]
+    fn polylines_are_split_where_disconnected() {
+        let segments = [
+            XYNDC::new(0.0, 0.0),
+            XYNDC::new(0.1, 0.0),
+            XYNDC::new(0.1, 0.0),
+            XYNDC::new(0.2, 0.0),
+            XYNDC::new(0.5, 0.5),
+            XYNDC::new(0.6, 0.5),
+        ];
+
+        let lines = polylines(&segments);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), 3);
+        assert_eq!(lines[1].len(), 2);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::CameraViewPort;
+use crate::ProjectionType;
+
+use cgmath::InnerSpace;
+
+use crate::coo_space::XYNDC;
+use crate::coo_space::XYZModel;
+
+use crate::LonLatT;
+
+use super::great_circle::{self, Arc};
+
+// Number of bisections done to locate a discontinuity of the projection
+const MAX_BISECTION: usize = 30;
+
+// Project an ephemeris track, i.e. the path of a moving object
+//
+// * Remark
+// The samples must be sorted by time. Two consecutive samples are joined by
+// the great circle arc between them. The arcs having a sample that cannot be
+// projected are skipped, a gap being left in the track. An arc of the track
+// crossing the boundary of the projection, e.g. at the opposite of its center,
+// is split there
+//
+// * Returns
+// A list of lines vertices
+pub fn project(
+    points: &[(f64, LonLatT<f64>)],
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Vec<XYNDC<f64>> {
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+
+    project_with(points, d_alpha, &|v| {
+        projection.model_to_normalized_device_space(&v.extend(1.0), camera)
+    })
+}
+
+// The arcs between consecutive points of the track projected with `proj`.
+// Those having an end out of the projection are skipped
+fn project_with<P>(points: &[(f64, LonLatT<f64>)], d_alpha: f64, proj: &P) -> Vec<XYNDC<f64>>
+where
+    P: Fn(&XYZModel<f64>) -> Option<XYNDC<f64>>,
+{
+    let mut vertices = vec![];
+
+    for w in points.windows(2) {
+        let (v1, v2): (XYZModel<f64>, XYZModel<f64>) = (w[0].1.vector(), w[1].1.vector());
+        if proj(&v1).is_none() || proj(&v2).is_none() {
+            continue;
+        }
+
+        let arc = Arc::new(v1, v2);
+        if arc.omega == 0.0 {
+            continue;
+        }
+        // the dichotomy precision along the arc
+        let dt = d_alpha / arc.omega;
+
+        if let Some((t1, t2)) = find_discontinuity(&|t| arc.at(t), proj) {
+            // Project the two sides of the discontinuity separately
+            vertices.extend(great_circle::project_curve(&|t| arc.at(t * t1), dt / t1, proj));
+            vertices.extend(great_circle::project_curve(
+                &|t| arc.at(t2 + t * (1.0 - t2)),
+                dt / (1.0 - t2),
+                proj,
+            ));
+        } else {
+            vertices.extend(great_circle::project_curve(&|t| arc.at(t), dt, proj));
+        }
+    }
+
+    vertices
+}
+
+// Look for a jump of the projection along the curve parametrized by t in [0; 1]
+//
+// The half of the curve spanning the most on the screen is bisected. Along a
+// continuous curve, that span vanishes whereas it stays as large as the jump
+// otherwise.
+//
+// * Returns
+// The parameters just before and after the jump, if any
+fn find_discontinuity<C, P>(curve: &C, proj: &P) -> Option<(f64, f64)>
+where
+    C: Fn(f64) -> XYZModel<f64>,
+    P: Fn(&XYZModel<f64>) -> Option<XYNDC<f64>>,
+{
+    let (mut t1, mut t2) = (0.0, 1.0);
+    let (mut p1, mut p2) = (proj(&curve(t1))?, proj(&curve(t2))?);
+
+    let span = (p2 - p1).magnitude();
+    if span == 0.0 {
+        return None;
+    }
+
+    for _ in 0..MAX_BISECTION {
+        let tm = (t1 + t2) * 0.5;
+        let pm = proj(&curve(tm))?;
+
+        if (pm - p1).magnitude2() > (p2 - pm).magnitude2() {
+            t2 = tm;
+            p2 = pm;
+        } else {
+            t1 = tm;
+            p1 = pm;
+        }
+    }
+
+    if (p2 - p1).magnitude() > 0.5 * span {
+        Some((t1, t2))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::angle::ToAngle;
+    use crate::math::projection::Projection;
+    use crate::renderable::line::stroke;
+
+    #[test]
+    fn track_straddling_ra_0_is_split() {
+        let projection = ProjectionType::Mol(mapproj::pseudocyl::mol::Mol::new());
+        // Project in the clip space, the center of the projection being (lon=180, lat=0)
+        // so that the boundary of the projection is the RA=0 meridian
+        let proj = |v: &XYZModel<f64>| {
+            projection.world_to_clip_space(&cgmath::Vector4::new(-v.x, v.y, -v.z, 1.0))
+        };
+
+        let lonlat = |lon: f64, lat: f64| LonLatT::new(lon.to_radians().to_angle(), lat.to_radians().to_angle());
+        let track = [
+            (0.0, lonlat(357.0, 10.0)),
+            (1.0, lonlat(358.5, 10.5)),
+            (2.0, lonlat(0.5, 11.0)),
+            (3.0, lonlat(2.0, 11.5)),
+        ];
+
+        let vertices = project_with(&track, 1e-3, &proj);
+        let polylines = stroke::polylines(&vertices);
+        assert_eq!(polylines.len(), 2);
+
+        // No horizontal streak across the projection
+        for s in vertices.chunks_exact(2) {
+            assert!((s[1] - s[0]).magnitude() < 0.1);
+        }
+
+        // Without crossing, the track is drawn in one piece
+        let polylines = stroke::polylines(&project_with(&track[..2], 1e-3, &proj));
+        assert_eq!(polylines.len(), 1);
+    }
+}
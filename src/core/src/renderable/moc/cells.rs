@@ -0,0 +1,209 @@
+use crate::CameraViewPort;
+use crate::ProjectionType;
+
+use crate::coo_space::XYNDC;
+use crate::healpix::coverage::HEALPixCoverage;
+use crate::math::angle::ToAngle;
+use crate::math::sph_geom::delta_lon;
+use crate::HEALPixCell;
+use crate::LonLatT;
+
+use al_api::color::{ColorRGB, ColorRGBA};
+use al_api::coo_system::CooSystem;
+use al_core::shader::{SendUniforms, ShaderBound};
+
+use moclib::moc::{RangeMOCIntoIterator, RangeMOCIterator};
+
+// Maximum number of segments a cell side is split into
+const MAX_SEGMENTS_BY_SIDE: u32 = 32;
+
+/// Color and opacity of the filled cells of a MOC
+#[derive(Clone, Copy, Debug)]
+pub struct CellsStyle {
+    pub color: ColorRGB,
+    pub opacity: f32,
+}
+
+impl SendUniforms for CellsStyle {
+    fn attach_uniforms<'a>(&self, shader: &'a ShaderBound<'a>) -> &'a ShaderBound<'a> {
+        let ColorRGB { r, g, b } = self.color;
+        let color = ColorRGBA {
+            r,
+            g,
+            b,
+            a: self.opacity,
+        };
+
+        shader.attach_uniform("u_color", &color)
+    }
+}
+
+/// Compute the union of a list of HEALPix cells
+///
+/// The 4 children of a cell are merged into it, recursively, so that the
+/// union is made of as few cells, hence edges, as possible. Cells covered
+/// by another one are dropped.
+///
+/// # Arguments
+///
+/// * ``cells`` - The (order, ipix) cells, in the nested scheme
+pub fn merge(cells: &[(u8, u64)]) -> Vec<HEALPixCell> {
+    let depth = match cells.iter().map(|(depth, _)| *depth).max() {
+        Some(depth) => depth,
+        None => return vec![],
+    };
+
+    let cells = cells
+        .iter()
+        .map(|(depth, idx)| HEALPixCell(*depth, *idx))
+        .collect::<Vec<_>>();
+    let moc = HEALPixCoverage::from_hpx_cells(depth, cells.iter(), Some(cells.len()));
+
+    (&moc.0)
+        .into_range_moc_iter()
+        .cells()
+        .map(|cell| HEALPixCell(cell.depth, cell.idx))
+        .collect()
+}
+
+/// Triangulate the union of a list of HEALPix cells
+///
+/// The cells are given in ICRS. The parts of the cells that cannot be projected
+/// are clipped, the limit being found by dichotomy along their sides.
+///
+/// # Returns
+///
+/// The vertices of the triangles, three by three
+pub fn project(cells: &[(u8, u64)], camera: &CameraViewPort, projection: &ProjectionType) -> Vec<XYNDC<f64>> {
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+    let coo_sys = camera.get_coo_system();
+
+    project_with(&merge(cells), d_alpha, &|lonlat| {
+        let xyzw = crate::coosys::apply_coo_system(CooSystem::ICRS, coo_sys, &lonlat.vector());
+        projection.model_to_normalized_device_space(&xyzw, camera)
+    })
+}
+
+// The triangles of the cells projected with `proj`, the sides of a cell being
+// split into segments of about `d_alpha`
+fn project_with<P>(cells: &[HEALPixCell], d_alpha: f64, proj: &P) -> Vec<XYNDC<f64>>
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let mut triangles = vec![];
+
+    for cell in cells {
+        // Approximative length of the side of a cell
+        let side = (crate::math::PI / 3.0).sqrt() / ((1_u64 << cell.depth()) as f64);
+        let n_segments = ((side / d_alpha).ceil() as u32).clamp(1, MAX_SEGMENTS_BY_SIDE);
+
+        let outline = outline(&cell.path_along_cell_edge(n_segments), d_alpha, proj);
+        if outline.len() < 3 {
+            continue;
+        }
+
+        // Fan around the center of the cell
+        let (lon, lat) = cell.center();
+        let center = proj(&LonLatT::new(lon.to_angle(), lat.to_angle())).unwrap_or_else(|| {
+            outline.iter().fold(XYNDC::new(0.0, 0.0), |c, p| c + *p) / (outline.len() as f64)
+        });
+
+        for (a, b) in outline.iter().zip(outline.iter().cycle().skip(1)) {
+            triangles.extend([center, *a, *b]);
+        }
+    }
+
+    triangles
+}
+
+// Project the closed path of a cell, replacing the parts that cannot be
+// projected by the limit of the valid domain along the path
+fn outline<P>(path: &[(f64, f64)], d_alpha: f64, proj: &P) -> Vec<XYNDC<f64>>
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let at = |&(lon, lat): &(f64, f64)| proj(&LonLatT::new(lon.to_angle(), lat.to_angle()));
+
+    let mut vertices = vec![];
+    for (a, b) in path.iter().zip(path.iter().cycle().skip(1)) {
+        match (at(a), at(b)) {
+            (Some(pa), Some(_)) => vertices.push(pa),
+            (Some(pa), None) => {
+                vertices.push(pa);
+                vertices.push(sub_valid_domain(a, b, d_alpha, proj));
+            }
+            (None, Some(_)) => vertices.push(sub_valid_domain(b, a, d_alpha, proj)),
+            (None, None) => {}
+        }
+    }
+
+    vertices
+}
+
+// Precondition:
+// * valid can be projected whereas invalid cannot
+fn sub_valid_domain<P>(valid: &(f64, f64), invalid: &(f64, f64), d_alpha: f64, proj: &P) -> XYNDC<f64>
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let dlon = delta_lon(valid.0, invalid.0);
+    let dlat = invalid.1 - valid.1;
+    let at = |t: f64| {
+        let lonlat = LonLatT::new((valid.0 + t * dlon).to_angle(), (valid.1 + t * dlat).to_angle());
+        proj(&lonlat)
+    };
+
+    let len = (dlon * valid.1.cos()).hypot(dlat);
+    let (mut t_valid, mut t_invalid) = (0.0, 1.0);
+    let mut p_valid = at(0.0).unwrap();
+    while (t_invalid - t_valid) * len > d_alpha {
+        let t = 0.5 * (t_valid + t_invalid);
+        if let Some(p) = at(t) {
+            t_valid = t;
+            p_valid = p;
+        } else {
+            t_invalid = t;
+        }
+    }
+
+    p_valid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::projection::clip_space_proj;
+
+    #[test]
+    fn adjacent_cells_are_merged() {
+        // The 4 children of the order 2 cell 5 and a cell of another parent
+        let cells = [(3, 20), (3, 21), (3, 22), (3, 23), (3, 24)];
+
+        assert_eq!(merge(&cells), vec![HEALPixCell(2, 5), HEALPixCell(3, 24)]);
+    }
+
+    #[test]
+    fn order_3_cell_is_a_quadrilateral() {
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        let proj = clip_space_proj(&projection);
+
+        let cell = HEALPixCell(3, 300);
+        // Big enough so that the sides are not subdivided
+        let triangles = project_with(&[cell], 1.0, &proj);
+        assert_eq!(triangles.len(), 3 * 4);
+
+        let corners = cell
+            .path_along_cell_edge(1)
+            .iter()
+            .map(|(lon, lat)| proj(&LonLatT::new(lon.to_angle(), lat.to_angle())).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(corners.len(), 4);
+
+        // The outer edges of the fan are the sides of the cell
+        for (i, t) in triangles.chunks_exact(3).enumerate() {
+            let (a, b) = (corners[i], corners[(i + 1) % 4]);
+            assert!((t[1] - a).x.abs() < 1e-9 && (t[1] - a).y.abs() < 1e-9);
+            assert!((t[2] - b).x.abs() < 1e-9 && (t[2] - b).y.abs() < 1e-9);
+        }
+    }
+}
@@ -24,6 +24,12 @@ impl MOCHierarchy {
         }
     }
 
+    pub fn set_cfg(&mut self, gl: &WebGlContext, cfg: &Cfg) {
+        for moc in &mut self.mocs {
+            moc.set_cfg(gl.clone(), cfg);
+        }
+    }
+
     pub fn select_moc_from_view(&mut self, camera: &mut CameraViewPort) -> &mut MOC {
         /*const MAX_NUM_CELLS_TO_DRAW: usize = 1500;
 
@@ -1,6 +1,7 @@
 mod graph;
 mod mode;
 
+pub mod cells;
 pub mod hierarchy;
 pub mod renderer;
 pub use renderer::MOCRenderer;
@@ -8,6 +9,7 @@ pub use renderer::MOCRenderer;
 use crate::camera::CameraViewPort;
 use crate::healpix::coverage::HEALPixCoverage;
 use crate::math::projection::ProjectionType;
+use crate::renderable::line::Precision;
 use crate::renderable::WebGl2RenderingContext;
 use crate::shader::ShaderManager;
 use al_api::moc::MOC as Cfg;
@@ -17,7 +19,7 @@ use wasm_bindgen::JsValue;
 use crate::WebGlContext;
 use al_core::VertexArrayObject;
 
-use al_api::color::ColorRGBA;
+use al_api::color::{ColorRGB, ColorRGBA};
 use al_api::coo_system::CooSystem;
 
 use moclib::elem::cell::Cell;
@@ -27,6 +29,8 @@ use moclib::moc::RangeMOCIterator;
 
 use crate::HEALPixCell;
 
+use cells::CellsStyle;
+
 use al_core::VecData;
 
 pub struct MOC {
@@ -43,7 +47,23 @@ impl MOC {
         let sky_fraction = moc.sky_fraction() as f32;
         let max_order = moc.depth_max();
 
-        let inner = [
+        let inner = Self::render_modes(gl, cfg);
+
+        Self {
+            inner,
+            max_order,
+            sky_fraction,
+            moc,
+        }
+    }
+
+    /// Draw the MOC with a new configuration, e.g. of another color
+    pub(super) fn set_cfg(&mut self, gl: WebGlContext, cfg: &Cfg) {
+        self.inner = Self::render_modes(gl, cfg);
+    }
+
+    fn render_modes(gl: WebGlContext, cfg: &Cfg) -> [Option<MOCIntern>; 3] {
+        [
             if cfg.perimeter {
                 // draw only perimeter
                 Some(MOCIntern::new(
@@ -57,13 +77,13 @@ impl MOC {
                 None
             },
             if cfg.filled {
-                // change color
-                let fill_color = cfg.fill_color;
-                // draw the edges
-                Some(MOCIntern::new(
-                    gl.clone(),
-                    RenderModeType::Filled { color: fill_color },
-                ))
+                // The opacity of the cells is the alpha of their color
+                let ColorRGBA { r, g, b, a } = cfg.fill_color;
+                let style = CellsStyle {
+                    color: ColorRGB { r, g, b },
+                    opacity: a,
+                };
+                Some(MOCIntern::new(gl.clone(), RenderModeType::Filled { style }))
             } else {
                 None
             },
@@ -78,14 +98,7 @@ impl MOC {
             } else {
                 None
             },
-        ];
-
-        Self {
-            inner,
-            max_order,
-            sky_fraction,
-            moc,
-        }
+        ]
     }
 
     /*pub(super) fn cell_indices_in_view(&mut self, camera: &mut CameraViewPort) {
@@ -156,7 +169,7 @@ struct MOCIntern {
 pub enum RenderModeType {
     Perimeter { thickness: f32, color: ColorRGBA },
     Edge { thickness: f32, color: ColorRGBA },
-    Filled { color: ColorRGBA },
+    Filled { style: CellsStyle },
 }
 impl MOCIntern {
     fn new(gl: WebGlContext, mode: RenderModeType) -> Self {
@@ -195,21 +208,16 @@ impl MOCIntern {
             }
             RenderModeType::Filled { .. } => {
                 let mut vao = VertexArrayObject::new(&gl);
-                let indices = vec![];
+                let ndc_pos = vec![];
                 vao.bind_for_update()
-                    // Store the cartesian position of the center of the source in the a instanced VBO
+                    // Store the vertices of the triangles of the cells projected in the NDC space
                     .add_array_buffer(
-                        "lonlat",
+                        "ndc_pos",
                         2 * std::mem::size_of::<f32>(),
                         &[2],
                         &[0],
                         WebGl2RenderingContext::DYNAMIC_DRAW,
-                        VecData::<f32>(&lonlat),
-                    )
-                    // Set the element buffer
-                    .add_element_buffer(
-                        WebGl2RenderingContext::DYNAMIC_DRAW,
-                        VecData::<u32>(&indices),
+                        VecData::<f32>(&ndc_pos),
                     )
                     // Unbind the buffer
                     .unbind();
@@ -390,34 +398,34 @@ impl MOCIntern {
 
                             if edges.get(moclib::moc::range::Ordinal::SE) {
                                 paths.extend([
-                                    v[0].0 as f32,
-                                    v[0].1 as f32,
-                                    v[1].0 as f32,
-                                    v[1].1 as f32,
+                                    v[0].0,
+                                    v[0].1,
+                                    v[1].0,
+                                    v[1].1,
                                 ]);
                             }
                             if edges.get(moclib::moc::range::Ordinal::NE) {
                                 paths.extend([
-                                    v[1].0 as f32,
-                                    v[1].1 as f32,
-                                    v[2].0 as f32,
-                                    v[2].1 as f32,
+                                    v[1].0,
+                                    v[1].1,
+                                    v[2].0,
+                                    v[2].1,
                                 ]);
                             }
                             if edges.get(moclib::moc::range::Ordinal::NW) {
                                 paths.extend([
-                                    v[2].0 as f32,
-                                    v[2].1 as f32,
-                                    v[3].0 as f32,
-                                    v[3].1 as f32,
+                                    v[2].0,
+                                    v[2].1,
+                                    v[3].0,
+                                    v[3].1,
                                 ]);
                             }
                             if edges.get(moclib::moc::range::Ordinal::SW) {
                                 paths.extend([
-                                    v[3].0 as f32,
-                                    v[3].1 as f32,
-                                    v[0].0 as f32,
-                                    v[0].1 as f32,
+                                    v[3].0,
+                                    v[3].1,
+                                    v[0].0,
+                                    v[0].1,
                                 ])
                             }
 
@@ -426,9 +434,74 @@ impl MOCIntern {
                     })
                     .flatten();
 
-                let mut buf: Vec<_> = vec![];
-                buf.extend(perimeter_vertices_iter);
+                let buf: Vec<_> = perimeter_vertices_iter.collect();
+                self.draw_lines(&buf, thickness, &color, camera, proj, shaders)?;
+            }
+            RenderModeType::Edge { thickness, color } => {
+                let buf: Vec<_> = self.compute_edge_paths_iter(moc, camera).collect();
+                self.draw_lines(&buf, thickness, &color, camera, proj, shaders)?;
+
+                /*rasterizer.add_stroke_paths(
+                    ,
+                    thickness,
+                    &color,
+                    &super::line::Style::None,
+                    CooSpace::LonLat,
+                );*/
+            }
+            RenderModeType::Filled { style } => {
+                // The union of the cells in view, clipped to the valid domain of the projection
+                let cells = moc
+                    .overlapped_by_iter(&camera.get_cov(CooSystem::ICRS))
+                    .cells()
+                    .map(|Cell { depth, idx }| (depth, idx))
+                    .collect::<Vec<_>>();
+                let ndc_pos = cells::project(&cells, camera, proj)
+                    .into_iter()
+                    .flat_map(|p| [p.x as f32, p.y as f32])
+                    .collect::<Vec<_>>();
+                let num_vertices = (ndc_pos.len() / 2) as i32;
+
+                self.vao.bind_for_update().update_array(
+                    "ndc_pos",
+                    WebGl2RenderingContext::DYNAMIC_DRAW,
+                    VecData(&ndc_pos),
+                );
+
+                self.gl.enable(WebGl2RenderingContext::BLEND);
+                // The winding of the triangles depends on the projection
+                self.gl.disable(WebGl2RenderingContext::CULL_FACE);
+
+                crate::shader::get_shader(&self.gl, shaders, "line_base.vert", "moc_base.frag")?
+                    .bind(&self.gl)
+                    .attach_uniforms_from(&style)
+                    .bind_vertex_array_object_ref(&self.vao)
+                    .draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, num_vertices);
+
+                self.gl.enable(WebGl2RenderingContext::CULL_FACE);
+                self.gl.disable(WebGl2RenderingContext::BLEND);
+            }
+        }
+        Ok(())
+        //});
+    }
 
+    // Draw the lines given as segment pairs of (lon, lat) in the ICRS frame
+    //
+    // Deep in the field, the positions are projected on the CPU in double precision
+    // so that the lines do not jitter. Otherwise the vertex shader does it
+    fn draw_lines(
+        &mut self,
+        lonlat: &[f64],
+        thickness: f32,
+        color: &ColorRGBA,
+        camera: &CameraViewPort,
+        proj: &ProjectionType,
+        shaders: &mut ShaderManager,
+    ) -> Result<(), JsValue> {
+        match camera.get_precision() {
+            Precision::Single => {
+                let buf: Vec<f32> = lonlat.iter().map(|&x| x as f32).collect();
                 self.vao.bind_for_update().update_instanced_array(
                     "lonlat",
                     WebGl2RenderingContext::DYNAMIC_DRAW,
@@ -450,7 +523,7 @@ impl MOCIntern {
                 .bind(&self.gl)
                 .attach_uniforms_from(camera)
                 .attach_uniform("u_2world", &icrs2world)
-                .attach_uniform("u_color", &color)
+                .attach_uniform("u_color", color)
                 .attach_uniform("u_width", &(camera.get_width()))
                 .attach_uniform("u_height", &(camera.get_height()))
                 .attach_uniform("u_thickness", &thickness)
@@ -462,11 +535,9 @@ impl MOCIntern {
                     num_instances as i32,
                 );
             }
-            RenderModeType::Edge { thickness, color } => {
-                let mut buf: Vec<_> = vec![];
-                buf.extend(self.compute_edge_paths_iter(moc, camera));
-                //let mut buf = self.compute_edge_paths_iter(moc, camera).collect();
-
+            Precision::Double => {
+                // The instanced buffer has the layout of the NDC line shader
+                let buf = crate::renderable::line::project_segments(lonlat, CooSystem::ICRS, camera, proj);
                 self.vao.bind_for_update().update_instanced_array(
                     "lonlat",
                     WebGl2RenderingContext::DYNAMIC_DRAW,
@@ -475,115 +546,34 @@ impl MOCIntern {
 
                 let num_instances = buf.len() / 4;
 
-                let icrs2view = CooSystem::ICRS.to(camera.get_coo_system());
-                let view2world = camera.get_m2w();
-                let icrs2world = view2world * icrs2view;
-
                 crate::shader::get_shader(
                     &self.gl,
                     shaders,
-                    "line_inst_lonlat.vert",
+                    "line_inst_ndc.vert",
                     "line_base.frag",
                 )?
                 .bind(&self.gl)
-                .attach_uniforms_from(camera)
-                .attach_uniform("u_2world", &icrs2world)
-                .attach_uniform("u_color", &color)
+                .attach_uniform("u_color", color)
                 .attach_uniform("u_width", &(camera.get_width()))
                 .attach_uniform("u_height", &(camera.get_height()))
                 .attach_uniform("u_thickness", &thickness)
-                .attach_uniform("u_proj", proj)
                 .bind_vertex_array_object_ref(&self.vao)
                 .draw_elements_instanced_with_i32(
                     WebGl2RenderingContext::TRIANGLES,
                     0,
                     num_instances as i32,
                 );
-
-                /*rasterizer.add_stroke_paths(
-                    ,
-                    thickness,
-                    &color,
-                    &super::line::Style::None,
-                    CooSpace::LonLat,
-                );*/
-            }
-            RenderModeType::Filled { color } => {
-                let mut off_idx = 0;
-                let mut indices: Vec<u32> = vec![];
-                let vertices = self
-                    .vertices_in_view(moc, camera)
-                    .map(|v| {
-                        let vertices = [
-                            v[0].0 as f32,
-                            v[0].1 as f32,
-                            v[1].0 as f32,
-                            v[1].1 as f32,
-                            v[2].0 as f32,
-                            v[2].1 as f32,
-                            v[3].0 as f32,
-                            v[3].1 as f32,
-                        ];
-
-                        indices.extend_from_slice(&[
-                            off_idx + 1,
-                            off_idx + 0,
-                            off_idx + 3,
-                            off_idx + 1,
-                            off_idx + 3,
-                            off_idx + 2,
-                        ]);
-
-                        off_idx += 4;
-
-                        vertices
-                    })
-                    .flatten()
-                    .collect();
-
-                let num_idx = indices.len() as i32;
-
-                self.vao
-                    .bind_for_update()
-                    .update_array(
-                        "lonlat",
-                        WebGl2RenderingContext::DYNAMIC_DRAW,
-                        VecData(&vertices),
-                    )
-                    .update_element_array(WebGl2RenderingContext::DYNAMIC_DRAW, VecData(&indices));
-
-                let icrs2view = CooSystem::ICRS.to(camera.get_coo_system());
-                let view2world = camera.get_m2w();
-                let icrs2world = view2world * icrs2view;
-
-                self.gl.enable(WebGl2RenderingContext::BLEND);
-
-                crate::shader::get_shader(&self.gl, shaders, "moc_base.vert", "moc_base.frag")?
-                    .bind(&self.gl)
-                    .attach_uniforms_from(camera)
-                    .attach_uniform("u_2world", &icrs2world)
-                    .attach_uniform("u_color", &color)
-                    .attach_uniform("u_proj", proj)
-                    .bind_vertex_array_object_ref(&self.vao)
-                    .draw_elements_with_i32(
-                        WebGl2RenderingContext::TRIANGLES,
-                        Some(num_idx),
-                        WebGl2RenderingContext::UNSIGNED_INT,
-                        0,
-                    );
-
-                self.gl.disable(WebGl2RenderingContext::BLEND);
             }
         }
+
         Ok(())
-        //});
     }
 
     fn compute_edge_paths_iter<'a>(
         &self,
         moc: &'a HEALPixCoverage,
         camera: &'a mut CameraViewPort,
-    ) -> impl Iterator<Item = f32> + 'a {
+    ) -> impl Iterator<Item = f64> + 'a {
         /*self.vertices_in_view(view_moc, moc, camera)
         .filter_map(move |cell_vertices| {
             let mut ndc: [[f32; 2]; 5] =
@@ -633,22 +623,22 @@ impl MOCIntern {
         self.vertices_in_view(moc, camera)
             .map(|v| {
                 let vertices = [
-                    v[0].0 as f32,
-                    v[0].1 as f32,
-                    v[1].0 as f32,
-                    v[1].1 as f32,
-                    v[1].0 as f32,
-                    v[1].1 as f32,
-                    v[2].0 as f32,
-                    v[2].1 as f32,
-                    v[2].0 as f32,
-                    v[2].1 as f32,
-                    v[3].0 as f32,
-                    v[3].1 as f32,
-                    v[3].0 as f32,
-                    v[3].1 as f32,
-                    v[0].0 as f32,
-                    v[0].1 as f32,
+                    v[0].0,
+                    v[0].1,
+                    v[1].0,
+                    v[1].1,
+                    v[1].0,
+                    v[1].1,
+                    v[2].0,
+                    v[2].1,
+                    v[2].0,
+                    v[2].1,
+                    v[3].0,
+                    v[3].1,
+                    v[3].0,
+                    v[3].1,
+                    v[0].0,
+                    v[0].1,
                 ];
 
                 vertices
@@ -121,6 +121,8 @@ impl MOCRenderer {
 
         if let Some(idx) = self.cfgs.iter().position(|cfg| cfg.get_uuid() == name) {
             let old_cfg = self.cfgs[idx].clone();
+            // The colors and the render modes are given to the MOCs when they are created
+            self.mocs[idx].set_cfg(&self.gl, &cfg);
             self.cfgs[idx] = cfg;
 
             let _ = self.draw(camera, projection, shaders);
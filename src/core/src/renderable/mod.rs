@@ -1,13 +1,18 @@
+pub mod blink;
 pub mod catalog;
 pub mod final_pass;
 pub mod grid;
 pub mod hips;
 pub mod image;
+pub mod limb;
 pub mod line;
 pub mod moc;
+pub mod polygon;
+pub mod ruler;
 pub mod shape;
 pub mod text;
 pub mod utils;
+pub mod vector_field;
 
 use crate::renderable::image::Image;
 
@@ -17,9 +22,12 @@ pub use hips::HiPS;
 
 pub use catalog::Manager;
 
+use blink::Blink;
+
 use al_api::color::ColorRGB;
 use al_api::hips::HiPSCfg;
 use al_api::hips::ImageMetadata;
+use al_api::colormap::CmapLabel;
 use al_api::image::ImageParams;
 
 use al_core::colormap::Colormaps;
@@ -29,7 +37,9 @@ use al_core::VertexArrayObject;
 use al_core::WebGlContext;
 
 use crate::camera::CameraViewPort;
+use crate::state::{LayerState, ViewerState};
 use crate::shader::ShaderId;
+use crate::time::DeltaTime;
 use crate::Abort;
 use crate::ProjectionType;
 use crate::{shader::ShaderManager, survey::config::HiPSConfig};
@@ -71,6 +81,11 @@ pub struct Layers {
 
     background_color: ColorRGB,
 
+    // The two layers currently blinked, if any
+    blink: Option<Blink>,
+    // Duration the tiles are faded in for once received
+    tile_fade_duration: DeltaTime,
+
     gl: WebGlContext,
 }
 
@@ -165,6 +180,9 @@ impl Layers {
             background_color,
             screen_vao,
 
+            blink: None,
+            tile_fade_duration: hips::fade::DEFAULT_FADE_DURATION,
+
             gl,
         })
     }
@@ -209,6 +227,67 @@ impl Layers {
         &self.raytracer
     }
 
+    /// Start blinking two layers
+    pub fn set_blink(&mut self, blink: Blink) -> Result<(), JsValue> {
+        for layer in [&blink.layer_a, &blink.layer_b] {
+            if !self.meta.contains_key(layer) {
+                return Err(JsValue::from_str(&format!(
+                    "Layer {:?} not found, so cannot be blinked.",
+                    layer
+                )));
+            }
+        }
+
+        self.blink = Some(blink);
+
+        Ok(())
+    }
+
+    pub fn stop_blink(&mut self) {
+        self.blink = None;
+    }
+
+    /// Move the blink forward by `dt`
+    ///
+    /// Returns true if two layers are blinked, i.e. the view needs to be redrawn
+    pub fn update_blink(&mut self, dt: DeltaTime) -> bool {
+        if let Some(blink) = self.blink.as_mut() {
+            blink.update(dt);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Move the fading in of the tiles received forward by `dt`
+    ///
+    /// Returns true if some tiles are fading in, i.e. the view needs to be redrawn
+    pub fn update_fades(&mut self, dt: DeltaTime) -> bool {
+        self.surveys
+            .values_mut()
+            .fold(false, |fading, hips| hips.update_fades(dt) || fading)
+    }
+
+    /// Set the duration the tiles are faded in for once received
+    pub fn set_tile_fade_duration(&mut self, duration: DeltaTime) {
+        self.tile_fade_duration = duration;
+
+        for hips in self.surveys.values_mut() {
+            hips.set_fade_duration(duration);
+        }
+    }
+
+    // The opacity a layer is drawn with
+    fn opacity(&self, layer: &str, meta: &ImageMetadata) -> f32 {
+        let factor = self
+            .blink
+            .as_ref()
+            .map(|blink| blink.opacity_factor(layer))
+            .unwrap_or(1.0);
+
+        meta.opacity * factor
+    }
+
     pub fn draw(
         &mut self,
         camera: &mut CameraViewPort,
@@ -228,7 +307,7 @@ impl Layers {
             if let Some(survey) = self.surveys.get(cdid) {
                 let hips_cfg = survey.get_config();
                 (survey.is_allsky() || hips_cfg.get_format().get_channel() == ChannelType::RGB8U)
-                    && meta.opacity == 1.0
+                    && self.opacity(layer, meta) == 1.0
             } else {
                 // image fits case
                 false
@@ -266,6 +345,7 @@ impl Layers {
         let mut idx_start_layer = 0;
         for (idx_layer, layer) in self.layers.iter().enumerate().skip(1) {
             let meta = self.meta.get(layer).expect("Meta should be found");
+            let opacity = self.opacity(layer, meta);
 
             let id = self.ids.get(layer).expect("Url should be found");
             if let Some(survey) = self.surveys.get_mut(id) {
@@ -273,7 +353,7 @@ impl Layers {
 
                 let fully_covering_survey = (survey.is_allsky()
                     || hips_cfg.get_format().get_channel() == ChannelType::RGB8U)
-                    && meta.opacity == 1.0;
+                    && opacity == 1.0;
                 if fully_covering_survey {
                     idx_start_layer = idx_layer;
                 }
@@ -282,7 +362,19 @@ impl Layers {
 
         let rendered_layers = &self.layers[idx_start_layer..];
         for layer in rendered_layers {
-            let draw_opt = self.meta.get(layer).expect("Meta should be found");
+            let meta = self.meta.get(layer).expect("Meta should be found");
+            // A blinked layer is drawn with its opacity modulated
+            let blinked_meta;
+            let draw_opt = if self.blink.is_some() {
+                blinked_meta = ImageMetadata {
+                    opacity: self.opacity(layer, meta),
+                    ..meta.clone()
+                };
+                &blinked_meta
+            } else {
+                meta
+            };
+
             if draw_opt.visible() {
                 // 1. Update the survey if necessary
                 let id = self.ids.get(layer).expect("Url should be found");
@@ -332,6 +424,15 @@ impl Layers {
             .ok_or(err_layer_not_found)?;
         self.layers.remove(id_layer);
 
+        if self
+            .blink
+            .as_ref()
+            .map(|blink| blink.layer_a == layer || blink.layer_b == layer)
+            .unwrap_or(false)
+        {
+            self.blink = None;
+        }
+
         // Loop over all the meta for its longitude reversed property
         // and set the camera to it if there is at least one
         let longitude_reversed = self.meta.values().any(|meta| meta.longitude_reversed);
@@ -386,6 +487,14 @@ impl Layers {
         self.meta.insert(new_layer.to_string(), meta);
         self.ids.insert(new_layer.to_string(), id);
 
+        if let Some(blink) = self.blink.as_mut() {
+            for blinked in [&mut blink.layer_a, &mut blink.layer_b] {
+                if *blinked == layer {
+                    *blinked = new_layer.to_string();
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -472,7 +581,8 @@ impl Layers {
             }*/
             camera.register_view_frame(cfg.get_frame(), proj);
 
-            let hips = HiPS::new(cfg, gl)?;
+            let mut hips = HiPS::new(cfg, gl)?;
+            hips.set_fade_duration(self.tile_fade_duration);
             // add the frame to the camera
 
             self.surveys.insert(creator_did.clone(), hips);
@@ -608,6 +718,64 @@ impl Layers {
         Ok(())
     }
 
+    /// Write the color config of the layers into a viewer state
+    pub fn save_state(&self, state: &mut ViewerState) {
+        state.layers = self
+            .layers
+            .iter()
+            .filter_map(|layer| {
+                let meta = self.meta.get(layer)?;
+                let id = self.ids.get(layer)?;
+                let (colormap, reversed) = meta.color.colormap();
+
+                let mut layer_state = LayerState {
+                    layer: layer.clone(),
+                    id: id.clone(),
+                    colormap: colormap.to_string(),
+                    reversed,
+                    min_cut: meta.color.min_cut,
+                    max_cut: meta.color.max_cut,
+                    opacity: meta.opacity,
+                    ..Default::default()
+                };
+                layer_state.set_transfer_function(&meta.color.stretch);
+
+                Some(layer_state)
+            })
+            .collect();
+    }
+
+    /// Apply the color config of a viewer state to the layers
+    ///
+    /// Only the layers already added are configured, the other ones have to
+    /// be added from their id beforehand
+    pub fn restore_state(
+        &mut self,
+        state: &ViewerState,
+        camera: &mut CameraViewPort,
+        projection: &ProjectionType,
+    ) -> Result<(), JsValue> {
+        for layer_state in &state.layers {
+            let mut meta = match self.meta.get(&layer_state.layer) {
+                Some(meta) => meta.clone(),
+                None => continue,
+            };
+
+            meta.color.stretch = layer_state
+                .get_transfer_function()
+                .map_err(|e| JsValue::from_str(&e))?;
+            meta.color.cmap_name = CmapLabel::new(&layer_state.colormap);
+            meta.color.reversed = layer_state.reversed;
+            meta.color.min_cut = layer_state.min_cut;
+            meta.color.max_cut = layer_state.max_cut;
+            meta.opacity = layer_state.opacity;
+
+            self.set_layer_cfg(layer_state.layer.clone(), meta, camera, projection)?;
+        }
+
+        Ok(())
+    }
+
     // Accessors
     // HiPSes getters
     pub fn get_hips_from_layer(&self, layer: &str) -> Option<&HiPS> {
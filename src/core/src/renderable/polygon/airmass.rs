@@ -0,0 +1,203 @@
+use crate::CameraViewPort;
+use crate::ProjectionType;
+
+use al_api::color::ColorRGBA;
+use al_api::coo_system::CooSystem;
+use cgmath::{Matrix4, Vector4};
+
+use crate::coosys::Observer;
+use crate::math::angle::ToAngle;
+use crate::math::lonlat::LonLat;
+use crate::math::{HALF_PI, TWICE_PI};
+use crate::LonLatT;
+
+use super::fill::{self, FillVertex};
+
+// Number of altitude bands the sky above the max airmass is shaded with
+const NUM_BANDS: usize = 8;
+// Azimuth span of the pieces the bands are filled by
+const SECTOR_AZ: f64 = TWICE_PI / 12.0;
+// Azimuth step between the vertices along the bands
+const STEP_AZ: f64 = TWICE_PI / 72.0;
+
+/// Airmass at an altitude
+///
+/// The atmosphere is considered as plane-parallel, i.e. the airmass is the secant
+/// of the zenith angle. Returns None below the horizon
+pub fn airmass(alt: f64) -> Option<f64> {
+    if alt > 0.0 {
+        Some(1.0 / alt.sin())
+    } else {
+        None
+    }
+}
+
+/// How the sky is shaded by its airmass
+#[derive(Clone, Copy, Debug)]
+pub struct AirmassStyle {
+    /// Color at the zenith, i.e. an airmass of 1
+    pub zenith: ColorRGBA,
+    /// Color from the max airmass down to the horizon
+    pub high: ColorRGBA,
+    /// Airmass above which the sky is given the ``high`` color
+    pub max_airmass: f64,
+    /// Color of the sky below the horizon
+    pub below_horizon: ColorRGBA,
+}
+
+impl Default for AirmassStyle {
+    fn default() -> Self {
+        Self {
+            zenith: ColorRGBA {
+                r: 0.0,
+                g: 0.6,
+                b: 0.2,
+                a: 0.0,
+            },
+            high: ColorRGBA {
+                r: 0.8,
+                g: 0.3,
+                b: 0.0,
+                a: 0.3,
+            },
+            max_airmass: 3.0,
+            below_horizon: ColorRGBA {
+                r: 0.0,
+                g: 0.0,
+                b: 0.0,
+                a: 0.6,
+            },
+        }
+    }
+}
+
+impl AirmassStyle {
+    // The color of the gradient at an airmass
+    fn color(&self, airmass: f64) -> ColorRGBA {
+        let t = (((airmass - 1.0) / (self.max_airmass - 1.0)) as f32).clamp(0.0, 1.0);
+        let lerp = |a: f32, b: f32| a + t * (b - a);
+
+        ColorRGBA {
+            r: lerp(self.zenith.r, self.high.r),
+            g: lerp(self.zenith.g, self.high.g),
+            b: lerp(self.zenith.b, self.high.b),
+            a: lerp(self.zenith.a, self.high.a),
+        }
+    }
+}
+
+/// A vertex of the triangles shading the sky
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadedVertex {
+    pub ndc_pos: [f32; 2],
+    pub color: [f32; 4],
+}
+
+/// Triangulate the sky of an observer shaded by its airmass
+///
+/// The sky above the max airmass is split into bands of constant altitude colored
+/// along the gradient of the style. The bands are filled as sky polygons, see ``fill::fill``.
+///
+/// # Returns
+///
+/// The vertices of the triangles, three by three
+pub fn shade(
+    observer: &Observer,
+    style: &AirmassStyle,
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Vec<ShadedVertex> {
+    let altaz_to_view = CooSystem::ICRS.to(camera.get_coo_system()) * observer.altaz_to_icrs();
+
+    let mut vertices = vec![];
+    for (polygon, color) in shaded_polygons(style) {
+        let polygon = to_view(&polygon, &altaz_to_view);
+
+        vertices.extend(fill::fill(&polygon, camera, projection).into_iter().map(
+            |FillVertex { ndc_pos, alpha }| ShadedVertex {
+                ndc_pos,
+                color: [color.r, color.g, color.b, color.a * alpha],
+            },
+        ));
+    }
+
+    vertices
+}
+
+// The polygons, given in the alt-az frame, shading the sky with their color
+fn shaded_polygons(style: &AirmassStyle) -> Vec<(Vec<LonLatT<f64>>, ColorRGBA)> {
+    let at = |az: f64, alt: f64| LonLatT::new(az.to_angle(), alt.to_angle());
+    // The vertices along a line of constant altitude
+    let arc = |alt: f64, az1: f64, az2: f64| {
+        let n = ((az2 - az1).abs() / STEP_AZ).ceil().max(1.0) as usize;
+        (0..=n)
+            .map(|i| at(az1 + (az2 - az1) * (i as f64) / (n as f64), alt))
+            .collect::<Vec<_>>()
+    };
+    let num_sectors = (TWICE_PI / SECTOR_AZ).round() as usize;
+    let sectors = (0..num_sectors).map(|i| {
+        let az1 = (i as f64) * SECTOR_AZ;
+        (az1, az1 + SECTOR_AZ)
+    });
+
+    // The altitude of the max airmass
+    let alt_max_airmass = (1.0 / style.max_airmass.max(1.0)).asin();
+    let alt_of_band = |i: usize| alt_max_airmass + (HALF_PI - alt_max_airmass) * (i as f64) / (NUM_BANDS as f64);
+
+    let mut polygons = vec![];
+    // The cap around the zenith
+    let top = alt_of_band(NUM_BANDS - 1);
+    let mut cap = arc(top, 0.0, TWICE_PI);
+    cap.pop();
+    polygons.push((cap, style.color(airmass(0.5 * (top + HALF_PI)).unwrap_or(1.0))));
+
+    // The bands down to the max airmass, then the one down to the horizon
+    let bands = (0..(NUM_BANDS - 1))
+        .map(|i| (alt_of_band(i), alt_of_band(i + 1)))
+        .map(|(lo, hi)| (lo, hi, style.color(airmass(0.5 * (lo + hi)).unwrap_or(1.0))))
+        .chain(std::iter::once((0.0, alt_max_airmass, style.high)));
+    for (lo, hi, color) in bands {
+        for (az1, az2) in sectors.clone() {
+            let mut polygon = arc(lo, az1, az2);
+            polygon.extend(arc(hi, az2, az1));
+
+            polygons.push((polygon, color));
+        }
+    }
+
+    // Below the horizon, sectors joining the horizon to the nadir
+    for (az1, az2) in sectors {
+        let mut polygon = arc(0.0, az1, az2);
+        polygon.push(at(0.0, -HALF_PI));
+
+        polygons.push((polygon, style.below_horizon));
+    }
+
+    polygons
+}
+
+fn to_view(polygon: &[LonLatT<f64>], altaz_to_view: &Matrix4<f64>) -> Vec<LonLatT<f64>> {
+    polygon
+        .iter()
+        .map(|lonlat| (altaz_to_view * lonlat.vector::<Vector4<f64>>()).lonlat())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn airmass_at_a_few_altitudes() {
+        assert!((airmass(HALF_PI).unwrap() - 1.0).abs() < 1e-12);
+        assert!((airmass(30.0_f64.to_radians()).unwrap() - 2.0).abs() < 1e-12);
+        assert!(airmass(-1.0_f64.to_radians()).is_none());
+
+        // The sky is shaded from the zenith color to the high one at the max airmass
+        let style = AirmassStyle::default();
+        let same = |a: ColorRGBA, b: ColorRGBA| a.r == b.r && a.g == b.g && a.b == b.b && a.a == b.a;
+        assert!(same(style.color(1.0), style.zenith));
+        assert!(same(style.color(style.max_airmass + 1.0), style.high));
+    }
+}
@@ -0,0 +1,373 @@
+use crate::CameraViewPort;
+use crate::ProjectionType;
+
+use cgmath::InnerSpace;
+
+use crate::coo_space::{XYNDC, XYZModel};
+use crate::math::angle::ToAngle;
+use crate::math::lonlat::LonLat;
+use crate::math::sph_geom::{delta_lon, is_in_lon_range, is_in_polygon};
+use crate::math::{HALF_PI, PI, TWICE_PI};
+use crate::LonLatT;
+
+// Maximum number of pieces an edge is split into
+const MAX_EDGE_SUBDIVISIONS: usize = 1024;
+// Longitude gap left on both sides of the seam
+const SEAM_EPS: f64 = 1e-9;
+
+/// A vertex of the triangles filling a sky region
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FillVertex {
+    pub ndc_pos: [f32; 2],
+    /// 0 on the edges created by the split of the region at the seam of the
+    /// projection so that they fade instead of drawing a hard border, 1 elsewhere
+    pub alpha: f32,
+}
+
+/// Triangulate a sky polygon
+///
+/// The edges of the polygon are great circle arcs. It is split at the meridian
+/// opposite to the center of the view, where an allsky projection is discontinuous.
+/// A polygon enclosing a pole is triangulated as a fan around that pole. The triangles
+/// having a vertex that cannot be projected are discarded.
+///
+/// # Arguments
+///
+/// * ``vertices`` - The vertices of the polygon, it is implicitely closed
+///
+/// # Returns
+///
+/// The vertices of the triangles, three by three
+pub fn fill(vertices: &[LonLatT<f64>], camera: &CameraViewPort, projection: &ProjectionType) -> Vec<FillVertex> {
+    let d_alpha = camera.get_aperture().to_radians() * 0.02;
+    let seam_lon = camera.get_center().lonlat().lon().to_radians() + PI;
+
+    fill_with(vertices, seam_lon, d_alpha, &|lonlat| {
+        crate::math::lonlat::proj(lonlat, projection, camera)
+    })
+}
+
+// A vertex of the polygon in the (lon, lat) plane, the longitude being
+// counted from the seam
+#[derive(Clone, Copy, Debug)]
+struct Vertex {
+    lon: f64,
+    lat: f64,
+    // whether it lies on the seam
+    cut: bool,
+}
+
+// The triangles of the polygon projected with `proj`, its longitudes being
+// counted from the `seam_lon` meridian so that no triangle crosses it
+fn fill_with<P>(vertices: &[LonLatT<f64>], seam_lon: f64, d_alpha: f64, proj: &P) -> Vec<FillVertex>
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    if vertices.len() < 3 {
+        return vec![];
+    }
+
+    let ring = densify(vertices, d_alpha);
+    let seam_lon = seam_lon.rem_euclid(TWICE_PI);
+
+    let north = LonLatT::new(0.0_f64.to_angle(), HALF_PI.to_angle());
+    let south = LonLatT::new(0.0_f64.to_angle(), (-HALF_PI).to_angle());
+    let pole = if is_in_polygon(&north, vertices) {
+        Some(HALF_PI)
+    } else if is_in_polygon(&south, vertices) {
+        Some(-HALF_PI)
+    } else {
+        None
+    };
+
+    let mut triangles = vec![];
+    let mut push = |a: &Vertex, b: &Vertex, c: &Vertex| {
+        let project = |v: &Vertex| {
+            let p = proj(&LonLatT::new((v.lon + seam_lon).to_angle(), v.lat.to_angle()))?;
+            Some(FillVertex {
+                ndc_pos: [p.x as f32, p.y as f32],
+                alpha: if v.cut { 0.0 } else { 1.0 },
+            })
+        };
+
+        if let (Some(a), Some(b), Some(c)) = (project(a), project(b), project(c)) {
+            triangles.extend([a, b, c]);
+        }
+    };
+
+    // Longitudes counted from the seam, in [0; 2PI[
+    let ring = ring
+        .iter()
+        .map(|v| Vertex {
+            lon: (v.lon().to_radians() - seam_lon).rem_euclid(TWICE_PI),
+            lat: v.lat().to_radians(),
+            cut: false,
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(pole_lat) = pole {
+        // Fan around the pole, the wedges crossing the seam being split there
+        let edges = ring.iter().zip(ring.iter().cycle().skip(1));
+        for (a, b) in edges {
+            let center = |lon: f64| Vertex {
+                lon,
+                lat: pole_lat,
+                cut: false,
+            };
+
+            if is_in_lon_range(0.0, a.lon, b.lon) && a.lon != 0.0 {
+                // The edge goes across the seam, i.e. lon = 0 mod 2PI. Get the
+                // longitude distances of its endpoints to the seam
+                let increasing = a.lon > b.lon;
+                let (da, db) = if increasing {
+                    (TWICE_PI - a.lon, b.lon)
+                } else {
+                    (a.lon, TWICE_PI - b.lon)
+                };
+                let t = da / (da + db);
+                let lat = a.lat + t * (b.lat - a.lat);
+
+                let (lon_a, lon_b) = if increasing {
+                    (TWICE_PI - SEAM_EPS, SEAM_EPS)
+                } else {
+                    (SEAM_EPS, TWICE_PI - SEAM_EPS)
+                };
+                let sa = Vertex { lon: lon_a, lat, cut: true };
+                let sb = Vertex { lon: lon_b, lat, cut: true };
+
+                push(&center(a.lon), a, &sa);
+                push(&center(b.lon), &sb, b);
+            } else {
+                push(&center(a.lon), a, b);
+            }
+        }
+    } else {
+        // Unwrap the longitudes along the ring. Not enclosing a pole, the ring
+        // closes with the same longitude it starts from
+        let mut unwrapped = Vec::with_capacity(ring.len());
+        let mut lon = ring[0].lon;
+        for (i, v) in ring.iter().enumerate() {
+            if i > 0 {
+                lon += delta_lon(ring[i - 1].lon, v.lon);
+            }
+            unwrapped.push(Vertex { lon, ..*v });
+        }
+
+        // Split the ring at each seam it goes across
+        let (lon_min, lon_max) = unwrapped
+            .iter()
+            .fold((f64::MAX, f64::MIN), |(min, max), v| (min.min(v.lon), max.max(v.lon)));
+        let k_min = (lon_min / TWICE_PI).floor() as i64;
+        let k_max = (lon_max / TWICE_PI).floor() as i64;
+
+        for k in k_min..=k_max {
+            let start = (k as f64) * TWICE_PI;
+            let piece = clip_lon(&unwrapped, start + SEAM_EPS, true);
+            let piece = clip_lon(&piece, start + TWICE_PI - SEAM_EPS, false);
+
+            for [i, j, l] in triangulate(&piece) {
+                push(&piece[i], &piece[j], &piece[l]);
+            }
+        }
+    }
+
+    triangles
+}
+
+// Split the edges of the polygon so that none is longer than d_alpha
+fn densify(vertices: &[LonLatT<f64>], d_alpha: f64) -> Vec<LonLatT<f64>> {
+    let mut ring = vec![];
+
+    for (v1, v2) in vertices.iter().zip(vertices.iter().cycle().skip(1)) {
+        let (a, b): (XYZModel<f64>, XYZModel<f64>) = (v1.vector(), v2.vector());
+        let omega = crate::math::vector::angle3(&a, &b).to_radians();
+        let sin_omega = omega.sin();
+
+        ring.push(*v1);
+        if sin_omega.abs() < 1e-12 {
+            continue;
+        }
+
+        let n = ((omega / d_alpha).ceil() as usize).clamp(1, MAX_EDGE_SUBDIVISIONS);
+        for i in 1..n {
+            let t = (i as f64) / (n as f64);
+            // spherical linear interpolation
+            let v = (a * ((1.0 - t) * omega).sin() + b * (t * omega).sin()) / sin_omega;
+            ring.push(v.normalize().lonlat());
+        }
+    }
+
+    ring
+}
+
+// Clip the polygon by the vertical line lon = lon0, keeping the part at its
+// right if `keep_right`, at its left otherwise.
+fn clip_lon(polygon: &[Vertex], lon0: f64, keep_right: bool) -> Vec<Vertex> {
+    let inside = |v: &Vertex| (v.lon >= lon0) == keep_right;
+
+    let mut res = vec![];
+    for (a, b) in polygon.iter().zip(polygon.iter().cycle().skip(1)) {
+        if inside(a) {
+            res.push(*a);
+        }
+
+        if inside(a) != inside(b) {
+            let t = (lon0 - a.lon) / (b.lon - a.lon);
+            res.push(Vertex {
+                lon: lon0,
+                lat: a.lat + t * (b.lat - a.lat),
+                cut: true,
+            });
+        }
+    }
+
+    res
+}
+
+// Triangulate a simple polygon of the plane by ear clipping
+fn triangulate(polygon: &[Vertex]) -> Vec<[usize; 3]> {
+    let n = polygon.len();
+    if n < 3 {
+        return vec![];
+    }
+
+    let cross = |o: usize, a: usize, b: usize| {
+        let (o, a, b) = (&polygon[o], &polygon[a], &polygon[b]);
+        (a.lon - o.lon) * (b.lat - o.lat) - (a.lat - o.lat) * (b.lon - o.lon)
+    };
+
+    // Work counter-clockwise
+    let area: f64 = (0..n).map(|i| cross(0, i, (i + 1) % n)).sum();
+    let mut idx: Vec<usize> = if area >= 0.0 {
+        (0..n).collect()
+    } else {
+        (0..n).rev().collect()
+    };
+
+    let mut triangles = Vec::with_capacity(n - 2);
+    while idx.len() > 3 {
+        let m = idx.len();
+        let ear = (0..m).find(|&i| {
+            let (a, b, c) = (idx[(i + m - 1) % m], idx[i], idx[(i + 1) % m]);
+            if cross(a, b, c) <= 0.0 {
+                // reflex or flat corner
+                return false;
+            }
+
+            // no other vertex lies inside the ear
+            idx.iter().all(|&p| {
+                p == a
+                    || p == b
+                    || p == c
+                    || cross(a, b, p) < 0.0
+                    || cross(b, c, p) < 0.0
+                    || cross(c, a, p) < 0.0
+            })
+        });
+
+        match ear {
+            Some(i) => {
+                triangles.push([idx[(i + m - 1) % m], idx[i], idx[(i + 1) % m]]);
+                idx.remove(i);
+            }
+            // Only flat corners are left
+            None => break,
+        }
+    }
+
+    if idx.len() == 3 && cross(idx[0], idx[1], idx[2]) > 0.0 {
+        triangles.push([idx[0], idx[1], idx[2]]);
+    }
+
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::projection::{clip_space_proj, Projection};
+
+    fn lonlat(lon: f64, lat: f64) -> LonLatT<f64> {
+        LonLatT::new(lon.to_radians().to_angle(), lat.to_radians().to_angle())
+    }
+
+    fn signed_area(t: &[FillVertex]) -> f64 {
+        let (a, b, c) = (t[0].ndc_pos, t[1].ndc_pos, t[2].ndc_pos);
+        0.5 * (((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])) as f64)
+    }
+
+    #[test]
+    fn sky_quad_across_ra_0_is_filled() {
+        let projection = ProjectionType::Ait(mapproj::pseudocyl::ait::Ait::new());
+        // The center of the projection is (0, 0), its seam is the lon = 180 meridian
+        let proj = clip_space_proj(&projection);
+
+        let quad = [lonlat(350.0, -10.0), lonlat(10.0, -10.0), lonlat(10.0, 10.0), lonlat(350.0, 10.0)];
+        let triangles = fill_with(&quad, PI, 1e-2, &proj);
+        assert!(!triangles.is_empty());
+        assert_eq!(triangles.len() % 3, 0);
+
+        // The triangles do not overlap: they all have the same orientation and
+        // their areas sum to the area of the outline
+        let areas = triangles.chunks_exact(3).map(signed_area).collect::<Vec<_>>();
+        assert!(areas.iter().all(|a| *a >= 0.0));
+
+        let outline = densify(&quad, 1e-2)
+            .iter()
+            .map(|v| proj(v).unwrap())
+            .collect::<Vec<_>>();
+        let outline_area = 0.5
+            * outline
+                .iter()
+                .zip(outline.iter().cycle().skip(1))
+                .map(|(a, b)| a.x * b.y - a.y * b.x)
+                .sum::<f64>();
+        let area: f64 = areas.iter().sum();
+        assert!((area - outline_area).abs() < 1e-3 * outline_area, "{} {}", area, outline_area);
+
+        // Nothing is cut
+        assert!(triangles.iter().all(|v| v.alpha == 1.0));
+    }
+
+    #[test]
+    fn polar_cap_is_a_disk() {
+        let projection = ProjectionType::Sin(mapproj::zenithal::sin::Sin);
+        // Look at the north pole
+        let proj = |lonlat: &LonLatT<f64>| {
+            let v: XYZModel<f64> = lonlat.vector();
+            projection.world_to_clip_space(&cgmath::Vector4::new(v.x, -v.z, v.y, 1.0))
+        };
+
+        let cap = (0..12)
+            .map(|i| lonlat(30.0 * (i as f64), 60.0))
+            .collect::<Vec<_>>();
+        let triangles = fill_with(&cap, PI, 1e-2, &proj);
+        assert!(triangles.len() >= 3 * 12);
+
+        // Every triangle has the pole as vertex and the cap is covered once
+        let pole = proj(&lonlat(0.0, 90.0)).unwrap();
+        let mut area = 0.0;
+        for t in triangles.chunks_exact(3) {
+            let p = t[0].ndc_pos;
+            assert!((p[0] as f64 - pole.x).abs() < 1e-6 && (p[1] as f64 - pole.y).abs() < 1e-6);
+
+            area += signed_area(t);
+        }
+        let same_orientation = |f: fn(f64) -> bool| triangles.chunks_exact(3).all(|t| f(signed_area(t)));
+        assert!(same_orientation(|a| a >= 0.0) || same_orientation(|a| a <= 0.0));
+
+        // The fan covers the whole cap outline
+        let outline = densify(&cap, 1e-2)
+            .iter()
+            .map(|v| proj(v).unwrap())
+            .collect::<Vec<_>>();
+        let outline_area = 0.5
+            * outline
+                .iter()
+                .zip(outline.iter().cycle().skip(1))
+                .map(|(a, b)| a.x * b.y - a.y * b.x)
+                .sum::<f64>();
+        assert!((area.abs() - outline_area.abs()).abs() < 1e-3 * outline_area.abs(), "{} {}", area, outline_area);
+    }
+}
@@ -0,0 +1,3 @@
+/// This module handles the filling of sky regions
+pub mod fill;
+pub mod airmass;
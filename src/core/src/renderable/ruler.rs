@@ -0,0 +1,234 @@
+use crate::shader::ShaderManager;
+use crate::CameraViewPort;
+use crate::LonLatT;
+use crate::ProjectionType;
+
+use al_api::color::ColorRGBA;
+use al_api::coo_system::CooSystem;
+use al_core::{VecData, VertexArrayObject, WebGlContext};
+use cgmath::Vector2;
+use wasm_bindgen::JsValue;
+use web_sys::{HtmlElement, WebGl2RenderingContext};
+
+use crate::coo_space::XYZModel;
+use crate::math::angle::{format_angle, Angle, AngleFormat};
+use crate::math::lonlat::{self, LonLat};
+use crate::renderable::line::great_circle::{self, Arc};
+use crate::renderable::line::{self, LineStyle};
+use crate::renderable::text::TextRenderManager;
+use crate::renderable::Renderer;
+
+const DEFAULT_COLOR: ColorRGBA = ColorRGBA {
+    r: 1.0,
+    g: 0.85,
+    b: 0.0,
+    a: 1.0,
+};
+const DEFAULT_THICKNESS: f32 = 2.0;
+
+/// The distance between the two ends of a ruler
+#[derive(Clone, Debug)]
+pub struct Measure {
+    /// The great circle distance between the ends
+    pub distance: Angle<f64>,
+    /// The distance formatted in DMS, without any sign
+    pub label: String,
+    /// The middle of the great circle arc joining the ends
+    pub midpoint: LonLatT<f64>,
+}
+
+impl Measure {
+    pub fn new(start: &LonLatT<f64>, end: &LonLatT<f64>) -> Self {
+        let distance = lonlat::angular_distance(start, end);
+        // The precision fits the distance measured
+        let label = format_angle(distance, AngleFormat::dms(distance))
+            .trim_start_matches('+')
+            .to_string();
+
+        let midpoint = Arc::new(start.vector(), end.vector()).at(0.5).lonlat();
+
+        Self {
+            distance,
+            label,
+            midpoint,
+        }
+    }
+}
+
+/// A ruler measuring the distance between two sky positions
+///
+/// The great circle arc joining them is drawn with the distance labeled at
+/// its middle. The part of the arc out of the valid domain of the projection
+/// is clipped. Moving one of the ends, e.g. while dragging it, updates the
+/// arc and the label at the next draw.
+pub struct Ruler {
+    // The ends, in the ICRS frame
+    ends: Option<(LonLatT<f64>, LonLatT<f64>)>,
+    measure: Option<Measure>,
+
+    style: LineStyle,
+    text_renderer: TextRenderManager,
+
+    vao: VertexArrayObject,
+    gl: WebGlContext,
+}
+
+impl Ruler {
+    pub fn new(gl: &WebGlContext, aladin_div: &HtmlElement) -> Result<Self, JsValue> {
+        let mut text_renderer = TextRenderManager::new(aladin_div)?;
+        text_renderer.set_color(&DEFAULT_COLOR);
+
+        Ok(Self {
+            ends: None,
+            measure: None,
+            style: LineStyle::new(&DEFAULT_COLOR, DEFAULT_THICKNESS),
+            text_renderer,
+            vao: line::segments_vao(gl, 2),
+            gl: gl.clone(),
+        })
+    }
+
+    /// Measure the distance between two positions given in the ICRS frame
+    pub fn set(&mut self, start: LonLatT<f64>, end: LonLatT<f64>) -> &Measure {
+        self.ends = Some((start, end));
+
+        self.measure.insert(Measure::new(&start, &end))
+    }
+
+    /// Move the second end of the ruler, e.g. while it is dragged
+    pub fn set_end(&mut self, end: LonLatT<f64>) -> Option<&Measure> {
+        let (start, _) = self.ends?;
+
+        Some(self.set(start, end))
+    }
+
+    /// Remove the ruler from the view
+    pub fn clear(&mut self) {
+        self.ends = None;
+        self.measure = None;
+    }
+
+    pub fn get_measure(&self) -> Option<&Measure> {
+        self.measure.as_ref()
+    }
+
+    pub fn set_color(&mut self, color: &ColorRGBA) {
+        self.style = LineStyle::new(color, self.style.width);
+        self.text_renderer.set_color(color);
+    }
+
+    // The ends in the frame of the view
+    fn view_ends(&self, camera: &CameraViewPort) -> Option<(LonLatT<f64>, LonLatT<f64>)> {
+        let (start, end) = self.ends?;
+
+        let to_view = |p: &LonLatT<f64>| {
+            let v: XYZModel<f64> = p.vector();
+            crate::coosys::apply_coo_system(CooSystem::ICRS, camera.get_coo_system(), &v).lonlat()
+        };
+
+        Some((to_view(&start), to_view(&end)))
+    }
+
+    pub fn draw(
+        &mut self,
+        camera: &CameraViewPort,
+        projection: &ProjectionType,
+        shaders: &mut ShaderManager,
+    ) -> Result<(), JsValue> {
+        let (start, end) = match self.view_ends(camera) {
+            Some(ends) => ends,
+            None => return Ok(()),
+        };
+
+        // Given as segment pairs, clipped at the boundary of the projection
+        let buf = great_circle::project(start, end, camera, projection)
+            .into_iter()
+            .flat_map(|v| [v.x as f32, v.y as f32])
+            .collect::<Vec<f32>>();
+        if buf.is_empty() {
+            return Ok(());
+        }
+
+        self.vao.bind_for_update().update_instanced_array(
+            "ndc_pos",
+            WebGl2RenderingContext::DYNAMIC_DRAW,
+            VecData(&buf),
+        );
+
+        self.gl.enable(WebGl2RenderingContext::BLEND);
+        crate::shader::get_shader(&self.gl, shaders, "line_inst_ndc.vert", "line_base.frag")?
+            .bind(&self.gl)
+            .attach_uniforms_from(&self.style)
+            .attach_uniform("u_width", &(camera.get_width()))
+            .attach_uniform("u_height", &(camera.get_height()))
+            .bind_vertex_array_object_ref(&self.vao)
+            .draw_elements_instanced_with_i32(
+                WebGl2RenderingContext::TRIANGLES,
+                0,
+                (buf.len() / 4) as i32,
+            );
+        self.gl.disable(WebGl2RenderingContext::BLEND);
+
+        Ok(())
+    }
+
+    /// Draw the distance at the middle of the arc, or at the middle of its
+    /// visible part when the middle is clipped
+    pub fn draw_label(&mut self, camera: &CameraViewPort, projection: &ProjectionType) -> Result<(), JsValue> {
+        let (measure, (start, end)) = match (&self.measure, self.view_ends(camera)) {
+            (Some(measure), Some(ends)) => (measure, ends),
+            _ => return Ok(()),
+        };
+
+        let midpoint = Measure::new(&start, &end).midpoint;
+        let position = lonlat::proj(&midpoint, projection, camera).or_else(|| {
+            let vertices = great_circle::project(start, end, camera, projection);
+            vertices.get(vertices.len() / 2).copied()
+        });
+
+        if let Some(position) = position {
+            let position = crate::math::projection::ndc_to_screen_space(&position, camera);
+
+            self.text_renderer.begin();
+            self.text_renderer.add_label(
+                &measure.label,
+                &Vector2::new(position.x as f32, position.y as f32),
+                cgmath::Rad(0.0),
+            )?;
+            self.text_renderer.end();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::angle::ToAngle;
+
+    #[test]
+    fn measure_is_the_angular_distance_of_the_ends() {
+        let lonlat = |lon: f64, lat: f64| LonLatT::new(lon.to_radians().to_angle(), lat.to_radians().to_angle());
+
+        for (start, end) in [
+            (lonlat(10.0, 20.0), lonlat(10.0, 21.0)),
+            (lonlat(350.0, -5.0), lonlat(15.0, 30.0)),
+            (lonlat(83.6, 22.0), lonlat(83.6001, 22.0001)),
+            (lonlat(0.0, 89.0), lonlat(180.0, 89.0)),
+        ] {
+            let measure = Measure::new(&start, &end);
+            let expected = lonlat::angular_distance(&start, &end).to_radians();
+            assert!((measure.distance.to_radians() - expected).abs() < 1e-15);
+
+            // The midpoint is halfway along the arc
+            let d1 = lonlat::angular_distance(&start, &measure.midpoint).to_radians();
+            let d2 = lonlat::angular_distance(&measure.midpoint, &end).to_radians();
+            assert!((d1 - 0.5 * expected).abs() < 1e-9 && (d2 - 0.5 * expected).abs() < 1e-9);
+        }
+
+        assert_eq!(Measure::new(&lonlat(10.0, 20.0), &lonlat(10.0, 21.0)).label, "01°00'00\"");
+        // Across the pole
+        assert_eq!(Measure::new(&lonlat(0.0, 89.0), &lonlat(180.0, 89.0)).label, "02°00'00\"");
+    }
+}
@@ -0,0 +1,164 @@
+use crate::CameraViewPort;
+use crate::ProjectionType;
+
+use cgmath::InnerSpace;
+use cgmath::Vector2;
+
+use crate::coo_space::XYNDC;
+use crate::math::angle::{Angle, ToAngle};
+use crate::math::HALF_PI;
+
+use crate::LonLatT;
+
+// Angle between the shaft of an arrow and the barbs of its head
+const HEAD_ANGLE: f64 = std::f64::consts::PI / 6.0;
+// Length of the barbs relative to the length of the arrow
+const HEAD_RATIO: f64 = 0.3;
+
+/// A vector of the field, e.g. a proper motion or a polarization
+#[derive(Clone, Copy, Debug)]
+pub struct VectorSample {
+    /// Position of the base of the arrow, in the frame of the view
+    pub position: LonLatT<f64>,
+    /// Position angle of the vector, from the north towards the east
+    pub angle: Angle<f64>,
+    /// Length of the arrow on screen
+    pub length_px: f32,
+}
+
+// Project the arrows of a vector field
+//
+// * Remark
+// The arrows are oriented along the north and east directions of the sky
+// at their base so that they point correctly whatever the projection. The
+// samples whose base, or its close neighbourhood, cannot be projected are skipped
+//
+// * Returns
+// A list of lines vertices, the shaft and the two barbs of the head of each arrow
+pub fn project(
+    samples: &[VectorSample],
+    camera: &CameraViewPort,
+    projection: &ProjectionType,
+) -> Vec<XYNDC<f64>> {
+    // step of the finite differences
+    let h = camera.get_aperture().to_radians() * 1e-3;
+    let screen_size = camera.get_screen_size();
+    let screen_size = Vector2::new(screen_size.x as f64, screen_size.y as f64);
+
+    project_with(samples, h, &screen_size, &|lonlat| {
+        crate::math::lonlat::proj(lonlat, projection, camera)
+    })
+}
+
+// Same as `project` but does the projection with `proj`
+fn project_with<P>(
+    samples: &[VectorSample],
+    h: f64,
+    screen_size: &Vector2<f64>,
+    proj: &P,
+) -> Vec<XYNDC<f64>>
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    // NDC to pixels
+    let (sx, sy) = (0.5 * screen_size.x, 0.5 * screen_size.y);
+    let to_px = |p: XYNDC<f64>| Vector2::new(p.x * sx, p.y * sy);
+    let to_ndc = |p: Vector2<f64>| XYNDC::new(p.x / sx, p.y / sy);
+
+    let mut vertices = vec![];
+    for sample in samples {
+        let (base, north, east) = match local_frame(&sample.position, h, proj) {
+            Some(frame) => frame,
+            None => continue,
+        };
+
+        // The directions are taken in pixels, the NDC space being stretched
+        // by the aspect ratio of the screen
+        let (north, east) = (to_px(north).normalize(), to_px(east).normalize());
+
+        let (sin_pa, cos_pa) = sample.angle.to_radians().sin_cos();
+        let dir = (north * cos_pa + east * sin_pa).normalize();
+
+        let base = to_px(base);
+        let tip = base + dir * (sample.length_px as f64);
+
+        vertices.push(to_ndc(base));
+        vertices.push(to_ndc(tip));
+
+        // The head
+        let barb = -dir * (HEAD_RATIO * sample.length_px as f64);
+        for theta in [HEAD_ANGLE, -HEAD_ANGLE] {
+            let (s, c) = theta.sin_cos();
+            let b = Vector2::new(c * barb.x - s * barb.y, s * barb.x + c * barb.y);
+
+            vertices.push(to_ndc(tip));
+            vertices.push(to_ndc(tip + b));
+        }
+    }
+
+    vertices
+}
+
+// Project a sky position with the local north and east directions there.
+// They are found by finite differences of step `h` along the meridian and the
+// parallel. They are not orthogonal on screen for non conformal projections
+fn local_frame<P>(
+    lonlat: &LonLatT<f64>,
+    h: f64,
+    proj: &P,
+) -> Option<(XYNDC<f64>, Vector2<f64>, Vector2<f64>)>
+where
+    P: Fn(&LonLatT<f64>) -> Option<XYNDC<f64>>,
+{
+    let (lon, lat) = (lonlat.lon().to_radians(), lonlat.lat().to_radians());
+    let at = |lon: f64, lat: f64| proj(&LonLatT::new(lon.to_angle(), lat.to_angle()));
+
+    let base = at(lon, lat)?;
+
+    // Close to the north pole, step towards the south
+    let north = if lat + h < HALF_PI {
+        at(lon, lat + h)? - base
+    } else {
+        base - at(lon, lat - h)?
+    };
+    // The step in longitude giving an angular step of h
+    let dlon = h / lat.cos().max(h);
+    let east = at(lon + dlon, lat)? - base;
+
+    if north.magnitude2() == 0.0 || east.magnitude2() == 0.0 {
+        return None;
+    }
+
+    Some((base, north, east))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::projection::clip_space_proj;
+
+    #[test]
+    fn north_arrow_at_the_center_points_up() {
+        let screen_size = Vector2::new(800.0, 600.0);
+        let samples = [VectorSample {
+            position: LonLatT::new(0.0_f64.to_angle(), 0.0_f64.to_angle()),
+            angle: 0.0_f64.to_angle(),
+            length_px: 20.0,
+        }];
+
+        for projection in [
+            ProjectionType::Sin(mapproj::zenithal::sin::Sin),
+            ProjectionType::Mol(mapproj::pseudocyl::mol::Mol::new()),
+        ] {
+            let proj = clip_space_proj(&projection);
+            let vertices = project_with(&samples, 1e-4, &screen_size, &proj);
+            // The shaft and the two barbs
+            assert_eq!(vertices.len(), 6);
+
+            let (base, tip) = (vertices[0], vertices[1]);
+            assert!(base.magnitude() < 1e-9);
+            assert!(tip.x.abs() < 1e-9);
+            assert!((tip.y * 0.5 * screen_size.y - 20.0).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+
+use al_api::coo_system::CooSystem;
+use al_api::hips::TransferFunction;
+
+/// The state of the viewer, to share or restore a view
+///
+/// The fields missing from a serialized state take their default value and the
+/// unknown ones are ignored so that states saved by other versions can be read.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ViewerState {
+    /// The center of the view in degrees, in the icrs frame
+    pub center: [f64; 2],
+    /// The aperture of the view in degrees
+    pub fov: f64,
+    /// The position angle of the view in degrees
+    pub rotation: f64,
+    /// The name of the projection, e.g. "TAN"
+    pub projection: String,
+    /// The frame of the view
+    pub frame: CooSystem,
+    /// The image layers, from the bottom to the top one
+    pub layers: Vec<LayerState>,
+}
+
+impl Default for ViewerState {
+    fn default() -> Self {
+        Self {
+            center: [0.0, 0.0],
+            fov: 180.0,
+            rotation: 0.0,
+            projection: String::from("SIN"),
+            frame: CooSystem::ICRS,
+            layers: vec![],
+        }
+    }
+}
+
+/// The color config of an image layer
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LayerState {
+    pub layer: String,
+    /// The creator did of the HiPS or the url of the image
+    pub id: String,
+    /// The id of the transfer function, e.g. "asinh:0.1", see ``TransferFunction::try_from_id``
+    pub transfer_function: String,
+    /// The table of the histogram equalization, which is not part of its id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hist_eq_lut: Option<Vec<f32>>,
+    /// The name of the colormap
+    pub colormap: String,
+    pub reversed: bool,
+    pub min_cut: Option<f32>,
+    pub max_cut: Option<f32>,
+    pub opacity: f32,
+}
+
+impl Default for LayerState {
+    fn default() -> Self {
+        Self {
+            layer: String::new(),
+            id: String::new(),
+            transfer_function: String::from("linear"),
+            hist_eq_lut: None,
+            colormap: String::from("grayscale"),
+            reversed: false,
+            min_cut: None,
+            max_cut: None,
+            opacity: 1.0,
+        }
+    }
+}
+
+impl LayerState {
+    /// Store ``stretch``, along with its table when it is a histogram equalization
+    pub fn set_transfer_function(&mut self, stretch: &TransferFunction) {
+        self.transfer_function = String::from(stretch.clone());
+        self.hist_eq_lut = stretch.hist_eq_lut().map(|lut| lut.to_vec());
+    }
+
+    /// The transfer function stored, see ``LayerState::set_transfer_function``
+    pub fn get_transfer_function(&self) -> Result<TransferFunction, String> {
+        let stretch = TransferFunction::try_from_id(&self.transfer_function)?;
+
+        match (stretch.hist_eq_lut(), &self.hist_eq_lut) {
+            (Some(_), Some(lut)) => stretch.with_hist_eq_lut(lut),
+            _ => Ok(stretch),
+        }
+    }
+}
+
+impl ViewerState {
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| e.to_string())
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use al_api::hips::HIST_EQ_LUT_SIZE;
+
+    #[test]
+    fn hist_eq_table_is_restored() {
+        // A table brightening the dark pixels
+        let mut lut = [0.0; HIST_EQ_LUT_SIZE];
+        for (i, v) in lut.iter_mut().enumerate() {
+            *v = ((i as f32) / ((HIST_EQ_LUT_SIZE - 1) as f32)).sqrt();
+        }
+        let stretch = TransferFunction::hist_eq(&lut).unwrap().reversed();
+
+        let mut layer = LayerState::default();
+        layer.set_transfer_function(&stretch);
+        let state = ViewerState {
+            layers: vec![layer],
+            ..Default::default()
+        };
+
+        let restored = ViewerState::from_json(&state.to_json().unwrap()).unwrap();
+        let restored = restored.layers[0].get_transfer_function().unwrap();
+        assert!(restored.is_reversed());
+        assert_eq!(restored.hist_eq_lut(), Some(&lut));
+
+        // The other functions are stored by their id only
+        let mut layer = LayerState::default();
+        layer.set_transfer_function(&TransferFunction::Asinh(0.1));
+        assert!(!serde_json::to_string(&layer).unwrap().contains("histEqLut"));
+    }
+}
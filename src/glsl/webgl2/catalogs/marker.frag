@@ -0,0 +1,19 @@
+#version 300 es
+precision lowp float;
+
+in vec2 out_uv;
+out vec4 color;
+
+uniform vec4 u_color;
+uniform float strength;
+
+void main() {
+    // A ring inscribed in the billboard
+    float r = 2.0 * length(out_uv - vec2(0.5));
+    if (r > 1.0 || r < 0.7) {
+        discard;
+    }
+
+    color = u_color;
+    color.a *= clamp(strength, 0.0, 1.0);
+}
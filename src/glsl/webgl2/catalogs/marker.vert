@@ -0,0 +1,17 @@
+#version 300 es
+precision highp float;
+layout (location = 0) in vec2 offset;
+layout (location = 1) in vec2 uv;
+// The position of the marker in the NDC space
+layout (location = 2) in vec2 ndc_pos;
+// The size of the marker relative to the one of the catalog
+layout (location = 3) in float size;
+
+uniform vec2 kernel_size;
+
+out vec2 out_uv;
+
+void main() {
+    gl_Position = vec4(ndc_pos + offset * kernel_size * size, 0.f, 1.f);
+    out_uv = uv;
+}
@@ -3,6 +3,8 @@ precision lowp float;
 layout (location = 0) in vec2 offset;
 layout (location = 1) in vec2 uv;
 layout (location = 2) in vec3 center;
+// The size of the marker relative to the one of the catalog
+layout (location = 3) in float size;
 
 uniform float current_time;
 uniform mat4 inv_model;
@@ -24,7 +26,7 @@ void main() {
     vec2 center_pos_clip_space = world2clip_mollweide(p);
 
     vec2 pos_clip_space = center_pos_clip_space;
-    gl_Position = vec4((pos_clip_space / (ndc_to_clip * czf)) + offset * kernel_size , 0.f, 1.f);
+    gl_Position = vec4((pos_clip_space / (ndc_to_clip * czf)) + offset * kernel_size * size, 0.f, 1.f);
 
     out_uv = uv;
     out_p = p;
@@ -12,6 +12,19 @@ uniform float blank;
 uniform float min_value;
 uniform float max_value;
 uniform int H;
+// Transfer functions of the channels of color tiles
+uniform int H_r;
+uniform int H_g;
+uniform int H_b;
+uniform float gamma_exp_r;
+uniform float gamma_exp_g;
+uniform float gamma_exp_b;
+uniform float asinh_softening_r;
+uniform float asinh_softening_g;
+uniform float asinh_softening_b;
+uniform float H_r_reversed;
+uniform float H_g_reversed;
+uniform float H_b_reversed;
 
 uniform float size_tile_uv;
 
@@ -45,9 +58,9 @@ uniform float reversed;
 vec4 get_color_from_texture(vec3 UV) {
     vec4 color = get_pixels(UV);
     
-    color.r = transfer_func(H, color.r, min_value, max_value);
-    color.g = transfer_func(H, color.g, min_value, max_value);
-    color.b = transfer_func(H, color.b, min_value, max_value);
+    color.r = transfer_func_params(H_r, color.r, min_value, max_value, gamma_exp_r, asinh_softening_r, H_r_reversed);
+    color.g = transfer_func_params(H_g, color.g, min_value, max_value, gamma_exp_g, asinh_softening_g, H_g_reversed);
+    color.b = transfer_func_params(H_b, color.b, min_value, max_value, gamma_exp_b, asinh_softening_b, H_b_reversed);
 
     // apply reversed
     color.rgb = mix(color.rgb, 1.0 - color.rgb, reversed);
@@ -56,13 +69,18 @@ vec4 get_color_from_texture(vec3 UV) {
 }
 
 vec4 apply_colormap_to_grayscale(float x, float a) {
+    // The undefined pixels are discarded before evaluating the transfer function
+    if (is_masked(x, blank)) {
+        return vec4(0.0);
+    }
+
     float alpha = x * scale + offset;
     alpha = transfer_func(H, alpha, min_value, max_value);
 
     // apply reversed
     alpha = mix(alpha, 1.0 - alpha, reversed);
 
-    vec4 new_color = mix(colormap_f(alpha) * a, vec4(0.0), float(x == blank || isnan(x)));
+    vec4 new_color = colormap_f(alpha) * a;
     return apply_tonal(new_color);
 }
 
@@ -47,12 +47,17 @@ vec4 get_colormap_from_grayscale_texture(vec3 UV) {
     vec3 uv = mix(UV, reverse_uv(UV), float(tex_storing_fits == 1));
 
     float x = float(get_pixels(uv).r);
+    // The undefined pixels are discarded before evaluating the transfer function
+    if (is_masked(x, blank)) {
+        return vec4(0.0);
+    }
+
     float alpha = x * scale + offset;
     alpha = transfer_func(H, alpha, min_value, max_value);
 
     // apply reversed
     alpha = mix(alpha, 1.0 - alpha, reversed);
 
-    vec4 new_color = mix(colormap_f(alpha), vec4(0.0), float(x == blank));
+    vec4 new_color = colormap_f(alpha);
     return apply_tonal(new_color);
 }
\ No newline at end of file
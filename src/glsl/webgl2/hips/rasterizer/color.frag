@@ -19,6 +19,8 @@ void main() {
     vec4 color_start = get_color_from_texture(frag_uv_start);
     vec4 color_end = get_color_from_texture(frag_uv_end);
 
+    // The tile fades in over the one of lower resolution, if there is one
+    color_start = mix(color_start, vec4(0.0), m_start);
     out_frag_color = mix(color_start, color_end, frag_blending_factor);
     out_frag_color.a = opacity * out_frag_color.a;
 }
\ No newline at end of file
@@ -20,6 +20,8 @@ void main() {
     vec4 color_start = get_colormap_from_grayscale_texture(frag_uv_start);
     vec4 color_end = get_colormap_from_grayscale_texture(frag_uv_end);
 
+    // The tile fades in over the one of lower resolution, if there is one
+    color_start = mix(color_start, vec4(0.0), m_start);
     out_frag_color = mix(color_start, color_end, frag_blending_factor);
     out_frag_color.a = out_frag_color.a * opacity;
 }
\ No newline at end of file
@@ -6,7 +6,7 @@ precision mediump int;
 layout (location = 0) in vec2 lonlat;
 layout (location = 1) in vec3 uv_start;
 layout (location = 2) in vec3 uv_end;
-layout (location = 3) in float time_tile_received;
+layout (location = 3) in float tile_alpha;
 layout (location = 4) in float m0;
 layout (location = 5) in float m1;
 
@@ -16,11 +16,9 @@ out float frag_blending_factor;
 out float m_start;
 out float m_end;
 
-// current time in ms
 uniform mat4 inv_model;
 uniform vec2 ndc_to_clip;
 uniform float czf;
-uniform float current_time;
 
 #include ../../projection/projection.glsl;
 
@@ -35,7 +33,7 @@ void main() {
 
     frag_uv_start = uv_start;
     frag_uv_end = uv_end;
-    frag_blending_factor = min((current_time - time_tile_received) / 200.0, 1.0);
+    frag_blending_factor = tile_alpha;
     m_start = m0;
     m_end = m1;
 }
\ No newline at end of file
@@ -1,4 +1,23 @@
+uniform float gamma_exp;
+uniform float H_reversed;
+uniform float asinh_softening;
+// affine scaling of the pixel values done before the cuts
+uniform float pixel_scale;
+uniform float pixel_offset;
+uniform sampler2D hist_eq_lut;
+// draw the pixels undefined, i.e. NaN or equal to BLANK, transparent
+uniform bool blank_as_transparent;
+
+bool is_masked(float x, float blank) {
+    return blank_as_transparent && (isnan(x) || x == blank);
+}
+
 float linear_f(float x, float min_value, float max_value) {
+    // Equal cuts, the values are thresholded at the cut
+    if (max_value == min_value) {
+        return step(min_value, x);
+    }
+
     return clamp((x - min_value)/(max_value - min_value), 0.0, 1.0);
 }
 
@@ -13,9 +32,9 @@ float log_f(float x, float min_value, float max_value) {
     return log(a*y + 1.0)/log(a);
 }
 
-float asinh_f(float x, float min_value, float max_value) {
+float asinh_f(float x, float min_value, float max_value, float softening) {
     float d = linear_f(x, min_value, max_value);
-    return asinh(10.0*d)/3.0;
+    return asinh(d/softening)/asinh(1.0/softening);
 }
 
 float pow2_f(float x, float min_value, float max_value) {
@@ -23,7 +42,28 @@ float pow2_f(float x, float min_value, float max_value) {
     return d*d;
 }
 
-float transfer_func(int H, float x, float min_value, float max_value) {
+float gamma_f(float x, float min_value, float max_value, float gamma) {
+    float d = linear_f(x, min_value, max_value);
+    return pow(d, gamma);
+}
+
+float hist_eq_f(float x, float min_value, float max_value) {
+    float d = linear_f(x, min_value, max_value);
+    // Interpolate between the two closest entries of the table
+    float p = d * 255.0;
+    int i = int(floor(p));
+    float t = p - float(i);
+
+    float a = texelFetch(hist_eq_lut, ivec2(i, 0), 0).r;
+    float b = texelFetch(hist_eq_lut, ivec2(min(i + 1, 255), 0), 0).r;
+    return mix(a, b, t);
+}
+
+float transfer_func_params(int H, float x, float min_value, float max_value, float gamma, float softening, float reversed) {
+    x = pixel_scale * x + pixel_offset;
+    // Evaluate H(1 - x) when reversed, x being normalized by the cuts
+    x = mix(x, min_value + max_value - x, reversed);
+
     if (H == 0) {
         return linear_f(x, min_value, max_value);
     } else if (H == 1) {
@@ -31,8 +71,16 @@ float transfer_func(int H, float x, float min_value, float max_value) {
     } else if (H == 2) {
         return log_f(x, min_value, max_value);
     } else if (H == 3) {
-        return asinh_f(x, min_value, max_value);
-    } else {
+        return asinh_f(x, min_value, max_value, softening);
+    } else if (H == 4) {
         return pow2_f(x, min_value, max_value);
+    } else if (H == 5) {
+        return gamma_f(x, min_value, max_value, gamma);
+    } else {
+        return hist_eq_f(x, min_value, max_value);
     }
-}
\ No newline at end of file
+}
+
+float transfer_func(int H, float x, float min_value, float max_value) {
+    return transfer_func_params(H, x, min_value, max_value, gamma_exp, asinh_softening, H_reversed);
+}
@@ -0,0 +1,16 @@
+#version 300 es
+
+precision lowp float;
+out vec4 color;
+in float d;
+
+uniform vec4 u_color;
+uniform float u_thickness;
+
+void main() {
+    // Coverage of the pixel by the line, over a pixel wide band on its edges
+    float half_thickness = 0.5 * u_thickness;
+    float coverage = 1.0 - smoothstep(half_thickness - 0.5, half_thickness + 0.5, abs(d));
+
+    color = vec4(u_color.rgb, u_color.a * coverage);
+}
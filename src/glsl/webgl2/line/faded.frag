@@ -0,0 +1,12 @@
+#version 300 es
+
+precision lowp float;
+out vec4 color;
+in float alpha;
+
+uniform vec4 u_color;
+
+void main() {
+    // The alpha fades the line out near the limb of the projection
+    color = vec4(u_color.rgb, u_color.a * alpha);
+}
\ No newline at end of file
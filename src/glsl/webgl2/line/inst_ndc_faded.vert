@@ -0,0 +1,24 @@
+#version 300 es
+precision highp float;
+// The position of the endpoints in the NDC space along with their alpha
+layout (location = 0) in vec3 p_a;
+layout (location = 1) in vec3 p_b;
+layout (location = 2) in vec2 vertex;
+
+out float alpha;
+
+uniform float u_width;
+uniform float u_height;
+uniform float u_thickness;
+
+void main() {
+    vec2 x_b = p_b.xy - p_a.xy;
+    vec2 y_b = normalize(vec2(-x_b.y, x_b.x));
+
+    float ndc2pix = 2.0 / u_width;
+
+    vec2 p = p_a.xy + x_b * vertex.x + u_thickness * y_b * vertex.y * vec2(1.0, u_width/u_height) * ndc2pix;
+    gl_Position = vec4(p, 0.f, 1.f);
+
+    alpha = mix(p_a.z, p_b.z, vertex.x);
+}
\ No newline at end of file
@@ -0,0 +1,3 @@
+vec2 w2c_car(vec3 p) {
+    return vec2(atan(-p.x, p.z) / PI, 2.0 * asin(p.y) / PI);
+}
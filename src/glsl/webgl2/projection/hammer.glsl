@@ -0,0 +1,7 @@
+vec2 w2c_hammer(vec3 p) {
+    float half_lon = 0.5 * atan(p.x, p.z);
+    float cos_lat = length(p.zx);
+    float w = sqrt(1.0 + cos_lat * cos(half_lon));
+
+    return vec2(-cos_lat * sin(half_lon), p.y) / w;
+}
@@ -1,3 +1,9 @@
+// sin(MER_MAX_LAT), i.e. tanh(PI), the latitude beyond which the map is cut
+const float SIN_MER_MAX_LAT = 0.99627207622075;
+
 vec2 w2c_mer(vec3 p) {
-    return vec2(atan(-p.x, p.z), atanh(p.y)) / PI;
+    // The latitudes beyond are clamped to the edge of the map, where the
+    // ordinate is 1, instead of going to infinity at the poles
+    float y = clamp(p.y, -SIN_MER_MAX_LAT, SIN_MER_MAX_LAT);
+    return vec2(atan(-p.x, p.z), atanh(y)) / PI;
 }
\ No newline at end of file
@@ -9,6 +9,8 @@ const float SQRT_2 = 1.41421356237309504880168872420969808;
 #include ./stg.glsl;
 #include ./zea.glsl;
 #include ./mer.glsl;
+#include ./car.glsl;
+#include ./hammer.glsl;
 
 vec3 lonlat2xyz(vec2 lonlat) {
     float t = lonlat.x;
@@ -44,9 +46,15 @@ vec2 proj(vec3 p) {
     } else if (u_proj == 5) {
         // MOL,      Mollweide                  */
         return w2c_mol(p);
-    } else {
+    } else if (u_proj == 6) {
         // Cylindrical projections
         // MER,      Mercator                   */
         return w2c_mer(p);
+    } else if (u_proj == 7) {
+        // CAR,      Plate carrée               */
+        return w2c_car(p);
+    } else {
+        // Hammer equal-area                    */
+        return w2c_hammer(p);
     }
 }
\ No newline at end of file
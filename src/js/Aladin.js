@@ -137,11 +137,17 @@ import { Polyline } from "./shapes/Polyline";
  *                    (see {@link https://developer.mozilla.org/en-US/docs/Web/CSS/named-color| named colors}),
  *                    as rgb (ex: "rgb(178, 50, 178)"), or as a hex color (ex: "#86D6AE").              
  * @property {number} [gridOptions.thickness=2] - The thickness of the grid, in pixels.
+ * @property {string} [gridOptions.widthPolicy="Fixed"] - "Fixed" for a thickness in pixels of the screen, "DevicePixelRatio"
+ *                    for a thickness scaled by the device pixel ratio.
+ * @property {number} [gridOptions.minThickness=1] - The minimum width of the grid lines, in pixels.
+ * @property {number} [gridOptions.maxThickness=16] - The maximum width of the grid lines, in pixels.
+ * @property {number} [gridOptions.limbFade=0] - The angular distance, in degrees, over which the grid lines fade out
+ *                    before the limb of the projection. 0 does not fade them.
  * @property {number} [gridOptions.opacity=0.8] - Opacity of the grid and labels. It is comprised between 0 and 1.
  * @property {boolean} [gridOptions.showLabels=true] - Whether the grid has labels.
  * @property {number} [gridOptions.labelSize=15] - The font size of the labels.
  * 
- * @property {string} [projection="SIN"] - Projection type. Can be 'SIN' for orthographic, 'MOL' for mollweide, 'AIT' for hammer-aitoff, 'ZEA' for zenital equal-area or 'MER' for mercator
+ * @property {string} [projection="SIN"] - Projection type. Can be 'SIN' for orthographic, 'MOL' for mollweide, 'AIT' for hammer-aitoff, 'ZEA' for zenital equal-area, 'MER' for mercator or 'CAR' for plate carrée
  * @property {boolean} [log=true] - Whether to log events.
  * @property {boolean} [samp=false] - Whether to enable SAMP (Simple Application Messaging Protocol).
  * @property {boolean} [realFullscreen=false] - Whether to use real fullscreen mode.
@@ -945,8 +951,10 @@ export let Aladin = (function () {
      * <br>"STG" (Stereographic projection)
      * <br>"SIN" (Orthographic projection)
      * <br>"ZEA" (Zenital equal-area projection)
-     * <br>"MER" (Mercator projection)
+     * <br>"MER" (Mercator projection, cut at ±85.05° of latitude)
+     * <br>"CAR" (Plate carrée projection)
      * <br>"AIT" (Hammer-Aitoff projection)
+     * <br>"HAMMER" (Hammer equal-area projection)
      * <br>"MOL" (Mollweide projection)
      *
      * @example
@@ -2165,6 +2173,10 @@ aladin.on("layerChanged", (layer, layerName, state) => {
      * @param {number} [options.opacity] - The opacity of the coordinate grid (value between 0 and 1).
      * @param {number} [options.labelSize] - The size of the coordinate grid labels in pixels.
      * @param {number} [options.thickness] - The thickness of the coordinate grid lines.
+     * @param {string} [options.widthPolicy] - "Fixed" or "DevicePixelRatio", how the thickness is scaled on screen.
+     * @param {number} [options.minThickness] - The minimum width of the coordinate grid lines, in pixels.
+     * @param {number} [options.maxThickness] - The maximum width of the coordinate grid lines, in pixels.
+     * @param {number} [options.limbFade] - The angular distance, in degrees, over which the lines fade out before the limb of the projection, 0 to disable it.
      * @param {boolean} [options.enabled] - If true, the coordinate grid is enabled; otherwise, it is disabled.
      *
      * @example
@@ -55,6 +55,8 @@
         }
 
         this.additiveBlending = options && options.additive;
+        // One of 'Over', 'Add', 'Screen' or 'Multiply'. It takes precedence over `additive`
+        this.blendMode = options && options.blendMode;
         if (this.additiveBlending === undefined)  {
             this.additiveBlending = false;
         }
@@ -84,6 +86,7 @@
         // Reset the whole meta object
         return {
             blendCfg: blend,
+            blendMode: this.blendMode,
             opacity: this.opacity,
             color: {
                 // Tonal corrections constants
@@ -149,6 +149,7 @@ PropertyParser.isPlanetaryBody = function (properties) {
  * @property {number} [minCut] - The minimum cut value for the color configuration. If not given, 0.0 for JPEG/PNG surveys, the value of the property file for FITS surveys
  * @property {number} [maxCut] - The maximum cut value for the color configuration. If not given, 1.0 for JPEG/PNG surveys, the value of the property file for FITS surveys
  * @property {boolean} [additive=false] - If true, additive blending is applied; otherwise, it is not applied.
+ * @property {string} [blendMode] - One of "Over", "Add", "Screen" or "Multiply". If given, it takes precedence over `additive`.
  * @property {number} [gamma=1.0] - The gamma correction value for the color configuration.
  * @property {number} [saturation=0.0] - The saturation value for the color configuration.
  * @property {number} [brightness=0.0] - The brightness value for the color configuration.
@@ -764,6 +765,20 @@ export let ImageHiPS = (function () {
         });
     };
 
+    /**
+     * Sets a histogram equalization as the stretch of the ImageHiPS.
+     *
+     * @memberof ImageHiPS
+     *
+     * @param {Float32Array|number[]} lut - The 256 values of the table sampled on [0, 1]. They must be non-decreasing
+     */
+    ImageHiPS.prototype.setHistEqLut = function (lut) {
+        this._updateMetadata(() => {
+            this.histEqLut = Float32Array.from(lut);
+            this.colorCfg.stretch = "histeq";
+        });
+    };
+
     // Private method for updating the backend with the new meta
     ImageHiPS.prototype._updateMetadata = function (callback) {
         if (callback) {
@@ -778,6 +793,10 @@ export let ImageHiPS = (function () {
                     longitudeReversed: this.longitudeReversed,
                     imgFormat: this.imgFormat,
                 });
+                // The table of the histogram equalization is not part of the meta
+                if (this.histEqLut && this.colorCfg.stretch.startsWith("histeq")) {
+                    this.view.wasm.setLayerHistEqLut(this.layer, this.histEqLut);
+                }
                 // once the meta have been well parsed, we can set the meta
                 ALEvent.HIPS_LAYER_CHANGED.dispatchedTo(this.view.aladinDiv, {
                     layer: this,
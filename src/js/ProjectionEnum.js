@@ -41,7 +41,7 @@ export let ProjectionEnum = {
    //NCP: {id: 8, fov: 180, label: "north celestial pole"},
    // Cylindrical
    MER: {id: 9, fov: 360, label: "Mercator"},
-   //CAR: {id: 10, fov: 360, label: "plate carrée"},
+   CAR: {id: 10, fov: 360, label: "plate carrée"},
    //CEA: {id: 11, fov: 360, label: "cylindrical equal area"},
    //CYP: {id: 12, fov: 360, label: "cylindrical perspective"},
    // Pseudo-cylindrical
@@ -49,6 +49,7 @@ export let ProjectionEnum = {
    //PAR: {id: 14, fov: 360, label: "parabolic"},
    //SFL: {id: 15, fov: 360, label: "sanson-flamsteed"},
    MOL: {id: 16, fov: 360, label: "Mollweide"},
+   HAMMER: {id: 20, fov: 360, label: "Hammer"},
    // Conic
    //COD: {id: 17, fov: 360, label: "conic equidistant"},
    // Hybrid
@@ -1422,15 +1422,12 @@ export let View = (function () {
             }
         }
 
-        // display grid labels
-        if (this.gridCfg.enabled && this.gridCfg.showLabels) {
-            if (!this.catalogCanvasCleared) {
-                ctx.clearRect(0, 0, this.width, this.height);
-                this.catalogCanvasCleared = true;
-            }
-
-            this.wasm.drawGridLabels();
+        // display the grid labels, the ruler measure and the number of sources of the catalog clusters
+        if (!this.catalogCanvasCleared) {
+            ctx.clearRect(0, 0, this.width, this.height);
+            this.catalogCanvasCleared = true;
         }
+        this.wasm.drawGridLabels();
 
         if (this.mode === View.SELECT) {
             if (!this.catalogCanvasCleared) {